@@ -7,7 +7,10 @@ RELEASE_PACKAGES=(
     tedge-flows
     tedge-agent
     tedge-watchdog
+    tedge-broker
     tedge-apt-plugin
+    tedge-archive-plugin
+    tedge-container-plugin
     c8y-remote-access-plugin
     c8y-firmware-plugin
     tedge-p11-server
@@ -0,0 +1,459 @@
+//! A minimal [EST](https://datatracker.ietf.org/doc/html/rfc7030) client
+//!
+//! Only the mandatory operations are supported:
+//! - `/cacerts` to get the current CA certificates,
+//! - `/simpleenroll` to get a first certificate for a device,
+//! - `/simplereenroll` to renew a certificate.
+//!
+//! The client is CA-agnostic: the EST server can be the one of a cloud provider,
+//! as Cumulocity, or the one of a private PKI.
+//! The client authenticates either using basic-auth or a TLS client certificate,
+//! the latter being configured on the [reqwest::Client] given to [EstClient::new].
+use base64::prelude::*;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+
+/// The path prefix of all the EST operations
+pub const EST_WELL_KNOWN_PATH: &str = ".well-known/est";
+
+pub struct EstClient {
+    base_url: String,
+    http: reqwest::Client,
+    credentials: Option<(String, String)>,
+}
+
+impl EstClient {
+    /// Create an EST client for the given server
+    ///
+    /// The `base_url` is the URL of the EST operations, e.g. `https://est.example.com/.well-known/est`,
+    /// as returned by [EstClient::base_url].
+    pub fn new(base_url: impl Into<String>, http: reqwest::Client) -> Self {
+        EstClient {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            http,
+            credentials: None,
+        }
+    }
+
+    /// Return the EST base URL of a server, with an optional CA label
+    pub fn base_url(host: impl std::fmt::Display, label: Option<&str>) -> String {
+        match label {
+            Some(label) => format!("https://{host}/{EST_WELL_KNOWN_PATH}/{label}"),
+            None => format!("https://{host}/{EST_WELL_KNOWN_PATH}"),
+        }
+    }
+
+    /// Authenticate the requests using HTTP basic-auth
+    pub fn with_basic_auth(self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        EstClient {
+            credentials: Some((username.into(), password.into())),
+            ..self
+        }
+    }
+
+    /// Get the CA certificates, returned as a PEM bundle
+    pub async fn cacerts(&self) -> Result<String, EstError> {
+        let url = self.url("cacerts");
+        let request = self.authenticate(self.http.get(&url));
+        let body = Self::send(&url, request).await?;
+        pk7_body_to_x509(&body)
+    }
+
+    /// Request a first certificate for a device, returning the signed certificate as PEM
+    pub async fn simple_enroll(&self, csr_pem: &str) -> Result<String, EstError> {
+        self.enroll("simpleenroll", csr_pem).await
+    }
+
+    /// Request the renewal of a certificate, returning the signed certificate as PEM
+    pub async fn simple_reenroll(&self, csr_pem: &str) -> Result<String, EstError> {
+        self.enroll("simplereenroll", csr_pem).await
+    }
+
+    async fn enroll(&self, operation: &str, csr_pem: &str) -> Result<String, EstError> {
+        let url = self.url(operation);
+        let csr = csr_to_base64(csr_pem)?;
+        let request = self
+            .http
+            .post(&url)
+            .header(CONTENT_TYPE, "application/pkcs10")
+            .header("Content-Transfer-Encoding", "base64")
+            .body(csr);
+        let body = Self::send(&url, self.authenticate(request)).await?;
+        pk7_body_to_x509(&body)
+    }
+
+    fn url(&self, operation: &str) -> String {
+        format!("{}/{operation}", self.base_url)
+    }
+
+    fn authenticate(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.credentials {
+            Some((username, password)) => request.basic_auth(username, Some(password)),
+            None => request,
+        }
+    }
+
+    async fn send(url: &str, request: reqwest::RequestBuilder) -> Result<Vec<u8>, EstError> {
+        let http_error = |source| EstError::Http {
+            url: url.to_string(),
+            source,
+        };
+        let response = request.send().await.map_err(http_error)?;
+        let status = response.status();
+        let body = response.bytes().await.map_err(http_error)?;
+        if status != StatusCode::OK {
+            return Err(EstError::Rejected {
+                url: url.to_string(),
+                status,
+                reason: String::from_utf8_lossy(&body).trim().to_string(),
+            });
+        }
+        Ok(body.to_vec())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum EstError {
+    #[error("Fail to connect to the EST server {url}")]
+    Http {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("The EST request {url} failed with {status}: {reason}")]
+    Rejected {
+        url: String,
+        status: StatusCode,
+        reason: String,
+    },
+
+    #[error("Invalid certificate signing request: {0}")]
+    InvalidCsr(String),
+
+    #[error(transparent)]
+    IllFormedResponse(#[from] IllFormedPk7Cert),
+}
+
+/// Return the base64-encoded DER of a PEM CSR, as expected by EST servers
+///
+/// Any text before or after the PEM block, as added by some tools, is ignored.
+fn csr_to_base64(csr_pem: &str) -> Result<String, EstError> {
+    let csr = pem::parse(csr_pem).map_err(|err| EstError::InvalidCsr(err.to_string()))?;
+    Ok(BASE64_STANDARD.encode(csr.contents()))
+}
+
+/// Extract the certificates of an EST response
+///
+/// The content is expected to be base64-encoded as mandated by RFC 7030,
+/// but raw DER content is also accepted as returned by some servers.
+fn pk7_body_to_x509(body: &[u8]) -> Result<String, EstError> {
+    const DER_SEQUENCE: u8 = 0x30;
+    let x509_pem = match body.first() {
+        Some(&DER_SEQUENCE) => pk7_der_to_x509(body)?,
+        _ => pk7_to_x509(String::from_utf8_lossy(body).to_string())?,
+    };
+    Ok(x509_pem)
+}
+
+/// Extract the x509 certificates from a pkcs7 pem
+///
+/// EST returns certificates using
+/// [application/pkcs7-mime;smime-type=certs-only](https://datatracker.ietf.org/doc/html/rfc5273.html#page-3).
+/// Meaning the content is a:
+/// - base64-encoded
+/// - BER [SignedData object](https://datatracker.ietf.org/doc/html/rfc2315.html#section-9.1)
+pub fn pk7_to_x509(pk7_base64: String) -> Result<String, IllFormedPk7Cert> {
+    let pk7_ber = BASE64_STANDARD.decode(pk7_base64.replace(['\n', '\r'], ""))?;
+    pk7_der_to_x509(&pk7_ber)
+}
+
+/// Extract the x509 certificates from a BER encoded pkcs7 SignedData object
+pub fn pk7_der_to_x509(pk7_ber: &[u8]) -> Result<String, IllFormedPk7Cert> {
+    use rasn::ber;
+    use rasn::der;
+    use rasn_cms::ContentInfo;
+    use rasn_cms::SignedData;
+
+    let content_info = ber::decode::<ContentInfo>(pk7_ber)?;
+    let pk7 = ber::decode::<SignedData>(content_info.content.as_bytes())?;
+    let x509_pem: Result<Vec<_>, IllFormedPk7Cert> = if let Some(certificates) = pk7.certificates {
+        certificates
+            .to_vec()
+            .iter()
+            .map(|cert| {
+                der::encode(cert)
+                    .map_err(|err| IllFormedPk7Cert::IllFormedCMS(format!("{err}")))
+                    .map(|x509_der| pem::encode(&pem::Pem::new("CERTIFICATE", x509_der)))
+            })
+            .collect()
+    } else {
+        Err(IllFormedPk7Cert::MissingCertificate)
+    };
+
+    Ok(x509_pem?.join("\r\n"))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum IllFormedPk7Cert {
+    #[error(transparent)]
+    NotBase64(#[from] base64::DecodeError),
+
+    #[error("Invalid pkcs#7 certificate: {0}")]
+    IllFormedCMS(String),
+
+    #[error("No certificate found in pkcs#7 content")]
+    MissingCertificate,
+}
+
+impl From<rasn::error::DecodeError> for IllFormedPk7Cert {
+    fn from(value: rasn::error::DecodeError) -> Self {
+        IllFormedPk7Cert::IllFormedCMS(format!("{value}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PemCertificate;
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn decode_certificate() {
+        let pk7 = r#"
+MIAGCSqGSIb3DQEHAqCAMIACAQExADALBgkqhkiG9w0BBwGggDCCAXkwggEgoAMC
+AQICBgGVPZIizTAKBggqhkjOPQQDAjBCMRYwFAYDVQQGEw1Vbml0ZWQgU3RhdGVz
+MRMwEQYDVQQKEwpDdW11bG9jaXR5MRMwEQYDVQQDEwptYW5hZ2VtZW50MB4XDTI1
+MDIyNTE0NDU0MloXDTI2MDIyNDA5NDE0NFowRjEaMBgGA1UEAwwRZGlkaWVyLWRl
+dmljZS0wMDExEjAQBgNVBAoMCVRoaW4gRWRnZTEUMBIGA1UECwwLVGVzdCBEZXZp
+Y2UwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAATwSjNE/7AJZEtrXW2CP2LSLlcl
+wDyh4YwHmpwDhnTCm+ZxeeXBUcUARcFXOtdmxMset9CgMQl1Fjw255dISpqiMAoG
+CCqGSM49BAMCA0cAMEQCICapYBWyzrDU36IVEtyOfdlDA0bW9HE3pwHz2X9LAgl1
+AiAD0naayxieH0RVE1vJtdD3iCJHrzLNM3Eff2gNOhuzJAAAMQAAAAAAAAA=
+"#
+        .to_string();
+
+        // Computed using `openssl pkcs7 -print_certs`
+        let expected_x509 = r#"
+-----BEGIN CERTIFICATE-----
+MIIBeTCCASCgAwIBAgIGAZU9kiLNMAoGCCqGSM49BAMCMEIxFjAUBgNVBAYTDVVu
+aXRlZCBTdGF0ZXMxEzARBgNVBAoTCkN1bXVsb2NpdHkxEzARBgNVBAMTCm1hbmFn
+ZW1lbnQwHhcNMjUwMjI1MTQ0NTQyWhcNMjYwMjI0MDk0MTQ0WjBGMRowGAYDVQQD
+DBFkaWRpZXItZGV2aWNlLTAwMTESMBAGA1UECgwJVGhpbiBFZGdlMRQwEgYDVQQL
+DAtUZXN0IERldmljZTBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABPBKM0T/sAlk
+S2tdbYI/YtIuVyXAPKHhjAeanAOGdMKb5nF55cFRxQBFwVc612bEyx630KAxCXUW
+PDbnl0hKmqIwCgYIKoZIzj0EAwIDRwAwRAIgJqlgFbLOsNTfohUS3I592UMDRtb0
+cTenAfPZf0sCCXUCIAPSdprLGJ4fRFUTW8m10PeIIkevMs0zcR9/aA06G7Mk
+-----END CERTIFICATE-----
+"#
+        .to_string();
+
+        let x509 = pk7_to_x509(pk7).unwrap();
+        let cert = PemCertificate::from_pem_string(&x509).unwrap();
+
+        assert_eq!(
+            x509.replace(['\n', '\r'], ""),
+            expected_x509.replace(['\n', '\r'], "")
+        );
+
+        assert_eq!(
+            cert.subject().unwrap(),
+            "CN=didier-device-001, O=Thin Edge, OU=Test Device".to_string()
+        );
+        assert_eq!(
+            cert.issuer().unwrap(),
+            "C=United States, O=Cumulocity, CN=management".to_string()
+        );
+        assert_eq!(
+            cert.not_before().unwrap(),
+            "Tue, 25 Feb 2025 14:45:42 +0000".to_string()
+        );
+        assert_eq!(
+            cert.not_after().unwrap(),
+            "Tue, 24 Feb 2026 09:41:44 +0000".to_string()
+        );
+        assert_eq!(
+            cert.thumbprint().unwrap(),
+            "9C68C7EC9A860366FB8D2697C53B2543D9EA525C".to_string()
+        );
+    }
+
+    #[test]
+    fn accept_raw_der_responses() {
+        let pk7 = "MIAGCSqGSIb3DQEHAqCAMIACAQExADALBgkqhkiG9w0BBwGggDCCAXkwggEgoAMC
+AQICBgGVPZIizTAKBggqhkjOPQQDAjBCMRYwFAYDVQQGEw1Vbml0ZWQgU3RhdGVz
+MRMwEQYDVQQKEwpDdW11bG9jaXR5MRMwEQYDVQQDEwptYW5hZ2VtZW50MB4XDTI1
+MDIyNTE0NDU0MloXDTI2MDIyNDA5NDE0NFowRjEaMBgGA1UEAwwRZGlkaWVyLWRl
+dmljZS0wMDExEjAQBgNVBAoMCVRoaW4gRWRnZTEUMBIGA1UECwwLVGVzdCBEZXZp
+Y2UwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAATwSjNE/7AJZEtrXW2CP2LSLlcl
+wDyh4YwHmpwDhnTCm+ZxeeXBUcUARcFXOtdmxMset9CgMQl1Fjw255dISpqiMAoG
+CCqGSM49BAMCA0cAMEQCICapYBWyzrDU36IVEtyOfdlDA0bW9HE3pwHz2X9LAgl1
+AiAD0naayxieH0RVE1vJtdD3iCJHrzLNM3Eff2gNOhuzJAAAMQAAAAAAAAA=";
+        let pk7_der = BASE64_STANDARD.decode(pk7.replace('\n', "")).unwrap();
+
+        assert_eq!(
+            pk7_body_to_x509(&pk7_der).unwrap(),
+            pk7_body_to_x509(pk7.as_bytes()).unwrap()
+        );
+    }
+
+    #[test]
+    fn csr_are_sent_as_base64_der() {
+        let csr = r#"
+Self signature: verified
+
+-----BEGIN CERTIFICATE REQUEST-----
+MIIBKTCB0AIBADA8MRIwEAYDVQQKEwlUaGluIEVkZ2UxFDASBgNVBAsTC1Rlc3Qg
+RGV2aWNlMRAwDgYDVQQDEwd0ZXN0MDAxMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcD
+QgAE0xjSVOQc2NA4RgGz4IksOc4Js48jTCD2uE1N4RoetahPhKnfv5f19o2B9B4T
+cUttQFLpQIG65YS2OAzkkM+0P6AyMDAGCSqGSIb3DQEJDjEjMCEwDwYDVR0TAQH/
+BAUwAwEB/zAOBgNVHQ8BAf8EBAMCB4AwCgYIKoZIzj0EAwIDSAAwRQIhALxYCCHa
+9ZdaZCd7YhhWmVcq+/KSLPK/PUvfV83PDy5TAiAA/e9yrH6rrLGhkhEPtTbyBbBe
+yzaWmqSb64bH/x0TjQ==
+-----END CERTIFICATE REQUEST-----
+"#;
+
+        let expected = "MIIBKTCB0AIBADA8MRIwEAYDVQQKEwlUaGluIEVkZ2UxFDASBgNVBAsTC1Rlc3QgRGV2aWNlMRAwDgYDVQQDEwd0ZXN0MDAxMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAE0xjSVOQc2NA4RgGz4IksOc4Js48jTCD2uE1N4RoetahPhKnfv5f19o2B9B4TcUttQFLpQIG65YS2OAzkkM+0P6AyMDAGCSqGSIb3DQEJDjEjMCEwDwYDVR0TAQH/BAUwAwEB/zAOBgNVHQ8BAf8EBAMCB4AwCgYIKoZIzj0EAwIDSAAwRQIhALxYCCHa9ZdaZCd7YhhWmVcq+/KSLPK/PUvfV83PDy5TAiAA/e9yrH6rrLGhkhEPtTbyBbBeyzaWmqSb64bH/x0TjQ==";
+        assert_eq!(csr_to_base64(csr).unwrap(), expected);
+    }
+
+    const PK7_CERT: &str = "MIAGCSqGSIb3DQEHAqCAMIACAQExADALBgkqhkiG9w0BBwGggDCCAXkwggEgoAMC
+AQICBgGVPZIizTAKBggqhkjOPQQDAjBCMRYwFAYDVQQGEw1Vbml0ZWQgU3RhdGVz
+MRMwEQYDVQQKEwpDdW11bG9jaXR5MRMwEQYDVQQDEwptYW5hZ2VtZW50MB4XDTI1
+MDIyNTE0NDU0MloXDTI2MDIyNDA5NDE0NFowRjEaMBgGA1UEAwwRZGlkaWVyLWRl
+dmljZS0wMDExEjAQBgNVBAoMCVRoaW4gRWRnZTEUMBIGA1UECwwLVGVzdCBEZXZp
+Y2UwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAATwSjNE/7AJZEtrXW2CP2LSLlcl
+wDyh4YwHmpwDhnTCm+ZxeeXBUcUARcFXOtdmxMset9CgMQl1Fjw255dISpqiMAoG
+CCqGSM49BAMCA0cAMEQCICapYBWyzrDU36IVEtyOfdlDA0bW9HE3pwHz2X9LAgl1
+AiAD0naayxieH0RVE1vJtdD3iCJHrzLNM3Eff2gNOhuzJAAAMQAAAAAAAAA=";
+
+    const CSR: &str = "-----BEGIN CERTIFICATE REQUEST-----
+MIIBKTCB0AIBADA8MRIwEAYDVQQKEwlUaGluIEVkZ2UxFDASBgNVBAsTC1Rlc3Qg
+RGV2aWNlMRAwDgYDVQQDEwd0ZXN0MDAxMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcD
+QgAE0xjSVOQc2NA4RgGz4IksOc4Js48jTCD2uE1N4RoetahPhKnfv5f19o2B9B4T
+cUttQFLpQIG65YS2OAzkkM+0P6AyMDAGCSqGSIb3DQEJDjEjMCEwDwYDVR0TAQH/
+BAUwAwEB/zAOBgNVHQ8BAf8EBAMCB4AwCgYIKoZIzj0EAwIDSAAwRQIhALxYCCHa
+9ZdaZCd7YhhWmVcq+/KSLPK/PUvfV83PDy5TAiAA/e9yrH6rrLGhkhEPtTbyBbBe
+yzaWmqSb64bH/x0TjQ==
+-----END CERTIFICATE REQUEST-----
+";
+
+    fn est_client(server: &mockito::ServerGuard) -> EstClient {
+        #[allow(clippy::disallowed_methods)]
+        let http = reqwest::Client::new();
+        EstClient::new(format!("{}/.well-known/est", server.url()), http)
+            .with_basic_auth("test", "test")
+    }
+
+    #[tokio::test]
+    async fn simple_enroll_returns_the_signed_certificate() {
+        let mut server = mockito::Server::new_async().await;
+        let _enroll = server
+            .mock("POST", "/.well-known/est/simpleenroll")
+            .match_header("authorization", "Basic dGVzdDp0ZXN0") // Base64 encoded test:test
+            .match_header("content-type", "application/pkcs10")
+            .match_body(csr_to_base64(CSR).unwrap().as_str())
+            .with_header(
+                "content-type",
+                "application/pkcs7-mime; smime-type=certs-only",
+            )
+            .with_body(PK7_CERT)
+            .create_async()
+            .await;
+
+        let cert = est_client(&server).simple_enroll(CSR).await.unwrap();
+
+        let cert = PemCertificate::from_pem_string(&cert).unwrap();
+        assert_eq!(
+            cert.subject().unwrap(),
+            "CN=didier-device-001, O=Thin Edge, OU=Test Device".to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn simple_reenroll_returns_the_signed_certificate() {
+        let mut server = mockito::Server::new_async().await;
+        let _reenroll = server
+            .mock("POST", "/.well-known/est/simplereenroll")
+            .match_header("content-type", "application/pkcs10")
+            .match_body(csr_to_base64(CSR).unwrap().as_str())
+            .with_body(PK7_CERT)
+            .create_async()
+            .await;
+
+        let cert = est_client(&server).simple_reenroll(CSR).await.unwrap();
+
+        let cert = PemCertificate::from_pem_string(&cert).unwrap();
+        assert_eq!(
+            cert.thumbprint().unwrap(),
+            "9C68C7EC9A860366FB8D2697C53B2543D9EA525C".to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn enrollment_rejected_by_the_server() {
+        let mut server = mockito::Server::new_async().await;
+        let _enroll = server
+            .mock("POST", "/.well-known/est/simpleenroll")
+            .with_status(401)
+            .with_body("Unauthorized\n")
+            .create_async()
+            .await;
+        let _reenroll = server
+            .mock("POST", "/.well-known/est/simplereenroll")
+            .with_status(401)
+            .with_body("Unauthorized\n")
+            .create_async()
+            .await;
+
+        let client = est_client(&server);
+        for result in [
+            client.simple_enroll(CSR).await,
+            client.simple_reenroll(CSR).await,
+        ] {
+            assert_matches!(
+                result,
+                Err(EstError::Rejected { status, reason, .. })
+                    if status == StatusCode::UNAUTHORIZED && reason == "Unauthorized"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn enrollment_response_not_in_pkcs7() {
+        let mut server = mockito::Server::new_async().await;
+        let _enroll = server
+            .mock("POST", "/.well-known/est/simpleenroll")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"certificate":"not a pkcs#7 content"}"#)
+            .create_async()
+            .await;
+        let _reenroll = server
+            .mock("POST", "/.well-known/est/simplereenroll")
+            .with_body(CSR)
+            .create_async()
+            .await;
+
+        let client = est_client(&server);
+        assert_matches!(
+            client.simple_enroll(CSR).await,
+            Err(EstError::IllFormedResponse(_))
+        );
+        assert_matches!(
+            client.simple_reenroll(CSR).await,
+            Err(EstError::IllFormedResponse(_))
+        );
+    }
+
+    #[test]
+    fn est_base_url() {
+        assert_eq!(
+            EstClient::base_url("est.example.com:8443", None),
+            "https://est.example.com:8443/.well-known/est"
+        );
+        assert_eq!(
+            EstClient::base_url("est.example.com", Some("devices")),
+            "https://est.example.com/.well-known/est/devices"
+        );
+    }
+}
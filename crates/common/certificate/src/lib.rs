@@ -8,6 +8,10 @@ use sha1::Sha1;
 use std::path::Path;
 use std::path::PathBuf;
 use tedge_p11_server::service::ChooseSchemeRequest;
+use tedge_p11_server::service::CreateCsrRequest;
+pub use tedge_p11_server::service::GenerateKeyRequest;
+pub use tedge_p11_server::service::GenerateKeyResponse;
+pub use tedge_p11_server::service::KeyTypeRequest;
 use tedge_p11_server::CryptokiConfig;
 use time::Duration;
 use time::OffsetDateTime;
@@ -17,6 +21,8 @@ pub use zeroize::Zeroizing;
 mod cloud_root_certificate;
 #[cfg(feature = "reqwest")]
 pub use cloud_root_certificate::*;
+#[cfg(feature = "reqwest")]
+pub mod est;
 
 pub mod device_id;
 pub mod parse_root_certificate;
@@ -233,6 +239,37 @@ impl KeyKind {
     }
 }
 
+/// Generate a new key pair on a PKCS#11 token
+///
+/// The private key is created as non-extractable and never leaves the token.
+pub fn generate_cryptoki_key(
+    cryptoki_config: CryptokiConfig,
+    request: GenerateKeyRequest,
+) -> Result<GenerateKeyResponse, CertificateError> {
+    let cryptoki = tedge_p11_server::tedge_p11_service(cryptoki_config)?;
+    Ok(cryptoki.generate_key(request)?)
+}
+
+/// Create a certificate signing request for a key pair stored on a PKCS#11 token
+///
+/// The CSR is built and signed by the PKCS#11 service,
+/// using the private key and the public key found on the token.
+pub fn new_cryptoki_certificate_sign_request(
+    cryptoki_config: CryptokiConfig,
+    config: &CsrTemplate,
+    id: &str,
+) -> Result<String, CertificateError> {
+    KeyCertPair::check_identifier(id, config.max_cn_size)?;
+    let cryptoki = tedge_p11_server::tedge_p11_service(cryptoki_config)?;
+    let csr = cryptoki.create_csr(CreateCsrRequest {
+        common_name: id.to_string(),
+        organization_name: config.organization_name.clone(),
+        organizational_unit_name: config.organizational_unit_name.clone(),
+        uri: None,
+    })?;
+    Ok(csr.0)
+}
+
 /// A key pair using a remote private key.
 ///
 /// To generate a CSR we need:
@@ -30,6 +30,23 @@ pub fn create_tls_config(
         .with_client_auth_cert(cert_chain, pvt_key)?)
 }
 
+/// Check that the private key matches the public key of the client certificate
+///
+/// This is the check done by rustls when the device connects to the cloud,
+/// so a mismatch is reported before any connection attempt.
+pub fn check_client_auth_key(
+    client_private_key: impl AsRef<Path>,
+    client_certificate: impl AsRef<Path>,
+) -> Result<(), CertificateError> {
+    let pvt_key = read_pvt_key(client_private_key)?;
+    let cert_chain = read_cert_chain(client_certificate)?;
+
+    ClientConfig::builder()
+        .with_root_certificates(RootCertStore::empty())
+        .with_client_auth_cert(cert_chain, pvt_key)?;
+    Ok(())
+}
+
 /// Create a TLS ClientConfig that uses a PKCS#11 device for client authentication.
 ///
 /// This TLS configuration should be used for communication between a device (or bridge) and a cloud
@@ -237,9 +254,41 @@ pub fn read_cert_chain(
 mod tests {
     use super::*;
     use std::io::Write;
+    use std::path::PathBuf;
     use tempfile::NamedTempFile;
     use tempfile::TempDir;
 
+    #[test]
+    fn matching_key_and_certificate_are_accepted() {
+        let dir = TempDir::new().unwrap();
+        let (cert, key) = write_key_pair(&dir, "device");
+
+        check_client_auth_key(key, cert).unwrap();
+    }
+
+    #[test]
+    fn key_not_matching_certificate_is_rejected() {
+        let dir = TempDir::new().unwrap();
+        let (cert, _) = write_key_pair(&dir, "device");
+        let (_, other_key) = write_key_pair(&dir, "other");
+
+        let err = check_client_auth_key(other_key, cert).unwrap_err();
+        assert!(matches!(err, CertificateError::CertParse(_)), "{err:?}");
+    }
+
+    fn write_key_pair(dir: &TempDir, name: &str) -> (PathBuf, PathBuf) {
+        let key_pair = rcgen::KeyPair::generate().unwrap();
+        let cert = rcgen::CertificateParams::new(vec![name.to_string()])
+            .unwrap()
+            .self_signed(&key_pair)
+            .unwrap();
+        let cert_path = dir.path().join(format!("{name}-cert.pem"));
+        let key_path = dir.path().join(format!("{name}-key.pem"));
+        fs::write(&cert_path, cert.pem()).unwrap();
+        fs::write(&key_path, key_pair.serialize_pem()).unwrap();
+        (cert_path, key_path)
+    }
+
     #[test]
     fn parse_supported_key() {
         let key = concat!(
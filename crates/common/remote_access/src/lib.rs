@@ -0,0 +1,9 @@
+//! Tunnelling of TCP connections over websockets
+//!
+//! A connection is opened to a local TCP socket, typically an SSH or VNC server,
+//! and to a websocket served by a remote access service, data being then copied in both directions.
+//! This is used by the Cumulocity remote access plugin as well as by the cloud-agnostic `remote_access` command of the agent.
+
+mod proxy;
+
+pub use proxy::WebsocketSocketProxy;
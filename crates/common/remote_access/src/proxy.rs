@@ -1,4 +1,3 @@
-use crate::auth::Auth;
 use async_compat::CompatExt;
 use async_http_proxy::http_connect_tokio;
 use async_http_proxy::http_connect_tokio_with_basic_auth;
@@ -30,10 +29,8 @@ use tokio_rustls::TlsConnector;
 use url::Url;
 use ws_stream_tungstenite::WsStream;
 
-use crate::SUCCESS_MESSAGE;
-
-/// This proxy creates a TCP connection to a local socket and creates a websocket. Cumulocity cloud will initiate a
-/// connection to the websocket. Any data received from the socket is sent out via the websocket and any data received
+/// This proxy creates a TCP connection to a local socket and creates a websocket. The remote access service
+/// (e.g. Cumulocity cloud) will initiate a connection to the websocket. Any data received from the socket is sent out via the websocket and any data received
 /// from the websocket is sent to the local socket.
 pub struct WebsocketSocketProxy {
     socket: TcpStream,
@@ -45,26 +42,27 @@ pub struct WebsocketSocketProxy {
 struct SocketError(#[from] std::io::Error);
 
 impl WebsocketSocketProxy {
+    /// Connect both ends of the tunnel
+    ///
+    /// The `authorization` header, if any, is sent along the websocket upgrade request.
     pub async fn connect<SA: ToSocketAddrs + std::fmt::Debug>(
         url: &Url,
         socket: SA,
-        auth: Auth,
+        authorization: Option<HeaderValue>,
         config: Option<ClientConfig>,
         proxy: &TEdgeConfigReaderProxy,
     ) -> miette::Result<Self> {
         let socket_future = TcpStream::connect(socket);
-        let websocket_future = Websocket::new(url, auth.authorization_header(), config, proxy);
+        let websocket_future = Websocket::new(url, authorization, config, proxy);
 
         match join(socket_future, websocket_future).await {
             (Err(socket_error), _) => Err(SocketError(socket_error))?,
             (_, Err(websocket_error)) => Err(websocket_error),
-            (Ok(socket), Ok(websocket)) => {
-                println!("{SUCCESS_MESSAGE}");
-                Ok(WebsocketSocketProxy { socket, websocket })
-            }
+            (Ok(socket), Ok(websocket)) => Ok(WebsocketSocketProxy { socket, websocket }),
         }
     }
 
+    /// Copy the data in both directions, until either end of the tunnel is closed
     pub async fn run(mut self) {
         let (mut ws_reader, mut ws_writer) = self.websocket.socket.split();
         let (mut reader, mut writer) = self.socket.split();
@@ -77,7 +75,6 @@ impl WebsocketSocketProxy {
 
             select(incoming, outgoing).await;
         }
-        println!("STOPPING");
         let _ = join(ws_writer.close(), writer.close()).await;
     }
 }
@@ -147,7 +144,7 @@ impl AsyncWrite for MaybeTlsStream {
 impl Websocket {
     async fn new(
         url: &Url,
-        authorization: HeaderValue,
+        authorization: Option<HeaderValue>,
         config: Option<ClientConfig>,
         proxy: &TEdgeConfigReaderProxy,
     ) -> miette::Result<Self> {
@@ -199,8 +196,11 @@ impl Websocket {
                     .into_diagnostic()?,
             )
         };
-        let request = http::Request::builder()
-            .header("Authorization", authorization)
+        let mut request = http::Request::builder();
+        if let Some(authorization) = authorization {
+            request = request.header("Authorization", authorization);
+        }
+        let request = request
             .header("Sec-WebSocket-Key", generate_sec_websocket_key())
             .header("Host", url.host_str().unwrap())
             .header("Connection", "Upgrade")
@@ -336,7 +336,7 @@ mod tests {
         let proxy = WebsocketSocketProxy::connect(
             &format!("ws://127.0.0.1:{axum_port}/ws").parse().unwrap(),
             format!("127.0.0.1:{target_port}"),
-            Auth::test_value(HeaderValue::from_static("AUTHORIZATION HEADER")),
+            Some(HeaderValue::from_static("AUTHORIZATION HEADER")),
             None,
             &tedge_config.proxy,
         )
@@ -391,7 +391,7 @@ mod tests {
             let proxy = WebsocketSocketProxy::connect(
                 &format!("ws://127.0.0.1:{axum_port}/ws").parse().unwrap(),
                 format!("127.0.0.1:{target_port}"),
-                Auth::test_value(HeaderValue::from_static("AUTHORIZATION HEADER")),
+                Some(HeaderValue::from_static("AUTHORIZATION HEADER")),
                 None,
                 &tedge_config.proxy,
             )
@@ -37,6 +37,17 @@ impl TEdgeConfig {
         self.location().update_toml(update).await
     }
 
+    /// Tell which configuration layer sets the value of the given key
+    ///
+    /// The layers are the `TEDGE_*` environment variables, `tedge.toml`
+    /// and the drop-in files of the `tedge.toml.d` directory.
+    pub async fn origin_of(
+        &self,
+        key: &tedge_toml::ReadableKey,
+    ) -> Result<ConfigOrigin, TEdgeConfigError> {
+        self.location().origin_of(&key.to_cow_str()).await
+    }
+
     #[cfg(feature = "test")]
     /// A test only method designed for injecting configuration into tests
     ///
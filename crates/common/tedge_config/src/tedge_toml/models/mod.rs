@@ -12,6 +12,7 @@ pub mod port;
 pub mod proxy_scheme;
 pub mod proxy_url;
 pub mod seconds;
+pub mod string_list;
 pub mod templates_set;
 pub mod topic_prefix;
 
@@ -38,6 +39,7 @@ pub use self::ipaddress::*;
 pub use self::path::*;
 pub use self::port::*;
 pub use self::seconds::*;
+pub use self::string_list::*;
 pub use self::templates_set::*;
 pub use tedge_utils::timestamp;
 pub use tedge_utils::timestamp::TimeFormat;
@@ -75,6 +77,39 @@ impl FromStr for AutoLogUpload {
     }
 }
 
+/// How tedge-watchdog handles unresponsive services
+#[derive(
+    Debug, Display, Clone, Copy, Eq, PartialEq, doku::Document, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum WatchdogMode {
+    /// Notify systemd on behalf of the services, systemd restarting the services missing `WatchdogSec`
+    Systemd,
+    /// Restart the services using the service manager configured in `system.toml`
+    Restart,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Failed to parse flag: {input}. Supported values are: 'systemd' or 'restart'")]
+pub struct InvalidWatchdogMode {
+    input: String,
+}
+
+impl FromStr for WatchdogMode {
+    type Err = InvalidWatchdogMode;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "systemd" => Ok(WatchdogMode::Systemd),
+            "restart" => Ok(WatchdogMode::Restart),
+            _ => Err(InvalidWatchdogMode {
+                input: input.to_string(),
+            }),
+        }
+    }
+}
+
 pub const MQTT_MAX_PAYLOAD_SIZE: u32 = 268435455;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, Document)]
@@ -49,7 +49,13 @@ impl SecondsOrHumanTime {
 
 impl doku::Document for SecondsOrHumanTime {
     fn ty() -> doku::Type {
-        String::ty()
+        let mut ty = String::ty();
+        // Either a number of seconds or a human readable duration, e.g. "1h"
+        ty.metas.add(
+            tedge_config_macros::JSON_SCHEMA_META,
+            r#"{"type": ["integer", "string"], "minimum": 0}"#,
+        );
+        ty
     }
 }
 
@@ -0,0 +1,94 @@
+use std::convert::Infallible;
+use std::str::FromStr;
+
+/// A list of strings, given either as a TOML array or as a comma-separated string.
+///
+/// Unlike [TemplatesSet](super::TemplatesSet), the entries are kept in the given order.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
+#[serde(from = "FromTomlOrCli")]
+pub struct StringList(pub Vec<String>);
+
+impl doku::Document for StringList {
+    fn ty() -> doku::Type {
+        Vec::<String>::ty()
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum FromTomlOrCli {
+    Toml(Vec<String>),
+    Cli(String),
+}
+
+impl From<FromTomlOrCli> for StringList {
+    fn from(value: FromTomlOrCli) -> Self {
+        match value {
+            FromTomlOrCli::Toml(entries) => Self(entries),
+            FromTomlOrCli::Cli(entries) => Self::from(entries.as_str()),
+        }
+    }
+}
+
+impl From<Vec<String>> for StringList {
+    fn from(value: Vec<String>) -> Self {
+        StringList(value)
+    }
+}
+
+impl<'a> From<&'a str> for StringList {
+    fn from(value: &'a str) -> Self {
+        StringList(
+            value
+                .split(',')
+                .map(|s| s.trim().to_owned())
+                .filter(|s| !s.is_empty())
+                .collect(),
+        )
+    }
+}
+
+impl FromStr for StringList {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(value))
+    }
+}
+
+impl std::fmt::Display for StringList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl StringList {
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_comma_separated_entries() {
+        let list: StringList = " cpu, memory,,disk ".parse().unwrap();
+        assert_eq!(list.0, vec!["cpu", "memory", "disk"]);
+    }
+
+    #[test]
+    fn deserialize_toml_array_or_string() {
+        #[derive(serde::Deserialize)]
+        struct Config {
+            list: StringList,
+        }
+
+        let config: Config = toml::from_str(r#"list = ["/", "/data"]"#).unwrap();
+        assert_eq!(config.list.0, vec!["/", "/data"]);
+
+        let config: Config = toml::from_str(r#"list = "/,/data""#).unwrap();
+        assert_eq!(config.list.0, vec!["/", "/data"]);
+    }
+}
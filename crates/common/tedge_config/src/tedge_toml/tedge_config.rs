@@ -17,8 +17,10 @@ use super::models::HostPort;
 use super::models::MqttPayloadLimit;
 use super::models::SecondsOrHumanTime;
 use super::models::SoftwareManagementApiFlag;
+use super::models::StringList;
 use super::models::TemplatesSet;
 use super::models::TopicPrefix;
+use super::models::WatchdogMode;
 use super::models::HTTPS_PORT;
 use super::models::MQTT_SVC_TLS_PORT;
 use super::models::MQTT_TLS_PORT;
@@ -28,6 +30,7 @@ use anyhow::anyhow;
 use anyhow::Context;
 use camino::Utf8Path;
 use camino::Utf8PathBuf;
+use certificate::est::EstClient;
 use certificate::parse_root_certificate::client_config_for_ca_certificates;
 use certificate::parse_root_certificate::create_tls_config;
 use certificate::parse_root_certificate::create_tls_config_without_client_cert;
@@ -101,6 +104,11 @@ impl TEdgeConfig {
     pub fn root_dir(&self) -> &Utf8Path {
         self.location.tedge_config_root_path()
     }
+
+    /// The directory of the drop-in configuration files, merged beneath `tedge.toml`
+    pub fn drop_in_dir(&self) -> Utf8PathBuf {
+        self.location.drop_in_dir()
+    }
 }
 
 /// The keys that can be read from the configuration
@@ -270,6 +278,28 @@ define_tedge_config! {
             key_uri: Arc<str>,
         },
 
+        est: {
+            /// EST (RFC 7030) server used to enroll and renew the device certificate, with optional port
+            #[tedge_config(example = "est.example.com:8443")]
+            url: HostPort<HTTPS_PORT>,
+
+            /// Optional CA label, added to the EST path as in `/.well-known/est/<label>/simpleenroll`
+            #[tedge_config(example = "devices")]
+            label: Arc<str>,
+
+            /// Path to a TOML file with the username and password used to authenticate to the EST server
+            #[tedge_config(example = "/etc/tedge/est-credentials.toml")]
+            credentials_path: AbsolutePath,
+
+            /// Path to a bootstrap certificate used to authenticate the initial enrollment
+            #[tedge_config(example = "/etc/tedge/device-certs/bootstrap-certificate.pem")]
+            bootstrap_cert_path: AbsolutePath,
+
+            /// Path to the private key of the bootstrap certificate
+            #[tedge_config(example = "/etc/tedge/device-certs/bootstrap-key.pem")]
+            bootstrap_key_path: AbsolutePath,
+        },
+
         smartrest: {
             /// Set of SmartREST template IDs the device should subscribe to
             #[tedge_config(example = "templateId1,templateId2", default(function = "TemplatesSet::default"))]
@@ -374,6 +404,29 @@ define_tedge_config! {
             /// trusted when checking incoming client certificates for the Cumulocity Proxy
             #[tedge_config(example = "/etc/ssl/certs")]
             ca_path: AbsolutePath,
+
+            /// Path to a TOML file restricting the requests local clients can make using the device identity.
+            /// When not set, any local client can use the proxy without restriction.
+            #[tedge_config(example = "/etc/tedge/c8y-proxy-policy.toml")]
+            policy_path: AbsolutePath,
+
+            cache: {
+                /// Whether the Cumulocity proxy caches responses and queues requests, to be used when Cumulocity cannot be reached
+                #[tedge_config(example = "true", default(value = false))]
+                enable: bool,
+
+                /// The directory where the Cumulocity proxy caches responses and queues requests
+                #[tedge_config(example = "/var/tedge/c8y-proxy-cache", default(from_str = "/var/tedge/c8y-proxy-cache"))]
+                path: AbsolutePath,
+
+                /// The maximum size in bytes of the responses cached by the Cumulocity proxy
+                #[tedge_config(example = "104857600", default(value = 104857600u64))]
+                max_size: u64,
+
+                /// The maximum size in bytes of the requests queued by the Cumulocity proxy while Cumulocity cannot be reached
+                #[tedge_config(example = "10485760", default(value = 10485760u64))]
+                queue_max_size: u64,
+            },
         },
 
         bridge: {
@@ -498,6 +551,46 @@ define_tedge_config! {
             key_uri: Arc<str>,
         },
 
+        est: {
+            /// EST (RFC 7030) server used to enroll and renew the device certificate, with optional port
+            #[tedge_config(example = "est.example.com:8443")]
+            url: HostPort<HTTPS_PORT>,
+
+            /// Optional CA label, added to the EST path as in `/.well-known/est/<label>/simpleenroll`
+            #[tedge_config(example = "devices")]
+            label: Arc<str>,
+
+            /// Path to a TOML file with the username and password used to authenticate to the EST server
+            #[tedge_config(example = "/etc/tedge/est-credentials.toml")]
+            credentials_path: AbsolutePath,
+
+            /// Path to a bootstrap certificate used to authenticate the initial enrollment
+            #[tedge_config(example = "/etc/tedge/device-certs/bootstrap-certificate.pem")]
+            bootstrap_cert_path: AbsolutePath,
+
+            /// Path to the private key of the bootstrap certificate
+            #[tedge_config(example = "/etc/tedge/device-certs/bootstrap-key.pem")]
+            bootstrap_key_path: AbsolutePath,
+        },
+
+        dps: {
+            /// ID scope of the Azure Device Provisioning Service (DPS) instance used to provision the device.
+            ///
+            /// When set and `az.url` is not, `tedge connect az` registers the device with DPS
+            /// and stores the assigned IoT Hub and device id in the profile configuration.
+            #[tedge_config(example = "0ne00000000")]
+            id_scope: String,
+
+            /// MQTT endpoint of the Azure Device Provisioning Service, with optional port
+            #[tedge_config(example = "global.azure-devices-provisioning.net")]
+            #[tedge_config(default(from_str = "global.azure-devices-provisioning.net"))]
+            url: HostPort<MQTT_TLS_PORT>,
+
+            /// Registration id of the device in the DPS enrollment, the device id by default
+            #[tedge_config(example = "my-device")]
+            registration_id: String,
+        },
+
         mapper: {
             /// Whether the Azure IoT mapper should add a timestamp or not
             #[tedge_config(example = "true")]
@@ -574,6 +667,28 @@ define_tedge_config! {
             key_uri: Arc<str>,
         },
 
+        est: {
+            /// EST (RFC 7030) server used to enroll and renew the device certificate, with optional port
+            #[tedge_config(example = "est.example.com:8443")]
+            url: HostPort<HTTPS_PORT>,
+
+            /// Optional CA label, added to the EST path as in `/.well-known/est/<label>/simpleenroll`
+            #[tedge_config(example = "devices")]
+            label: Arc<str>,
+
+            /// Path to a TOML file with the username and password used to authenticate to the EST server
+            #[tedge_config(example = "/etc/tedge/est-credentials.toml")]
+            credentials_path: AbsolutePath,
+
+            /// Path to a bootstrap certificate used to authenticate the initial enrollment
+            #[tedge_config(example = "/etc/tedge/device-certs/bootstrap-certificate.pem")]
+            bootstrap_cert_path: AbsolutePath,
+
+            /// Path to the private key of the bootstrap certificate
+            #[tedge_config(example = "/etc/tedge/device-certs/bootstrap-key.pem")]
+            bootstrap_key_path: AbsolutePath,
+        },
+
         mapper: {
             /// Whether the AWS IoT mapper should add a timestamp or not
             #[tedge_config(example = "true")]
@@ -798,6 +913,14 @@ define_tedge_config! {
             /// Determines if tedge-agent should enable log_upload operation
             #[tedge_config(example = "true", default(value = true))]
             log_upload: bool,
+
+            /// Determines if tedge-agent should enable diag_collect operation, running the `tedge diag` plugins
+            #[tedge_config(example = "true", default(value = true))]
+            diag_collect: bool,
+
+            /// Determines if tedge-agent should enable remote_access operation, opening TCP tunnels over websockets
+            #[tedge_config(example = "true", default(value = false))]
+            remote_access: bool,
         },
 
         entity_store: {
@@ -810,6 +933,81 @@ define_tedge_config! {
             clean_start: bool,
         },
 
+        inventory: {
+            /// Interval at which the inventory collectors are run (in seconds if no unit is provided). The collectors are only run on start-up if set to 0
+            #[tedge_config(example = "1h", default(from_str = "1h"))]
+            interval: SecondsOrHumanTime,
+
+            /// Determines if tedge-agent should run the builtin inventory collectors (OS release, kernel, CPU, memory, disks and network interfaces)
+            #[tedge_config(example = "true", default(value = true))]
+            builtin_collectors: bool,
+        },
+
+        cert_renewal: {
+            /// Determines if tedge-agent should periodically check the device certificates and renew those about to expire
+            #[tedge_config(note = "A certificate is renewed when valid for less than certificate.validity.minimum_duration")]
+            #[tedge_config(example = "true", default(value = false))]
+            enable: bool,
+
+            /// Interval at which the device certificates are checked (in seconds if no unit is provided)
+            #[tedge_config(example = "1h", default(from_str = "1h"))]
+            interval: SecondsOrHumanTime,
+        },
+
+        remote_access: {
+            /// The `host:port` targets to which remote access tunnels can be opened, any other target being rejected
+            #[tedge_config(example = "127.0.0.1:22,127.0.0.1:8080", default(value = "127.0.0.1:22"))]
+            allowed_targets: StringList,
+        },
+
+        resources: {
+            /// Determines if tedge-agent should publish the device resource usage (CPU, memory, disks, network) as measurements
+            #[tedge_config(example = "true", default(value = false))]
+            enable: bool,
+
+            /// Interval at which the device resource usage is sampled (in seconds if no unit is provided). Must be greater than zero
+            #[tedge_config(example = "60s", default(from_str = "60s"))]
+            interval: SecondsOrHumanTime,
+
+            /// The groups of metrics published by the resource monitor, among cpu, load, memory, disk and network
+            #[tedge_config(example = "cpu,memory", default(value = "cpu,load,memory,disk,network"))]
+            groups: StringList,
+
+            /// The mount points which disk usage is published
+            #[tedge_config(example = "/,/data", default(value = "/"))]
+            disks: StringList,
+        },
+
+        processes: {
+            /// Determines if tedge-agent should monitor the processes of the services registered with a `@process` property,
+            /// publishing their resource usage as measurements and a `down` health status when not running
+            #[tedge_config(example = "true", default(value = false))]
+            enable: bool,
+
+            /// Interval at which the service processes are sampled (in seconds if no unit is provided). Must be greater than zero
+            #[tedge_config(example = "60s", default(from_str = "60s"))]
+            interval: SecondsOrHumanTime,
+        },
+
+        health_history: {
+            /// The maximum number of health status changes recorded on disk for each service
+            #[tedge_config(example = "100", default(value = 100u32))]
+            max_entries: u32,
+
+            /// Interval at which the uptime statistics of the services are published as `health` measurements (in seconds if no unit is provided). The statistics are not published if set to 0
+            #[tedge_config(example = "5m", default(from_str = "5m"))]
+            interval: SecondsOrHumanTime,
+
+            flapping: {
+                /// Number of times a service can go down within `agent.health_history.flapping.window` before a `health_flapping` alarm is raised. No alarm is raised if set to 0
+                #[tedge_config(example = "5", default(value = 5u32))]
+                threshold: u32,
+
+                /// Time window over which the down transitions of a service are counted to detect flapping (in seconds if no unit is provided)
+                #[tedge_config(example = "10m", default(from_str = "10m"))]
+                window: SecondsOrHumanTime,
+            },
+        },
 
     },
 
@@ -885,6 +1083,13 @@ define_tedge_config! {
         #[tedge_config(example = "unix")]
         #[tedge_config(default(variable = "TimeFormat::Unix"))]
         timestamp_format: TimeFormat,
+
+        metrics: {
+            /// The interval at which the thin-edge.io services publish the metrics of their actors
+            #[tedge_config(note = "If set to 0, the metrics are only published on demand")]
+            #[tedge_config(example = "60s", default(from_str = "0s"))]
+            interval: SecondsOrHumanTime,
+        },
     },
 
     apt: {
@@ -907,6 +1112,22 @@ define_tedge_config! {
         },
     },
 
+    archive: {
+        /// The directory where the software modules installed from archives by the tedge-archive-plugin are stored
+        #[tedge_config(example = "/opt/tedge/archives", default(from_str = "/opt/tedge/archives"))]
+        root: AbsolutePath,
+    },
+
+    container: {
+        /// The container engine CLI used by the tedge-container-plugin, `docker` or `podman` being used if not set
+        #[tedge_config(example = "podman", example = "/usr/local/bin/docker")]
+        engine: String,
+
+        /// The directory where the compose files of the container groups are stored by the tedge-container-plugin
+        #[tedge_config(example = "/var/tedge/container-groups", default(from_str = "/var/tedge/container-groups"))]
+        compose_dir: AbsolutePath,
+    },
+
     sudo: {
         /// Determines if thin-edge should use `sudo` when attempting to write to files possibly
         /// not owned by `tedge`.
@@ -932,6 +1153,47 @@ define_tedge_config! {
         no_proxy: String,
     },
 
+    watchdog: {
+        /// How tedge-watchdog handles unresponsive services: `systemd` notifies the systemd watchdog on behalf of the services,
+        /// `restart` lets tedge-watchdog restart them itself using the service manager configured in `system.toml`
+        #[tedge_config(example = "systemd", example = "restart", default(variable = "WatchdogMode::Systemd"))]
+        mode: WatchdogMode,
+
+        /// The interval at which tedge-watchdog checks the health of the services, when `watchdog.mode` is `restart`
+        #[tedge_config(example = "30s", default(from_str = "30s"))]
+        interval: SecondsOrHumanTime,
+
+        /// The services monitored by tedge-watchdog, when `watchdog.mode` is `restart`
+        #[tedge_config(example = "tedge-agent,tedge-mapper-c8y", default(value = "tedge-agent,tedge-mapper-c8y"))]
+        services: StringList,
+
+        restart: {
+            /// The number of consecutive health checks a service can miss before being restarted
+            #[tedge_config(example = "3", default(value = 3u32))]
+            missed_checks: u32,
+
+            /// The maximum number of restarts of a service within `watchdog.restart.window`
+            #[tedge_config(note = "When this limit is reached, the service is no more restarted and the device is rebooted if `watchdog.reboot.enable` is true")]
+            #[tedge_config(example = "3", default(value = 3u32))]
+            max_attempts: u32,
+
+            /// The period over which the restarts of a service are counted
+            #[tedge_config(example = "1h", default(from_str = "1h"))]
+            window: SecondsOrHumanTime,
+        },
+
+        reboot: {
+            /// Reboot the device when a service is still unresponsive after `watchdog.restart.max_attempts` restarts
+            #[tedge_config(example = "true", default(value = false))]
+            enable: bool,
+
+            /// The maximum number of consecutive reboots triggered by tedge-watchdog
+            #[tedge_config(note = "The count is reset once all the monitored services respond to a health check")]
+            #[tedge_config(example = "3", default(value = 3u32))]
+            max_reboots: u32,
+        },
+    },
+
     diag: {
         /// The directories where diagnostic plugins are stored
         #[tedge_config(example = "/usr/share/diag-plugins,/etc/tedge/diag-plugins", default(value = "/usr/share/tedge/diag-plugins"))]
@@ -1077,6 +1339,96 @@ pub trait CloudConfig {
     fn device_cert_path(&self) -> &Utf8Path;
     fn root_cert_path(&self) -> &Utf8Path;
     fn key_uri(&self) -> Option<Arc<str>>;
+    fn est_config(&self) -> Option<EstConfig>;
+}
+
+/// The EST server settings of a cloud profile, if any
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EstConfig {
+    pub url: HostPort<HTTPS_PORT>,
+    pub label: Option<Arc<str>>,
+    pub credentials_path: Option<Utf8PathBuf>,
+    pub bootstrap_cert_path: Option<Utf8PathBuf>,
+    pub bootstrap_key_path: Option<Utf8PathBuf>,
+}
+
+impl EstConfig {
+    /// The URL prefix of the EST operations
+    pub fn base_url(&self) -> String {
+        EstClient::base_url(&self.url, self.label.as_deref())
+    }
+
+    /// The bootstrap certificate and key used for the initial enrollment, if both are configured
+    pub fn bootstrap_cert(&self) -> Option<(&Utf8Path, &Utf8Path)> {
+        Some((
+            self.bootstrap_cert_path.as_deref()?,
+            self.bootstrap_key_path.as_deref()?,
+        ))
+    }
+
+    /// Build an EST client
+    ///
+    /// The requests are authenticated using the configured credentials, if any,
+    /// and using the given TLS client certificate and private key, if any.
+    pub async fn client(
+        &self,
+        http_config: &CloudHttpConfig,
+        client_cert: Option<(&Utf8Path, &Utf8Path)>,
+    ) -> anyhow::Result<EstClient> {
+        let mut http = http_config.client_builder();
+        if let Some((cert, key)) = client_cert {
+            let mut pem = tokio::fs::read(key)
+                .await
+                .with_context(|| format!("reading private key: {key}"))?;
+            let cert_pem = tokio::fs::read(cert)
+                .await
+                .with_context(|| format!("reading certificate: {cert}"))?;
+            pem.extend_from_slice(&cert_pem);
+            http = http.identity(reqwest::Identity::from_pem(&pem)?);
+        }
+        let client = EstClient::new(self.base_url(), http.build()?);
+
+        Ok(match self.read_credentials().await? {
+            Some((username, password)) => client.with_basic_auth(username, password),
+            None => client,
+        })
+    }
+
+    async fn read_credentials(&self) -> anyhow::Result<Option<(String, String)>> {
+        #[derive(serde::Deserialize)]
+        struct Credentials {
+            est: BasicCredentials,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct BasicCredentials {
+            username: String,
+            password: String,
+        }
+
+        let Some(path) = &self.credentials_path else {
+            return Ok(None);
+        };
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("reading EST credentials: {path}"))?;
+        let credentials: Credentials =
+            toml::from_str(&content).with_context(|| format!("parsing EST credentials: {path}"))?;
+        Ok(Some((credentials.est.username, credentials.est.password)))
+    }
+}
+
+macro_rules! est_config {
+    ($est:expr) => {{
+        let est = $est;
+        est.url.or_none().map(|url| EstConfig {
+            url: url.clone(),
+            label: est.label.or_none().cloned(),
+            credentials_path: est.credentials_path.or_none().map(|p| p.to_path_buf()),
+            bootstrap_cert_path: est.bootstrap_cert_path.or_none().map(|p| p.to_path_buf()),
+            bootstrap_key_path: est.bootstrap_key_path.or_none().map(|p| p.to_path_buf()),
+        })
+    }};
 }
 
 impl CloudConfig for TEdgeConfigReaderC8y {
@@ -1095,6 +1447,10 @@ impl CloudConfig for TEdgeConfigReaderC8y {
     fn key_uri(&self) -> Option<Arc<str>> {
         self.device.key_uri.or_none().cloned()
     }
+
+    fn est_config(&self) -> Option<EstConfig> {
+        est_config!(&self.est)
+    }
 }
 
 impl CloudConfig for TEdgeConfigReaderAz {
@@ -1113,6 +1469,10 @@ impl CloudConfig for TEdgeConfigReaderAz {
     fn key_uri(&self) -> Option<Arc<str>> {
         self.device.key_uri.or_none().cloned()
     }
+
+    fn est_config(&self) -> Option<EstConfig> {
+        est_config!(&self.est)
+    }
 }
 
 impl CloudConfig for TEdgeConfigReaderAws {
@@ -1131,6 +1491,10 @@ impl CloudConfig for TEdgeConfigReaderAws {
     fn key_uri(&self) -> Option<Arc<str>> {
         self.device.key_uri.or_none().cloned()
     }
+
+    fn est_config(&self) -> Option<EstConfig> {
+        est_config!(&self.est)
+    }
 }
 
 fn c8y_topic_prefix() -> TopicPrefix {
@@ -1490,4 +1854,53 @@ mod tests {
             "c8y.url"
         );
     }
+
+    #[test]
+    fn json_schema_describes_field_types_and_defaults() {
+        let schema = TEdgeConfigDto::json_schema();
+        let proxy_port = &schema["properties"]["c8y"]["properties"]["proxy"]["properties"]["bind"]
+            ["properties"]["port"];
+
+        assert_eq!(proxy_port["type"], "integer");
+        assert_eq!(proxy_port["default"], 8001);
+        assert_eq!(proxy_port["examples"][0], 8001);
+        assert_eq!(
+            proxy_port["description"],
+            "The port local Cumulocity HTTP proxy binds to"
+        );
+    }
+
+    #[test]
+    fn json_schema_accepts_profiles_of_multi_groups() {
+        let schema = TEdgeConfigDto::json_schema();
+        let c8y = &schema["properties"]["c8y"];
+
+        assert_eq!(
+            c8y["properties"]["profiles"]["additionalProperties"]["properties"]["url"],
+            c8y["properties"]["url"]
+        );
+    }
+
+    #[test]
+    fn json_schema_describes_custom_model_types() {
+        let schema = TEdgeConfigDto::json_schema();
+        let c8y = &schema["properties"]["c8y"]["properties"];
+
+        assert_eq!(c8y["url"]["type"], "string");
+        let interval = &c8y["availability"]["properties"]["interval"];
+        assert_eq!(interval["type"][0], "integer");
+        assert_eq!(interval["type"][1], "string");
+        assert_eq!(interval["default"], "60m");
+    }
+
+    #[test]
+    fn json_schema_includes_keys_omitted_from_the_reader() {
+        let schema = TEdgeConfigDto::json_schema();
+
+        assert_eq!(
+            schema["properties"]["config"]["properties"]["version"]["enum"][1],
+            "2"
+        );
+        assert_eq!(schema["additionalProperties"], false);
+    }
 }
@@ -50,6 +50,7 @@ impl_append_remove_for_single_value!(
     TopicPrefix,
     SoftwareManagementApiFlag,
     AutoLogUpload,
+    WatchdogMode,
     TimeFormat,
     NonZeroU16,
     SecondsOrHumanTime,
@@ -61,6 +62,35 @@ impl_append_remove_for_single_value!(
     ProxyUrl,
 );
 
+impl AppendRemoveItem for StringList {
+    type Item = StringList;
+
+    fn append(current_value: Option<Self::Item>, new_value: Self::Item) -> Option<Self::Item> {
+        if let Some(mut current_value) = current_value {
+            for value in new_value.0 {
+                if !current_value.0.contains(&value) {
+                    current_value.0.push(value);
+                }
+            }
+            Some(current_value)
+        } else {
+            Some(new_value)
+        }
+    }
+
+    fn remove(current_value: Option<Self::Item>, remove_value: Self::Item) -> Option<Self::Item> {
+        let mut current_value = current_value;
+
+        if let Some(ref mut current_value) = current_value {
+            current_value
+                .0
+                .retain(|value| !remove_value.0.contains(value));
+        }
+
+        current_value
+    }
+}
+
 impl AppendRemoveItem for TemplatesSet {
     type Item = TemplatesSet;
 
@@ -27,7 +27,7 @@ impl TryFrom<String> for TEdgeTomlVersion {
         match value.as_str() {
             "1" => Ok(Self::One),
             "2" => Ok(Self::Two),
-            _ => todo!(),
+            _ => Err(format!("unsupported tedge.toml version: {value:?}")),
         }
     }
 }
@@ -41,6 +41,17 @@ impl From<TEdgeTomlVersion> for &'static str {
     }
 }
 
+impl doku::Document for TEdgeTomlVersion {
+    fn ty() -> doku::Type {
+        let mut ty = String::ty();
+        ty.metas.add(
+            tedge_config_macros::JSON_SCHEMA_META,
+            r#"{"type": "string", "enum": ["1", "2"]}"#,
+        );
+        ty
+    }
+}
+
 impl From<TEdgeTomlVersion> for toml::Value {
     fn from(value: TEdgeTomlVersion) -> Self {
         let str: &str = value.into();
@@ -24,11 +24,14 @@ use tracing::warn;
 
 use super::tedge_config;
 use super::ParseKeyError;
+use super::ReadError;
+use super::ReadableKey;
 use super::WritableKey;
 
 const DEFAULT_TEDGE_CONFIG_PATH: &str = "/etc/tedge";
 const ENV_TEDGE_CONFIG_DIR: &str = "TEDGE_CONFIG_DIR";
 const TEDGE_CONFIG_FILE: &str = "tedge.toml";
+const TEDGE_CONFIG_DROP_IN_DIR: &str = "tedge.toml.d";
 
 /// Get the location of the configuration directory
 ///
@@ -85,8 +88,11 @@ impl TEdgeConfigLocation {
         &self,
         update: &impl Fn(&mut TEdgeConfigDto, &TEdgeConfigReader) -> ConfigSettingResult<()>,
     ) -> Result<(), TEdgeConfigError> {
+        // Only tedge.toml is updated, but the values set by the drop-in files are used to update arrays
         let mut config = self.load_dto::<FileOnly>().await?;
-        let reader = TEdgeConfigReader::from_dto(&config, self);
+        // The warnings have already been emitted when loading tedge.toml
+        let (layered_config, _warnings) = self.load_dto_with_warnings::<AllFiles>().await?;
+        let reader = TEdgeConfigReader::from_dto(&layered_config, self);
         update(&mut config, &reader)?;
 
         self.store(&config).await
@@ -96,6 +102,11 @@ impl TEdgeConfigLocation {
         &self.tedge_config_file_path
     }
 
+    /// The directory of the drop-in configuration files, merged beneath `tedge.toml`
+    pub(crate) fn drop_in_dir(&self) -> Utf8PathBuf {
+        self.tedge_config_root_path.join(TEDGE_CONFIG_DROP_IN_DIR)
+    }
+
     pub(crate) async fn load(self) -> Result<TEdgeConfig, TEdgeConfigError> {
         let dto = self.load_dto_from_toml_and_env().await?;
         debug!(
@@ -154,21 +165,27 @@ impl TEdgeConfigLocation {
                 tedge_toml_readable = false;
                 String::new()
             });
-        let toml: toml::Value = toml::de::from_str(&config)?;
-        let (mut dto, mut warnings) = deserialize_toml(toml, toml_path)?;
+        let mut toml: toml::Value = toml::de::from_str(&config)?;
+        let (mut dto, mut warnings) = deserialize_toml(toml.clone(), toml_path)?;
 
         if let Some(migrations) = dto.config.version.unwrap_or_default().migrations() {
             if tedge_toml_readable {
                 tracing::info!("Migrating tedge.toml configuration to version 2");
 
-                let toml: toml::Value = toml::de::from_str(&config)?;
-                let migrated_toml = migrations
+                toml = migrations
                     .into_iter()
                     .fold(toml, |toml, migration| migration.apply_to(toml));
 
-                self.store(&migrated_toml).await?;
+                self.store(&toml).await?;
+
+                (dto, warnings) = deserialize_toml(toml.clone(), toml_path)?;
+            }
+        }
 
-                (dto, warnings) = deserialize_toml(migrated_toml, toml_path)?;
+        if Sources::INCLUDE_DROP_INS {
+            let drop_ins = self.read_drop_ins().await?;
+            if !drop_ins.is_empty() {
+                (dto, warnings) = deserialize_layers(drop_ins, (toml_path.to_owned(), toml))?;
             }
         }
 
@@ -188,22 +205,28 @@ impl TEdgeConfigLocation {
             tedge_toml_readable = false;
             String::new()
         });
-        let toml: toml::Value = toml::de::from_str(&config)?;
-        let (mut dto, mut warnings) = deserialize_toml(toml, toml_path)?;
+        let mut toml: toml::Value = toml::de::from_str(&config)?;
+        let (mut dto, mut warnings) = deserialize_toml(toml.clone(), toml_path)?;
 
         if let Some(migrations) = dto.config.version.unwrap_or_default().migrations() {
             if !tedge_toml_readable {
                 tracing::info!("Migrating tedge.toml configuration to version 2");
 
-                let toml = toml::de::from_str(&config)?;
-                let migrated_toml = migrations
+                toml = migrations
                     .into_iter()
                     .fold(toml, |toml, migration| migration.apply_to(toml));
 
-                self.store_sync(&migrated_toml)?;
+                self.store_sync(&toml)?;
 
                 // Reload DTO to get the settings in the right place
-                (dto, warnings) = deserialize_toml(migrated_toml, toml_path)?;
+                (dto, warnings) = deserialize_toml(toml.clone(), toml_path)?;
+            }
+        }
+
+        if Sources::INCLUDE_DROP_INS {
+            let drop_ins = self.read_drop_ins_sync()?;
+            if !drop_ins.is_empty() {
+                (dto, warnings) = deserialize_layers(drop_ins, (toml_path.to_owned(), toml))?;
             }
         }
 
@@ -214,6 +237,85 @@ impl TEdgeConfigLocation {
         Ok((dto, warnings))
     }
 
+    /// Read the drop-in configuration files, in lexical order
+    async fn read_drop_ins(&self) -> Result<Vec<(Utf8PathBuf, toml::Value)>, TEdgeConfigError> {
+        let Ok(mut entries) = tokio::fs::read_dir(self.drop_in_dir()).await else {
+            return Ok(vec![]);
+        };
+        let mut paths = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(path) = drop_in_path(entry.path()) {
+                if tokio::fs::metadata(&path)
+                    .await
+                    .is_ok_and(|metadata| metadata.is_file())
+                {
+                    paths.push(path);
+                }
+            }
+        }
+        paths.sort();
+
+        let mut drop_ins = Vec::new();
+        for path in paths {
+            let content = tokio::fs::read_to_string(&path).await?;
+            drop_ins.push(parse_drop_in(path, &content)?);
+        }
+        Ok(drop_ins)
+    }
+
+    fn read_drop_ins_sync(&self) -> Result<Vec<(Utf8PathBuf, toml::Value)>, TEdgeConfigError> {
+        let Ok(entries) = std::fs::read_dir(self.drop_in_dir()) else {
+            return Ok(vec![]);
+        };
+        let mut paths = Vec::new();
+        for entry in entries {
+            if let Some(path) = drop_in_path(entry?.path()) {
+                if std::fs::metadata(&path).is_ok_and(|metadata| metadata.is_file()) {
+                    paths.push(path);
+                }
+            }
+        }
+        paths.sort();
+
+        let mut drop_ins = Vec::new();
+        for path in paths {
+            let content = std::fs::read_to_string(&path)?;
+            drop_ins.push(parse_drop_in(path, &content)?);
+        }
+        Ok(drop_ins)
+    }
+
+    /// Tell which layer, if any, sets the value of a configuration key
+    ///
+    /// The layers are, from the highest to the lowest precedence:
+    /// the `TEDGE_*` environment variables, `tedge.toml` and the drop-in files in reverse lexical order.
+    /// As when the configuration is loaded, an empty environment variable resets the key to its default value.
+    pub(crate) async fn origin_of(&self, key: &str) -> Result<ConfigOrigin, TEdgeConfigError> {
+        let env_var = format!("TEDGE_{}", key.replace('.', "_").to_ascii_uppercase());
+        match std::env::var_os(&env_var) {
+            Some(value) if value.is_empty() => return Ok(ConfigOrigin::Default),
+            Some(_) => return Ok(ConfigOrigin::Environment(env_var)),
+            None => (),
+        }
+
+        let toml_path = self.toml_path();
+        let config = tokio::fs::read_to_string(toml_path)
+            .await
+            .unwrap_or_default();
+        let toml: toml::Value = toml::de::from_str(&config)?;
+        if toml_contains_key(&toml, key) {
+            return Ok(ConfigOrigin::File(toml_path.to_owned()));
+        }
+
+        for (path, toml) in self.read_drop_ins().await?.into_iter().rev() {
+            if toml_contains_key(&toml, key) {
+                return Ok(ConfigOrigin::File(path));
+            }
+        }
+
+        Ok(ConfigOrigin::Default)
+    }
+
     async fn store<S: Serialize>(&self, config: &S) -> Result<(), TEdgeConfigError> {
         let toml = toml::to_string_pretty(&config)?;
 
@@ -267,22 +369,208 @@ impl TEdgeConfigLocation {
 }
 
 pub trait ConfigSources {
+    const INCLUDE_DROP_INS: bool;
     const INCLUDE_ENVIRONMENT: bool;
 }
 
+/// `tedge.toml`, the drop-in files and the environment variables
 #[derive(Clone, Debug)]
 pub struct FileAndEnvironment;
+
+/// `tedge.toml` and the drop-in files
+#[derive(Clone, Debug)]
+pub struct AllFiles;
+
+/// `tedge.toml` only
 #[derive(Clone, Debug)]
 pub struct FileOnly;
 
 impl ConfigSources for FileAndEnvironment {
+    const INCLUDE_DROP_INS: bool = true;
     const INCLUDE_ENVIRONMENT: bool = true;
 }
 
+impl ConfigSources for AllFiles {
+    const INCLUDE_DROP_INS: bool = true;
+    const INCLUDE_ENVIRONMENT: bool = false;
+}
+
 impl ConfigSources for FileOnly {
+    const INCLUDE_DROP_INS: bool = false;
     const INCLUDE_ENVIRONMENT: bool = false;
 }
 
+/// Where the value of a configuration key comes from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    /// The key is not set, the default value being used if any
+    Default,
+
+    /// The key is set in `tedge.toml` or in a drop-in file
+    File(Utf8PathBuf),
+
+    /// The key is set by a `TEDGE_*` environment variable
+    Environment(String),
+}
+
+impl std::fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigOrigin::Default => write!(f, "default"),
+            ConfigOrigin::File(path) => write!(f, "file:{path}"),
+            ConfigOrigin::Environment(var) => write!(f, "env:{var}"),
+        }
+    }
+}
+
+/// A problem found in a configuration file by [validate_config_file]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigFileProblem {
+    /// The key is not a known configuration key
+    UnknownKey(String),
+
+    /// The value of the key cannot be deserialized
+    InvalidValue { key: String, message: String },
+}
+
+impl std::fmt::Display for ConfigFileProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigFileProblem::UnknownKey(key) => write!(f, "Unknown configuration field {key:?}"),
+            ConfigFileProblem::InvalidValue { key, message } => {
+                write!(f, "Invalid value for {key:?}: {message}")
+            }
+        }
+    }
+}
+
+/// Check the content of a configuration file, without loading it
+///
+/// The content is checked as `tedge.toml` or a drop-in file would be, once
+/// migrated to the latest `tedge.toml` version. All the unknown keys and
+/// invalid values are reported, not only the first one. An error is returned
+/// only if the content is not valid TOML.
+pub fn validate_config_file(content: &str) -> Result<Vec<ConfigFileProblem>, TEdgeConfigError> {
+    // An invalid version is reported below, in which case the file is checked as is
+    let toml = migrate_toml(toml::de::from_str(content)?);
+
+    let mut problems = vec![];
+    if let Some(table) = toml.as_table() {
+        for (key, value) in table {
+            if !value.is_table() {
+                problems.push(ConfigFileProblem::UnknownKey(key.to_owned()));
+            }
+        }
+    }
+    for key in keys_in(&toml) {
+        if key.parse::<DtoKey>().is_err() {
+            problems.push(ConfigFileProblem::UnknownKey(key));
+        } else if let Some(value) = toml_value_at(&toml, &key) {
+            if let Err(err) = TEdgeConfigDto::deserialize(toml_with_key(&key, value)) {
+                let message = err.to_string().trim().to_owned();
+                problems.push(ConfigFileProblem::InvalidValue { key, message });
+            }
+        }
+    }
+
+    Ok(problems)
+}
+
+/// A partial `tedge.toml` could not be applied by [patch_config_file]
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigPatchError {
+    #[error("Invalid configuration patch: {}", display_problems(.0))]
+    InvalidPatch(Vec<ConfigFileProblem>),
+
+    #[error("The configuration key {0:?} cannot be updated")]
+    NotWritable(String),
+
+    #[error(transparent)]
+    Config(#[from] TEdgeConfigError),
+}
+
+fn display_problems(problems: &[ConfigFileProblem]) -> String {
+    problems
+        .iter()
+        .map(|problem| problem.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The outcome of [patch_config_file]
+#[derive(Debug)]
+pub struct PatchedConfigFile {
+    /// The new content of `tedge.toml`
+    pub content: String,
+
+    /// The keys whose value is changed by the patch
+    pub updated_keys: Vec<WritableKey>,
+}
+
+/// Apply a partial `tedge.toml` on top of the current content of `tedge.toml`
+///
+/// The patch is a toml document setting only the keys to be updated. It is
+/// rejected as a whole if any of its keys is unknown or read-only, or if any
+/// of the updated values cannot be read back by the typed configuration
+/// reader. The keys of the patch that are already set to the same value are
+/// not reported as updated.
+pub fn patch_config_file(
+    config_dir: &Utf8Path,
+    current: &str,
+    patch: &str,
+) -> Result<PatchedConfigFile, ConfigPatchError> {
+    let problems = validate_config_file(patch)?;
+    if !problems.is_empty() {
+        return Err(ConfigPatchError::InvalidPatch(problems));
+    }
+
+    let patch = migrate_toml(toml::de::from_str(patch).map_err(TEdgeConfigError::from)?);
+    let mut toml = migrate_toml(toml::de::from_str(current).map_err(TEdgeConfigError::from)?);
+
+    let mut updated_keys = vec![];
+    for key in keys_in(&patch) {
+        let Some(value) = toml_value_at(&patch, &key) else {
+            continue;
+        };
+        if toml_value_at(&toml, &key) == Some(value) {
+            continue;
+        }
+        let writable_key = parse_key_without_warnings(&key)
+            .map_err(|_| ConfigPatchError::NotWritable(key.clone()))?;
+        if let toml::Value::Table(table) = &mut toml {
+            merge_toml(table, table_with_key(&key, value));
+        }
+        updated_keys.push(writable_key);
+    }
+
+    let dto = TEdgeConfigDto::deserialize(toml).map_err(TEdgeConfigError::from)?;
+    let location = TEdgeConfigLocation::from_custom_root(config_dir);
+    let reader = TEdgeConfigReader::from_dto(&dto, &location);
+    let mut problems = vec![];
+    for key in &updated_keys {
+        let key = key.to_cow_str();
+        let Ok(readable_key) = key.parse::<ReadableKey>() else {
+            continue;
+        };
+        match reader.read_string(&readable_key) {
+            Ok(_) | Err(ReadError::ConfigNotSet(_)) => (),
+            Err(err) => problems.push(ConfigFileProblem::InvalidValue {
+                key: key.into_owned(),
+                message: err.to_string().trim().to_owned(),
+            }),
+        }
+    }
+    if !problems.is_empty() {
+        return Err(ConfigPatchError::InvalidPatch(problems));
+    }
+
+    let content = toml::to_string_pretty(&dto).map_err(TEdgeConfigError::from)?;
+    Ok(PatchedConfigFile {
+        content,
+        updated_keys,
+    })
+}
+
 #[derive(Default, Debug, PartialEq, Eq)]
 #[must_use]
 pub struct UnusedValueWarnings(Vec<String>);
@@ -357,19 +645,112 @@ fn deserialize_toml(
     toml_path: &Utf8Path,
 ) -> Result<(TEdgeConfigDto, UnusedValueWarnings), TEdgeConfigError> {
     let mut warnings = UnusedValueWarnings(vec![]);
-    let keys = keys_in(&toml);
+    warn_unknown_keys(&toml, toml_path, &mut warnings);
     let dto: TEdgeConfigDto = TEdgeConfigDto::deserialize(toml)?;
-    for key in keys {
+
+    Ok((dto, warnings))
+}
+
+fn warn_unknown_keys(toml: &toml::Value, toml_path: &Utf8Path, warnings: &mut UnusedValueWarnings) {
+    for key in keys_in(toml) {
         if key.parse::<DtoKey>().is_err() {
             warnings.push(format!(
                 "Unknown configuration field {key:?} from toml file {toml_path}",
             ));
         }
     }
+}
+
+/// Merge the drop-in files, in order, then `tedge.toml` on top
+fn deserialize_layers(
+    drop_ins: Vec<(Utf8PathBuf, toml::Value)>,
+    tedge_toml: (Utf8PathBuf, toml::Value),
+) -> Result<(TEdgeConfigDto, UnusedValueWarnings), TEdgeConfigError> {
+    let mut warnings = UnusedValueWarnings(vec![]);
+    let mut merged = toml::Table::new();
+    for (path, toml) in drop_ins.into_iter().chain(std::iter::once(tedge_toml)) {
+        warn_unknown_keys(&toml, &path, &mut warnings);
+        if let toml::Value::Table(table) = toml {
+            merge_toml(&mut merged, table);
+        }
+    }
+    let dto: TEdgeConfigDto = TEdgeConfigDto::deserialize(toml::Value::Table(merged))?;
 
     Ok((dto, warnings))
 }
 
+/// Merge the `upper` toml table into the `lower` one, the values of the `upper` table taking precedence
+fn merge_toml(lower: &mut toml::Table, upper: toml::Table) {
+    for (key, value) in upper {
+        let merged = match (lower.remove(&key), value) {
+            (Some(toml::Value::Table(mut lower_table)), toml::Value::Table(upper_table)) => {
+                merge_toml(&mut lower_table, upper_table);
+                toml::Value::Table(lower_table)
+            }
+            (_, value) => value,
+        };
+        lower.insert(key, merged);
+    }
+}
+
+fn drop_in_path(path: PathBuf) -> Option<Utf8PathBuf> {
+    let path = Utf8PathBuf::try_from(path).ok()?;
+    (path.extension() == Some("toml")).then_some(path)
+}
+
+/// Parse a drop-in file, checking that it can be used as a configuration on its own
+fn parse_drop_in(
+    path: Utf8PathBuf,
+    content: &str,
+) -> Result<(Utf8PathBuf, toml::Value), TEdgeConfigError> {
+    let toml: toml::Value = toml::de::from_str(content)
+        .with_context(|| format!("Failed to parse the drop-in configuration file {path}"))?;
+    TEdgeConfigDto::deserialize(toml.clone())
+        .with_context(|| format!("Invalid drop-in configuration file {path}"))?;
+    Ok((path, toml))
+}
+
+fn toml_contains_key(toml: &toml::Value, key: &str) -> bool {
+    toml_value_at(toml, key).is_some()
+}
+
+fn toml_value_at<'a>(toml: &'a toml::Value, key: &str) -> Option<&'a toml::Value> {
+    key.split('.')
+        .try_fold(toml, |toml, field| toml.as_table()?.get(field))
+}
+
+/// Builds a toml document setting only the given key
+fn toml_with_key(key: &str, value: &toml::Value) -> toml::Value {
+    key.rsplit('.').fold(value.clone(), |value, field| {
+        toml::Value::Table(toml::Table::from_iter([(field.to_owned(), value)]))
+    })
+}
+
+fn table_with_key(key: &str, value: &toml::Value) -> toml::Table {
+    match toml_with_key(key, value) {
+        toml::Value::Table(table) => table,
+        _ => unreachable!("a key always has at least one field"),
+    }
+}
+
+/// Migrate a toml document to the latest `tedge.toml` version
+///
+/// The document is returned as is if its version is invalid.
+fn migrate_toml(toml: toml::Value) -> toml::Value {
+    let version = match toml_value_at(&toml, "config.version") {
+        Some(version) => TEdgeConfigDto::deserialize(toml_with_key("config.version", version))
+            .ok()
+            .map(|dto| dto.config.version.unwrap_or_default()),
+        None => Some(<_>::default()),
+    };
+    match version.and_then(|version| version.migrations()) {
+        Some(migrations) => migrations
+            .into_iter()
+            .fold(toml, |toml, migration| migration.apply_to(toml)),
+        None => toml,
+    }
+}
+
 fn keys_in(toml: &toml::Value) -> Vec<String> {
     let table = toml.as_table().unwrap();
     let mut keys = vec![];
@@ -756,6 +1137,256 @@ type = "a-service-type""#;
         assert!(dbg!(warnings.0.first().unwrap()).contains("c8y.profiles.test.unknown"));
     }
 
+    #[tokio::test]
+    async fn drop_in_files_are_merged_in_lexical_order_beneath_tedge_toml() {
+        let (dir, t) = create_temp_tedge_config("device.type = \"from-tedge-toml\"").unwrap();
+        let drop_ins = dir.dir("tedge.toml.d");
+        drop_ins
+            .file("20-site.toml")
+            .with_raw_content("device.type = \"from-site\"\nservice.type = \"from-site\"");
+        drop_ins.file("10-base.toml").with_raw_content(
+            "device.type = \"from-base\"\nservice.type = \"from-base\"\nsudo.enable = false",
+        );
+        drop_ins
+            .file("30-ignored.conf")
+            .with_raw_content("service.type = \"not-a-drop-in\"");
+        let _env_lock = EnvSandbox::new().await;
+
+        let config = t.load().await.unwrap();
+
+        assert_eq!(config.device.ty, "from-tedge-toml");
+        assert_eq!(config.service.ty, "from-site");
+        assert!(!config.sudo.enable);
+    }
+
+    #[tokio::test]
+    async fn environment_variables_override_drop_in_files() {
+        let (dir, t) = create_temp_tedge_config("").unwrap();
+        dir.dir("tedge.toml.d")
+            .file("10-base.toml")
+            .with_raw_content("device.type = \"from-base\"");
+        let mut env = EnvSandbox::new().await;
+        env.set_var("TEDGE_DEVICE_TYPE", "from-env");
+
+        let config = t.load().await.unwrap();
+
+        assert_eq!(config.device.ty, "from-env");
+    }
+
+    #[tokio::test]
+    async fn drop_in_files_are_not_copied_into_tedge_toml_on_update() {
+        let (dir, t) = create_temp_tedge_config("").unwrap();
+        dir.dir("tedge.toml.d")
+            .file("10-base.toml")
+            .with_raw_content("device.type = \"from-base\"");
+        let _env_lock = EnvSandbox::new().await;
+
+        t.update_toml(&|dto, _reader| {
+            dto.try_update_str(&WritableKey::ServiceType, "updated")
+                .map_err(|e| e.into())
+        })
+        .await
+        .unwrap();
+
+        let tedge_toml = std::fs::read_to_string(dir.path().join("tedge.toml")).unwrap();
+        assert!(tedge_toml.contains("updated"));
+        assert!(!tedge_toml.contains("from-base"));
+        let config = t.load().await.unwrap();
+        assert_eq!(config.device.ty, "from-base");
+        assert_eq!(config.service.ty, "updated");
+    }
+
+    #[tokio::test]
+    async fn the_origin_of_a_value_is_the_layer_with_the_highest_precedence() {
+        let (dir, t) = create_temp_tedge_config("device.type = \"from-tedge-toml\"").unwrap();
+        let drop_ins = dir.dir("tedge.toml.d");
+        drop_ins
+            .file("10-base.toml")
+            .with_raw_content("device.type = \"from-base\"\nservice.type = \"from-base\"");
+        drop_ins
+            .file("20-site.toml")
+            .with_raw_content("service.type = \"from-site\"\nsudo.enable = false");
+        let mut env = EnvSandbox::new().await;
+        env.set_var("TEDGE_SUDO_ENABLE", "true");
+
+        assert_eq!(
+            t.origin_of("device.type").await.unwrap(),
+            ConfigOrigin::File(dir.utf8_path().join("tedge.toml"))
+        );
+        assert_eq!(
+            t.origin_of("service.type").await.unwrap(),
+            ConfigOrigin::File(dir.utf8_path().join("tedge.toml.d/20-site.toml"))
+        );
+        assert_eq!(
+            t.origin_of("sudo.enable").await.unwrap(),
+            ConfigOrigin::Environment("TEDGE_SUDO_ENABLE".to_string())
+        );
+        assert_eq!(
+            t.origin_of("mqtt.bind.port").await.unwrap(),
+            ConfigOrigin::Default
+        );
+
+        // An empty variable resets the value set by the files
+        env.set_var("TEDGE_DEVICE_TYPE", "");
+        assert_eq!(
+            t.origin_of("device.type").await.unwrap(),
+            ConfigOrigin::Default
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_fields_of_drop_in_files_are_reported_with_the_file_path() {
+        let (dir, t) = create_temp_tedge_config("").unwrap();
+        dir.dir("tedge.toml.d")
+            .file("10-base.toml")
+            .with_raw_content("c8y.unknown = \"test.c8y.io\"");
+        let _env_lock = EnvSandbox::new().await;
+        let drop_in_path = dir.utf8_path().join("tedge.toml.d/10-base.toml");
+
+        let (_config, warnings) = t
+            .load_dto_with_warnings::<FileAndEnvironment>()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            warnings.0,
+            [format!(
+                "Unknown configuration field \"c8y.unknown\" from toml file {drop_in_path}"
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_drop_in_files_are_reported_with_the_file_path() {
+        let (dir, t) = create_temp_tedge_config("").unwrap();
+        dir.dir("tedge.toml.d")
+            .file("10-base.toml")
+            .with_raw_content("sudo.enable = \"maybe\"");
+        let _env_lock = EnvSandbox::new().await;
+
+        let err = t.load().await.err().unwrap();
+
+        assert!(format!("{err:#}").contains("tedge.toml.d/10-base.toml"));
+    }
+
+    #[test]
+    fn valid_config_file_has_no_problems() {
+        let toml = "config.version = \"2\"\n[c8y]\nurl = \"example.c8y.io\"\n[c8y.profiles.second]\nurl = \"second.c8y.io\"\n";
+
+        assert_eq!(validate_config_file(toml).unwrap(), []);
+    }
+
+    #[test]
+    fn validation_reports_all_unknown_keys() {
+        let toml = "unknown = 1\n[c8y]\nurl = \"example.c8y.io\"\nnot_a_key = true\n[mqtt.bind]\nnope = 1\n";
+
+        let problems = validate_config_file(toml).unwrap();
+
+        assert_eq!(
+            problems,
+            [
+                ConfigFileProblem::UnknownKey("unknown".into()),
+                ConfigFileProblem::UnknownKey("c8y.not_a_key".into()),
+                ConfigFileProblem::UnknownKey("mqtt.bind.nope".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn validation_reports_all_invalid_values() {
+        let toml = "[mqtt.bind]\nport = \"not a port\"\n[sudo]\nenable = \"maybe\"\n";
+
+        let problems = validate_config_file(toml).unwrap();
+
+        let keys = problems
+            .iter()
+            .map(|problem| match problem {
+                ConfigFileProblem::InvalidValue { key, .. } => key.as_str(),
+                ConfigFileProblem::UnknownKey(key) => panic!("unexpected unknown key {key}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["mqtt.bind.port", "sudo.enable"]);
+    }
+
+    #[test]
+    fn validation_accepts_config_files_that_need_migrating() {
+        let toml = "[c8y]\nsmartrest_templates = [\"id1\"]\n[mqtt]\nport = 1886\n";
+
+        assert_eq!(validate_config_file(toml).unwrap(), []);
+    }
+
+    #[test]
+    fn validation_reports_unsupported_versions() {
+        let problems = validate_config_file("config.version = \"3\"").unwrap();
+
+        assert!(matches!(
+            &problems[..],
+            [ConfigFileProblem::InvalidValue { key, .. }] if key == "config.version"
+        ));
+    }
+
+    #[test]
+    fn validation_fails_on_invalid_toml() {
+        assert!(validate_config_file("[c8y").is_err());
+    }
+
+    #[test]
+    fn patch_updates_only_the_given_keys() {
+        let current =
+            "config.version = \"2\"\n[c8y]\nurl = \"example.c8y.io\"\n[mqtt.bind]\nport = 1883\n";
+        let patch = "[mqtt.bind]\nport = 1884\n";
+
+        let patched = patch_config_file(Utf8Path::new("/etc/tedge"), current, patch).unwrap();
+
+        let dto =
+            TEdgeConfigDto::deserialize(toml::from_str::<toml::Value>(&patched.content).unwrap())
+                .unwrap();
+        assert_eq!(dto.mqtt.bind.port.map(u16::from), Some(1884));
+        assert_eq!(
+            dto.c8y.non_profile.url.unwrap().to_string(),
+            "example.c8y.io"
+        );
+        let keys = patched
+            .updated_keys
+            .iter()
+            .map(|key| key.to_cow_str())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["mqtt.bind.port"]);
+    }
+
+    #[test]
+    fn patch_values_that_are_unchanged_are_not_reported_as_updated() {
+        let current = "config.version = \"2\"\n[c8y]\nurl = \"example.c8y.io\"\n";
+        let patch = "[c8y]\nurl = \"example.c8y.io\"\n";
+
+        let patched = patch_config_file(Utf8Path::new("/etc/tedge"), current, patch).unwrap();
+
+        assert!(patched.updated_keys.is_empty());
+    }
+
+    #[test]
+    fn patch_can_set_profiled_keys() {
+        let patch = "[c8y.profiles.second]\nurl = \"second.c8y.io\"\n";
+
+        let patched = patch_config_file(Utf8Path::new("/etc/tedge"), "", patch).unwrap();
+
+        let keys = patched
+            .updated_keys
+            .iter()
+            .map(|key| key.to_cow_str())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["c8y.profiles.second.url"]);
+    }
+
+    #[test]
+    fn patch_with_invalid_values_is_rejected() {
+        let patch = "[mqtt.bind]\nport = \"not a port\"\n[c8y]\nunknown = 1\n";
+
+        let err = patch_config_file(Utf8Path::new("/etc/tedge"), "", patch).unwrap_err();
+
+        assert!(matches!(err, ConfigPatchError::InvalidPatch(problems) if problems.len() == 2));
+    }
+
     static LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 
     #[allow(unused)]
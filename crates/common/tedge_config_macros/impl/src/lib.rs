@@ -15,6 +15,7 @@ mod namegen;
 mod optional_error;
 mod query;
 mod reader;
+mod schema;
 
 #[doc(hidden)]
 pub fn generate_configuration(tokens: TokenStream) -> Result<TokenStream, syn::Error> {
@@ -128,12 +129,15 @@ pub fn generate_configuration(tokens: TokenStream) -> Result<TokenStream, syn::E
 
     let enums = query::generate_writable_keys(&input.groups);
 
+    let schema = schema::generate_json_schema(&input.groups);
+
     Ok(quote! {
         #(#example_tests)*
         #(#fromstr_default_tests)*
         #dto
         #reader
         #enums
+        #schema
     })
 }
 
@@ -0,0 +1,181 @@
+use darling::ast::NestedMeta;
+use proc_macro2::TokenStream;
+use quote::quote;
+use quote::quote_spanned;
+use syn::spanned::Spanned;
+
+use crate::error::extract_type_from_result;
+use crate::input::ConfigurableField;
+use crate::input::FieldDefault;
+use crate::input::FieldOrGroup;
+
+/// Generates `TEdgeConfigDto::json_schema`, describing the keys that can be
+/// set in `tedge.toml`
+pub fn generate_json_schema(items: &[FieldOrGroup]) -> TokenStream {
+    let root = generate_group(None, items);
+    quote! {
+        impl TEdgeConfigDto {
+            /// A [JSON Schema](https://json-schema.org) describing the content of `tedge.toml`
+            pub fn json_schema() -> JsonSchema {
+                json_schema_root("tedge.toml", #root)
+            }
+        }
+    }
+}
+
+fn generate_group(doc: Option<String>, items: &[FieldOrGroup]) -> TokenStream {
+    let mut names = Vec::new();
+    let mut schemas = Vec::new();
+    for item in items {
+        let schema = match item {
+            FieldOrGroup::Field(field) => match generate_field(field, item.doc()) {
+                Some(schema) => schema,
+                None => continue,
+            },
+            FieldOrGroup::Group(group) if !group.dto.skip => {
+                generate_group(item.doc(), &group.contents)
+            }
+            FieldOrGroup::Multi(group) if !group.dto.skip => {
+                let group = generate_group(item.doc(), &group.contents);
+                quote!(json_schema_multi(#group))
+            }
+            FieldOrGroup::Group(_) | FieldOrGroup::Multi(_) => continue,
+        };
+        names.push(item.name().into_owned());
+        schemas.push(schema);
+    }
+
+    let doc = option_tokens(doc);
+    quote! {
+        json_schema_group(#doc, vec![#((#names, #schemas)),*])
+    }
+}
+
+/// Generates the schema of a field, if the field is stored in `tedge.toml`
+fn generate_field(field: &ConfigurableField, doc: Option<String>) -> Option<TokenStream> {
+    // Mirrors the fields included in the DTO
+    let field = match field {
+        ConfigurableField::ReadWrite(field) if field.reader.function.is_some() => field,
+        ConfigurableField::ReadWrite(field) if !field.dto.skip => field,
+        _ => return None,
+    };
+
+    let ty = field.from.as_ref().unwrap_or(&field.ty);
+    let ty = extract_type_from_result(ty).map_or(ty, |(ok, _err)| ok);
+    let doku_ty = doku_as(&field.attrs).unwrap_or_else(|| ty.clone());
+    let doc = option_tokens(doc);
+    let examples = field.examples.iter().map(|example| {
+        let example = example.as_str();
+        quote_spanned! {ty.span()=>
+            #example.parse::<#ty>().ok().and_then(|example| json_schema_value(&example))
+        }
+    });
+    let default = match &field.default {
+        FieldDefault::Value(default) => quote_spanned! {ty.span()=>
+            json_schema_value::<#ty>(&#default.into())
+        },
+        FieldDefault::Variable(default) => quote_spanned! {ty.span()=>
+            json_schema_value::<#ty>(&#default.into())
+        },
+        FieldDefault::FromStr(default) => quote_spanned! {ty.span()=>
+            #default.parse::<#ty>().ok().and_then(|default| json_schema_value(&default))
+        },
+        // Other defaults depend on the rest of the configuration
+        _ => quote!(None),
+    };
+
+    Some(quote_spanned! {ty.span()=>
+        json_schema_field(
+            <#doku_ty as ::doku::Document>::ty(),
+            #doc,
+            vec![#(#examples),*],
+            #default,
+        )
+    })
+}
+
+/// The type given by `#[doku(as = "...")]`, for fields whose type doesn't
+/// implement `doku::Document`
+fn doku_as(attrs: &[syn::Attribute]) -> Option<syn::Type> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doku"))
+        .filter_map(|attr| attr.meta.require_list().ok())
+        .filter_map(|attr| NestedMeta::parse_meta_list(attr.tokens.clone()).ok())
+        .flatten()
+        .filter_map(|meta| match meta {
+            NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("as") => Some(nv.value),
+            _ => None,
+        })
+        .find_map(|value| match value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(ty),
+                ..
+            }) => ty.parse().ok(),
+            _ => None,
+        })
+}
+
+fn option_tokens(value: Option<String>) -> TokenStream {
+    match value {
+        Some(value) => quote!(Some(#value)),
+        None => quote!(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn doku_as_type_is_extracted() {
+        let attrs: Vec<syn::Attribute> = vec![
+            parse_quote!(#[doku(example = "test")]),
+            parse_quote!(#[doku(as = "String")]),
+        ];
+
+        assert_eq!(doku_as(&attrs), Some(parse_quote!(String)));
+    }
+
+    #[test]
+    fn fields_without_doku_as_use_their_own_type() {
+        let attrs: Vec<syn::Attribute> = vec![parse_quote!(#[doku(meta("note = A note"))])];
+
+        assert_eq!(doku_as(&attrs), None);
+    }
+
+    #[test]
+    fn read_only_and_skipped_fields_are_not_in_the_schema() {
+        let input: crate::input::Configuration = parse_quote!(
+            device: {
+                #[tedge_config(readonly(write_error = "Not writable", function = "device_id"))]
+                id: String,
+                #[tedge_config(dto(skip))]
+                skipped: String,
+                #[tedge_config(default(value = 1883_u16))]
+                port: u16,
+            },
+        );
+
+        let generated = generate_json_schema(&input.groups).to_string();
+
+        assert!(generated.contains("\"port\""));
+        assert!(!generated.contains("\"id\""));
+        assert!(!generated.contains("\"skipped\""));
+    }
+
+    #[test]
+    fn multi_groups_accept_profiles() {
+        let input: crate::input::Configuration = parse_quote!(
+            #[tedge_config(multi)]
+            c8y: {
+                url: String,
+            },
+        );
+
+        let generated = generate_json_schema(&input.groups).to_string();
+
+        assert!(generated.contains("json_schema_multi"));
+    }
+}
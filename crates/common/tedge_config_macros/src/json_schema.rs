@@ -0,0 +1,235 @@
+//! Helpers used by [define_tedge_config](crate::define_tedge_config) to
+//! describe the configuration file with a [JSON Schema](https://json-schema.org)
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+
+/// A JSON Schema document, as generated by `TEdgeConfigDto::json_schema`
+pub type JsonSchema = Value;
+
+/// The doku meta key a type can set to override its JSON schema
+///
+/// The value of the meta must be a JSON object, e.g.
+/// `{"type": ["integer", "string"]}` for a type that can be deserialized from
+/// either an integer or a string.
+pub const JSON_SCHEMA_META: &str = "json_schema";
+
+const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+#[doc(hidden)]
+pub fn json_schema_root(title: &str, mut group: JsonSchema) -> JsonSchema {
+    if let Some(schema) = group.as_object_mut() {
+        schema.insert("$schema".into(), JSON_SCHEMA_DIALECT.into());
+        schema.insert("title".into(), title.into());
+    }
+    group
+}
+
+#[doc(hidden)]
+pub fn json_schema_group(doc: Option<&str>, properties: Vec<(&str, JsonSchema)>) -> JsonSchema {
+    let mut schema = Map::new();
+    schema.insert("type".into(), "object".into());
+    if let Some(doc) = doc {
+        schema.insert("description".into(), doc.into());
+    }
+    schema.insert(
+        "properties".into(),
+        properties
+            .into_iter()
+            .map(|(name, property)| (name.to_owned(), property))
+            .collect::<Map<_, _>>()
+            .into(),
+    );
+    schema.insert("additionalProperties".into(), false.into());
+    schema.into()
+}
+
+/// Adds the `profiles` table of a multi-profile group, each profile accepting
+/// the same keys as the group itself
+#[doc(hidden)]
+pub fn json_schema_multi(mut group: JsonSchema) -> JsonSchema {
+    let profile = group.clone();
+    if let Some(properties) = group
+        .get_mut("properties")
+        .and_then(|properties| properties.as_object_mut())
+    {
+        properties.insert(
+            "profiles".into(),
+            json!({
+                "type": "object",
+                "description": "Named profiles, each configuring an additional connection",
+                "propertyNames": { "pattern": "^[\\w-]*[^\\W_][\\w-]*$" },
+                "additionalProperties": profile,
+            }),
+        );
+    }
+    group
+}
+
+#[doc(hidden)]
+pub fn json_schema_field(
+    ty: doku::Type,
+    doc: Option<&str>,
+    examples: Vec<Option<JsonSchema>>,
+    default: Option<JsonSchema>,
+) -> JsonSchema {
+    let mut schema = type_schema(&ty);
+    if let Some(schema) = schema.as_object_mut() {
+        if let Some(doc) = doc {
+            schema.insert("description".into(), doc.into());
+        }
+        let examples = examples.into_iter().flatten().collect::<Vec<_>>();
+        if !examples.is_empty() {
+            schema.insert("examples".into(), examples.into());
+        }
+        if let Some(default) = default {
+            schema.insert("default".into(), default);
+        }
+    }
+    schema
+}
+
+/// Serializes a default or example value to include it in the schema
+#[doc(hidden)]
+pub fn json_schema_value<T: Serialize>(value: &T) -> Option<JsonSchema> {
+    serde_json::to_value(value).ok()
+}
+
+fn type_schema(ty: &doku::Type) -> JsonSchema {
+    if let Some(schema) = ty
+        .metas
+        .get(JSON_SCHEMA_META)
+        .and_then(|schema| serde_json::from_str(schema).ok())
+    {
+        return schema;
+    }
+
+    match &ty.kind {
+        doku::TypeKind::String => json!({ "type": "string" }),
+        doku::TypeKind::Bool => json!({ "type": "boolean" }),
+        doku::TypeKind::Integer => json!({ "type": "integer" }),
+        doku::TypeKind::Float => json!({ "type": "number" }),
+        doku::TypeKind::Optional { ty } => type_schema(ty),
+        doku::TypeKind::Array { ty, .. } => json!({ "type": "array", "items": type_schema(ty) }),
+        doku::TypeKind::Map { value, .. } => {
+            json!({ "type": "object", "additionalProperties": type_schema(value) })
+        }
+        doku::TypeKind::Enum { variants, .. } => {
+            let ids = variants
+                .iter()
+                .filter(|variant| matches!(variant.fields, doku::Fields::Unit))
+                .map(|variant| variant.id)
+                .collect::<Vec<_>>();
+            if ids.is_empty() {
+                json!({})
+            } else {
+                json!({ "type": "string", "enum": ids })
+            }
+        }
+        doku::TypeKind::Struct {
+            fields: doku::Fields::Named { fields },
+            transparent: false,
+        } => {
+            let properties = fields
+                .iter()
+                .map(|(name, field)| {
+                    let mut property = type_schema(&field.ty);
+                    if let (Some(property), Some(comment)) =
+                        (property.as_object_mut(), field.ty.comment)
+                    {
+                        property.insert("description".into(), comment.into());
+                    }
+                    (name.to_string(), property)
+                })
+                .collect::<Map<_, _>>();
+            json!({ "type": "object", "properties": properties })
+        }
+        doku::TypeKind::Struct {
+            fields: doku::Fields::Named { fields },
+            transparent: true,
+        } => fields
+            .first()
+            .map_or_else(|| json!({}), |(_, field)| type_schema(&field.ty)),
+        _ => json!({}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use doku::Document;
+
+    #[test]
+    fn primitive_types_are_mapped_to_json_types() {
+        assert_eq!(type_schema(&String::ty()), json!({ "type": "string" }));
+        assert_eq!(type_schema(&bool::ty()), json!({ "type": "boolean" }));
+        assert_eq!(type_schema(&u16::ty()), json!({ "type": "integer" }));
+        assert_eq!(type_schema(&f64::ty()), json!({ "type": "number" }));
+    }
+
+    #[test]
+    fn arrays_describe_their_items() {
+        assert_eq!(
+            type_schema(&Vec::<String>::ty()),
+            json!({ "type": "array", "items": { "type": "string" } })
+        );
+    }
+
+    #[test]
+    fn unit_enums_list_their_variants() {
+        #[derive(Document, Serialize)]
+        #[allow(unused)]
+        #[serde(rename_all = "lowercase")]
+        enum Flag {
+            On,
+            Off,
+        }
+
+        assert_eq!(
+            type_schema(&Flag::ty()),
+            json!({ "type": "string", "enum": ["on", "off"] })
+        );
+    }
+
+    #[test]
+    fn json_schema_meta_overrides_the_doku_type() {
+        let mut ty = String::ty();
+        ty.metas
+            .add(JSON_SCHEMA_META, r#"{"type": ["integer", "string"]}"#);
+
+        assert_eq!(type_schema(&ty), json!({ "type": ["integer", "string"] }));
+    }
+
+    #[test]
+    fn fields_include_description_examples_and_default() {
+        let schema = json_schema_field(
+            u16::ty(),
+            Some("The port"),
+            vec![json_schema_value(&1883), None],
+            json_schema_value(&8883),
+        );
+
+        assert_eq!(
+            schema,
+            json!({
+                "type": "integer",
+                "description": "The port",
+                "examples": [1883],
+                "default": 8883,
+            })
+        );
+    }
+
+    #[test]
+    fn multi_groups_accept_profiles_with_the_same_keys() {
+        let group = json_schema_group(None, vec![("url", json!({ "type": "string" }))]);
+        let schema = json_schema_multi(group.clone());
+
+        assert_eq!(schema["properties"]["url"], json!({ "type": "string" }));
+        assert_eq!(
+            schema["properties"]["profiles"]["additionalProperties"],
+            group
+        );
+    }
+}
@@ -7,6 +7,7 @@ pub use all_or_nothing::*;
 pub use connect_url::*;
 pub use default::*;
 pub use doku_aliases::*;
+pub use json_schema::*;
 pub use multi::*;
 pub use option::*;
 
@@ -16,5 +17,6 @@ mod default;
 mod doku_aliases;
 #[cfg(doc)]
 pub mod example;
+mod json_schema;
 mod multi;
 mod option;
@@ -0,0 +1,94 @@
+use camino::Utf8PathBuf;
+use tedge_config_macros::*;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReadError {
+    #[error(transparent)]
+    ConfigNotSet(#[from] ConfigNotSet),
+    #[error(transparent)]
+    Multi(#[from] MultiError),
+}
+
+pub trait AppendRemoveItem {
+    type Item;
+
+    fn append(current_value: Option<Self::Item>, new_value: Self::Item) -> Option<Self::Item>;
+
+    fn remove(current_value: Option<Self::Item>, remove_value: Self::Item) -> Option<Self::Item>;
+}
+
+impl<T> AppendRemoveItem for T {
+    type Item = T;
+
+    fn append(_current_value: Option<Self::Item>, _new_value: Self::Item) -> Option<Self::Item> {
+        unimplemented!()
+    }
+
+    fn remove(_current_value: Option<Self::Item>, _remove_value: Self::Item) -> Option<Self::Item> {
+        unimplemented!()
+    }
+}
+
+define_tedge_config! {
+    device: {
+        /// The type of the device
+        #[tedge_config(rename = "type", example = "thin-edge.io", default(value = "thin-edge.io"))]
+        ty: String,
+
+        #[tedge_config(default(from_str = "/etc/tedge/device-certs"))]
+        #[doku(as = "std::path::PathBuf")]
+        cert_path: Utf8PathBuf,
+    },
+
+    #[tedge_config(multi)]
+    c8y: {
+        url: String,
+
+        #[tedge_config(example = "8001", default(value = 8001_u16))]
+        port: u16,
+    },
+}
+
+#[test]
+fn fields_are_described_by_their_type_doc_examples_and_default() {
+    let schema = TEdgeConfigDto::json_schema();
+
+    assert_eq!(
+        schema["properties"]["device"]["properties"]["type"],
+        serde_json::json!({
+            "type": "string",
+            "description": "The type of the device",
+            "examples": ["thin-edge.io"],
+            "default": "thin-edge.io",
+        })
+    );
+    assert_eq!(
+        schema["properties"]["device"]["properties"]["cert_path"],
+        serde_json::json!({
+            "type": "string",
+            "default": "/etc/tedge/device-certs",
+        })
+    );
+}
+
+#[test]
+fn unknown_keys_are_not_allowed() {
+    let schema = TEdgeConfigDto::json_schema();
+
+    assert_eq!(schema["additionalProperties"], false);
+    assert_eq!(
+        schema["properties"]["device"]["additionalProperties"],
+        false
+    );
+}
+
+#[test]
+fn multi_groups_accept_profiles() {
+    let schema = TEdgeConfigDto::json_schema();
+    let c8y = &schema["properties"]["c8y"];
+    let profile = &c8y["properties"]["profiles"]["additionalProperties"];
+
+    assert_eq!(c8y["properties"]["port"]["default"], 8001);
+    assert_eq!(profile["properties"]["port"], c8y["properties"]["port"]);
+    assert!(profile["properties"].get("profiles").is_none());
+}
@@ -2,6 +2,7 @@ pub mod atomic;
 pub mod file;
 pub mod fs;
 pub mod paths;
+pub mod resources;
 pub mod signals;
 pub mod size_threshold;
 pub mod timers;
@@ -0,0 +1,89 @@
+//! Helpers reading the memory and disk usage of the system
+
+use std::path::Path;
+
+/// The memory usage, in bytes, as reported by `/proc/meminfo`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub total: u64,
+    /// Not reported by kernels older than 3.14
+    pub available: Option<u64>,
+    pub swap_total: Option<u64>,
+}
+
+impl MemoryUsage {
+    /// Parse the content of `/proc/meminfo`, returning `None` if the total memory is not given
+    pub fn parse(meminfo: &str) -> Option<Self> {
+        let read_bytes = |name: &str| {
+            meminfo
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(key, _)| *key == name)
+                .and_then(|(_, value)| value.trim().trim_end_matches("kB").trim().parse().ok())
+                .map(|kb: u64| kb * 1024)
+        };
+        Some(MemoryUsage {
+            total: read_bytes("MemTotal")?,
+            available: read_bytes("MemAvailable"),
+            swap_total: read_bytes("SwapTotal"),
+        })
+    }
+}
+
+/// The usage, in bytes, of a file system
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiskUsage {
+    pub total: u64,
+    pub free: u64,
+    /// The free space available to unprivileged users
+    pub available: u64,
+}
+
+impl DiskUsage {
+    /// Get the usage of the file system containing the given path
+    pub fn of(path: impl AsRef<Path>) -> Result<Self, nix::Error> {
+        let stat = nix::sys::statvfs::statvfs(path.as_ref())?;
+        let block_size = stat.fragment_size() as u64;
+        Ok(DiskUsage {
+            total: stat.blocks() as u64 * block_size,
+            free: stat.blocks_free() as u64 * block_size,
+            available: stat.blocks_available() as u64 * block_size,
+        })
+    }
+
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.free)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_meminfo() {
+        let meminfo = "MemTotal:        2048 kB\nMemFree:          512 kB\nMemAvailable:    1024 kB\nSwapTotal:         0 kB\n";
+
+        assert_eq!(
+            MemoryUsage::parse(meminfo),
+            Some(MemoryUsage {
+                total: 2048 * 1024,
+                available: Some(1024 * 1024),
+                swap_total: Some(0),
+            })
+        );
+    }
+
+    #[test]
+    fn the_total_memory_is_required() {
+        assert_eq!(MemoryUsage::parse("MemFree:          512 kB\n"), None);
+    }
+
+    #[test]
+    fn disk_usage_of_the_current_dir() {
+        let usage = DiskUsage::of(".").unwrap();
+
+        assert!(usage.available <= usage.free);
+        assert!(usage.used() <= usage.total);
+    }
+}
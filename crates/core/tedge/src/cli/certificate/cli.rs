@@ -6,6 +6,7 @@ use super::show::ShowCertCmd;
 use crate::certificate_is_self_signed;
 use crate::cli::certificate::c8y;
 use crate::cli::certificate::create_csr::Key;
+use crate::cli::certificate::est;
 use crate::cli::common::Cloud;
 use crate::cli::common::CloudArg;
 use crate::command::BuildCommand;
@@ -16,10 +17,13 @@ use anyhow::anyhow;
 use c8y_api::http_proxy::C8yEndPoint;
 use camino::Utf8PathBuf;
 use certificate::CsrTemplate;
+use certificate::GenerateKeyRequest;
+use certificate::KeyTypeRequest;
 use clap::ValueHint;
 use std::time::Duration;
 use tedge_config::models::HostPort;
 use tedge_config::models::HTTPS_PORT;
+use tedge_config::tedge_toml::EstConfig;
 use tedge_config::tedge_toml::OptionalConfigError;
 use tedge_config::tedge_toml::ProfileName;
 use tedge_config::TEdgeConfig;
@@ -47,6 +51,37 @@ pub enum TEdgeCertCli {
         #[clap(long = "output-path", global = true, value_hint = ValueHint::FilePath)]
         output_path: Option<Utf8PathBuf>,
 
+        /// Generate a new key pair on the PKCS#11 token, and use it for the CSR
+        ///
+        /// Requires `device.cryptoki.mode` to be set to `module` or `socket`.
+        /// The private key is created as non-extractable and never leaves the token.
+        /// The URI of the new key is printed and has to be set as `device.key_uri`.
+        #[clap(long, global = true)]
+        generate_key: bool,
+
+        /// Type of the key pair to be generated on the PKCS#11 token
+        #[clap(long, global = true, default_value_t = KeyType::Ecdsa, requires = "generate_key")]
+        key_type: KeyType,
+
+        /// Size in bits of the key pair to be generated on the PKCS#11 token
+        ///
+        /// 256 (default) or 384 for ECDSA keys; 2048 (default), 3072 or 4096 for RSA keys
+        #[clap(long, global = true, requires = "generate_key")]
+        key_size: Option<u16>,
+
+        /// Label of the key pair to be generated on the PKCS#11 token
+        #[clap(
+            long,
+            global = true,
+            default_value = "tedge",
+            requires = "generate_key"
+        )]
+        key_label: String,
+
+        /// Id of the key pair to be generated on the PKCS#11 token, as an hexadecimal string
+        #[clap(long, global = true, value_parser = parse_hex_id, requires = "generate_key")]
+        key_id: Option<KeyId>,
+
         #[clap(subcommand)]
         cloud: Option<CloudArg>,
     },
@@ -72,13 +107,15 @@ pub enum TEdgeCertCli {
 
         /// Certificate Authority (CA) used to renew the certificate
         ///
-        /// Cumulocity CA is currently the only supported one and this is the default,
+        /// Cumulocity CA is the default,
         /// even if the current certificate has not been signed by Cumulocity.
         /// In most cases, the default behavior is what you want:
         /// substitute a proper CA-signed certificate for a self-signed certificate.
         ///
         /// However, if this is not the case, or if the cloud endpoint doesn't provide a CA:
-        /// use `--ca self-signed` to get a renewed self-signed certificate.
+        /// use `--ca self-signed` to get a renewed self-signed certificate,
+        /// or `--ca est` to renew the certificate using the EST server configured
+        /// for the cloud profile (`<cloud>.est.url`).
         #[clap(long = "ca", default_value_t = CA::C8y, global = true)]
         ca: CA,
 
@@ -141,6 +178,62 @@ pub enum CA {
 
     #[strum(serialize = "c8y")]
     C8y,
+
+    #[strum(serialize = "est")]
+    Est,
+}
+
+/// Type of a key pair to be generated on a PKCS#11 token
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Eq, PartialEq, strum_macros::Display)]
+pub enum KeyType {
+    #[strum(serialize = "ecdsa")]
+    Ecdsa,
+
+    #[strum(serialize = "rsa")]
+    Rsa,
+}
+
+impl KeyType {
+    /// The key to be generated, provided a CSR can be created for a key of that size
+    ///
+    /// The size is checked before generating anything on the token,
+    /// as a key for which no CSR can be created would be left orphaned on the token.
+    /// Notably, P521 keys are not supported by rcgen.
+    fn with_size(self, size: Option<u16>) -> Result<KeyTypeRequest, ConfigError> {
+        match (self, size) {
+            (KeyType::Ecdsa, None) => Ok(KeyTypeRequest::Ec(256)),
+            (KeyType::Ecdsa, Some(size @ (256 | 384))) => Ok(KeyTypeRequest::Ec(size)),
+            (KeyType::Rsa, None) => Ok(KeyTypeRequest::Rsa(2048)),
+            (KeyType::Rsa, Some(size @ (2048 | 3072 | 4096))) => Ok(KeyTypeRequest::Rsa(size)),
+            (KeyType::Ecdsa, Some(size)) => Err(anyhow!(
+                "Unsupported ECDSA key size: {size}. Only 256 and 384 are supported"
+            )
+            .into()),
+            (KeyType::Rsa, Some(size)) => Err(anyhow!(
+                "Unsupported RSA key size: {size}. Only 2048, 3072 and 4096 are supported"
+            )
+            .into()),
+        }
+    }
+}
+
+/// The id of a PKCS#11 object
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeyId(Vec<u8>);
+
+fn parse_hex_id(hex: &str) -> Result<KeyId, String> {
+    if hex.is_empty() || hex.len() % 2 != 0 {
+        return Err("expected a non-empty string of hexadecimal byte values".to_string());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| format!("invalid hexadecimal byte value at position {i}"))
+        })
+        .collect::<Result<Vec<u8>, _>>()
+        .map(KeyId)
 }
 
 impl BuildCommand for TEdgeCertCli {
@@ -182,6 +275,11 @@ impl BuildCommand for TEdgeCertCli {
             TEdgeCertCli::CreateCsr {
                 id,
                 output_path,
+                generate_key,
+                key_type,
+                key_size,
+                key_label,
+                key_id,
                 cloud,
             } => {
                 let cloud: Option<Cloud> = cloud.map(<_>::try_into).transpose()?;
@@ -198,6 +296,22 @@ impl BuildCommand for TEdgeCertCli {
                     ));
                 debug!(?key);
 
+                let generate_key = match (generate_key, &key) {
+                    (false, _) => None,
+                    (true, Key::Local(_)) => {
+                        return Err(anyhow!(
+                            "A key pair can only be generated on a PKCS#11 token: `device.cryptoki.mode` is `off`"
+                        )
+                        .into())
+                    }
+                    (true, Key::Cryptoki(_)) => Some(GenerateKeyRequest {
+                        key: key_type.with_size(key_size)?,
+                        label: key_label,
+                        id: key_id.map(|id| id.0),
+                        uri: None,
+                    }),
+                };
+
                 let cmd = CreateCsrCmd {
                     id: get_device_id(id, config, &cloud)?,
                     key,
@@ -210,6 +324,7 @@ impl BuildCommand for TEdgeCertCli {
                     user: user.to_owned(),
                     group: group.to_owned(),
                     csr_template,
+                    generate_key,
                 };
                 cmd.into_boxed()
             }
@@ -319,6 +434,32 @@ impl BuildCommand for TEdgeCertCli {
                 cmd.into_boxed()
             }
 
+            TEdgeCertCli::Download(DownloadCertCli::Est {
+                id,
+                csr_path,
+                ca_certs_path,
+                cloud,
+            }) => {
+                let cloud: Option<Cloud> = cloud.map(<_>::try_into).transpose()?;
+                let (csr_path, generate_csr) = match csr_path {
+                    None => (config.device_csr_path(cloud.as_ref())?.to_owned(), true),
+                    Some(csr_path) => (csr_path, false),
+                };
+                let (est, key) = est_config_and_key(config, cloud.as_ref())?;
+                let cmd = est::EnrollCertCmd {
+                    device_id: get_device_id(id, config, &cloud)?,
+                    est,
+                    http_config: config.cloud_root_certs()?,
+                    cert_path: config.device_cert_path(cloud.as_ref())?.to_owned(),
+                    key,
+                    csr_path,
+                    generate_csr,
+                    ca_certs_path,
+                    csr_template,
+                };
+                cmd.into_boxed()
+            }
+
             TEdgeCertCli::Renew {
                 csr_path,
                 cloud,
@@ -357,6 +498,23 @@ impl BuildCommand for TEdgeCertCli {
                     return Err(
                         anyhow!("Cannot renew certificate with self-signed ca: {cert_path} is not self-signed").into()
                     );
+                } else if ca == CA::Est {
+                    let (csr_path, generate_csr) = match csr_path {
+                        None => (config.device_csr_path(cloud.as_ref())?.to_owned(), true),
+                        Some(csr_path) => (csr_path, false),
+                    };
+                    let (est, key) = est_config_and_key(config, cloud.as_ref())?;
+                    let cmd = est::RenewCertCmd {
+                        est,
+                        http_config: config.cloud_root_certs()?,
+                        cert_path,
+                        new_cert_path,
+                        key,
+                        csr_path,
+                        generate_csr,
+                        csr_template,
+                    };
+                    cmd.into_boxed()
                 } else {
                     let (csr_path, generate_csr) = match csr_path {
                         None => (config.device_csr_path(cloud.as_ref())?.to_owned(), true),
@@ -453,6 +611,27 @@ fn get_device_id(
     }
 }
 
+/// Returns the EST settings and the device key of a cloud profile
+///
+/// The default Cumulocity profile is used if no cloud is provided.
+fn est_config_and_key(
+    config: &TEdgeConfig,
+    cloud: Option<&Cloud>,
+) -> Result<(EstConfig, Key), anyhow::Error> {
+    let cloud_config = match cloud {
+        Some(cloud) => config.as_cloud_config(cloud.into())?,
+        None => config.as_cloud_config(tedge_config::tedge_toml::Cloud::C8y(None))?,
+    };
+    let est = cloud_config.est_config().ok_or_else(|| {
+        anyhow!("No EST server is configured: the `est.url` of the cloud profile must be set")
+    })?;
+    let key = match config.device.cryptoki_config(Some(cloud_config))? {
+        Some(cryptoki) => Key::Cryptoki(cryptoki),
+        None => Key::Local(cloud_config.device_key_path().to_owned()),
+    };
+    Ok((est, key))
+}
+
 #[derive(clap::Subcommand, Debug)]
 pub enum DownloadCertCli {
     #[clap(verbatim_doc_comment)]
@@ -523,6 +702,38 @@ pub enum DownloadCertCli {
         /// Maximum time waiting for the device to be registered
         max_timeout: Duration,
     },
+
+    #[clap(verbatim_doc_comment)]
+    /// Request and download the device certificate from an EST server
+    ///
+    /// - Generate a private key and Certificate Signing Request (CSR) for the device
+    /// - Send this CSR to the EST server configured for the cloud profile
+    /// - Store the certificate returned by the EST server
+    ///
+    /// The request is authenticated using basic-auth, if `<cloud>.est.credentials_path` is set,
+    /// and/or using a bootstrap certificate, if `<cloud>.est.bootstrap_cert_path`
+    /// and `<cloud>.est.bootstrap_key_path` are set.
+    Est {
+        /// The device identifier to be used as the common name for the certificate
+        #[clap(long = "device-id", global = true)]
+        id: Option<String>,
+
+        /// Path to a Certificate Signing Request (CSR) ready to be used
+        ///
+        /// If none is provided a CSR is generated using the device id and private key
+        /// configured for the given cloud profile.
+        #[clap(long = "csr-path", global = true, value_hint = ValueHint::FilePath)]
+        csr_path: Option<Utf8PathBuf>,
+
+        /// Path where the CA certificates returned by the EST server are stored
+        ///
+        /// If none is provided, the CA certificates are not requested.
+        #[clap(long = "ca-certs-path", global = true, value_hint = ValueHint::FilePath)]
+        ca_certs_path: Option<Utf8PathBuf>,
+
+        #[clap(subcommand)]
+        cloud: Option<CloudArg>,
+    },
 }
 
 #[cfg(test)]
@@ -691,4 +902,14 @@ mod tests {
         let result = get_device_id(id, &config, &cloud);
         assert!(result.is_err());
     }
+
+    #[test_case("01", Ok(vec![0x01]))]
+    #[test_case("a0B1ff", Ok(vec![0xa0, 0xb1, 0xff]))]
+    #[test_case("", Err(()))]
+    #[test_case("abc", Err(()))]
+    #[test_case("0g", Err(()))]
+    #[test_case("é1", Err(()))]
+    fn parsing_hexadecimal_key_ids(input: &str, expected: Result<Vec<u8>, ()>) {
+        assert_eq!(parse_hex_id(input).map(|id| id.0).map_err(|_| ()), expected);
+    }
 }
@@ -5,8 +5,11 @@ use crate::override_public_key;
 use crate::persist_new_private_key;
 use crate::reuse_private_key;
 use camino::Utf8PathBuf;
+use certificate::generate_cryptoki_key;
+use certificate::new_cryptoki_certificate_sign_request;
 use certificate::parse_root_certificate::CryptokiConfig;
 use certificate::CsrTemplate;
+use certificate::GenerateKeyRequest;
 use certificate::KeyCertPair;
 use certificate::KeyKind;
 use tedge_config::TEdgeConfig;
@@ -30,6 +33,9 @@ pub struct CreateCsrCmd {
 
     /// CSR template
     pub csr_template: CsrTemplate,
+
+    /// The key pair to be generated on the PKCS#11 token, if any
+    pub generate_key: Option<GenerateKeyRequest>,
 }
 
 #[derive(Debug, Clone)]
@@ -58,34 +64,78 @@ impl CreateCsrCmd {
         let csr_path = &self.csr_path;
         debug!(?id, ?csr_path);
 
-        let previous_key = match &self.key {
-            Key::Local(key_path) => reuse_private_key(key_path)
-                .await
-                .map_err(|e| CertError::IoError(e).key_context(key_path.clone()))?,
-
-            Key::Cryptoki(config) => KeyKind::from_cryptoki(config.clone())?,
+        let csr = match &self.key {
+            Key::Local(key_path) => self.create_local_csr(key_path).await?,
+            Key::Cryptoki(config) => self.create_cryptoki_csr(config.clone())?,
         };
+        override_public_key(csr_path, csr)
+            .await
+            .map_err(|err| err.cert_context(csr_path.clone()))?;
+        Ok(())
+    }
+
+    /// Create a CSR using the local private key, creating this key if it doesn't exist yet
+    async fn create_local_csr(&self, key_path: &Utf8PathBuf) -> Result<String, CertError> {
+        let previous_key = reuse_private_key(key_path)
+            .await
+            .map_err(|e| CertError::IoError(e).key_context(key_path.clone()))?;
         debug!(?previous_key);
 
         let cert =
-            KeyCertPair::new_certificate_sign_request(&self.csr_template, id, &previous_key)?;
-
-        if let Key::Local(key_path) = &self.key {
-            if let KeyKind::New = previous_key {
-                persist_new_private_key(
-                    key_path,
-                    cert.private_key_pem_string()?,
-                    &self.user,
-                    &self.group,
-                )
-                .await
-                .map_err(|err| err.key_context(key_path.clone()))?;
-            }
-        }
-        override_public_key(csr_path, cert.certificate_signing_request_string()?)
+            KeyCertPair::new_certificate_sign_request(&self.csr_template, &self.id, &previous_key)?;
+
+        if let KeyKind::New = previous_key {
+            persist_new_private_key(
+                key_path,
+                cert.private_key_pem_string()?,
+                &self.user,
+                &self.group,
+            )
             .await
-            .map_err(|err| err.cert_context(csr_path.clone()))?;
-        Ok(())
+            .map_err(|err| err.key_context(key_path.clone()))?;
+        }
+        Ok(cert.certificate_signing_request_string()?)
+    }
+
+    /// Create a CSR signed by the PKCS#11 token, generating first a new key pair if requested
+    ///
+    /// Only the generation of a new key pair uses the `generate_key` and `create_csr` requests,
+    /// so a CSR can still be created for an existing key with an older `tedge-p11-server`.
+    fn create_cryptoki_csr(&self, config: CryptokiConfig) -> Result<String, CertError> {
+        let Some(request) = &self.generate_key else {
+            let previous_key = KeyKind::from_cryptoki(config)?;
+            debug!(?previous_key);
+            let cert = KeyCertPair::new_certificate_sign_request(
+                &self.csr_template,
+                &self.id,
+                &previous_key,
+            )?;
+            return Ok(cert.certificate_signing_request_string()?);
+        };
+
+        let key = generate_cryptoki_key(config.clone(), request.clone())?;
+        eprintln!("A new key pair has been generated on the PKCS#11 token.");
+        eprintln!("    To use this key, set the device key URI:");
+        eprintln!("    => tedge config set device.key_uri '{}'\n", key.uri);
+        Ok(new_cryptoki_certificate_sign_request(
+            with_key_uri(config, key.uri),
+            &self.csr_template,
+            &self.id,
+        )?)
+    }
+}
+
+/// Use the given key, instead of the key configured for the PKCS#11 token
+fn with_key_uri(config: CryptokiConfig, key_uri: String) -> CryptokiConfig {
+    match config {
+        CryptokiConfig::Direct(mut config) => {
+            config.uri = Some(key_uri.into());
+            CryptokiConfig::Direct(config)
+        }
+        CryptokiConfig::SocketService { socket_path, .. } => CryptokiConfig::SocketService {
+            socket_path,
+            uri: Some(key_uri.into()),
+        },
     }
 }
 
@@ -111,6 +161,7 @@ mod tests {
             user: "mosquitto".to_string(),
             group: "mosquitto".to_string(),
             csr_template: CsrTemplate::default(),
+            generate_key: None,
         };
 
         assert_matches!(cmd.create_certificate_signing_request().await, Ok(()));
@@ -154,6 +205,7 @@ mod tests {
             user: "mosquitto".to_string(),
             group: "mosquitto".to_string(),
             csr_template: CsrTemplate::default(),
+            generate_key: None,
         };
 
         // create csr using existing private key and device_id from public cert
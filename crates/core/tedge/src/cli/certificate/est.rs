@@ -0,0 +1,186 @@
+use crate::certificate_cn;
+use crate::cli::certificate::c8y::create_device_csr;
+use crate::cli::certificate::create_csr::Key;
+use crate::cli::certificate::show::ShowCertCmd;
+use crate::command::Command;
+use crate::get_webpki_error_from_reqwest;
+use crate::log::MaybeFancy;
+use crate::override_public_key;
+use crate::read_cert_to_string;
+use anyhow::Context;
+use anyhow::Error;
+use camino::Utf8PathBuf;
+use certificate::est::EstClient;
+use certificate::est::EstError;
+use certificate::CloudHttpConfig;
+use certificate::CsrTemplate;
+use tedge_config::tedge_toml::EstConfig;
+use tedge_config::TEdgeConfig;
+use tracing::instrument;
+
+/// Command to request a first device certificate from an EST server
+pub struct EnrollCertCmd {
+    /// The device identifier to be used as the common name for the certificate
+    pub device_id: String,
+
+    /// The EST server settings of the cloud profile
+    pub est: EstConfig,
+
+    /// Root certificates used to authenticate the EST server
+    pub http_config: CloudHttpConfig,
+
+    /// The path where the device certificate will be stored
+    pub cert_path: Utf8PathBuf,
+
+    /// The device private key
+    pub key: Key,
+
+    /// The path where the device CSR file will be stored
+    pub csr_path: Utf8PathBuf,
+
+    /// Tell if the CSR has to be generated or is ready to be used
+    pub generate_csr: bool,
+
+    /// The path where the CA certificates returned by the EST server will be stored, if any
+    pub ca_certs_path: Option<Utf8PathBuf>,
+
+    /// CSR template
+    pub csr_template: CsrTemplate,
+}
+
+#[async_trait::async_trait]
+impl Command for EnrollCertCmd {
+    fn description(&self) -> String {
+        format!(
+            "enroll the device {} with the EST server {}",
+            self.device_id,
+            self.est.base_url()
+        )
+    }
+
+    async fn execute(&self, _: TEdgeConfig) -> Result<(), MaybeFancy<Error>> {
+        self.enroll_device_certificate().await?;
+        eprintln!("Certificate enrolled successfully");
+        eprintln!("    => the device can now be connected\n");
+        ShowCertCmd::show(&self.cert_path).await?;
+        Ok(())
+    }
+}
+
+impl EnrollCertCmd {
+    #[instrument(skip_all)]
+    async fn enroll_device_certificate(&self) -> Result<(), Error> {
+        if self.generate_csr {
+            create_device_csr(
+                self.device_id.clone(),
+                self.key.clone(),
+                self.csr_path.clone(),
+                self.csr_template.clone(),
+            )
+            .await?;
+        }
+        let csr = read_cert_to_string(&self.csr_path).await?;
+
+        let est = self
+            .est
+            .client(&self.http_config, self.est.bootstrap_cert())
+            .await
+            .context("Fail to build the EST client")?;
+
+        if let Some(ca_certs_path) = &self.ca_certs_path {
+            let ca_certs = est.cacerts().await.map_err(with_webpki_error)?;
+            override_public_key(ca_certs_path, ca_certs).await?;
+        }
+
+        let cert = est.simple_enroll(&csr).await.map_err(with_webpki_error)?;
+        override_public_key(&self.cert_path, cert).await?;
+        Ok(())
+    }
+}
+
+/// Command to renew a device certificate using an EST server
+pub struct RenewCertCmd {
+    /// The EST server settings of the cloud profile
+    pub est: EstConfig,
+
+    /// Root certificates used to authenticate the EST server
+    pub http_config: CloudHttpConfig,
+
+    /// The path of the certificate to be renewed
+    pub cert_path: Utf8PathBuf,
+
+    /// The path where the new certificate will be stored
+    pub new_cert_path: Utf8PathBuf,
+
+    /// The private key to re-use
+    pub key: Key,
+
+    /// The path where the device CSR file will be stored
+    pub csr_path: Utf8PathBuf,
+
+    /// Tell if the CSR has to be generated or is ready to be used
+    pub generate_csr: bool,
+
+    /// CSR template
+    pub csr_template: CsrTemplate,
+}
+
+#[async_trait::async_trait]
+impl Command for RenewCertCmd {
+    fn description(&self) -> String {
+        format!(
+            "renew the device certificate with the EST server {}",
+            self.est.base_url()
+        )
+    }
+
+    async fn execute(&self, _: TEdgeConfig) -> Result<(), MaybeFancy<Error>> {
+        self.renew_device_certificate().await?;
+        eprintln!("Certificate renewed successfully");
+        eprintln!("    For an un-interrupted service:");
+        eprintln!("    => the device has to be reconnected to the cloud\n");
+        ShowCertCmd::show(&self.new_cert_path).await?;
+        Ok(())
+    }
+}
+
+impl RenewCertCmd {
+    #[instrument(skip_all)]
+    async fn renew_device_certificate(&self) -> Result<(), Error> {
+        if self.generate_csr {
+            let common_name = certificate_cn(&self.cert_path).await?;
+            create_device_csr(
+                common_name,
+                self.key.clone(),
+                self.csr_path.clone(),
+                self.csr_template.clone(),
+            )
+            .await?;
+        }
+        let csr = read_cert_to_string(&self.csr_path).await?;
+
+        // The current certificate is used to authenticate the renewal request,
+        // unless the private key is not accessible, being stored on a HSM.
+        let client_cert = match &self.key {
+            Key::Local(key_path) => Some((self.cert_path.as_path(), key_path.as_path())),
+            Key::Cryptoki(_) => None,
+        };
+        let est = self
+            .est
+            .client(&self.http_config, client_cert)
+            .await
+            .context("Fail to build the EST client")?;
+
+        let cert = est.simple_reenroll(&csr).await.map_err(with_webpki_error)?;
+        override_public_key(&self.new_cert_path, cert).await?;
+        Ok(())
+    }
+}
+
+fn with_webpki_error(err: EstError) -> Error {
+    match err {
+        EstError::Http { url, source } => Error::new(get_webpki_error_from_reqwest(source))
+            .context(format!("Fail to connect to the EST server {url}")),
+        err => err.into(),
+    }
+}
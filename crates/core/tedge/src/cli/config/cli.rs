@@ -2,6 +2,7 @@ use crate::cli::common::profile_completions;
 use crate::cli::config::commands::*;
 use crate::command::*;
 use crate::ConfigError;
+use camino::Utf8PathBuf;
 use clap_complete::ArgValueCandidates;
 use tedge_config::tedge_toml::ProfileName;
 use tedge_config::tedge_toml::ReadableKey;
@@ -24,6 +25,10 @@ pub enum ConfigCmd {
         #[clap(long)]
         #[arg(add = ArgValueCandidates::new(profile_completions))]
         profile: Option<ProfileName>,
+
+        /// Prints where the value comes from, before the value: `file:<path>`, `env:<variable>` or `default`
+        #[clap(long)]
+        show_origin: bool,
     },
 
     /// Set or update the provided configuration key with the given value
@@ -112,6 +117,18 @@ pub enum ConfigCmd {
         /// Prints only the keys that contain the provided filter string
         filter: Option<String>,
     },
+
+    /// Print the JSON schema describing the content of tedge.toml
+    Schema,
+
+    /// Check a configuration file for unknown keys and invalid values
+    ///
+    /// The file is checked as tedge.toml or a tedge.toml.d drop-in file would be,
+    /// without loading it or modifying the current configuration.
+    Validate {
+        /// The path of the configuration file to check
+        file: Utf8PathBuf,
+    },
 }
 
 #[macro_export]
@@ -135,8 +152,13 @@ macro_rules! try_with_profile {
 impl BuildCommand for ConfigCmd {
     fn build_command(self, _: &TEdgeConfig) -> Result<Box<dyn Command>, ConfigError> {
         match self {
-            ConfigCmd::Get { key, profile } => Ok(GetConfigCommand {
+            ConfigCmd::Get {
+                key,
+                profile,
+                show_origin,
+            } => Ok(GetConfigCommand {
                 key: try_with_profile!(key, profile),
+                show_origin,
             }
             .into_boxed()),
             ConfigCmd::Set {
@@ -180,6 +202,8 @@ impl BuildCommand for ConfigCmd {
                 filter,
             }
             .into_boxed()),
+            ConfigCmd::Schema => Ok(ConfigSchemaCommand.into_boxed()),
+            ConfigCmd::Validate { file } => Ok(ValidateConfigCommand { file }.into_boxed()),
         }
     }
 }
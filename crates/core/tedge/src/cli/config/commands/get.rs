@@ -6,6 +6,7 @@ use crate::log::MaybeFancy;
 
 pub struct GetConfigCommand {
     pub key: ReadableKey,
+    pub show_origin: bool,
 }
 
 #[async_trait::async_trait]
@@ -16,6 +17,13 @@ impl Command for GetConfigCommand {
 
     async fn execute(&self, tedge_config: TEdgeConfig) -> Result<(), MaybeFancy<anyhow::Error>> {
         match tedge_config.read_string(&self.key) {
+            Ok(value) if self.show_origin => {
+                let origin = tedge_config
+                    .origin_of(&self.key)
+                    .await
+                    .map_err(anyhow::Error::new)?;
+                println!("{origin}\t{value}");
+            }
             Ok(value) => {
                 println!("{}", value);
             }
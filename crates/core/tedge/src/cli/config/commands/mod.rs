@@ -2,12 +2,16 @@ mod add;
 mod get;
 mod list;
 mod remove;
+mod schema;
 mod set;
 mod unset;
+mod validate;
 
 pub use self::add::*;
 pub use self::get::*;
 pub use self::list::*;
 pub use self::remove::*;
+pub use self::schema::*;
 pub use self::set::*;
 pub use self::unset::*;
+pub use self::validate::*;
@@ -0,0 +1,20 @@
+use crate::command::Command;
+use crate::log::MaybeFancy;
+use tedge_config::TEdgeConfig;
+use tedge_config::TEdgeConfigDto;
+
+pub struct ConfigSchemaCommand;
+
+#[async_trait::async_trait]
+impl Command for ConfigSchemaCommand {
+    fn description(&self) -> String {
+        "print the JSON schema of tedge.toml".into()
+    }
+
+    async fn execute(&self, _: TEdgeConfig) -> Result<(), MaybeFancy<anyhow::Error>> {
+        let schema = serde_json::to_string_pretty(&TEdgeConfigDto::json_schema())
+            .map_err(anyhow::Error::new)?;
+        println!("{schema}");
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+use crate::command::Command;
+use crate::log::MaybeFancy;
+use anyhow::Context;
+use camino::Utf8PathBuf;
+use tedge_config::validate_config_file;
+use tedge_config::TEdgeConfig;
+
+pub struct ValidateConfigCommand {
+    pub file: Utf8PathBuf,
+}
+
+#[async_trait::async_trait]
+impl Command for ValidateConfigCommand {
+    fn description(&self) -> String {
+        format!("validate the configuration file {}", self.file)
+    }
+
+    async fn execute(&self, _: TEdgeConfig) -> Result<(), MaybeFancy<anyhow::Error>> {
+        let content = tokio::fs::read_to_string(&self.file)
+            .await
+            .context("failed to read the file")?;
+        let problems = validate_config_file(&content).map_err(anyhow::Error::new)?;
+
+        if problems.is_empty() {
+            println!("{} is a valid configuration file", self.file);
+            return Ok(());
+        }
+
+        for problem in &problems {
+            println!("{}: {problem}", self.file);
+        }
+        Err(anyhow::anyhow!("{} problem(s) found", problems.len()).into())
+    }
+}
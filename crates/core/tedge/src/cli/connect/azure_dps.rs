@@ -0,0 +1,454 @@
+use crate::cli::log::Spinner;
+use crate::log::MaybeFancy;
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use rumqttc::AsyncClient;
+use rumqttc::Event;
+use rumqttc::Incoming;
+use rumqttc::MqttOptions;
+use rumqttc::Outgoing;
+use rumqttc::Packet;
+use rumqttc::QoS::AtLeastOnce;
+use rumqttc::Transport;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tedge_config::tedge_toml::ProfileName;
+use tedge_config::tedge_toml::WritableKey;
+use tedge_config::TEdgeConfig;
+
+const API_VERSION: &str = "2019-03-31";
+const RESPONSE_TOPICS: &str = "$dps/registrations/res/#";
+const RESPONSE_TOPIC_PREFIX: &str = "$dps/registrations/res/";
+
+/// Time to wait before polling the registration status, if not told by DPS
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(3);
+
+/// Time given to DPS to complete the registration
+const REGISTRATION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The IoT Hub assignment returned by the Azure Device Provisioning Service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DpsAssignment {
+    pub assigned_hub: String,
+    pub device_id: String,
+}
+
+/// Register the device with Azure DPS, when the profile is configured to do so
+///
+/// The IoT Hub and device id assigned by DPS are stored in the profile configuration,
+/// and the updated configuration is returned. The configuration is returned unchanged
+/// when no DPS id scope is set or when the IoT Hub is already known.
+pub(crate) async fn provision_device(
+    tedge_config: TEdgeConfig,
+    profile: Option<&ProfileName>,
+    offline_mode: bool,
+) -> Result<TEdgeConfig, MaybeFancy<anyhow::Error>> {
+    let az_config = tedge_config
+        .az
+        .try_get(profile)
+        .map_err(anyhow::Error::from)?;
+    if az_config.url.or_none().is_some() {
+        return Ok(tedge_config);
+    }
+    let Some(id_scope) = az_config.dps.id_scope.or_none() else {
+        return Ok(tedge_config);
+    };
+    if offline_mode {
+        return Err(anyhow!("The device cannot be registered with Azure DPS in offline mode: the IoT Hub url (az.url) has to be set").into());
+    }
+
+    let registration_id = match az_config.dps.registration_id.or_none() {
+        Some(registration_id) => registration_id.clone(),
+        None => az_config.device.id().map_err(anyhow::Error::from)?.clone(),
+    };
+    let dps_url = &az_config.dps.url;
+    let tls_config = tedge_config
+        .mqtt_auth_config_cloud_broker(az_config)?
+        .to_rustls_client_config()?;
+    let mut mqtt_options = MqttOptions::new(
+        registration_id.clone(),
+        dps_url.host().to_string(),
+        dps_url.port().into(),
+    );
+    mqtt_options.set_transport(Transport::tls_with_config(tls_config.into()));
+
+    let spinner = Spinner::start(format!(
+        "Registering the device {registration_id} with Azure DPS"
+    ));
+    let res = tokio::time::timeout(
+        REGISTRATION_TIMEOUT,
+        register_device(mqtt_options, id_scope, &registration_id),
+    )
+    .await
+    .unwrap_or_else(|_| Err(anyhow!("No response from Azure DPS")));
+    let assignment = spinner.finish(res)?;
+    eprintln!(
+        "Device {} assigned to the IoT Hub {}",
+        assignment.device_id, assignment.assigned_hub
+    );
+
+    let profile = profile.map(|profile| profile.to_string());
+    let root_dir = tedge_config.root_dir().to_owned();
+    tedge_config
+        .update_toml(&|dto, _reader| {
+            dto.try_update_str(
+                &WritableKey::AzUrl(profile.clone()),
+                &assignment.assigned_hub,
+            )?;
+            dto.try_update_str(
+                &WritableKey::AzDeviceId(profile.clone()),
+                &assignment.device_id,
+            )?;
+            Ok(())
+        })
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(TEdgeConfig::load(root_dir)
+        .await
+        .map_err(anyhow::Error::from)?)
+}
+
+// The registration flow over MQTT is described here:
+// https://learn.microsoft.com/en-us/azure/iot-dps/iot-dps-mqtt-support
+//
+// The device connects with its registration id as client id and subscribes to `$dps/registrations/res/#`.
+// The registration request is published on `$dps/registrations/PUT/iotdps-register/?$rid={request id}`.
+// DPS responds on `$dps/registrations/res/{status}/?$rid={request id}&retry-after={seconds}`:
+// - 202 while the registration is processed, the status being polled using the operation id
+//   on `$dps/registrations/GET/iotdps-get-operationstatus/?$rid={request id}&operationId={operation id}`
+// - 200 with the assigned hub and device id once the registration is completed
+// - any other status is an error
+//
+// The MQTT options must provide the endpoint and the TLS config used to authenticate the device.
+pub(crate) async fn register_device(
+    mut mqtt_options: MqttOptions,
+    id_scope: &str,
+    registration_id: &str,
+) -> anyhow::Result<DpsAssignment> {
+    mqtt_options.set_credentials(
+        format!("{id_scope}/registrations/{registration_id}/api-version={API_VERSION}"),
+        "",
+    );
+    mqtt_options.set_keep_alive(Duration::from_secs(30));
+    let (client, mut event_loop) = AsyncClient::new(mqtt_options, 10);
+    client.subscribe(RESPONSE_TOPICS, AtLeastOnce).await?;
+
+    let registration_request = json!({ "registrationId": registration_id }).to_string();
+    let mut request_id: u32 = 0;
+    let result = loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(Packet::SubAck(_))) => {
+                request_id += 1;
+                client
+                    .publish(
+                        format!("$dps/registrations/PUT/iotdps-register/?$rid={request_id}"),
+                        AtLeastOnce,
+                        false,
+                        registration_request.clone(),
+                    )
+                    .await?;
+            }
+            Ok(Event::Incoming(Packet::Publish(response))) => {
+                let payload = String::from_utf8_lossy(&response.payload);
+                match DpsResponse::parse(&response.topic, &payload) {
+                    Ok(DpsResponse::Assigned(assignment)) => break Ok(assignment),
+                    Ok(DpsResponse::Assigning {
+                        operation_id,
+                        retry_after,
+                    }) => {
+                        tokio::time::sleep(retry_after).await;
+                        request_id += 1;
+                        client
+                            .publish(
+                                format!("$dps/registrations/GET/iotdps-get-operationstatus/?$rid={request_id}&operationId={operation_id}"),
+                                AtLeastOnce,
+                                false,
+                                "",
+                            )
+                            .await?;
+                    }
+                    Err(err) => break Err(err),
+                }
+            }
+            Ok(Event::Incoming(Incoming::Disconnect)) => {
+                break Err(anyhow!("Disconnected by Azure DPS during the registration"))
+            }
+            Err(err) => {
+                break Err(anyhow::Error::from(err).context("Failed to connect to Azure DPS"));
+            }
+            _ => {}
+        }
+    };
+
+    // Cleanly disconnect client
+    if client.disconnect().await.is_ok() {
+        loop {
+            match event_loop.poll().await {
+                Ok(Event::Outgoing(Outgoing::Disconnect)) | Err(_) => break,
+                _ => {}
+            }
+        }
+    }
+
+    result
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum DpsResponse {
+    Assigning {
+        operation_id: String,
+        retry_after: Duration,
+    },
+    Assigned(DpsAssignment),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegistrationOperation {
+    operation_id: Option<String>,
+    status: Option<String>,
+    registration_state: Option<RegistrationState>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegistrationState {
+    assigned_hub: Option<String>,
+    device_id: Option<String>,
+    error_message: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DpsErrorResponse {
+    error_code: Option<u64>,
+    message: Option<String>,
+}
+
+impl DpsResponse {
+    fn parse(topic: &str, payload: &str) -> anyhow::Result<Self> {
+        let (status, parameters) = topic
+            .strip_prefix(RESPONSE_TOPIC_PREFIX)
+            .and_then(|suffix| suffix.split_once("/?"))
+            .ok_or_else(|| anyhow!("Unexpected message from Azure DPS on {topic}"))?;
+        let status: u16 = status
+            .parse()
+            .with_context(|| format!("Unexpected message from Azure DPS on {topic}"))?;
+
+        if status >= 300 {
+            let error: Option<DpsErrorResponse> = serde_json::from_str(payload).ok();
+            let (code, message) = error
+                .map(|err| (err.error_code, err.message))
+                .unwrap_or_default();
+            let code = code.map_or_else(|| status.to_string(), |code| code.to_string());
+            let message = message.unwrap_or_else(|| payload.to_string());
+            bail!("Azure DPS registration failed with status {status} (error {code}): {message}")
+        }
+
+        let operation: RegistrationOperation = serde_json::from_str(payload)
+            .with_context(|| format!("Invalid Azure DPS response: {payload}"))?;
+        match operation.status.as_deref() {
+            Some("assigned") => {
+                let state = operation
+                    .registration_state
+                    .ok_or_else(|| anyhow!("Azure DPS returned no registration state"))?;
+                let assigned_hub = state
+                    .assigned_hub
+                    .ok_or_else(|| anyhow!("Azure DPS returned no assigned hub"))?;
+                let device_id = state
+                    .device_id
+                    .ok_or_else(|| anyhow!("Azure DPS returned no device id"))?;
+                Ok(DpsResponse::Assigned(DpsAssignment {
+                    assigned_hub,
+                    device_id,
+                }))
+            }
+            Some("assigning") | Some("unassigned") => {
+                let operation_id = operation
+                    .operation_id
+                    .ok_or_else(|| anyhow!("Azure DPS returned no operation id"))?;
+                let retry_after = parameters
+                    .split('&')
+                    .filter_map(|parameter| parameter.strip_prefix("retry-after="))
+                    .find_map(|seconds| seconds.parse().ok())
+                    .map_or(DEFAULT_RETRY_AFTER, Duration::from_secs);
+                Ok(DpsResponse::Assigning {
+                    operation_id,
+                    retry_after,
+                })
+            }
+            status => {
+                let reason = operation
+                    .registration_state
+                    .and_then(|state| state.error_message)
+                    .unwrap_or_else(|| "no reason given".to_string());
+                bail!(
+                    "Azure DPS registration {}: {reason}",
+                    status.unwrap_or("failed")
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rumqttc::QoS;
+
+    #[test]
+    fn parse_pending_registration() {
+        let response = DpsResponse::parse(
+            "$dps/registrations/res/202/?$rid=1&retry-after=1",
+            r#"{"operationId":"4.d0a671905ea5b2c8.42d78160","status":"assigning"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            response,
+            DpsResponse::Assigning {
+                operation_id: "4.d0a671905ea5b2c8.42d78160".to_string(),
+                retry_after: Duration::from_secs(1)
+            }
+        );
+    }
+
+    #[test]
+    fn parse_completed_registration() {
+        let response = DpsResponse::parse(
+            "$dps/registrations/res/200/?$rid=2",
+            r#"{
+                "operationId":"4.d0a671905ea5b2c8.42d78160",
+                "status":"assigned",
+                "registrationState":{
+                    "registrationId":"my-device",
+                    "assignedHub":"my-hub.azure-devices.net",
+                    "deviceId":"my-device",
+                    "status":"assigned"
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            response,
+            DpsResponse::Assigned(DpsAssignment {
+                assigned_hub: "my-hub.azure-devices.net".to_string(),
+                device_id: "my-device".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_registration_errors() {
+        let err = DpsResponse::parse(
+            "$dps/registrations/res/401/?$rid=1",
+            r#"{"errorCode":401002,"trackingId":"1234","message":"Unauthorized"}"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Azure DPS registration failed with status 401 (error 401002): Unauthorized"
+        );
+
+        let err = DpsResponse::parse(
+            "$dps/registrations/res/200/?$rid=2",
+            r#"{"operationId":"4.1","status":"disabled","registrationState":{"errorMessage":"Enrollment disabled"}}"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Azure DPS registration disabled: Enrollment disabled"
+        );
+    }
+
+    #[tokio::test]
+    async fn register_device_with_a_local_dps() {
+        let broker = mqtt_tests::test_mqtt_broker();
+        spawn_fake_dps(broker.port, "my-hub.azure-devices.net").await;
+
+        let mqtt_options = MqttOptions::new("my-device", "127.0.0.1", broker.port);
+        let assignment = tokio::time::timeout(
+            Duration::from_secs(10),
+            register_device(mqtt_options, "0ne00000000", "my-device"),
+        )
+        .await
+        .expect("registration to complete")
+        .unwrap();
+
+        assert_eq!(
+            assignment,
+            DpsAssignment {
+                assigned_hub: "my-hub.azure-devices.net".to_string(),
+                device_id: "my-device".to_string(),
+            }
+        );
+    }
+
+    /// A stand-in for DPS, responding first that the registration is in progress then assigned
+    async fn spawn_fake_dps(port: u16, assigned_hub: &'static str) {
+        let options = MqttOptions::new("fake-dps", "127.0.0.1", port);
+        let (client, mut event_loop) = AsyncClient::new(options, 10);
+        client
+            .subscribe("$dps/registrations/PUT/#", QoS::AtLeastOnce)
+            .await
+            .unwrap();
+        client
+            .subscribe("$dps/registrations/GET/#", QoS::AtLeastOnce)
+            .await
+            .unwrap();
+
+        let (ready, subscribed) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let mut ready = Some(ready);
+            let mut subscriptions = 0;
+            loop {
+                match event_loop.poll().await {
+                    Ok(Event::Incoming(Packet::SubAck(_))) => {
+                        subscriptions += 1;
+                        if subscriptions == 2 {
+                            if let Some(ready) = ready.take() {
+                                let _ = ready.send(());
+                            }
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::Publish(request))) => {
+                        let rid = request.topic.split("$rid=").nth(1).unwrap_or("0");
+                        let rid = rid.split('&').next().unwrap_or("0").to_string();
+                        let (topic, payload) = if request.topic.contains("/PUT/") {
+                            let request: serde_json::Value =
+                                serde_json::from_slice(&request.payload).unwrap();
+                            assert_eq!(request["registrationId"], "my-device");
+                            (
+                                format!("$dps/registrations/res/202/?$rid={rid}&retry-after=0"),
+                                json!({"operationId": "4.1234", "status": "assigning"}),
+                            )
+                        } else {
+                            assert!(request.topic.contains("operationId=4.1234"));
+                            (
+                                format!("$dps/registrations/res/200/?$rid={rid}"),
+                                json!({
+                                    "operationId": "4.1234",
+                                    "status": "assigned",
+                                    "registrationState": {
+                                        "registrationId": "my-device",
+                                        "assignedHub": assigned_hub,
+                                        "deviceId": "my-device",
+                                        "status": "assigned",
+                                    }
+                                }),
+                            )
+                        };
+                        client
+                            .publish(topic, QoS::AtLeastOnce, false, payload.to_string())
+                            .await
+                            .unwrap();
+                    }
+                    Err(_) => break,
+                    _ => {}
+                }
+            }
+        });
+        subscribed.await.unwrap();
+    }
+}
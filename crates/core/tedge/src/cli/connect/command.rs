@@ -94,6 +94,15 @@ impl Command for ConnectCommand {
     }
 
     async fn execute(&self, tedge_config: TEdgeConfig) -> Result<(), MaybeFancy<anyhow::Error>> {
+        #[cfg(feature = "azure")]
+        let tedge_config = match &self.cloud {
+            Cloud::Azure(profile) if !self.is_test_connection => {
+                azure_dps::provision_device(tedge_config, profile.as_deref(), self.offline_mode)
+                    .await?
+            }
+            _ => tedge_config,
+        };
+
         let bridge_config =
             bridge_config(&tedge_config, &self.cloud).map_err(anyhow::Error::new)?;
         let credentials_path =
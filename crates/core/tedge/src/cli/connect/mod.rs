@@ -6,6 +6,8 @@ pub use self::error::*;
 mod aws;
 #[cfg(feature = "azure")]
 mod azure;
+#[cfg(feature = "azure")]
+mod azure_dps;
 #[cfg(feature = "c8y")]
 mod c8y;
 mod cli;
@@ -19,6 +19,10 @@ pub enum TEdgeDiagCli {
         #[clap(long, value_delimiter = ',')]
         plugin_dir: Option<Vec<String>>,
 
+        /// Names of the plugins to run, without their file extension. All the plugins are run by default
+        #[clap(long, value_delimiter = ',')]
+        plugins: Option<Vec<String>>,
+
         /// Directory where output tarball and temporary output files are stored. The path from tmp.path will be used by default
         #[clap(long)]
         output_dir: Option<Utf8PathBuf>,
@@ -48,6 +52,7 @@ impl BuildCommand for TEdgeDiagCli {
         match self {
             TEdgeDiagCli::Collect {
                 plugin_dir,
+                plugins,
                 output_dir,
                 name,
                 keep_dir,
@@ -66,6 +71,7 @@ impl BuildCommand for TEdgeDiagCli {
 
                 let cmd = DiagCollectCommand {
                     plugin_dir,
+                    plugins: plugins.map(BTreeSet::from_iter),
                     config_dir: get_absolute_path(config.root_dir().to_path_buf())?,
                     working_dir: get_absolute_path(output_dir.clone())?,
                     diag_dir: get_absolute_path(output_dir.join(&tarball_name))?,
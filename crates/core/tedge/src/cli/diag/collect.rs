@@ -23,6 +23,8 @@ use uzers;
 #[derive(Debug)]
 pub struct DiagCollectCommand {
     pub plugin_dir: BTreeSet<AbsolutePath>,
+    /// The names of the plugins to run, all the plugins being run if `None`
+    pub plugins: Option<BTreeSet<String>>,
     pub config_dir: AbsolutePath,
     pub working_dir: AbsolutePath,
     pub diag_dir: AbsolutePath,
@@ -141,6 +143,10 @@ impl DiagCollectCommand {
 
         while let Some(entry) = entries.next_entry().await? {
             if let Ok(path) = Utf8PathBuf::from_path_buf(entry.path()) {
+                if !self.is_selected(&path) {
+                    debug!("Skipping file: {path} (not selected)");
+                    continue;
+                }
                 if validate_plugin(&path, logger).await {
                     plugins.insert(path);
                     continue;
@@ -152,6 +158,15 @@ impl DiagCollectCommand {
         Ok(plugins)
     }
 
+    /// Tell if a plugin has been selected, using its file name without extension
+    fn is_selected(&self, plugin_path: &Utf8Path) -> bool {
+        match (&self.plugins, plugin_path.file_stem()) {
+            (None, _) => true,
+            (Some(plugins), Some(name)) => plugins.contains(name),
+            (Some(_), None) => false,
+        }
+    }
+
     async fn execute_diag_plugin(
         &self,
         plugin_path: &Utf8Path,
@@ -281,6 +296,29 @@ mod tests {
         assert_eq!(plugins.len(), 6);
     }
 
+    #[tokio::test]
+    async fn read_diag_plugins_only_returns_the_selected_plugins() {
+        let ttd = TempTedgeDir::new();
+        let mut command = DiagCollectCommand::new(&ttd);
+        command.plugins = Some(BTreeSet::from([
+            "plugin_a".to_string(),
+            "plugin_c".to_string(),
+        ]));
+        with_exec_permission(command.first_plugin_dir().join("plugin_a.sh"), "pwd");
+        with_exec_permission(command.first_plugin_dir().join("plugin_b.sh"), "pwd");
+        with_exec_permission(command.first_plugin_dir().join("plugin_c"), "pwd");
+
+        let mut logger = DualLogger::new(command.diag_dir.join("summary.log")).unwrap();
+        let plugins = command.read_diag_plugins(&mut logger).await.unwrap();
+        assert_eq!(
+            plugins,
+            BTreeSet::from([
+                command.first_plugin_dir().join("plugin_a.sh"),
+                command.first_plugin_dir().join("plugin_c"),
+            ])
+        );
+    }
+
     #[tokio::test]
     async fn test_read_diag_plugins_ignores_not_existing_plugin_dirs() {
         let ttd = TempTedgeDir::new();
@@ -402,6 +440,7 @@ mod tests {
                 plugin_dir: BTreeSet::from([
                     AbsolutePath::from_path(plugin_dir.utf8_path_buf()).unwrap()
                 ]),
+                plugins: None,
                 config_dir: AbsolutePath::from_path(config_dir.utf8_path_buf()).unwrap(),
                 working_dir: AbsolutePath::from_path(working_dir.utf8_path_buf()).unwrap(),
                 diag_dir: AbsolutePath::from_path(diag_dir.utf8_path_buf()).unwrap(),
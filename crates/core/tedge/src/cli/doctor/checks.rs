@@ -0,0 +1,628 @@
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use certificate::parse_root_certificate::check_client_auth_key;
+use certificate::PemCertificate;
+use certificate::ValidityStatus;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::os::unix::fs::MetadataExt;
+use std::time::Duration;
+use tedge_api::health::HealthStatus;
+use tedge_api::health::Status;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_config::models::auth_method::AuthType;
+use tedge_config::models::TopicPrefix;
+use tedge_config::tedge_toml::CloudConfig;
+use tedge_config::validate_config_file;
+use tedge_config::TEdgeConfig;
+use tedge_utils::resources::DiskUsage;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// The user expected to own the thin-edge directories
+pub const TEDGE_USER: &str = "tedge";
+
+/// Below this amount of free space, thin-edge is likely to fail to store files
+const CRITICAL_DISK_SPACE: u64 = 10 * 1024 * 1024;
+
+/// Below this amount of free space, software updates and log uploads are likely to fail
+const LOW_DISK_SPACE: u64 = 100 * 1024 * 1024;
+
+/// 2025-01-01T00:00:00Z, a clock set before this date has not been synchronized
+const MIN_PLAUSIBLE_TIMESTAMP: i64 = 1_735_689_600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Error,
+    Skipped,
+}
+
+/// The outcome of a single check
+#[derive(Debug, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl CheckResult {
+    fn new(name: impl Into<String>, status: CheckStatus, message: impl Into<String>) -> Self {
+        CheckResult {
+            name: name.into(),
+            status,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn ok(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Ok, message)
+    }
+
+    pub fn warning(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Warning, message)
+    }
+
+    pub fn error(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Error, message)
+    }
+
+    pub fn skipped(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Skipped, message)
+    }
+
+    pub fn with_hint(self, hint: impl Into<String>) -> Self {
+        CheckResult {
+            hint: Some(hint.into()),
+            ..self
+        }
+    }
+}
+
+/// Check that `tedge.toml` and the drop-in files only contain known settings with valid values,
+/// and that the configuration merged from all these files can be loaded
+pub async fn check_config_file(config_dir: &Utf8Path, drop_in_dir: &Utf8Path) -> CheckResult {
+    let name = "config";
+    let mut paths = drop_in_files(drop_in_dir).await;
+    let toml_path = config_dir.join("tedge.toml");
+    if tokio::fs::try_exists(&toml_path).await.unwrap_or(false) {
+        paths.push(toml_path);
+    }
+    if paths.is_empty() {
+        return CheckResult::ok(
+            name,
+            "no configuration file found, the default settings are used",
+        );
+    }
+
+    for path in &paths {
+        let content = match tokio::fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(err) => {
+                return CheckResult::error(name, format!("cannot read {path}: {err}"))
+                    .with_hint(format!("check the permissions of {path}"))
+            }
+        };
+        match validate_config_file(&content) {
+            Ok(problems) if problems.is_empty() => (),
+            Ok(problems) => {
+                let problems: Vec<_> = problems.iter().map(|p| p.to_string()).collect();
+                return CheckResult::error(name, format!("{path}: {}", problems.join("; ")))
+                    .with_hint(format!("fix or remove the listed settings from {path}"));
+            }
+            Err(err) => {
+                return CheckResult::error(name, format!("{path} cannot be parsed: {err}"))
+                    .with_hint(format!("fix the syntax of {path}"))
+            }
+        }
+    }
+
+    if let Err(err) = TEdgeConfig::load(config_dir).await {
+        return CheckResult::error(name, format!("the configuration cannot be loaded: {err:#}"))
+            .with_hint(format!(
+                "check the settings of {config_dir}/tedge.toml, the files of {drop_in_dir} and the TEDGE_* environment variables"
+            ));
+    }
+
+    let paths: Vec<_> = paths.iter().map(|path| path.as_str()).collect();
+    CheckResult::ok(
+        name,
+        format!("valid configuration files: {}", paths.join(", ")),
+    )
+}
+
+/// List the drop-in configuration files, in the order they are merged
+async fn drop_in_files(drop_in_dir: &Utf8Path) -> Vec<Utf8PathBuf> {
+    let Ok(mut entries) = tokio::fs::read_dir(drop_in_dir).await else {
+        return vec![];
+    };
+    let mut paths = vec![];
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Ok(path) = Utf8PathBuf::try_from(entry.path()) else {
+            continue;
+        };
+        if path.extension() == Some("toml") && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths
+}
+
+/// Check that a directory exists, is owned by the expected user and accessible to that user
+pub fn check_directory(name: &str, path: &Utf8Path, expected_owner: &str) -> CheckResult {
+    let name = format!("{name} directory");
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return CheckResult::error(name, format!("{path} doesn't exist"))
+                .with_hint("run `sudo tedge init` to create the thin-edge directories")
+        }
+        Err(err) => return CheckResult::error(name, format!("cannot access {path}: {err}")),
+    };
+
+    if !metadata.is_dir() {
+        return CheckResult::error(name, format!("{path} is not a directory"));
+    }
+
+    let mode = metadata.mode() & 0o777;
+    if mode & 0o700 != 0o700 {
+        return CheckResult::error(
+            name,
+            format!("{path} is not fully accessible to its owner (mode {mode:o})"),
+        )
+        .with_hint(format!("run `sudo chmod u+rwx {path}`"));
+    }
+
+    let owner = uzers::get_user_by_uid(metadata.uid())
+        .map(|user| user.name().to_string_lossy().into_owned())
+        .unwrap_or_else(|| metadata.uid().to_string());
+    if owner != expected_owner {
+        return CheckResult::warning(
+            name,
+            format!("{path} is owned by {owner} instead of {expected_owner}"),
+        )
+        .with_hint(format!(
+            "run `sudo chown -R {expected_owner}:{expected_owner} {path}` or `sudo tedge init`"
+        ));
+    }
+
+    CheckResult::ok(name, format!("{path} is owned by {owner} (mode {mode:o})"))
+}
+
+/// A cloud profile as seen by the checks
+pub struct CloudProfile<'a> {
+    cloud: &'static str,
+    profile: Option<&'a str>,
+    cert_path: &'a Utf8Path,
+    key_path: &'a Utf8Path,
+    key_on_hsm: bool,
+    uses_certificate: bool,
+    pub topic_prefix: &'a TopicPrefix,
+}
+
+impl<'a> CloudProfile<'a> {
+    fn new(
+        cloud: &'static str,
+        profile: Option<&'a str>,
+        config: &'a impl CloudConfig,
+        uses_certificate: bool,
+        topic_prefix: &'a TopicPrefix,
+    ) -> Self {
+        CloudProfile {
+            cloud,
+            profile,
+            cert_path: config.device_cert_path(),
+            key_path: config.device_key_path(),
+            key_on_hsm: config.key_uri().is_some(),
+            uses_certificate,
+            topic_prefix,
+        }
+    }
+
+    /// The name of the profile, as used for the bridge configuration file: e.g. `c8y` or `c8y@test`
+    pub fn name(&self) -> String {
+        match self.profile {
+            None => self.cloud.to_string(),
+            Some(profile) => format!("{}@{profile}", self.cloud),
+        }
+    }
+
+    /// The arguments to be given to `tedge` commands to target this cloud profile
+    pub fn cli_args(&self) -> String {
+        match self.profile {
+            None => self.cloud.to_string(),
+            Some(profile) => format!("{} --profile {profile}", self.cloud),
+        }
+    }
+
+    /// Tell if the device is connected to this cloud profile
+    ///
+    /// The bridge configuration file of a cloud profile is created on `tedge connect`
+    /// and removed on `tedge disconnect`.
+    pub fn is_connected(&self, config_dir: &Utf8Path) -> bool {
+        config_dir
+            .join("mosquitto-conf")
+            .join(format!("{}-bridge.conf", self.name()))
+            .exists()
+    }
+}
+
+/// All the cloud profiles configured in `tedge.toml`
+pub fn cloud_profiles(config: &TEdgeConfig) -> Vec<CloudProfile<'_>> {
+    let mut profiles = Vec::new();
+    for (profile, c8y) in config.c8y.entries() {
+        let uses_certificate =
+            c8y.auth_method.to_type(&c8y.credentials_path) == AuthType::Certificate;
+        profiles.push(CloudProfile::new(
+            "c8y",
+            profile,
+            c8y,
+            uses_certificate,
+            &c8y.bridge.topic_prefix,
+        ));
+    }
+    for (profile, az) in config.az.entries() {
+        profiles.push(CloudProfile::new(
+            "az",
+            profile,
+            az,
+            true,
+            &az.bridge.topic_prefix,
+        ));
+    }
+    for (profile, aws) in config.aws.entries() {
+        profiles.push(CloudProfile::new(
+            "aws",
+            profile,
+            aws,
+            true,
+            &aws.bridge.topic_prefix,
+        ));
+    }
+    profiles
+}
+
+/// Check the validity period of the device certificate used for a cloud profile
+/// and that the private key matches this certificate
+pub fn check_certificate(cloud: &CloudProfile<'_>, minimum_validity: Duration) -> Vec<CheckResult> {
+    let name = format!("{} certificate", cloud.name());
+    if !cloud.uses_certificate {
+        return vec![CheckResult::skipped(
+            name,
+            "the device authenticates with basic credentials",
+        )];
+    }
+
+    let cert_path = cloud.cert_path;
+    let certificate = match PemCertificate::from_pem_file(cert_path) {
+        Ok(certificate) => certificate,
+        Err(err) => {
+            return vec![
+                CheckResult::error(name, format!("cannot read {cert_path}: {err}")).with_hint(
+                    format!(
+                        "create a certificate with `tedge cert create {}`",
+                        cloud.cli_args()
+                    ),
+                ),
+            ]
+        }
+    };
+
+    let renew_hint = format!(
+        "renew the certificate with `tedge cert renew {}`",
+        cloud.cli_args()
+    );
+    let validity = match certificate.still_valid() {
+        Ok(ValidityStatus::Valid { expired_in }) if expired_in > minimum_validity => {
+            CheckResult::ok(
+                &name,
+                format!("{cert_path} expires in {}", format_duration(expired_in)),
+            )
+        }
+        Ok(ValidityStatus::Valid { expired_in }) => CheckResult::warning(
+            &name,
+            format!(
+                "{cert_path} expires soon, in {}",
+                format_duration(expired_in)
+            ),
+        )
+        .with_hint(renew_hint),
+        Ok(ValidityStatus::Expired { since }) => CheckResult::error(
+            &name,
+            format!("{cert_path} expired {} ago", format_duration(since)),
+        )
+        .with_hint(renew_hint),
+        Ok(ValidityStatus::NotValidYet { valid_in }) => CheckResult::error(
+            &name,
+            format!(
+                "{cert_path} is not valid yet, it will be in {}",
+                format_duration(valid_in)
+            ),
+        )
+        .with_hint("check that the system clock is synchronized"),
+        Err(err) => CheckResult::error(&name, format!("{cert_path} cannot be parsed: {err}")),
+    };
+
+    let key_name = format!("{} private key", cloud.name());
+    let key_path = cloud.key_path;
+    let key_match = if cloud.key_on_hsm {
+        CheckResult::skipped(key_name, "the private key is stored on an HSM")
+    } else {
+        match check_client_auth_key(key_path, cert_path) {
+            Ok(()) => CheckResult::ok(key_name, format!("{key_path} matches {cert_path}")),
+            Err(err) => CheckResult::error(
+                key_name,
+                format!("{key_path} cannot be used with {cert_path}: {err}"),
+            )
+            .with_hint(format!(
+                "restore the private key of the certificate or create a new certificate with `tedge cert create {}`",
+                cloud.cli_args()
+            )),
+        }
+    };
+
+    vec![validity, key_match]
+}
+
+/// Check the health status of a service, as published on its `status/health` channel
+pub fn check_service_health(entity: &EntityTopicId, health: &HealthStatus) -> CheckResult {
+    let service = entity
+        .default_service_name()
+        .map(str::to_string)
+        .unwrap_or_else(|| entity.to_string());
+    let name = format!("{service} health");
+    match &health.status {
+        Status::Up => CheckResult::ok(name, format!("{entity} is up")),
+        Status::Down => CheckResult::warning(name, format!("{entity} is down")).with_hint(format!(
+            "check the service logs, e.g. with `journalctl -u {service}`"
+        )),
+        Status::Other(status) => CheckResult::warning(
+            name,
+            format!("{entity} reports an unknown status: {status:?}"),
+        ),
+    }
+}
+
+/// Check the health status of the bridge of a connected cloud profile
+pub fn check_bridge_health(
+    cloud: &CloudProfile<'_>,
+    health_topic: &str,
+    statuses: &BTreeMap<String, (EntityTopicId, HealthStatus)>,
+) -> CheckResult {
+    let name = format!("{} bridge", cloud.name());
+    let reconnect_hint = format!(
+        "check the network connectivity and run `tedge reconnect {}`",
+        cloud.cli_args()
+    );
+    match statuses.get(health_topic).map(|(_, health)| &health.status) {
+        Some(Status::Up) => CheckResult::ok(name, "the bridge is connected"),
+        Some(Status::Down) => {
+            CheckResult::error(name, "the bridge is disconnected").with_hint(reconnect_hint)
+        }
+        Some(Status::Other(status)) => CheckResult::warning(
+            name,
+            format!("the bridge reports an unknown status: {status:?}"),
+        ),
+        None => CheckResult::warning(
+            name,
+            format!("no health status has been published on {health_topic}"),
+        )
+        .with_hint(reconnect_hint),
+    }
+}
+
+/// Check that the system clock has been set
+pub fn check_clock(now: OffsetDateTime) -> CheckResult {
+    let name = "clock";
+    let now_str = now.format(&Rfc3339).unwrap_or_else(|_| now.to_string());
+    if now.unix_timestamp() < MIN_PLAUSIBLE_TIMESTAMP {
+        return CheckResult::error(name, format!("the system time is {now_str}"))
+            .with_hint("synchronize the system clock, e.g. with `timedatectl set-ntp true`: TLS connections are rejected when the clock is wrong");
+    }
+    CheckResult::ok(name, format!("the system time is {now_str}"))
+}
+
+/// Check the free space on the file system of a directory
+pub fn check_disk_space(name: &str, path: &Utf8Path) -> CheckResult {
+    match DiskUsage::of(path) {
+        Ok(usage) => disk_space_result(name, path, usage.available),
+        Err(err) => CheckResult::warning(
+            format!("{name} disk space"),
+            format!("cannot get the free space of {path}: {err}"),
+        ),
+    }
+}
+
+fn disk_space_result(name: &str, path: &Utf8Path, available: u64) -> CheckResult {
+    let name = format!("{name} disk space");
+    let message = format!("{} MiB available for {path}", available / (1024 * 1024));
+    let hint = format!("free some space on the file system of {path}");
+    if available < CRITICAL_DISK_SPACE {
+        CheckResult::error(name, message).with_hint(hint)
+    } else if available < LOW_DISK_SPACE {
+        CheckResult::warning(name, message).with_hint(hint)
+    } else {
+        CheckResult::ok(name, message)
+    }
+}
+
+/// Check that the tedge user is allowed to run `tedge-write` with sudo
+pub async fn check_sudoers(config: &TEdgeConfig) -> CheckResult {
+    let name = "sudo";
+    if !config.sudo.enable {
+        return CheckResult::skipped(
+            name,
+            "sudo.enable is false, tedge-write is run without sudo",
+        );
+    }
+
+    let Ok(tedge_write) = which::which_global("tedge-write") else {
+        return CheckResult::error(name, "tedge-write cannot be found in the PATH")
+            .with_hint("run `sudo tedge init` to create the tedge-write symlink");
+    };
+    let Ok(sudo) = which::which_global("sudo") else {
+        return CheckResult::warning(name, "sudo is not installed").with_hint(
+            "install sudo or set sudo.enable to false with `tedge config set sudo.enable false`",
+        );
+    };
+
+    let mut command = tokio::process::Command::new(sudo);
+    command.arg("-n").arg("-l");
+    if uzers::get_current_uid() == 0 {
+        command.arg("-U").arg(TEDGE_USER);
+    } else if uzers::get_current_username().is_none_or(|user| user != TEDGE_USER) {
+        return CheckResult::skipped(
+            name,
+            "the sudo rules can only be checked by root or the tedge user",
+        )
+        .with_hint("run `sudo tedge doctor`");
+    }
+    let target = config.root_dir().join("tedge.toml");
+    command.arg(&tedge_write).arg(target.as_str());
+
+    let tedge_write = tedge_write.display();
+    match command.output().await {
+        Ok(output) if output.status.success() => CheckResult::ok(
+            name,
+            format!("{TEDGE_USER} is allowed to run {tedge_write} with sudo"),
+        ),
+        Ok(_) => CheckResult::error(
+            name,
+            format!("{TEDGE_USER} is not allowed to run {tedge_write} with sudo"),
+        )
+        .with_hint(format!(
+            "add the rule `{TEDGE_USER} ALL = (ALL) NOPASSWD:SETENV: {tedge_write} /etc/*` to /etc/sudoers.d/tedge"
+        )),
+        Err(err) => CheckResult::warning(name, format!("cannot run sudo: {err}")),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    humantime::format_duration(Duration::from_secs(duration.as_secs())).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[test]
+    fn the_report_of_a_check_is_serialized_with_lowercase_status() {
+        let result = CheckResult::warning("clock", "the system time is wrong").with_hint("sync");
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "name": "clock",
+                "status": "warning",
+                "message": "the system time is wrong",
+                "hint": "sync",
+            })
+        );
+    }
+
+    #[test]
+    fn a_missing_hint_is_not_serialized() {
+        let result = CheckResult::ok("clock", "fine");
+        let json = serde_json::to_value(&result).unwrap();
+        assert!(json.get("hint").is_none());
+    }
+
+    #[test]
+    fn a_clock_set_in_the_past_is_an_error() {
+        let epoch = OffsetDateTime::UNIX_EPOCH;
+        assert_eq!(check_clock(epoch).status, CheckStatus::Error);
+
+        let plausible =
+            OffsetDateTime::from_unix_timestamp(MIN_PLAUSIBLE_TIMESTAMP + 3600).unwrap();
+        assert_eq!(check_clock(plausible).status, CheckStatus::Ok);
+    }
+
+    #[test]
+    fn disk_space_is_checked_against_thresholds() {
+        let path = Utf8Path::new("/var/tedge");
+        let mib = 1024 * 1024;
+        assert_eq!(
+            disk_space_result("data", path, 5 * mib).status,
+            CheckStatus::Error
+        );
+        assert_eq!(
+            disk_space_result("data", path, 50 * mib).status,
+            CheckStatus::Warning
+        );
+        assert_eq!(
+            disk_space_result("data", path, 500 * mib).status,
+            CheckStatus::Ok
+        );
+    }
+
+    #[tokio::test]
+    async fn the_drop_in_files_are_checked() {
+        let ttd = TempTedgeDir::new();
+        ttd.file("tedge.toml")
+            .with_raw_content("[device]\ntype = \"gateway\"\n");
+        let drop_ins = ttd.dir("tedge.toml.d");
+        drop_ins
+            .file("10-base.toml")
+            .with_raw_content("[mqtt.bind]\nport = 1883\n");
+        let drop_in_dir = ttd.utf8_path().join("tedge.toml.d");
+
+        let result = check_config_file(ttd.utf8_path(), &drop_in_dir).await;
+        assert_eq!(result.status, CheckStatus::Ok, "{}", result.message);
+        assert!(result.message.contains("10-base.toml"));
+
+        drop_ins
+            .file("20-site.toml")
+            .with_raw_content("[mqtt.bind]\nunknown = 1\n");
+
+        let result = check_config_file(ttd.utf8_path(), &drop_in_dir).await;
+        assert_eq!(result.status, CheckStatus::Error);
+        assert!(
+            result.message.contains("20-site.toml"),
+            "{}",
+            result.message
+        );
+    }
+
+    #[test]
+    fn a_missing_directory_is_an_error() {
+        let ttd = TempTedgeDir::new();
+        let path = ttd.utf8_path().join("missing");
+        let result = check_directory("data", &path, TEDGE_USER);
+        assert_eq!(result.status, CheckStatus::Error);
+        assert!(result.hint.is_some());
+    }
+
+    #[test]
+    fn a_directory_owned_by_another_user_is_a_warning() {
+        let ttd = TempTedgeDir::new();
+        let current_user = uzers::get_current_username().unwrap();
+        let current_user = current_user.to_str().unwrap();
+
+        let result = check_directory("data", ttd.utf8_path(), current_user);
+        assert_eq!(result.status, CheckStatus::Ok);
+
+        let result = check_directory("data", ttd.utf8_path(), "not-the-owner");
+        assert_eq!(result.status, CheckStatus::Warning);
+    }
+
+    #[test]
+    fn services_reported_down_are_flagged() {
+        let entity: EntityTopicId = "device/main/service/tedge-agent".parse().unwrap();
+        let up = HealthStatus { status: Status::Up };
+        let down = HealthStatus {
+            status: Status::Down,
+        };
+
+        assert_eq!(check_service_health(&entity, &up).status, CheckStatus::Ok);
+        let result = check_service_health(&entity, &down);
+        assert_eq!(result.status, CheckStatus::Warning);
+        assert_eq!(result.name, "tedge-agent health");
+    }
+}
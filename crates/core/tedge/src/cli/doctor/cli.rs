@@ -0,0 +1,27 @@
+use super::command::DoctorCommand;
+use crate::command::BuildCommand;
+use crate::command::Command;
+use crate::ConfigError;
+use tedge_config::models::SecondsOrHumanTime;
+use tedge_config::TEdgeConfig;
+
+#[derive(clap::Args, Debug)]
+pub struct TEdgeDoctorCli {
+    /// Print the report as JSON on stdout
+    #[clap(long)]
+    json: bool,
+
+    /// How long to wait for the MQTT broker and the retained health status messages
+    #[clap(long, default_value = "2s")]
+    timeout: SecondsOrHumanTime,
+}
+
+impl BuildCommand for TEdgeDoctorCli {
+    fn build_command(self, _: &TEdgeConfig) -> Result<Box<dyn Command>, ConfigError> {
+        Ok(DoctorCommand {
+            json: self.json,
+            timeout: self.timeout.duration(),
+        }
+        .into_boxed())
+    }
+}
@@ -0,0 +1,240 @@
+use super::checks::check_bridge_health;
+use super::checks::check_certificate;
+use super::checks::check_clock;
+use super::checks::check_config_file;
+use super::checks::check_directory;
+use super::checks::check_disk_space;
+use super::checks::check_service_health;
+use super::checks::check_sudoers;
+use super::checks::cloud_profiles;
+use super::checks::CheckResult;
+use super::checks::CheckStatus;
+use super::checks::TEDGE_USER;
+use crate::cli::bridge_health_topic;
+use crate::command::Command;
+use crate::log::MaybeFancy;
+use anyhow::Context;
+use mqtt_channel::StreamExt;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tedge_api::health::HealthStatus;
+use tedge_api::mqtt_topics::ChannelFilter;
+use tedge_api::mqtt_topics::EntityFilter;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_config::TEdgeConfig;
+use time::OffsetDateTime;
+use tokio::time::Instant;
+use yansi::Paint as _;
+
+/// How long to wait for more retained health messages after the last one received
+const QUIET_PERIOD: Duration = Duration::from_millis(500);
+
+pub struct DoctorCommand {
+    pub json: bool,
+    pub timeout: Duration,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Report {
+    checks: Vec<CheckResult>,
+}
+
+impl Report {
+    fn push(&mut self, result: CheckResult) {
+        self.checks.push(result)
+    }
+
+    fn count(&self, status: CheckStatus) -> usize {
+        self.checks.iter().filter(|c| c.status == status).count()
+    }
+
+    fn print(&self) {
+        for check in &self.checks {
+            let mark = match check.status {
+                CheckStatus::Ok => "✓".green().bold(),
+                CheckStatus::Warning => "⚠".yellow().bold(),
+                CheckStatus::Error => "✗".red().bold(),
+                CheckStatus::Skipped => "-".dim(),
+            };
+            println!("{mark} {}: {}", check.name.bold(), check.message);
+            if let Some(hint) = &check.hint {
+                println!("    {} {hint}", "hint:".cyan());
+            }
+        }
+        println!(
+            "\n{} ok, {} warning(s), {} error(s), {} skipped",
+            self.count(CheckStatus::Ok),
+            self.count(CheckStatus::Warning),
+            self.count(CheckStatus::Error),
+            self.count(CheckStatus::Skipped),
+        );
+    }
+}
+
+#[async_trait::async_trait]
+impl Command for DoctorCommand {
+    fn description(&self) -> String {
+        "check the thin-edge installation".to_string()
+    }
+
+    async fn execute(&self, config: TEdgeConfig) -> Result<(), MaybeFancy<anyhow::Error>> {
+        let report = self.run_checks(&config).await;
+
+        if self.json {
+            let json = serde_json::to_string_pretty(&report).context("serializing the report")?;
+            println!("{json}");
+        } else {
+            report.print();
+        }
+
+        match report.count(CheckStatus::Error) {
+            0 => Ok(()),
+            errors => Err(anyhow::anyhow!("{errors} problem(s) found").into()),
+        }
+    }
+}
+
+impl DoctorCommand {
+    async fn run_checks(&self, config: &TEdgeConfig) -> Report {
+        let mut report = Report::default();
+        let config_dir = config.root_dir();
+
+        report.push(check_config_file(config_dir, &config.drop_in_dir()).await);
+
+        let directories = [
+            ("config", config_dir),
+            ("data", config.data.path.as_path()),
+            ("logs", config.logs.path.as_path()),
+        ];
+        for (name, path) in directories {
+            report.push(check_directory(name, path, TEDGE_USER));
+        }
+
+        let clouds: Vec<_> = cloud_profiles(config)
+            .into_iter()
+            .filter(|cloud| cloud.is_connected(config_dir))
+            .collect();
+        if clouds.is_empty() {
+            report.push(CheckResult::skipped(
+                "certificates",
+                "the device is not connected to any cloud",
+            ));
+        }
+        let minimum_validity = config.certificate.validity.minimum_duration.duration();
+        for cloud in &clouds {
+            for result in check_certificate(cloud, minimum_validity) {
+                report.push(result);
+            }
+        }
+
+        match self.read_health_statuses(config).await {
+            Ok(statuses) => {
+                report.push(CheckResult::ok(
+                    "mqtt broker",
+                    format!(
+                        "connected to {}:{}",
+                        config.mqtt.client.host,
+                        u16::from(config.mqtt.client.port)
+                    ),
+                ));
+
+                if statuses.is_empty() {
+                    report.push(
+                        CheckResult::warning("services", "no service health status found")
+                            .with_hint("check that tedge-agent and the mappers are running"),
+                    );
+                }
+                for (entity, health) in statuses.values() {
+                    report.push(check_service_health(entity, health));
+                }
+
+                for cloud in &clouds {
+                    match bridge_health_topic(cloud.topic_prefix, config) {
+                        Ok(topic) => {
+                            report.push(check_bridge_health(cloud, &topic.name, &statuses))
+                        }
+                        Err(err) => report.push(CheckResult::error(
+                            format!("{} bridge", cloud.name()),
+                            format!("invalid bridge health topic: {err}"),
+                        )),
+                    }
+                }
+            }
+            Err(err) => {
+                report.push(
+                    CheckResult::error("mqtt broker", format!("{err:#}")).with_hint(
+                        "check that mosquitto is running, e.g. with `systemctl status mosquitto`",
+                    ),
+                );
+                report.push(CheckResult::skipped(
+                    "services",
+                    "the health status of the services cannot be read without the MQTT broker",
+                ));
+            }
+        }
+
+        report.push(check_clock(OffsetDateTime::now_utc()));
+
+        let disks = [
+            ("data", config.data.path.as_path()),
+            ("logs", config.logs.path.as_path()),
+            ("tmp", config.tmp.path.as_path()),
+        ];
+        for (name, path) in disks {
+            report.push(check_disk_space(name, path));
+        }
+
+        report.push(check_sudoers(config).await);
+
+        report
+    }
+
+    /// Read the retained health status messages of all the services
+    async fn read_health_statuses(
+        &self,
+        config: &TEdgeConfig,
+    ) -> anyhow::Result<BTreeMap<String, (EntityTopicId, HealthStatus)>> {
+        let mqtt_schema = MqttSchema::with_root(config.mqtt.topic_root.clone());
+        let mqtt_config = config
+            .mqtt_config()?
+            .with_no_session()
+            .with_subscriptions(mqtt_schema.topics(EntityFilter::AnyEntity, ChannelFilter::Health));
+
+        let mut mqtt =
+            tokio::time::timeout(self.timeout, mqtt_channel::Connection::new(&mqtt_config))
+                .await
+                .with_context(|| {
+                    format!(
+                        "cannot connect to the MQTT broker at {}:{} within {:?}",
+                        config.mqtt.client.host,
+                        u16::from(config.mqtt.client.port),
+                        self.timeout
+                    )
+                })?
+                .context("cannot connect to the MQTT broker")?;
+
+        let mut statuses = BTreeMap::new();
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let wait = QUIET_PERIOD.min(deadline.saturating_duration_since(Instant::now()));
+            let Ok(Some(message)) = tokio::time::timeout(wait, mqtt.received.next()).await else {
+                break;
+            };
+            if !message.retain || message.payload_bytes().is_empty() {
+                continue;
+            }
+            if let Ok((entity, _)) = mqtt_schema.entity_channel_of(&message.topic) {
+                if let Ok(health) =
+                    HealthStatus::try_from_health_status_message(&message, &mqtt_schema)
+                {
+                    statuses.insert(message.topic.name.clone(), (entity, health));
+                }
+            }
+        }
+        mqtt.close().await;
+
+        Ok(statuses)
+    }
+}
@@ -0,0 +1,5 @@
+mod checks;
+mod cli;
+mod command;
+
+pub use cli::TEdgeDoctorCli;
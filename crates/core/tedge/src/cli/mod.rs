@@ -8,8 +8,11 @@ use completions::Shell;
 pub use connect::*;
 use tedge_agent::AgentOpt;
 use tedge_apt_plugin::AptCli;
+use tedge_archive_plugin::ArchiveCli;
+use tedge_broker::BrokerOpt;
 use tedge_config::cli::CommonArgs;
 use tedge_config::TEdgeConfig;
+use tedge_container_plugin::ContainerCli;
 use tedge_mapper::MapperOpt;
 use tedge_watchdog::WatchdogOpt;
 use tedge_write::bin::Args as TedgeWriteOpt;
@@ -22,6 +25,7 @@ pub mod config;
 mod connect;
 mod diag;
 mod disconnect;
+mod doctor;
 #[cfg(feature = "tedge-flows")]
 mod flows;
 mod http;
@@ -70,6 +74,14 @@ pub enum Component {
     #[clap(alias = "apt")]
     TedgeAptPlugin(AptCli),
 
+    #[clap(alias = "archive")]
+    TedgeArchivePlugin(ArchiveCli),
+
+    TedgeBroker(BrokerOpt),
+
+    #[clap(alias = "container")]
+    TedgeContainerPlugin(ContainerCli),
+
     TedgeMapper(MapperOpt),
 
     TedgeWatchdog(WatchdogOpt),
@@ -122,6 +134,9 @@ pub enum TEdgeOpt {
     #[clap(subcommand)]
     Diag(diag::TEdgeDiagCli),
 
+    /// Check the thin-edge installation and report problems
+    Doctor(doctor::TEdgeDoctorCli),
+
     /// Reconnect command, calls disconnect followed by connect
     Reconnect(reconnect::TEdgeReconnectCli),
 
@@ -211,6 +226,7 @@ impl BuildCommand for TEdgeOpt {
             TEdgeOpt::Connect(opt) => opt.build_command(config),
             TEdgeOpt::Diag(opt) => opt.build_command(config),
             TEdgeOpt::Disconnect(opt) => opt.build_command(config),
+            TEdgeOpt::Doctor(opt) => opt.build_command(config),
             TEdgeOpt::RefreshBridges => RefreshBridgesCmd::new(config).map(Command::into_boxed),
             TEdgeOpt::Mqtt(opt) => opt.build_command(config),
             TEdgeOpt::Http(opt) => opt.build_command(config),
@@ -97,7 +97,7 @@ pub trait Command {
     ///
     /// impl SomeStruct {
     ///     fn build_command(self, _: TEdgeConfig) -> Result<Box<dyn Command>, ConfigError> {
-    ///         let cmd = GetConfigCommand { key: ReadableKey::MqttBindPort };
+    ///         let cmd = GetConfigCommand { key: ReadableKey::MqttBindPort, show_origin: false };
     ///         Ok(cmd.into_boxed())
     ///     }
     /// }
@@ -141,6 +141,7 @@ pub trait Command {
 ///             }.into_boxed(),
 ///             ConfigCmd::Get { key } => GetConfigCommand {
 ///                 key,
+///                 show_origin: false,
 ///             }.into_boxed(),
 ///         };
 ///         Ok(cmd)
@@ -5,6 +5,8 @@ pub mod command;
 pub mod error;
 mod system_services;
 
+pub use system_services::watchdog_service_restarter;
+
 pub type ConfigError = crate::error::TEdgeError;
 const BROKER_USER: &str = "mosquitto";
 const BROKER_GROUP: &str = "mosquitto";
@@ -21,9 +21,12 @@ use tedge::TEdgeCli;
 use tedge::TEdgeOpt;
 use tedge::TEdgeOptMulticall;
 use tedge_apt_plugin::AptCli;
+use tedge_archive_plugin::ArchiveCli;
 use tedge_config::cli::CommonArgs;
 use tedge_config::log_init;
 use tedge_config::unconfigured_logger;
+use tedge_container_plugin::ContainerCli;
+use tedge_container_plugin::ModuleType;
 use tracing::log;
 
 #[global_allocator]
@@ -55,8 +58,13 @@ async fn main() -> anyhow::Result<()> {
             Ok(())
         }
         TEdgeOptMulticall::Component(Component::TedgeWatchdog(opt)) => {
-            tedge_watchdog::run(opt).await
+            let config_dir = opt.common.config_dir.clone();
+            tedge_watchdog::run(opt, move || {
+                Ok(tedge::watchdog_service_restarter(&config_dir)?)
+            })
+            .await
         }
+        TEdgeOptMulticall::Component(Component::TedgeBroker(opt)) => tedge_broker::run(opt).await,
         TEdgeOptMulticall::Component(Component::TedgeWrite(opt)) => {
             tokio::task::spawn_blocking(move || tedge_write::bin::run(opt))
                 .await
@@ -68,6 +76,21 @@ async fn main() -> anyhow::Result<()> {
                 .await
                 .context("failed to run tedge apt plugin")?
         }
+        TEdgeOptMulticall::Component(Component::TedgeArchivePlugin(opt)) => {
+            let tedge_config = tedge_config::TEdgeConfig::load(&opt.common.config_dir).await?;
+            let root = tedge_config.archive.root.clone();
+            tokio::task::spawn_blocking(move || tedge_archive_plugin::run_and_exit(opt, &root))
+                .await
+                .context("failed to run tedge archive plugin")?
+        }
+        TEdgeOptMulticall::Component(Component::TedgeContainerPlugin(opt)) => {
+            let tedge_config = tedge_config::TEdgeConfig::load(&opt.common.config_dir).await?;
+            tokio::task::spawn_blocking(move || {
+                tedge_container_plugin::run_and_exit(opt, tedge_config)
+            })
+            .await
+            .context("failed to run tedge container plugin")?
+        }
         TEdgeOptMulticall::Tedge(TEdgeCli { cmd, common }) => {
             log_init(
                 "tedge",
@@ -127,11 +150,26 @@ where
     Args: IntoIterator<Item = Arg>,
     Arg: Into<OsString> + Clone,
 {
-    if matches!(executable_name.as_deref(), Some("apt" | "tedge-apt-plugin")) {
-        // the apt plugin must be treated apart
-        // as we want to exit 1 and not 2 when the command line cannot be parsed
-        match AptCli::try_parse() {
-            Ok(apt) => return TEdgeOptMulticall::Component(Component::TedgeAptPlugin(apt)),
+    // the software management plugins must be treated apart
+    // as we want to exit 1 and not 2 when the command line cannot be parsed
+    let sm_plugin = match executable_name.as_deref() {
+        Some("apt" | "tedge-apt-plugin") => {
+            Some(AptCli::try_parse().map(Component::TedgeAptPlugin))
+        }
+        Some("archive" | "tedge-archive-plugin") => {
+            Some(ArchiveCli::try_parse().map(Component::TedgeArchivePlugin))
+        }
+        Some("container" | "tedge-container-plugin") => {
+            Some(ContainerCli::try_parse().map(Component::TedgeContainerPlugin))
+        }
+        Some("container-group") => Some(ContainerCli::try_parse().map(|cli| {
+            Component::TedgeContainerPlugin(cli.with_module_type(ModuleType::ContainerGroup))
+        })),
+        _ => None,
+    };
+    if let Some(sm_plugin) = sm_plugin {
+        match sm_plugin {
+            Ok(plugin) => return TEdgeOptMulticall::Component(plugin),
             Err(e) => {
                 eprintln!("{}", RichFormatter::format_error(&e));
                 std::process::exit(1);
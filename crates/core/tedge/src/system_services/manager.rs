@@ -42,3 +42,12 @@ pub fn service_manager(
 ) -> Result<Arc<dyn SystemServiceManager>, SystemTomlError> {
     Ok(Arc::new(GeneralServiceManager::try_new(config_root)?))
 }
+
+/// The service manager used by tedge-watchdog to restart unresponsive services
+pub fn watchdog_service_restarter(
+    config_root: &Utf8Path,
+) -> Result<Arc<dyn tedge_watchdog::ServiceRestarter>, SystemTomlError> {
+    Ok(Arc::new(WatchdogServiceRestarter::new(service_manager(
+        config_root,
+    )?)))
+}
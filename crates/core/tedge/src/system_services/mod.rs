@@ -12,8 +12,10 @@ mod error;
 mod manager;
 mod managers;
 mod services;
+mod watchdog;
 
 pub use self::error::*;
 pub use self::manager::*;
 pub use self::managers::*;
 pub use self::services::*;
+pub use self::watchdog::*;
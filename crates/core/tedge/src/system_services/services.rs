@@ -16,9 +16,15 @@ pub enum SystemService<'a> {
     #[strum(serialize = "tedge-mapper-c8y")]
     /// Cumulocity TEdge mapper
     TEdgeMapperC8y(Option<&'a ProfileName>),
+    #[strum(serialize = "tedge-mapper-collectd")]
+    /// Collectd TEdge mapper
+    TEdgeMapperCollectd,
     #[strum(serialize = "tedge-agent")]
     /// TEdge SM agent
     TEdgeSMAgent,
+    #[strum(serialize = "c8y-firmware-plugin")]
+    /// Cumulocity firmware plugin
+    C8yFirmwarePlugin,
 }
 
 impl fmt::Display for SystemService<'_> {
@@ -31,7 +37,75 @@ impl fmt::Display for SystemService<'_> {
             Self::TEdgeMapperAws(Some(profile)) => write!(f, "tedge-mapper-aws@{profile}"),
             Self::TEdgeMapperC8y(None) => write!(f, "tedge-mapper-c8y"),
             Self::TEdgeMapperC8y(Some(profile)) => write!(f, "tedge-mapper-c8y@{profile}"),
+            Self::TEdgeMapperCollectd => write!(f, "tedge-mapper-collectd"),
             Self::TEdgeSMAgent => write!(f, "tedge-agent"),
+            Self::C8yFirmwarePlugin => write!(f, "c8y-firmware-plugin"),
+        }
+    }
+}
+
+impl<'a> SystemService<'a> {
+    /// The thin-edge service with the given name, if any
+    ///
+    /// The cloud profile of a mapper is given by a `@profile` suffix, as in `tedge-mapper-c8y@second`.
+    /// This profile name is parsed into `profile`, from which the returned service borrows it.
+    pub fn from_name(name: &str, profile: &'a mut Option<ProfileName>) -> Option<Self> {
+        let (name, profile_name) = match name.split_once('@') {
+            Some((name, profile_name)) => (name, Some(profile_name.parse().ok()?)),
+            None => (name, None),
+        };
+        *profile = profile_name;
+        let profile: &'a Option<ProfileName> = profile;
+
+        match (name, profile.as_ref()) {
+            ("mosquitto", None) => Some(Self::Mosquitto),
+            ("tedge-mapper-az", profile) => Some(Self::TEdgeMapperAz(profile)),
+            ("tedge-mapper-aws", profile) => Some(Self::TEdgeMapperAws(profile)),
+            ("tedge-mapper-c8y", profile) => Some(Self::TEdgeMapperC8y(profile)),
+            ("tedge-mapper-collectd", None) => Some(Self::TEdgeMapperCollectd),
+            ("tedge-agent", None) => Some(Self::TEdgeSMAgent),
+            ("c8y-firmware-plugin", None) => Some(Self::C8yFirmwarePlugin),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_service_names() {
+        let mut profile = None;
+        let service = SystemService::from_name("tedge-agent", &mut profile).unwrap();
+        assert_eq!(service.to_string(), "tedge-agent");
+
+        let mut profile = None;
+        let service = SystemService::from_name("tedge-mapper-c8y", &mut profile).unwrap();
+        assert_eq!(service.to_string(), "tedge-mapper-c8y");
+    }
+
+    #[test]
+    fn parse_mapper_names_with_a_profile() {
+        let mut profile = None;
+        let service = SystemService::from_name("tedge-mapper-c8y@second", &mut profile).unwrap();
+        assert!(matches!(service, SystemService::TEdgeMapperC8y(Some(_))));
+        assert_eq!(service.to_string(), "tedge-mapper-c8y@second");
+    }
+
+    #[test]
+    fn reject_unknown_services_and_profiles() {
+        for name in [
+            "unknown",
+            "tedge-agent@second",
+            "tedge-mapper-c8y@",
+            "tedge-mapper-c8y@not/valid",
+        ] {
+            let mut profile = None;
+            assert!(
+                SystemService::from_name(name, &mut profile).is_none(),
+                "{name} should be rejected"
+            );
         }
     }
 }
@@ -0,0 +1,41 @@
+use super::SystemService;
+use super::SystemServiceManager;
+use std::sync::Arc;
+use tedge_config::tedge_toml::ProfileName;
+use tedge_watchdog::ServiceRestarter;
+
+/// Let tedge-watchdog restart the unresponsive services using the system service manager
+#[derive(Debug)]
+pub struct WatchdogServiceRestarter {
+    service_manager: Arc<dyn SystemServiceManager>,
+}
+
+impl WatchdogServiceRestarter {
+    pub fn new(service_manager: Arc<dyn SystemServiceManager>) -> Self {
+        WatchdogServiceRestarter { service_manager }
+    }
+}
+
+#[async_trait::async_trait]
+impl ServiceRestarter for WatchdogServiceRestarter {
+    fn check_service(&self, service: &str) -> Result<(), anyhow::Error> {
+        let mut profile = None;
+        known_service(service, &mut profile)?;
+        Ok(())
+    }
+
+    async fn restart_service(&self, service: &str) -> Result<(), anyhow::Error> {
+        let mut profile = None;
+        let service = known_service(service, &mut profile)?;
+        self.service_manager.restart_service(service).await?;
+        Ok(())
+    }
+}
+
+fn known_service<'a>(
+    name: &str,
+    profile: &'a mut Option<ProfileName>,
+) -> Result<SystemService<'a>, anyhow::Error> {
+    SystemService::from_name(name, profile)
+        .ok_or_else(|| anyhow::anyhow!("{name} is not a known thin-edge service"))
+}
@@ -67,6 +67,9 @@ use crate::LoggingReceiver;
 use crate::LoggingSender;
 use crate::MappingSender;
 use crate::Message;
+use crate::MessageBoxMetrics;
+use crate::MeteredSender;
+use crate::MetricsSignalSender;
 use crate::NullSender;
 use crate::RuntimeRequest;
 use crate::SimpleMessageBox;
@@ -223,6 +226,22 @@ pub trait RuntimeRequestSink {
     fn get_signal_sender(&self) -> DynSender<RuntimeRequest>;
 }
 
+/// Builds the successive instances of a restartable actor
+///
+/// Each call returns a new instance of the actor, or `None` if no new instance can be built.
+pub type ActorRebuilder<A> = Box<dyn FnMut() -> Option<A> + Send>;
+
+/// The [Builder] of an [Actor](crate::Actor) that can be restarted by the [Runtime](crate::Runtime)
+/// after a failure, as requested by a [SupervisionStrategy](crate::SupervisionStrategy).
+///
+/// The new instances of the actor must receive the messages sent to the failed instance,
+/// its peers being kept connected. For an actor using a [SimpleMessageBox],
+/// this is done by building the message box as a restartable [SimpleMessageBoxBuilder].
+pub trait RestartableBuilder<A>: RuntimeRequestSink + Sized {
+    /// Build the first instance of the actor, along with a function building the next ones
+    fn build_restartable(self) -> (A, ActorRebuilder<A>);
+}
+
 /// A [Builder] of [SimpleMessageBox]
 ///
 /// This builder can be used as a building block for actor builders
@@ -378,26 +397,30 @@ pub trait RuntimeRequestSink {
 ///
 pub struct SimpleMessageBoxBuilder<I: Debug, O> {
     name: String,
-    input_sender: mpsc::Sender<I>,
-    signal_sender: mpsc::Sender<RuntimeRequest>,
+    input_sender: MeteredSender<I>,
+    signal_sender: MetricsSignalSender,
     output_sender: DynSender<O>,
     input_receiver: LoggingReceiver<I>,
+    metrics: MessageBoxMetrics,
 }
 
 impl<I: Message, O: Message> SimpleMessageBoxBuilder<I, O> {
     pub fn new(name: &str, capacity: usize) -> Self {
+        let metrics = MessageBoxMetrics::default();
         let (input_sender, input_receiver) = mpsc::channel(capacity);
         let (signal_sender, signal_receiver) = mpsc::channel(4);
         let output_sender = NullSender.into();
         let input_receiver =
-            LoggingReceiver::new(name.to_string(), input_receiver, signal_receiver);
+            LoggingReceiver::new(name.to_string(), input_receiver, signal_receiver)
+                .with_metrics(metrics.clone());
 
         SimpleMessageBoxBuilder {
             name: name.to_string(),
-            input_sender,
-            signal_sender,
+            input_sender: MeteredSender::new(input_sender, metrics.clone()),
+            signal_sender: MetricsSignalSender::new(signal_sender, metrics.clone()),
             output_sender,
             input_receiver,
+            metrics,
         }
     }
 
@@ -458,7 +481,29 @@ impl<Req: Message, Res: Message> Builder<SimpleMessageBox<Req, Res>>
     }
 
     fn build(self) -> SimpleMessageBox<Req, Res> {
-        let sender = LoggingSender::new(self.name, self.output_sender);
+        let sender = LoggingSender::new(self.name, self.output_sender).with_metrics(self.metrics);
         SimpleMessageBox::new(self.input_receiver, sender)
     }
 }
+
+/// A `SimpleMessageBoxBuilder<Input,Output>` builds message boxes that can be used by restarted actors.
+impl<Req: Message, Res: Message> RestartableBuilder<SimpleMessageBox<Req, Res>>
+    for SimpleMessageBoxBuilder<Req, Res>
+{
+    fn build_restartable(
+        self,
+    ) -> (
+        SimpleMessageBox<Req, Res>,
+        ActorRebuilder<SimpleMessageBox<Req, Res>>,
+    ) {
+        let (input_receiver, recovery) = self.input_receiver.recoverable();
+        let output_sender =
+            LoggingSender::new(self.name, self.output_sender).with_metrics(self.metrics);
+        let message_box = SimpleMessageBox::new(input_receiver, output_sender.clone());
+        let rebuild = move || {
+            let input_receiver = recovery.recover()?;
+            Some(SimpleMessageBox::new(input_receiver, output_sender.clone()))
+        };
+        (message_box, Box::new(rebuild))
+    }
+}
@@ -237,7 +237,8 @@
 //! has several benefits:
 //! - The runtime monitors all the running actors, catching normal terminations, aborts and panics.
 //! - The runtime can send [RuntimeRequest] to all the running actors,
-//!   notably to trigger a graceful shutdown of the application.
+//!   notably to trigger a graceful shutdown of the application
+//!   or to collect [metrics](crate::metrics) on the message boxes of the actors.
 //! - Any actor can send [RuntimeAction] to the runtime,
 //!   to spawn a new actor or to request a global shutdown of the application.
 //! - An actor can subscribe to the [RuntimeEvent] published by the runtime,
@@ -306,6 +307,7 @@ pub mod converter;
 mod errors;
 pub mod message_boxes;
 mod messages;
+pub mod metrics;
 #[doc(hidden)]
 mod run_actor;
 pub mod runtime;
@@ -318,6 +320,7 @@ pub use converter::*;
 pub use errors::*;
 pub use message_boxes::*;
 pub use messages::*;
+pub use metrics::*;
 pub use runtime::*;
 pub use servers::*;
 
@@ -89,12 +89,16 @@ use crate::ChannelError;
 use crate::CloneSender;
 use crate::DynSender;
 use crate::Message;
+use crate::MessageBoxMetrics;
 use crate::RuntimeRequest;
 use async_trait::async_trait;
 use futures::channel::mpsc;
 use futures::StreamExt;
 use log::debug;
 use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
 
 #[async_trait]
 pub trait MessageReceiver<Input> {
@@ -115,8 +119,13 @@ pub trait MessageReceiver<Input> {
 pub struct LoggingReceiver<Input: Debug> {
     name: String,
     receiver: CombinedReceiver<Input>,
+    recovery: Option<RecoverySlot<Input>>,
+    metrics: MessageBoxMetrics,
+    handling_since: Option<Instant>,
 }
 
+type RecoverySlot<Input> = Arc<Mutex<Option<CombinedReceiver<Input>>>>;
+
 impl<Input: Debug> LoggingReceiver<Input> {
     pub fn new(
         name: String,
@@ -124,7 +133,35 @@ impl<Input: Debug> LoggingReceiver<Input> {
         signal_receiver: mpsc::Receiver<RuntimeRequest>,
     ) -> Self {
         let receiver = CombinedReceiver::new(input_receiver, signal_receiver);
-        Self { name, receiver }
+        Self {
+            name,
+            receiver,
+            recovery: None,
+            metrics: MessageBoxMetrics::default(),
+            handling_since: None,
+        }
+    }
+
+    /// Count the messages received by this receiver and the time spent to handle each of them
+    pub fn with_metrics(mut self, metrics: MessageBoxMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Make this receiver recoverable, its channels being handed back to the returned
+    /// [ReceiverRecovery] when the receiver is dropped.
+    ///
+    /// This is used to restart a failing actor: the new instance of the actor
+    /// receives the messages sent to the failed instance, its peers being kept connected.
+    pub fn recoverable(mut self) -> (Self, ReceiverRecovery<Input>) {
+        let slot = Arc::new(Mutex::new(None));
+        self.recovery = Some(slot.clone());
+        let recovery = ReceiverRecovery {
+            name: self.name.clone(),
+            slot,
+            metrics: self.metrics.clone(),
+        };
+        (self, recovery)
     }
 
     /// Splits a `LoggingReceiver` into an input receiver and a signal receiver,
@@ -149,27 +186,86 @@ impl<Input: Debug> LoggingReceiver<Input> {
     ///
     /// This method returns consumes the `LoggingReceiver` and returns owned
     /// receivers, which can then be separately moved.
-    pub fn into_split(self) -> (mpsc::Receiver<Input>, mpsc::Receiver<RuntimeRequest>) {
-        (self.receiver.input_receiver, self.receiver.signal_receiver)
+    ///
+    /// The channels of a recoverable receiver are no longer recovered once split.
+    pub fn into_split(mut self) -> (mpsc::Receiver<Input>, mpsc::Receiver<RuntimeRequest>) {
+        self.recovery = None;
+        let receiver = std::mem::replace(&mut self.receiver, CombinedReceiver::closed());
+        (receiver.input_receiver, receiver.signal_receiver)
     }
 
     /// Close the input so no new messages can be sent to this receiver
     pub fn close_input(&mut self) {
         self.receiver.close_input();
     }
+
+    /// Called when the actor is ready for a new message, i.e. done with the previous one
+    fn handling_done(&mut self) {
+        if let Some(since) = self.handling_since.take() {
+            self.metrics.message_handled(since.elapsed());
+        }
+    }
+
+    /// Called when a new message is handed over to the actor
+    fn handling_started(&mut self) {
+        self.metrics.message_received();
+        self.handling_since = Some(Instant::now());
+    }
+}
+
+impl<Input: Debug> Drop for LoggingReceiver<Input> {
+    fn drop(&mut self) {
+        if let Some(slot) = self.recovery.take() {
+            let receiver = std::mem::replace(&mut self.receiver, CombinedReceiver::closed());
+            if let Ok(mut slot) = slot.lock() {
+                *slot = Some(receiver);
+            }
+        }
+    }
+}
+
+/// Recovers the channels of a [LoggingReceiver] once dropped
+pub struct ReceiverRecovery<Input: Debug> {
+    name: String,
+    slot: RecoverySlot<Input>,
+    metrics: MessageBoxMetrics,
+}
+
+impl<Input: Debug> ReceiverRecovery<Input> {
+    /// Return a new receiver for the channels of the dropped one, if that receiver has been dropped
+    ///
+    /// The new receiver is itself recoverable by this [ReceiverRecovery].
+    pub fn recover(&self) -> Option<LoggingReceiver<Input>> {
+        let receiver = self.slot.lock().ok()?.take()?;
+        Some(LoggingReceiver {
+            name: self.name.clone(),
+            receiver,
+            recovery: Some(self.slot.clone()),
+            metrics: self.metrics.clone(),
+            handling_since: None,
+        })
+    }
 }
 
 #[async_trait]
 impl<Input: Send + Debug> MessageReceiver<Input> for LoggingReceiver<Input> {
     async fn try_recv(&mut self) -> Result<Option<Input>, RuntimeRequest> {
+        self.handling_done();
         let message = self.receiver.try_recv().await;
         debug!(target: &self.name, "recv {:?}", message);
+        if let Ok(Some(_)) = message {
+            self.handling_started();
+        }
         message
     }
 
     async fn recv(&mut self) -> Option<Input> {
+        self.handling_done();
         let message = self.receiver.recv().await;
         debug!(target: &self.name, "recv {:?}", message);
+        if message.is_some() {
+            self.handling_started();
+        }
         message
     }
 
@@ -183,6 +279,7 @@ impl<Input: Send + Debug> MessageReceiver<Input> for LoggingReceiver<Input> {
 pub struct LoggingSender<Output> {
     name: String,
     sender: DynSender<Output>,
+    metrics: MessageBoxMetrics,
 }
 
 impl<Output: 'static> Clone for LoggingSender<Output> {
@@ -190,13 +287,24 @@ impl<Output: 'static> Clone for LoggingSender<Output> {
         LoggingSender {
             name: self.name.clone(),
             sender: self.sender.sender_clone(),
+            metrics: self.metrics.clone(),
         }
     }
 }
 
 impl<Output> LoggingSender<Output> {
     pub fn new(name: String, sender: DynSender<Output>) -> Self {
-        Self { name, sender }
+        Self {
+            name,
+            sender,
+            metrics: MessageBoxMetrics::default(),
+        }
+    }
+
+    /// Count the messages sent by this sender
+    pub fn with_metrics(mut self, metrics: MessageBoxMetrics) -> Self {
+        self.metrics = metrics;
+        self
     }
 }
 
@@ -204,6 +312,7 @@ impl<Output> LoggingSender<Output> {
 impl<Output: Message> Sender<Output> for LoggingSender<Output> {
     async fn send(&mut self, message: Output) -> Result<(), ChannelError> {
         log_message_sent(&self.name, &message);
+        self.metrics.message_sent();
         self.sender.send(message).await
     }
 }
@@ -239,7 +348,7 @@ impl<Input: Debug> UnboundedLoggingReceiver<Input> {
         tokio::select! {
             biased;
 
-            Some(runtime_request) = self.signal_receiver.next() => {
+            Some(runtime_request) = next_signal(&mut self.signal_receiver) => {
                 Err(runtime_request)
             }
             Some(message) = self.input_receiver.next() => {
@@ -268,7 +377,7 @@ impl<Input: Send + Debug> MessageReceiver<Input> for UnboundedLoggingReceiver<In
     }
 
     async fn recv_signal(&mut self) -> Option<RuntimeRequest> {
-        let message = self.signal_receiver.next().await;
+        let message = next_signal(&mut self.signal_receiver).await;
         debug!(target: &self.name, "recv {:?}", message);
         message
     }
@@ -369,6 +478,13 @@ impl<Input> CombinedReceiver<Input> {
         self.input_receiver.close();
         self.signal_receiver.close();
     }
+
+    /// A receiver whose senders have all been dropped
+    fn closed() -> Self {
+        let (_, input_receiver) = mpsc::channel(0);
+        let (_, signal_receiver) = mpsc::channel(0);
+        Self::new(input_receiver, signal_receiver)
+    }
 }
 
 #[async_trait]
@@ -377,7 +493,7 @@ impl<Input: Send> MessageReceiver<Input> for CombinedReceiver<Input> {
         tokio::select! {
             biased;
 
-            Some(runtime_request) = self.signal_receiver.next() => {
+            Some(runtime_request) = next_signal(&mut self.signal_receiver) => {
                 Err(runtime_request)
             }
             Some(message) = self.input_receiver.next() => {
@@ -395,6 +511,22 @@ impl<Input: Send> MessageReceiver<Input> for CombinedReceiver<Input> {
     }
 
     async fn recv_signal(&mut self) -> Option<RuntimeRequest> {
-        self.signal_receiver.next().await
+        next_signal(&mut self.signal_receiver).await
+    }
+}
+
+/// Return the next runtime request to be handled by the actor
+///
+/// The [RuntimeRequest::ReportMetrics] requests reaching a receiver are dropped:
+/// these requests are answered by the message boxes maintaining metrics
+/// and are left unanswered by the others.
+pub async fn next_signal(
+    signal_receiver: &mut mpsc::Receiver<RuntimeRequest>,
+) -> Option<RuntimeRequest> {
+    loop {
+        match signal_receiver.next().await {
+            Some(RuntimeRequest::ReportMetrics(_)) => continue,
+            request => return request,
+        }
     }
 }
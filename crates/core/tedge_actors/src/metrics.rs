@@ -0,0 +1,258 @@
+//! Introspection of the actors: mailbox depth, throughput and handling time
+//!
+//! A [SimpleMessageBoxBuilder](crate::SimpleMessageBoxBuilder) or a [ServerMessageBoxBuilder](crate::ServerMessageBoxBuilder)
+//! maintains [MessageBoxMetrics] shared by all the senders and receivers of the message box under construction:
+//! - the senders connected to the actor count the messages queued in its mailbox,
+//! - the receiver of the actor counts the messages it receives and the time spent to handle each of them,
+//! - the sender of the actor counts the messages sent by the actor.
+//!
+//! A snapshot of these metrics is requested by the [Runtime](crate::Runtime)
+//! with a [RuntimeRequest::ReportMetrics] sent to the actor.
+//! Such a request is answered by the message box on behalf of the actor,
+//! hence even when the actor is busy, and is never returned to the actor itself.
+//!
+//! Actors using their own message boxes, as the MQTT actor or the file system watcher,
+//! report metrics by using a [MeteredSender] for their inputs, a [MetricsSignalSender] for their signals,
+//! and by updating the [MessageBoxMetrics] when a message is received, handled or sent.
+use crate::ChannelError;
+use crate::Message;
+use crate::RuntimeRequest;
+use crate::Sender;
+use async_trait::async_trait;
+use futures::channel::mpsc;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A snapshot of the activity of an actor, as observed by its message box
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ActorMetrics {
+    /// Number of messages queued in the mailbox of the actor, waiting to be processed
+    pub queue_depth: u64,
+
+    /// Number of messages received by the actor since started
+    pub messages_in: u64,
+
+    /// Number of messages sent by the actor since started
+    pub messages_out: u64,
+
+    /// Mean time spent by the actor to process a message
+    pub mean_handling_time: Duration,
+
+    /// Maximum time spent by the actor to process a message
+    pub max_handling_time: Duration,
+}
+
+/// The metrics of the running actors, indexed by the names given by the runtime
+pub type RuntimeMetrics = BTreeMap<String, ActorMetrics>;
+
+/// The metrics maintained by a message box
+#[derive(Clone, Default)]
+pub struct MessageBoxMetrics {
+    counters: Arc<Mutex<Counters>>,
+}
+
+#[derive(Default)]
+struct Counters {
+    queued: u64,
+    received: u64,
+    sent: u64,
+    handled: u64,
+    handling_time: Duration,
+    max_handling_time: Duration,
+}
+
+impl MessageBoxMetrics {
+    /// A message has been pushed into the mailbox
+    pub fn message_queued(&self) {
+        self.update(|counters| counters.queued += 1)
+    }
+
+    /// A message has been pulled out of the mailbox by the actor
+    pub fn message_received(&self) {
+        self.update(|counters| counters.received += 1)
+    }
+
+    /// A message has been sent by the actor
+    pub fn message_sent(&self) {
+        self.update(|counters| counters.sent += 1)
+    }
+
+    /// The actor spent that time to process a message
+    pub fn message_handled(&self, handling_time: Duration) {
+        self.update(|counters| {
+            counters.handled += 1;
+            counters.handling_time += handling_time;
+            counters.max_handling_time = counters.max_handling_time.max(handling_time);
+        })
+    }
+
+    /// The current values of these metrics
+    pub fn snapshot(&self) -> ActorMetrics {
+        let Ok(counters) = self.counters.lock() else {
+            return ActorMetrics::default();
+        };
+        let mean_handling_time = match u32::try_from(counters.handled) {
+            Ok(0) => Duration::ZERO,
+            Ok(handled) => counters.handling_time / handled,
+            Err(_) => Duration::from_secs_f64(
+                counters.handling_time.as_secs_f64() / counters.handled as f64,
+            ),
+        };
+        ActorMetrics {
+            queue_depth: counters.queued.saturating_sub(counters.received),
+            messages_in: counters.received,
+            messages_out: counters.sent,
+            mean_handling_time,
+            max_handling_time: counters.max_handling_time,
+        }
+    }
+
+    fn update(&self, update: impl FnOnce(&mut Counters)) {
+        if let Ok(mut counters) = self.counters.lock() {
+            update(&mut counters)
+        }
+    }
+}
+
+/// A sender of input messages to a message box, counting the messages queued in the mailbox
+pub struct MeteredSender<M> {
+    sender: mpsc::Sender<M>,
+    metrics: MessageBoxMetrics,
+}
+
+impl<M> Clone for MeteredSender<M> {
+    fn clone(&self) -> Self {
+        MeteredSender {
+            sender: self.sender.clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+impl<M> MeteredSender<M> {
+    pub fn new(sender: mpsc::Sender<M>, metrics: MessageBoxMetrics) -> Self {
+        MeteredSender { sender, metrics }
+    }
+}
+
+#[async_trait]
+impl<M: Message, N: Message + Into<M>> Sender<N> for MeteredSender<M> {
+    async fn send(&mut self, message: N) -> Result<(), ChannelError> {
+        Sender::send(&mut self.sender, message.into()).await?;
+        // The message might have already been received, the queue depth being then transiently under-estimated
+        self.metrics.message_queued();
+        Ok(())
+    }
+}
+
+/// The sender of runtime requests to a message box maintaining metrics
+///
+/// The [RuntimeRequest::ReportMetrics] requests are answered by this sender on behalf of the actor,
+/// while the other requests are forwarded to the actor.
+#[derive(Clone)]
+pub struct MetricsSignalSender {
+    signal_sender: mpsc::Sender<RuntimeRequest>,
+    metrics: MessageBoxMetrics,
+}
+
+impl MetricsSignalSender {
+    pub fn new(signal_sender: mpsc::Sender<RuntimeRequest>, metrics: MessageBoxMetrics) -> Self {
+        MetricsSignalSender {
+            signal_sender,
+            metrics,
+        }
+    }
+}
+
+#[async_trait]
+impl Sender<RuntimeRequest> for MetricsSignalSender {
+    async fn send(&mut self, request: RuntimeRequest) -> Result<(), ChannelError> {
+        match request {
+            RuntimeRequest::ReportMetrics(reply) => {
+                reply.send(self.metrics.snapshot());
+                Ok(())
+            }
+            request => Sender::send(&mut self.signal_sender, request).await,
+        }
+    }
+}
+
+/// Where to send the metrics requested by a [RuntimeRequest::ReportMetrics]
+#[derive(Clone, Debug)]
+pub struct MetricsReply(mpsc::Sender<ActorMetrics>);
+
+impl MetricsReply {
+    /// Create a reply address along the receiver of the metrics
+    ///
+    /// The receiver returns `None` if the request is dropped without being answered,
+    /// notably when sent to an actor whose message box doesn't maintain metrics.
+    pub fn channel() -> (MetricsReply, mpsc::Receiver<ActorMetrics>) {
+        let (sender, receiver) = mpsc::channel(1);
+        (MetricsReply(sender), receiver)
+    }
+
+    /// Send the requested metrics, ignoring any subsequent response
+    pub fn send(mut self, metrics: ActorMetrics) {
+        let _ = self.0.try_send(metrics);
+    }
+}
+
+impl PartialEq for MetricsReply {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.same_receiver(&other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn queue_depth_is_the_number_of_messages_not_received_yet() {
+        let metrics = MessageBoxMetrics::default();
+        metrics.message_queued();
+        metrics.message_queued();
+        metrics.message_queued();
+        metrics.message_received();
+        metrics.message_sent();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.queue_depth, 2);
+        assert_eq!(snapshot.messages_in, 1);
+        assert_eq!(snapshot.messages_out, 1);
+    }
+
+    #[test]
+    fn handling_times_are_aggregated() {
+        let metrics = MessageBoxMetrics::default();
+        assert_eq!(metrics.snapshot().mean_handling_time, Duration::ZERO);
+
+        metrics.message_handled(Duration::from_millis(10));
+        metrics.message_handled(Duration::from_millis(30));
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.mean_handling_time, Duration::from_millis(20));
+        assert_eq!(snapshot.max_handling_time, Duration::from_millis(30));
+    }
+
+    #[tokio::test]
+    async fn metrics_requests_are_answered_by_the_signal_sender() {
+        let (signal_sender, mut signal_receiver) = mpsc::channel(4);
+        let metrics = MessageBoxMetrics::default();
+        metrics.message_queued();
+        let mut sender = MetricsSignalSender::new(signal_sender, metrics);
+
+        let (reply, mut metrics_receiver) = MetricsReply::channel();
+        sender
+            .send(RuntimeRequest::ReportMetrics(reply))
+            .await
+            .unwrap();
+        sender.send(RuntimeRequest::Shutdown).await.unwrap();
+
+        assert_eq!(metrics_receiver.next().await.unwrap().queue_depth, 1);
+        assert_eq!(signal_receiver.next().await, Some(RuntimeRequest::Shutdown));
+    }
+}
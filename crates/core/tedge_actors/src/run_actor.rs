@@ -1,9 +1,12 @@
 use crate::Actor;
+use crate::ActorRebuilder;
 use crate::Builder;
 use crate::DynSender;
+use crate::RestartableBuilder;
 use crate::RuntimeError;
 use crate::RuntimeRequest;
 use crate::RuntimeRequestSink;
+use crate::SupervisionStrategy;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 
@@ -11,6 +14,14 @@ use std::fmt::Formatter;
 pub struct RunActor {
     actor: Box<dyn Actor>,
     runtime_request_sender: DynSender<RuntimeRequest>,
+    supervision: Supervision,
+}
+
+/// How the runtime handles the failures of an actor
+#[derive(Default)]
+pub(crate) struct Supervision {
+    pub(crate) strategy: SupervisionStrategy,
+    pub(crate) rebuild: Option<ActorRebuilder<Box<dyn Actor>>>,
 }
 
 impl RunActor {
@@ -21,9 +32,33 @@ impl RunActor {
         RunActor {
             actor,
             runtime_request_sender,
+            supervision: Supervision::default(),
         }
     }
 
+    pub fn from_restartable_builder<A, T>(actor_builder: T, strategy: SupervisionStrategy) -> Self
+    where
+        A: Actor,
+        T: RestartableBuilder<A>,
+    {
+        let runtime_request_sender = actor_builder.get_signal_sender();
+        let (actor, mut rebuild) = actor_builder.build_restartable();
+        let rebuild: ActorRebuilder<Box<dyn Actor>> =
+            Box::new(move || rebuild().map(|actor| Box::new(actor) as Box<dyn Actor>));
+        RunActor {
+            actor: Box::new(actor),
+            runtime_request_sender,
+            supervision: Supervision {
+                strategy,
+                rebuild: Some(rebuild),
+            },
+        }
+    }
+
+    pub(crate) fn take_supervision(&mut self) -> Supervision {
+        std::mem::take(&mut self.supervision)
+    }
+
     pub fn from_builder<A, T>(actor_builder: T) -> Self
     where
         A: Actor,
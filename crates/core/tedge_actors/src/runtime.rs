@@ -1,36 +1,53 @@
 //! Supervise the actors of an application
 //!
 use crate::run_actor::RunActor;
+use crate::run_actor::Supervision;
 use crate::Actor;
 use crate::Builder;
 use crate::ChannelError;
+use crate::CloneSender;
 use crate::DynSender;
 use crate::MessageSink;
+use crate::MetricsReply;
+use crate::RestartableBuilder;
 use crate::RuntimeError;
+use crate::RuntimeMetrics;
 use crate::RuntimeRequestSink;
 use futures::channel::mpsc;
+use futures::channel::oneshot;
 use futures::prelude::*;
 use futures::stream::FuturesUnordered;
 use log::debug;
 use log::error;
 use log::info;
+use log::warn;
 use std::collections::HashMap;
 use std::panic;
 use std::time::Duration;
 use tokio::task::JoinError;
 use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// How long the runtime waits for the metrics of an actor
+const METRICS_TIMEOUT: Duration = Duration::from_secs(1);
 
 /// Actions sent by actors to the runtime
 #[derive(Debug)]
 pub enum RuntimeAction {
     Shutdown,
     Spawn(RunActor),
+    ReportMetrics(oneshot::Sender<RuntimeMetrics>),
 }
 
 /// Requests sent by the runtime to actors
 #[derive(Clone, Debug, PartialEq)]
 pub enum RuntimeRequest {
     Shutdown,
+
+    /// Request a snapshot of the [metrics](crate::metrics) of the actor
+    ///
+    /// Such a request is answered by the message box and never returned to the actor.
+    ReportMetrics(MetricsReply),
 }
 
 /// Events published by the runtime
@@ -40,6 +57,54 @@ pub enum RuntimeEvent {
     Started { task: String },
     Stopped { task: String },
     Aborted { task: String, error: String },
+    Restarting { task: String, delay: Duration },
+}
+
+/// How the [Runtime] handles an actor that fails, i.e. that returns an error or panics
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum SupervisionStrategy {
+    /// Stop all the actors and the runtime
+    #[default]
+    Escalate,
+
+    /// Keep the other actors running without the failed one
+    Ignore,
+
+    /// Restart the actor after a delay, growing with the number of successive failures
+    Restart(RestartBackoff),
+}
+
+/// The delays applied before restarting a failed actor
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RestartBackoff {
+    /// The delay before restarting an actor after a first failure
+    pub initial_delay: Duration,
+
+    /// The maximum delay, the delay being doubled on each successive failure up to this value
+    ///
+    /// The failures are no longer considered successive once an actor has run for longer than this delay.
+    pub max_delay: Duration,
+
+    /// The number of successive restarts after which a failure is escalated, if any
+    pub max_restarts: Option<u32>,
+}
+
+impl Default for RestartBackoff {
+    fn default() -> Self {
+        RestartBackoff {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            max_restarts: None,
+        }
+    }
+}
+
+impl RestartBackoff {
+    fn delay(&self, restarts: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(2u32.saturating_pow(restarts))
+            .min(self.max_delay)
+    }
 }
 
 /// The actor runtime
@@ -87,6 +152,19 @@ impl Runtime {
         self.handle.spawn(actor_builder).await
     }
 
+    /// Spawn an actor, handling its failures as specified by the supervision strategy
+    pub async fn spawn_supervised<A, T>(
+        &mut self,
+        actor_builder: T,
+        strategy: SupervisionStrategy,
+    ) -> Result<(), RuntimeError>
+    where
+        A: Actor,
+        T: RestartableBuilder<A>,
+    {
+        self.handle.spawn_supervised(actor_builder, strategy).await
+    }
+
     /// Run the runtime up to completion
     ///
     /// I.e until
@@ -136,6 +214,31 @@ impl RuntimeHandle {
         Ok(self.send(RuntimeAction::Spawn(run_actor)).await?)
     }
 
+    /// Spawn an actor, handling its failures as specified by the supervision strategy
+    pub async fn spawn_supervised<A, T>(
+        &mut self,
+        actor_builder: T,
+        strategy: SupervisionStrategy,
+    ) -> Result<(), RuntimeError>
+    where
+        A: Actor,
+        T: RestartableBuilder<A>,
+    {
+        let run_actor = RunActor::from_restartable_builder(actor_builder, strategy);
+
+        Ok(self.send(RuntimeAction::Spawn(run_actor)).await?)
+    }
+
+    /// Collect a snapshot of the metrics of the running actors
+    ///
+    /// The actors whose message box doesn't maintain metrics,
+    /// or that don't answer within a second, are omitted.
+    pub async fn metrics(&mut self) -> Result<RuntimeMetrics, RuntimeError> {
+        let (reply, metrics) = oneshot::channel();
+        self.send(RuntimeAction::ReportMetrics(reply)).await?;
+        Ok(metrics.await.map_err(|_| ChannelError::ReceiveError())?)
+    }
+
     /// Send an action to the runtime
     async fn send(&mut self, action: RuntimeAction) -> Result<(), ChannelError> {
         debug!(target: "Runtime", "schedule {:?}", action);
@@ -157,6 +260,15 @@ struct RuntimeActor {
     cleanup_duration: Duration,
     futures: FuturesUnordered<JoinHandle<Result<String, (String, RuntimeError)>>>,
     running_actors: HashMap<String, DynSender<RuntimeRequest>>,
+    supervisors: HashMap<String, Supervisor>,
+    shutting_down: bool,
+}
+
+/// The supervision state of a running actor
+struct Supervisor {
+    supervision: Supervision,
+    restarts: u32,
+    started_at: Instant,
 }
 
 impl RuntimeActor {
@@ -171,6 +283,8 @@ impl RuntimeActor {
             cleanup_duration,
             futures: FuturesUnordered::new(),
             running_actors: HashMap::default(),
+            supervisors: HashMap::default(),
+            shutting_down: false,
         }
     }
 
@@ -184,7 +298,7 @@ impl RuntimeActor {
                     match action {
                         Some(action) => {
                             match action {
-                                RuntimeAction::Spawn(actor) => {
+                                RuntimeAction::Spawn(mut actor) => {
                                     let running_name = format!("{}-{}", actor.name(), actors_count);
                                     info!(target: "Runtime", "Running {running_name}");
                                     self.send_event(RuntimeEvent::Started {
@@ -192,11 +306,24 @@ impl RuntimeActor {
                                     })
                                     .await;
                                     self.running_actors.insert(running_name.clone(), actor.get_signal_sender());
+                                    self.supervisors.insert(running_name.clone(), Supervisor {
+                                        supervision: actor.take_supervision(),
+                                        restarts: 0,
+                                        started_at: Instant::now(),
+                                    });
                                     self.futures.push(tokio::spawn(run_task(actor, running_name)));
                                     actors_count += 1;
                                }
+                               RuntimeAction::ReportMetrics(reply) => {
+                                    let actors = self.running_actors
+                                        .iter()
+                                        .map(|(name, sender)| (name.clone(), sender.sender_clone()))
+                                        .collect();
+                                    tokio::spawn(collect_metrics(actors, reply));
+                               }
                                RuntimeAction::Shutdown => {
                                     info!(target: "Runtime", "Shutting down");
+                                    self.shutting_down = true;
                                     shutdown_actors(&mut self.running_actors).await;
                                     break;
                                }
@@ -204,6 +331,7 @@ impl RuntimeActor {
                         }
                         None => {
                             info!(target: "Runtime", "Runtime actions channel closed, runtime stopping");
+                            self.shutting_down = true;
                             shutdown_actors(&mut self.running_actors).await;
                             break;
                         }
@@ -213,6 +341,7 @@ impl RuntimeActor {
                     if let Err(error) = self.handle_actor_finishing(finished_actor).await {
                         info!(target: "Runtime", "Shutting down on error: {error}");
                         aborting_error = Some(error);
+                        self.shutting_down = true;
                         shutdown_actors(&mut self.running_actors).await;
                         break
                     }
@@ -253,21 +382,89 @@ impl RuntimeActor {
             }
             Ok(Ok(actor)) => {
                 self.running_actors.remove(&actor);
+                self.supervisors.remove(&actor);
                 info!(target: "Runtime", "Actor has finished: {actor}");
                 self.send_event(RuntimeEvent::Stopped { task: actor }).await;
                 Ok(())
             }
             Ok(Err((actor, error))) => {
-                self.running_actors.remove(&actor);
                 error!(target: "Runtime", "Actor {actor} has finished unsuccessfully: {error:?}");
                 self.send_event(RuntimeEvent::Aborted {
                     task: actor.clone(),
                     error: format!("{error}"),
                 })
                 .await;
-                Err(error)
+                self.handle_actor_failure(actor, error).await
+            }
+        }
+    }
+
+    /// Apply the supervision strategy of a failed actor
+    async fn handle_actor_failure(
+        &mut self,
+        actor: String,
+        error: RuntimeError,
+    ) -> Result<(), RuntimeError> {
+        let Some(mut supervisor) = self.supervisors.remove(&actor) else {
+            self.running_actors.remove(&actor);
+            return Err(error);
+        };
+
+        let backoff = match &supervisor.supervision.strategy {
+            SupervisionStrategy::Escalate => {
+                self.running_actors.remove(&actor);
+                return Err(error);
+            }
+            SupervisionStrategy::Ignore => {
+                warn!(target: "Runtime", "Ignoring the failure of {actor}");
+                self.running_actors.remove(&actor);
+                return Ok(());
+            }
+            SupervisionStrategy::Restart(_) if self.shutting_down => {
+                self.running_actors.remove(&actor);
+                return Ok(());
             }
+            SupervisionStrategy::Restart(backoff) => backoff.clone(),
+        };
+
+        if supervisor.started_at.elapsed() > backoff.max_delay {
+            supervisor.restarts = 0;
+        }
+        if backoff
+            .max_restarts
+            .is_some_and(|max_restarts| supervisor.restarts >= max_restarts)
+        {
+            error!(target: "Runtime", "Actor {actor} has failed after {} restarts", supervisor.restarts);
+            self.running_actors.remove(&actor);
+            return Err(error);
         }
+        let new_instance = supervisor
+            .supervision
+            .rebuild
+            .as_mut()
+            .and_then(|rebuild| rebuild());
+        let (Some(new_instance), Some(signal_sender)) =
+            (new_instance, self.running_actors.get(&actor))
+        else {
+            error!(target: "Runtime", "Actor {actor} cannot be restarted");
+            self.running_actors.remove(&actor);
+            return Err(error);
+        };
+
+        let delay = backoff.delay(supervisor.restarts);
+        info!(target: "Runtime", "Restarting {actor} in {delay:?}");
+        let run_actor = RunActor::new(new_instance, signal_sender.sender_clone());
+        let running_name = actor.clone();
+        self.futures.push(tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            run_task(run_actor, running_name).await
+        }));
+        supervisor.restarts += 1;
+        supervisor.started_at = Instant::now() + delay;
+        self.supervisors.insert(actor.clone(), supervisor);
+        self.send_event(RuntimeEvent::Restarting { task: actor, delay })
+            .await;
+        Ok(())
     }
 
     async fn send_event(&mut self, event: RuntimeEvent) {
@@ -295,6 +492,36 @@ where
     }
 }
 
+/// Request the metrics of the given actors, concurrently so the slow ones don't delay the others
+async fn collect_metrics(
+    actors: Vec<(String, DynSender<RuntimeRequest>)>,
+    reply: oneshot::Sender<RuntimeMetrics>,
+) {
+    let requests = actors
+        .into_iter()
+        .map(|(running_as, mut sender)| async move {
+            let (metrics_reply, mut metrics) = MetricsReply::channel();
+            let request = async {
+                sender
+                    .send(RuntimeRequest::ReportMetrics(metrics_reply))
+                    .await
+                    .ok()?;
+                metrics.next().await
+            };
+            let metrics = tokio::time::timeout(METRICS_TIMEOUT, request)
+                .await
+                .ok()
+                .flatten();
+            metrics.map(|metrics| (running_as, metrics))
+        });
+    let metrics = futures::future::join_all(requests)
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+    let _ = reply.send(metrics);
+}
+
 async fn run_task(task: RunActor, running_name: String) -> Result<String, (String, RuntimeError)> {
     match tokio::spawn(task.run()).await {
         Ok(r) => r
@@ -312,7 +539,10 @@ mod tests {
     use crate::LoggingReceiver;
     use crate::LoggingSender;
     use crate::Message;
+    use crate::MessageSource;
+    use crate::NoConfig;
     use crate::SimpleMessageBox;
+    use crate::SimpleMessageBoxBuilder;
     use async_trait::async_trait;
     use futures::channel::mpsc;
     use std::time::Duration;
@@ -377,6 +607,67 @@ mod tests {
         }
     }
 
+    /// Echoes messages, failing on `"fail"`
+    struct Failing {
+        messages: SimpleMessageBox<String, String>,
+    }
+
+    #[async_trait]
+    impl Actor for Failing {
+        fn name(&self) -> &str {
+            "Failing"
+        }
+
+        async fn run(mut self) -> Result<(), RuntimeError> {
+            while let Some(message) = self.messages.recv().await {
+                if message == "fail" {
+                    return Err(ChannelError::ReceiveError().into());
+                }
+                crate::Sender::send(&mut self.messages, message).await?;
+            }
+            Ok(())
+        }
+    }
+
+    struct FailingBuilder {
+        messages: SimpleMessageBoxBuilder<String, String>,
+    }
+
+    impl RuntimeRequestSink for FailingBuilder {
+        fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+            self.messages.get_signal_sender()
+        }
+    }
+
+    impl RestartableBuilder<Failing> for FailingBuilder {
+        fn build_restartable(self) -> (Failing, crate::ActorRebuilder<Failing>) {
+            let (messages, mut rebuild) = self.messages.build_restartable();
+            let rebuild = move || rebuild().map(|messages| Failing { messages });
+            (Failing { messages }, Box::new(rebuild))
+        }
+    }
+
+    fn create_failing_actor(
+        strategy: SupervisionStrategy,
+    ) -> (DynSender<String>, mpsc::Receiver<String>, RunActor) {
+        let mut messages = SimpleMessageBoxBuilder::new("Failing", 16);
+        let (output_sender, output_receiver) = mpsc::channel(16);
+        let output_sender: DynSender<String> = output_sender.into();
+        messages.connect_sink(NoConfig, &output_sender);
+        let input_sender = messages.get_sender();
+        let actor = RunActor::from_restartable_builder(FailingBuilder { messages }, strategy);
+
+        (input_sender, output_receiver, actor)
+    }
+
+    fn fast_restart(max_restarts: Option<u32>) -> SupervisionStrategy {
+        SupervisionStrategy::Restart(RestartBackoff {
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_secs(1),
+            max_restarts,
+        })
+    }
+
     struct Panic;
 
     impl Panic {
@@ -604,4 +895,152 @@ mod tests {
             EchoMessage::String("Echo stopped".into())
         );
     }
+
+    #[tokio::test]
+    async fn failing_actor_is_restarted_with_the_same_message_box() {
+        let (mut actions_sender, mut events_receiver, ra) = init();
+        let (mut input, mut output, actor) = create_failing_actor(fast_restart(None));
+
+        actions_sender
+            .send(RuntimeAction::Spawn(actor))
+            .await
+            .unwrap();
+        tokio::spawn(ra.run());
+
+        crate::Sender::send(&mut input, "hello".to_string())
+            .await
+            .unwrap();
+        crate::Sender::send(&mut input, "fail".to_string())
+            .await
+            .unwrap();
+        crate::Sender::send(&mut input, "hello again".to_string())
+            .await
+            .unwrap();
+
+        let received = async {
+            let first = output.next().await;
+            let second = output.next().await;
+            (first, second)
+        };
+        let (first, second) = tokio::time::timeout(Duration::from_secs(1), received)
+            .await
+            .expect("The restarted actor to process the pending messages");
+        assert_eq!(first.as_deref(), Some("hello"));
+        assert_eq!(second.as_deref(), Some("hello again"));
+
+        let mut restarted = false;
+        while let Ok(Some(event)) =
+            tokio::time::timeout(Duration::from_millis(100), events_receiver.next()).await
+        {
+            if let RuntimeEvent::Restarting { task, .. } = event {
+                assert_eq!(task, "Failing-0");
+                restarted = true;
+            }
+        }
+        assert!(restarted, "A restart event is published");
+    }
+
+    #[tokio::test]
+    async fn failures_of_ignored_actors_do_not_stop_the_runtime() {
+        let (mut actions_sender, _events_receiver, ra) = init();
+        let (mut input, _output, failing_actor) = create_failing_actor(SupervisionStrategy::Ignore);
+        let (mut sender, mut receiver, echo_actor) = create_actor(Echo::new);
+
+        actions_sender
+            .send(RuntimeAction::Spawn(failing_actor))
+            .await
+            .unwrap();
+        actions_sender
+            .send(RuntimeAction::Spawn(echo_actor))
+            .await
+            .unwrap();
+        let runtime = tokio::spawn(ra.run());
+
+        crate::Sender::send(&mut input, "fail".to_string())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The other actors are still running
+        sender
+            .send(EchoMessage::String("hello".into()))
+            .await
+            .unwrap();
+        assert_eq!(
+            receiver.next().await.unwrap(),
+            EchoMessage::String("hello".into())
+        );
+        assert!(!runtime.is_finished());
+    }
+
+    #[tokio::test]
+    async fn failures_are_escalated_once_the_max_restarts_is_reached() {
+        let (mut actions_sender, _events_receiver, ra) = init();
+        let (mut input, _output, actor) = create_failing_actor(fast_restart(Some(1)));
+
+        actions_sender
+            .send(RuntimeAction::Spawn(actor))
+            .await
+            .unwrap();
+        crate::Sender::send(&mut input, "fail".to_string())
+            .await
+            .unwrap();
+        crate::Sender::send(&mut input, "fail".to_string())
+            .await
+            .unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(1), ra.run())
+            .await
+            .expect("The runtime to stop on the second failure");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn restart_delay_doubles_up_to_the_max_delay() {
+        let backoff = RestartBackoff {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            max_restarts: None,
+        };
+
+        let delays = (0..5).map(|restarts| backoff.delay(restarts).as_secs());
+
+        assert_eq!(delays.collect::<Vec<_>>(), [1, 2, 4, 5, 5]);
+    }
+
+    #[tokio::test]
+    async fn runtime_collects_the_metrics_of_the_actors() {
+        let (mut actions_sender, _events_receiver, ra) = init();
+        let (mut input, mut output, actor) = create_failing_actor(SupervisionStrategy::Escalate);
+
+        actions_sender
+            .send(RuntimeAction::Spawn(actor))
+            .await
+            .unwrap();
+        tokio::spawn(ra.run());
+
+        crate::Sender::send(&mut input, "hello".to_string())
+            .await
+            .unwrap();
+        crate::Sender::send(&mut input, "world".to_string())
+            .await
+            .unwrap();
+        assert_eq!(output.next().await, Some("hello".to_string()));
+        assert_eq!(output.next().await, Some("world".to_string()));
+
+        let (reply, metrics) = oneshot::channel();
+        actions_sender
+            .send(RuntimeAction::ReportMetrics(reply))
+            .await
+            .unwrap();
+        let metrics = tokio::time::timeout(Duration::from_secs(1), metrics)
+            .await
+            .expect("the runtime to answer")
+            .unwrap();
+
+        let actor_metrics = metrics.get("Failing-0").expect("the metrics of the actor");
+        assert_eq!(actor_metrics.queue_depth, 0);
+        assert_eq!(actor_metrics.messages_in, 2);
+        assert_eq!(actor_metrics.messages_out, 2);
+    }
 }
@@ -7,7 +7,10 @@ use crate::ConcurrentServerMessageBox;
 use crate::DynSender;
 use crate::LoggingReceiver;
 use crate::Message;
+use crate::MessageBoxMetrics;
 use crate::MessageSink;
+use crate::MeteredSender;
+use crate::MetricsSignalSender;
 use crate::RequestEnvelope;
 use crate::RuntimeError;
 use crate::RuntimeRequest;
@@ -21,25 +24,27 @@ use std::fmt::Debug;
 /// A message box builder for request-response services
 pub struct ServerMessageBoxBuilder<Request: Debug, Response> {
     max_concurrency: usize,
-    request_sender: mpsc::Sender<RequestEnvelope<Request, Response>>,
+    request_sender: MeteredSender<RequestEnvelope<Request, Response>>,
     request_receiver: LoggingReceiver<RequestEnvelope<Request, Response>>,
-    signal_sender: mpsc::Sender<RuntimeRequest>,
+    signal_sender: MetricsSignalSender,
 }
 
 impl<Request: Message, Response: Message> ServerMessageBoxBuilder<Request, Response> {
     /// Start to build a new message box for a server
     pub fn new(server_name: &str, capacity: usize) -> Self {
         let max_concurrency = 1;
+        let metrics = MessageBoxMetrics::default();
         let (request_sender, request_receiver) = mpsc::channel(capacity);
         let (signal_sender, signal_receiver) = mpsc::channel(4);
         let request_receiver =
-            LoggingReceiver::new(server_name.to_string(), request_receiver, signal_receiver);
+            LoggingReceiver::new(server_name.to_string(), request_receiver, signal_receiver)
+                .with_metrics(metrics.clone());
 
         ServerMessageBoxBuilder {
             max_concurrency,
-            request_sender,
+            request_sender: MeteredSender::new(request_sender, metrics.clone()),
             request_receiver,
-            signal_sender,
+            signal_sender: MetricsSignalSender::new(signal_sender, metrics),
         }
     }
 
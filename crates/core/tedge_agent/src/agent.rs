@@ -1,5 +1,12 @@
+use crate::cert_renewal_manager::builder::CertRenewalBuilder;
+use crate::cert_renewal_manager::config::CertRenewalConfig;
 use crate::device_profile_manager::DeviceProfileManagerBuilder;
+use crate::diag_manager::builder::DiagCollectBuilder;
+use crate::diag_manager::config::DiagCollectConfig;
 use crate::entity_manager;
+use crate::entity_manager::health_history::HealthHistoryConfig;
+use crate::entity_manager::health_history::HEALTH_HISTORY_FILE;
+use crate::entity_manager::health_ticker::HealthTickerBuilder;
 use crate::entity_manager::server::EntityStoreRequest;
 use crate::entity_manager::server::EntityStoreServer;
 use crate::entity_manager::server::EntityStoreServerConfig;
@@ -8,6 +15,8 @@ use crate::http_server::actor::HttpServerConfig;
 use crate::operation_file_cache::FileCacheActorBuilder;
 use crate::operation_workflows::OperationConfig;
 use crate::operation_workflows::WorkflowActorBuilder;
+use crate::remote_access_manager::builder::RemoteAccessBuilder;
+use crate::remote_access_manager::config::RemoteAccessConfig;
 use crate::restart_manager::builder::RestartManagerBuilder;
 use crate::restart_manager::config::RestartManagerConfig;
 use crate::software_manager::builder::SoftwareManagerBuilder;
@@ -30,6 +39,7 @@ use reqwest::Identity;
 use std::fmt::Debug;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tedge_actors::Concurrent;
 use tedge_actors::ConvertingActor;
 use tedge_actors::ConvertingActorBuilder;
@@ -38,11 +48,14 @@ use tedge_actors::MessageSource;
 use tedge_actors::NoConfig;
 use tedge_actors::NullSender;
 use tedge_actors::RequestEnvelope;
+use tedge_actors::RestartBackoff;
 use tedge_actors::Runtime;
 use tedge_actors::Sequential;
 use tedge_actors::ServerActorBuilder;
 use tedge_actors::ServerConfig;
+use tedge_actors::SupervisionStrategy;
 use tedge_api::entity_store::EntityRegistrationMessage;
+use tedge_api::mqtt_topics::Channel;
 use tedge_api::mqtt_topics::DeviceTopicId;
 use tedge_api::mqtt_topics::EntityTopicId;
 use tedge_api::mqtt_topics::MqttSchema;
@@ -56,14 +69,21 @@ use tedge_config_manager::ConfigManagerOptions;
 use tedge_downloader_ext::DownloaderActor;
 use tedge_file_system_ext::FsWatchActorBuilder;
 use tedge_health_ext::HealthMonitorBuilder;
+use tedge_http_ext::HttpActor;
 use tedge_log_manager::LogManagerBuilder;
 use tedge_log_manager::LogManagerConfig;
 use tedge_log_manager::LogManagerOptions;
 use tedge_mqtt_ext::MqttActorBuilder;
 use tedge_mqtt_ext::MqttConfig;
 use tedge_mqtt_ext::TopicFilter;
+use tedge_resource_monitor_ext::MetricGroup;
+use tedge_resource_monitor_ext::ProcessMonitorBuilder;
+use tedge_resource_monitor_ext::ProcessMonitorConfig;
+use tedge_resource_monitor_ext::ResourceMonitorBuilder;
+use tedge_resource_monitor_ext::ResourceMonitorConfig;
 use tedge_script_ext::ScriptActor;
 use tedge_signal_ext::SignalActor;
+use tedge_timer_ext::TimerActor;
 use tedge_uploader_ext::UploaderActor;
 use tedge_utils::file::create_directory_with_defaults;
 use tracing::info;
@@ -77,6 +97,9 @@ pub(crate) struct AgentConfig {
     pub mqtt_config: MqttConfig,
     pub http_config: HttpServerConfig,
     pub restart_config: RestartManagerConfig,
+    pub cert_renewal_config: CertRenewalConfig,
+    pub remote_access_config: Option<RemoteAccessConfig>,
+    pub diag_collect_config: Option<DiagCollectConfig>,
     pub sw_update_config: SoftwareManagerConfig,
     pub operation_config: OperationConfig,
     pub config_dir: Utf8PathBuf,
@@ -94,11 +117,18 @@ pub(crate) struct AgentConfig {
     pub service: TEdgeConfigReaderService,
     pub identity: Option<Identity>,
     pub cloud_root_certs: CloudHttpConfig,
+    pub http_client_tls_config: rustls::ClientConfig,
     pub fts_url: Arc<str>,
     pub is_sudo_enabled: bool,
     pub capabilities: Capabilities,
     entity_auto_register: bool,
     entity_store_clean_start: bool,
+    health_history_config: HealthHistoryConfig,
+    health_stats_interval: Duration,
+    inventory_interval: Duration,
+    builtin_inventory_collectors: bool,
+    resource_monitor_config: Option<ResourceMonitorConfig>,
+    process_monitor_config: Option<ProcessMonitorConfig>,
 }
 
 impl AgentConfig {
@@ -148,6 +178,27 @@ impl AgentConfig {
         let restart_config =
             RestartManagerConfig::from_tedge_config(&mqtt_device_topic_id, &tedge_config).await?;
 
+        // Certificate renewal config
+        let cert_renewal_config = CertRenewalConfig::from_tedge_config(
+            MqttSchema::with_root(mqtt_topic_root.to_string()),
+            mqtt_device_topic_id.clone(),
+            &tedge_config,
+        );
+
+        // Remote access config, if enabled
+        let remote_access_config = tedge_config
+            .agent
+            .enable
+            .remote_access
+            .then(|| RemoteAccessConfig::from_tedge_config(&tedge_config));
+
+        // Diagnostic collection config, if enabled
+        let diag_collect_config = tedge_config
+            .agent
+            .enable
+            .diag_collect
+            .then(|| DiagCollectConfig::from_tedge_config(&tedge_config));
+
         // Software update config
         let sw_update_config = SoftwareManagerConfig::from_tedge_config(&tedge_config).await?;
 
@@ -170,6 +221,7 @@ impl AgentConfig {
 
         let identity = tedge_config.http.client.auth.identity()?;
         let cloud_root_certs = tedge_config.cloud_root_certs()?;
+        let http_client_tls_config = tedge_config.http.client_tls_config()?;
 
         let is_sudo_enabled = tedge_config.sudo.enable;
 
@@ -187,10 +239,70 @@ impl AgentConfig {
         let entity_auto_register = tedge_config.agent.entity_store.auto_register;
         let entity_store_clean_start = tedge_config.agent.entity_store.clean_start;
 
+        let health_history_config = HealthHistoryConfig::from_tedge_config(&tedge_config);
+        let health_stats_interval = tedge_config.agent.health_history.interval.duration();
+
+        let inventory_interval = tedge_config.agent.inventory.interval.duration();
+        let builtin_inventory_collectors = tedge_config.agent.inventory.builtin_collectors;
+
+        let resource_monitor_config = if tedge_config.agent.resources.enable {
+            let interval = tedge_config.agent.resources.interval.duration();
+            anyhow::ensure!(
+                !interval.is_zero(),
+                "Invalid agent.resources.interval: the resource usage cannot be sampled every 0s"
+            );
+            let topic = MqttSchema::with_root(mqtt_topic_root.to_string()).topic_for(
+                &mqtt_device_topic_id,
+                &Channel::Measurement {
+                    measurement_type: "resources".to_string(),
+                },
+            );
+            let groups = tedge_config
+                .agent
+                .resources
+                .groups
+                .iter()
+                .map(|group| group.parse())
+                .collect::<Result<Vec<MetricGroup>, _>>()
+                .context("Invalid agent.resources.groups")?;
+            let disks = tedge_config
+                .agent
+                .resources
+                .disks
+                .iter()
+                .map(Utf8PathBuf::from)
+                .collect();
+            let config = ResourceMonitorConfig::new(topic, interval)
+                .with_groups(groups)
+                .with_disks(disks);
+            Some(config)
+        } else {
+            None
+        };
+        let process_monitor_config = if tedge_config.agent.processes.enable {
+            let interval = tedge_config.agent.processes.interval.duration();
+            anyhow::ensure!(
+                !interval.is_zero(),
+                "Invalid agent.processes.interval: the service processes cannot be sampled every 0s"
+            );
+            let config = ProcessMonitorConfig::new(
+                MqttSchema::with_root(mqtt_topic_root.to_string()),
+                mqtt_device_topic_id.clone(),
+                interval,
+            )
+            .with_time_format(tedge_config.service.timestamp_format);
+            Some(config)
+        } else {
+            None
+        };
+
         Ok(Self {
             mqtt_config,
             http_config,
             restart_config,
+            cert_renewal_config,
+            remote_access_config,
+            diag_collect_config,
             sw_update_config,
             operation_config,
             config_dir,
@@ -207,12 +319,19 @@ impl AgentConfig {
             tedge_http_host,
             identity,
             cloud_root_certs,
+            http_client_tls_config,
             fts_url,
             is_sudo_enabled,
             service: tedge_config.service.clone(),
             capabilities,
             entity_auto_register,
             entity_store_clean_start,
+            health_history_config,
+            health_stats_interval,
+            inventory_interval,
+            builtin_inventory_collectors,
+            resource_monitor_config,
+            process_monitor_config,
         })
     }
 }
@@ -272,6 +391,9 @@ impl Agent {
         // Script actor
         let mut script_runner: ServerActorBuilder<ScriptActor, Concurrent> = ScriptActor::builder();
 
+        // HTTP client actor, used by the workflows to send HTTP requests
+        let mut http_actor_builder = HttpActor::new(self.config.http_client_tls_config).builder();
+
         // Restart actor
         let mut restart_actor_builder = RestartManagerBuilder::new(self.config.restart_config);
 
@@ -281,16 +403,38 @@ impl Agent {
         // Software update actor
         let mut software_update_builder = SoftwareManagerBuilder::new(self.config.sw_update_config);
 
+        // Timer actor, used by the workflows to delay the retries
+        let mut timer_actor_builder = TimerActor::builder();
+
         // Converter actor
         let mut converter_actor_builder = WorkflowActorBuilder::new(
             self.config.operation_config,
             &mut mqtt_actor_builder,
             &mut script_runner,
+            &mut http_actor_builder,
             &mut fs_watch_actor_builder,
+            &mut timer_actor_builder,
         );
         converter_actor_builder.register_builtin_operation(&mut restart_actor_builder);
         converter_actor_builder.register_builtin_operation(&mut software_update_builder);
 
+        // Certificate renewal actor, only on the main device where the cloud connections are established
+        let cert_renewal_builder = if self.config.mqtt_device_topic_id.is_default_main_device() {
+            let mut cert_renewal_builder =
+                CertRenewalBuilder::new(self.config.cert_renewal_config, &mut mqtt_actor_builder);
+            converter_actor_builder.register_builtin_operation(&mut cert_renewal_builder);
+            Some(cert_renewal_builder)
+        } else {
+            None
+        };
+
+        // Remote access actor, opening TCP tunnels over websockets
+        let remote_access_builder = self.config.remote_access_config.map(|config| {
+            let mut remote_access_builder = RemoteAccessBuilder::new(config);
+            converter_actor_builder.register_builtin_operation(&mut remote_access_builder);
+            remote_access_builder
+        });
+
         // Shutdown on SIGINT
         let signal_actor_builder = SignalActor::builder(&runtime.get_handle());
 
@@ -310,7 +454,9 @@ impl Agent {
             mqtt_schema.clone(),
             device_topic_id.clone(),
             service_topic_id.into(),
-        );
+        )
+        .with_inventory_interval(self.config.inventory_interval)
+        .with_builtin_collectors(self.config.builtin_inventory_collectors);
         let twin_manager_builder =
             TwinManagerActorBuilder::new(twin_manager_config, &mut mqtt_actor_builder);
 
@@ -319,6 +465,7 @@ impl Agent {
             &mut mqtt_actor_builder,
             &mqtt_schema,
             &self.config.service,
+            &runtime.get_handle(),
         );
 
         let mut downloader_actor_builder = DownloaderActor::new(
@@ -378,6 +525,26 @@ impl Agent {
             None
         };
 
+        // Diagnostic collection actor, running the `tedge diag` plugins on demand
+        let diag_collect_builder = self.config.diag_collect_config.map(|config| {
+            let mut diag_collect_builder =
+                DiagCollectBuilder::new(config, &mut uploader_actor_builder);
+            converter_actor_builder.register_builtin_operation(&mut diag_collect_builder);
+            diag_collect_builder
+        });
+
+        // Instantiate the resource monitor if enabled
+        let resource_monitor_builder = self
+            .config
+            .resource_monitor_config
+            .map(|config| ResourceMonitorBuilder::new(config, &mqtt_actor_builder));
+
+        // Monitor the processes of the services registered with a `@process` property, if enabled
+        let process_monitor_builder = self
+            .config
+            .process_monitor_config
+            .map(|config| ProcessMonitorBuilder::new(config, &mut mqtt_actor_builder));
+
         // TODO: replace with a call to entity store when we stop assuming default MQTT schema
         let is_main_device = device_topic_id == EntityTopicId::default_main_device();
         if is_main_device {
@@ -391,6 +558,11 @@ impl Agent {
             let clean_start = self.config.entity_store_clean_start;
             let telemetry_cache_size = 0; // Agent need not cache any data messages, the mapper would
 
+            let health_history_config = self
+                .config
+                .health_history_config
+                .with_path(state_dir.join(HEALTH_HISTORY_FILE));
+
             let main_device = EntityRegistrationMessage::main_device(None);
             let entity_store = EntityStore::with_main_device(
                 mqtt_schema.clone(),
@@ -400,7 +572,9 @@ impl Agent {
                 clean_start,
             )?;
             let entity_store_server_config =
-                EntityStoreServerConfig::new(mqtt_schema.clone(), self.config.entity_auto_register);
+                EntityStoreServerConfig::new(mqtt_schema.clone(), self.config.entity_auto_register)
+                    .with_health_history(health_history_config)
+                    .with_time_format(self.config.service.timestamp_format);
             let entity_store_server = EntityStoreServer::new(
                 entity_store_server_config,
                 entity_store,
@@ -419,6 +593,13 @@ impl Agent {
                 },
             );
 
+            // Persist the health history and publish the uptime statistics of the services, unless disabled
+            let health_stats_interval = self.config.health_stats_interval;
+            let health_ticker_builder = HealthTickerBuilder::new(
+                (!health_stats_interval.is_zero()).then_some(health_stats_interval),
+                &mut entity_store_actor_builder,
+            );
+
             let file_transfer_server_builder = HttpServerBuilder::try_bind(
                 self.config.http_config,
                 &mut entity_store_actor_builder,
@@ -435,6 +616,7 @@ impl Agent {
 
             runtime.spawn(file_transfer_server_builder).await?;
             runtime.spawn(entity_store_actor_builder).await?;
+            runtime.spawn(health_ticker_builder).await?;
             runtime.spawn(operation_file_cache_builder).await?;
         } else {
             info!("Running as a child device, tedge_to_te_converter and File Transfer Service disabled");
@@ -451,11 +633,33 @@ impl Agent {
             runtime.spawn(config_actor_builder).await?;
         }
         if let Some(log_actor_builder) = log_actor_builder {
-            runtime.spawn(log_actor_builder).await?;
+            runtime
+                .spawn_supervised(
+                    log_actor_builder,
+                    SupervisionStrategy::Restart(RestartBackoff::default()),
+                )
+                .await?;
+        }
+        if let Some(resource_monitor_builder) = resource_monitor_builder {
+            runtime.spawn(resource_monitor_builder).await?;
+        }
+        if let Some(process_monitor_builder) = process_monitor_builder {
+            runtime.spawn(process_monitor_builder).await?;
         }
         runtime.spawn(restart_actor_builder).await?;
+        if let Some(cert_renewal_builder) = cert_renewal_builder {
+            runtime.spawn(cert_renewal_builder).await?;
+        }
+        if let Some(remote_access_builder) = remote_access_builder {
+            runtime.spawn(remote_access_builder).await?;
+        }
+        if let Some(diag_collect_builder) = diag_collect_builder {
+            runtime.spawn(diag_collect_builder).await?;
+        }
         runtime.spawn(software_update_builder).await?;
         runtime.spawn(script_runner).await?;
+        runtime.spawn(http_actor_builder).await?;
+        runtime.spawn(timer_actor_builder).await?;
         runtime.spawn(converter_actor_builder).await?;
         runtime.spawn(health_actor).await?;
 
@@ -0,0 +1,270 @@
+use crate::cert_renewal_manager::config::CertRenewalConfig;
+use crate::cert_renewal_manager::config::CloudCertificate;
+use async_trait::async_trait;
+use certificate::PemCertificate;
+use certificate::ValidityStatus;
+use std::time::Duration;
+use tedge_actors::Actor;
+use tedge_actors::LoggingSender;
+use tedge_actors::MessageReceiver;
+use tedge_actors::RuntimeError;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_api::commands::CertRenewCmdPayload;
+use tedge_api::commands::CertRenewCommand;
+use tedge_api::commands::CommandStatus;
+use tedge_mqtt_ext::MqttMessage;
+use time::OffsetDateTime;
+use tokio::process::Command;
+use tokio::time::interval_at;
+use tokio::time::Instant;
+use tokio::time::MissedTickBehavior;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+const TEDGE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Delay after which a renewal command triggered by a periodic check is no more awaited
+const PENDING_RENEWAL_TIMEOUT: Duration = Duration::from_secs(3600);
+
+pub struct CertRenewalActor {
+    config: CertRenewalConfig,
+    message_box: SimpleMessageBox<CertRenewCommand, CertRenewCommand>,
+    mqtt_publisher: LoggingSender<MqttMessage>,
+
+    /// The id of the last renewal command triggered by a periodic check, till executed or timed out
+    pending_renewal: Option<(String, Instant)>,
+}
+
+#[async_trait]
+impl Actor for CertRenewalActor {
+    fn name(&self) -> &str {
+        "CertRenewalActor"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        let Some(check_interval) = self.config.check_interval else {
+            while let Some(request) = self.message_box.recv().await {
+                self.process_command(request).await?;
+            }
+            return Ok(());
+        };
+
+        // The first check is done on start, then periodically
+        let mut ticker = interval_at(Instant::now(), check_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                request = self.message_box.recv() => match request {
+                    Some(request) => self.process_command(request).await?,
+                    None => return Ok(()),
+                },
+                _ = ticker.tick() => self.check_certificates().await?,
+            }
+        }
+    }
+}
+
+impl CertRenewalActor {
+    pub fn new(
+        config: CertRenewalConfig,
+        message_box: SimpleMessageBox<CertRenewCommand, CertRenewCommand>,
+        mqtt_publisher: LoggingSender<MqttMessage>,
+    ) -> Self {
+        CertRenewalActor {
+            config,
+            message_box,
+            mqtt_publisher,
+            pending_renewal: None,
+        }
+    }
+
+    /// Trigger a `cert_renew` command if the certificate of any connected cloud is about to expire
+    async fn check_certificates(&mut self) -> Result<(), RuntimeError> {
+        if let Some((cmd_id, triggered)) = &self.pending_renewal {
+            if triggered.elapsed() < PENDING_RENEWAL_TIMEOUT {
+                return Ok(());
+            }
+            warn!("No progress made by the certificate renewal command {cmd_id}: triggering a new one");
+            self.pending_renewal = None;
+        }
+
+        let clouds: Vec<String> = self
+            .connected_clouds()
+            .filter(|cloud| self.needs_renewal(cloud))
+            .map(|cloud| cloud.name())
+            .collect();
+        if clouds.is_empty() {
+            return Ok(());
+        }
+
+        let cmd_id = format!(
+            "cert-renewal-{}",
+            OffsetDateTime::now_utc().unix_timestamp()
+        );
+        info!("Triggering the renewal of the device certificate for {clouds:?}");
+        let command = CertRenewCommand {
+            target: self.config.device_topic_id.clone(),
+            cmd_id: cmd_id.clone(),
+            payload: CertRenewCmdPayload {
+                clouds,
+                ..Default::default()
+            },
+        };
+        self.pending_renewal = Some((cmd_id, Instant::now()));
+        self.mqtt_publisher
+            .send(command.command_message(&self.config.mqtt_schema))
+            .await?;
+        Ok(())
+    }
+
+    async fn process_command(&mut self, mut command: CertRenewCommand) -> Result<(), RuntimeError> {
+        let status = command.status();
+        if matches!(
+            status,
+            CommandStatus::Scheduled | CommandStatus::Successful | CommandStatus::Failed { .. }
+        ) && self
+            .pending_renewal
+            .as_ref()
+            .is_some_and(|(cmd_id, _)| cmd_id == &command.cmd_id)
+        {
+            self.pending_renewal = None;
+        }
+        if status != CommandStatus::Scheduled {
+            // Only handle commands in the scheduled state
+            return Ok(());
+        }
+
+        command.executing();
+        self.message_box.send(command.clone()).await?;
+
+        let clouds = match self.target_clouds(&command.payload) {
+            Ok(clouds) => clouds,
+            Err(reason) => {
+                error!(reason);
+                command.failed(reason);
+                self.message_box.send(command).await?;
+                return Ok(());
+            }
+        };
+
+        let mut errors = Vec::new();
+        for cloud in clouds {
+            if !command.payload.force && !self.needs_renewal(&cloud) {
+                info!("No need to renew the certificate for {}", cloud.name());
+                continue;
+            }
+            match self.renew_certificate(&cloud).await {
+                Ok(()) => {
+                    info!("The certificate for {} has been renewed", cloud.name());
+                    command.payload.renewed.push(cloud.name())
+                }
+                Err(err) => {
+                    let reason =
+                        format!("Fail to renew the certificate for {}: {err}", cloud.name());
+                    error!(reason);
+                    errors.push(reason)
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            command.successful();
+        } else {
+            command.failed(errors.join("; "));
+        }
+        self.message_box.send(command).await?;
+        Ok(())
+    }
+
+    fn connected_clouds(&self) -> impl Iterator<Item = &CloudCertificate> {
+        self.config
+            .clouds
+            .iter()
+            .filter(|cloud| cloud.is_connected(&self.config.config_dir))
+    }
+
+    /// Return the clouds targeted by a command: all the connected clouds if none is specified
+    fn target_clouds(
+        &self,
+        payload: &CertRenewCmdPayload,
+    ) -> Result<Vec<CloudCertificate>, String> {
+        if payload.clouds.is_empty() {
+            return Ok(self.connected_clouds().cloned().collect());
+        }
+
+        payload
+            .clouds
+            .iter()
+            .map(|name| {
+                self.config
+                    .clouds
+                    .iter()
+                    .find(|cloud| &cloud.name() == name)
+                    .cloned()
+                    .ok_or_else(|| format!("Certificate renewal is not supported for {name}"))
+            })
+            .collect()
+    }
+
+    /// Tell if the certificate of a cloud profile has to be renewed
+    ///
+    /// A certificate that cannot be read is not renewed, as the issue is not related to its expiry.
+    fn needs_renewal(&self, cloud: &CloudCertificate) -> bool {
+        match PemCertificate::from_pem_file(&cloud.cert_path).and_then(|cert| cert.still_valid()) {
+            Ok(ValidityStatus::Valid { expired_in }) => expired_in < self.config.minimum_validity,
+            Ok(ValidityStatus::Expired { .. }) => true,
+            Ok(ValidityStatus::NotValidYet { .. }) => false,
+            Err(err) => {
+                warn!("Cannot check the certificate {}: {err}", cloud.cert_path);
+                false
+            }
+        }
+    }
+
+    /// Renew the certificate and reconnect the cloud
+    ///
+    /// The new certificate is only substituted to the current one by `tedge reconnect`,
+    /// provided the connection to the cloud is successful with the new certificate.
+    async fn renew_certificate(&self, cloud: &CloudCertificate) -> Result<(), String> {
+        self.run_tedge(cloud.renew_args()).await?;
+
+        let new_cert_path = cloud.new_cert_path();
+        match PemCertificate::from_pem_file(&new_cert_path).and_then(|cert| cert.still_valid()) {
+            Ok(ValidityStatus::Valid { .. }) => {}
+            Ok(ValidityStatus::Expired { .. }) => {
+                return Err(format!("the new certificate {new_cert_path} has expired"))
+            }
+            Ok(ValidityStatus::NotValidYet { .. }) => {
+                return Err(format!(
+                    "the new certificate {new_cert_path} is not valid yet"
+                ))
+            }
+            Err(err) => return Err(format!("invalid new certificate: {err}")),
+        }
+
+        self.run_tedge(cloud.reconnect_args()).await
+    }
+
+    async fn run_tedge(&self, args: Vec<String>) -> Result<(), String> {
+        let command_line = format!("{} {}", self.config.tedge_bin, args.join(" "));
+        let mut command: Command = self.config.sudo.command(&self.config.tedge_bin).into();
+        command.args(&args).kill_on_drop(true);
+
+        let output = match tokio::time::timeout(TEDGE_TIMEOUT, command.output()).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(err)) => return Err(format!("`{command_line}` failed to execute: {err}")),
+            Err(_) => return Err(format!("`{command_line}` timed out")),
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!(
+                "`{command_line}` failed with {}: {}",
+                output.status,
+                stderr.trim()
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use crate::cert_renewal_manager::actor::CertRenewalActor;
+use crate::cert_renewal_manager::config::CertRenewalConfig;
+use tedge_actors::Builder;
+use tedge_actors::DynSender;
+use tedge_actors::LinkError;
+use tedge_actors::LoggingSender;
+use tedge_actors::MappingSender;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::commands::CertRenewCmdPayload;
+use tedge_api::commands::CertRenewCommand;
+use tedge_api::commands::CommandPayload;
+use tedge_api::commands::CommandStatus;
+use tedge_api::commands::CERT_RENEW_OPERATION;
+use tedge_api::mqtt_topics::ChannelFilter;
+use tedge_api::mqtt_topics::EntityFilter;
+use tedge_api::workflow::GenericCommandData;
+use tedge_api::workflow::GenericCommandState;
+use tedge_api::workflow::OperationName;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::TopicFilter;
+
+pub struct CertRenewalBuilder {
+    config: CertRenewalConfig,
+    message_box: SimpleMessageBoxBuilder<CertRenewCommand, CertRenewCommand>,
+    mqtt_publisher: DynSender<MqttMessage>,
+}
+
+impl CertRenewalBuilder {
+    pub fn new(
+        config: CertRenewalConfig,
+        mqtt: &mut (impl MessageSource<MqttMessage, TopicFilter> + MessageSink<MqttMessage>),
+    ) -> Self {
+        let message_box = SimpleMessageBoxBuilder::new("CertRenewal", 10);
+
+        // The final states of the cert_renew commands are only observed on MQTT,
+        // as not forwarded to the builtin operation actors by the workflow engine
+        let subscriptions = config.mqtt_schema.topics(
+            EntityFilter::Entity(&config.device_topic_id),
+            ChannelFilter::Command(CertRenewCmdPayload::operation_type()),
+        );
+        let mqtt_schema = config.mqtt_schema.clone();
+        mqtt.connect_mapped_sink(subscriptions, &message_box, move |message| {
+            CertRenewCommand::parse(&mqtt_schema, message)
+                .ok()
+                .flatten()
+                .filter(|command| {
+                    matches!(
+                        command.status(),
+                        CommandStatus::Successful | CommandStatus::Failed { .. }
+                    )
+                })
+        });
+
+        Self {
+            config,
+            message_box,
+            mqtt_publisher: mqtt.get_sender(),
+        }
+    }
+}
+
+impl MessageSink<CertRenewCommand> for CertRenewalBuilder {
+    fn get_sender(&self) -> DynSender<CertRenewCommand> {
+        self.message_box.get_sender()
+    }
+}
+
+impl MessageSource<CertRenewCommand, NoConfig> for CertRenewalBuilder {
+    fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<CertRenewCommand>) {
+        self.message_box.connect_sink(config, peer)
+    }
+}
+
+impl MessageSource<GenericCommandData, NoConfig> for CertRenewalBuilder {
+    fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<GenericCommandData>) {
+        self.message_box.connect_sink(config, &peer.get_sender())
+    }
+}
+
+impl IntoIterator for &CertRenewalBuilder {
+    type Item = (OperationName, DynSender<GenericCommandState>);
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let sender =
+            MappingSender::new(self.message_box.get_sender(), |msg: GenericCommandState| {
+                msg.try_into().ok()
+            });
+        vec![(CERT_RENEW_OPERATION.to_string(), sender.into())].into_iter()
+    }
+}
+
+impl RuntimeRequestSink for CertRenewalBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.message_box.get_signal_sender()
+    }
+}
+
+impl Builder<CertRenewalActor> for CertRenewalBuilder {
+    type Error = LinkError;
+
+    fn try_build(self) -> Result<CertRenewalActor, Self::Error> {
+        Ok(self.build())
+    }
+
+    fn build(self) -> CertRenewalActor {
+        let mqtt_publisher = LoggingSender::new("MqttPublisher".into(), self.mqtt_publisher);
+        CertRenewalActor::new(self.config, self.message_box.build(), mqtt_publisher)
+    }
+}
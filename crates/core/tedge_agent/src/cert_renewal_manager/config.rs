@@ -0,0 +1,151 @@
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use std::time::Duration;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_config::tedge_toml::CloudConfig;
+use tedge_config::SudoCommandBuilder;
+use tedge_config::TEdgeConfig;
+
+#[derive(Debug, Clone)]
+pub struct CertRenewalConfig {
+    pub mqtt_schema: MqttSchema,
+    pub device_topic_id: EntityTopicId,
+    pub config_dir: Utf8PathBuf,
+
+    /// The cloud profiles for which the device certificate can be renewed
+    pub clouds: Vec<CloudCertificate>,
+
+    /// Minimum validity duration below which a new certificate is requested
+    pub minimum_validity: Duration,
+
+    /// Interval at which the certificates are checked, if enabled
+    pub check_interval: Option<Duration>,
+
+    /// The `tedge` command used to renew the certificates and reconnect the clouds
+    pub tedge_bin: Utf8PathBuf,
+    pub sudo: SudoCommandBuilder,
+}
+
+/// The device certificate used to connect a cloud profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloudCertificate {
+    /// The cloud type: `c8y`, `az` or `aws`
+    pub cloud: String,
+
+    /// The cloud profile, if not the default one
+    pub profile: Option<String>,
+
+    /// Path of the device certificate
+    pub cert_path: Utf8PathBuf,
+
+    /// Use the EST server of the profile, rather than the cloud CA
+    pub use_est: bool,
+}
+
+impl CloudCertificate {
+    fn new(cloud: &str, profile: Option<&str>, config: &dyn CloudConfig) -> Self {
+        CloudCertificate {
+            cloud: cloud.to_string(),
+            profile: profile.map(|p| p.to_string()),
+            cert_path: config.device_cert_path().to_owned(),
+            use_est: config.est_config().is_some(),
+        }
+    }
+
+    /// The profile name, as `c8y`, `c8y@eu` or `aws`
+    pub fn name(&self) -> String {
+        match &self.profile {
+            None => self.cloud.clone(),
+            Some(profile) => format!("{}@{profile}", self.cloud),
+        }
+    }
+
+    /// The path where the new certificate is stored by `tedge cert renew`, till the next connection
+    pub fn new_cert_path(&self) -> Utf8PathBuf {
+        let mut new_cert_path = self.cert_path.clone();
+        new_cert_path.set_file_name(match self.cert_path.file_name() {
+            None => "certificate.new".to_string(),
+            Some(filename) => format!("{filename}.new"),
+        });
+        new_cert_path
+    }
+
+    /// Tell if the device is connected to this cloud profile
+    ///
+    /// The bridge configuration file of a cloud profile is created on `tedge connect`
+    /// and removed on `tedge disconnect`.
+    pub fn is_connected(&self, config_dir: &Utf8Path) -> bool {
+        config_dir
+            .join("mosquitto-conf")
+            .join(format!("{}-bridge.conf", self.name()))
+            .exists()
+    }
+
+    /// The `tedge` arguments to renew the certificate
+    pub fn renew_args(&self) -> Vec<String> {
+        let mut args = vec!["cert".to_string(), "renew".to_string()];
+        if self.use_est {
+            args.extend(["--ca".to_string(), "est".to_string()]);
+        }
+        args.extend(self.cloud_args());
+        args
+    }
+
+    /// The `tedge` arguments to reconnect the cloud, installing the new certificate
+    pub fn reconnect_args(&self) -> Vec<String> {
+        let mut args = vec!["reconnect".to_string()];
+        args.extend(self.cloud_args());
+        args
+    }
+
+    fn cloud_args(&self) -> Vec<String> {
+        let mut args = vec![self.cloud.clone()];
+        if let Some(profile) = &self.profile {
+            args.extend(["--profile".to_string(), profile.clone()]);
+        }
+        args
+    }
+}
+
+impl CertRenewalConfig {
+    pub fn from_tedge_config(
+        mqtt_schema: MqttSchema,
+        device_topic_id: EntityTopicId,
+        tedge_config: &TEdgeConfig,
+    ) -> Self {
+        let mut clouds = Vec::new();
+
+        // Cumulocity certificates are renewed by the Cumulocity CA, unless an EST server is configured.
+        for (profile, c8y) in tedge_config.c8y.entries() {
+            clouds.push(CloudCertificate::new("c8y", profile, c8y));
+        }
+        // Azure and AWS don't provide a CA: the certificates can only be renewed using an EST server.
+        for (profile, az) in tedge_config.az.entries() {
+            if az.est_config().is_some() {
+                clouds.push(CloudCertificate::new("az", profile, az));
+            }
+        }
+        for (profile, aws) in tedge_config.aws.entries() {
+            if aws.est_config().is_some() {
+                clouds.push(CloudCertificate::new("aws", profile, aws));
+            }
+        }
+
+        let renewal = &tedge_config.agent.cert_renewal;
+        CertRenewalConfig {
+            mqtt_schema,
+            device_topic_id,
+            config_dir: tedge_config.root_dir().to_owned(),
+            clouds,
+            minimum_validity: tedge_config
+                .certificate
+                .validity
+                .minimum_duration
+                .duration(),
+            check_interval: renewal.enable.then(|| renewal.interval.duration()),
+            tedge_bin: "tedge".into(),
+            sudo: SudoCommandBuilder::new(tedge_config),
+        }
+    }
+}
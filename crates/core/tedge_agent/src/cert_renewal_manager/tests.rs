@@ -0,0 +1,234 @@
+use crate::cert_renewal_manager::builder::CertRenewalBuilder;
+use crate::cert_renewal_manager::config::CertRenewalConfig;
+use crate::cert_renewal_manager::config::CloudCertificate;
+use serde_json::json;
+use serde_json::Value;
+use std::time::Duration;
+use tedge_actors::test_helpers::MessageReceiverExt;
+use tedge_actors::test_helpers::TimedMessageBox;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::MessageReceiver;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::commands::CertRenewCmdPayload;
+use tedge_api::commands::CertRenewCommand;
+use tedge_api::commands::CommandStatus;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_config::SudoCommandBuilder;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_test_utils::fs::with_exec_permission;
+use tedge_test_utils::fs::TempTedgeDir;
+use time::OffsetDateTime;
+
+const TEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[tokio::test]
+async fn renew_certificate_about_to_expire() {
+    let ttd = TempTedgeDir::new();
+    let cert_path = device_certificate(&ttd, time::Duration::days(1));
+    let (mut workflow, _mqtt) = spawn_cert_renewal(&ttd, &cert_path, None);
+
+    workflow.send(scheduled_command(false)).await.unwrap();
+
+    assert_eq!(
+        workflow.recv().await.unwrap().status(),
+        CommandStatus::Executing
+    );
+    let response = workflow.recv().await.unwrap();
+    assert_eq!(response.status(), CommandStatus::Successful);
+    assert_eq!(response.payload.renewed, vec!["c8y".to_string()]);
+    assert_eq!(
+        tedge_calls(&ttd),
+        vec!["cert renew c8y".to_string(), "reconnect c8y".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn valid_certificates_are_only_renewed_on_demand() {
+    let ttd = TempTedgeDir::new();
+    let cert_path = device_certificate(&ttd, time::Duration::days(365));
+    let (mut workflow, _mqtt) = spawn_cert_renewal(&ttd, &cert_path, None);
+
+    workflow.send(scheduled_command(false)).await.unwrap();
+    workflow.skip(1).await;
+    let response = workflow.recv().await.unwrap();
+    assert_eq!(response.status(), CommandStatus::Successful);
+    assert!(response.payload.renewed.is_empty());
+    assert!(tedge_calls(&ttd).is_empty());
+
+    workflow.send(scheduled_command(true)).await.unwrap();
+    workflow.skip(1).await;
+    let response = workflow.recv().await.unwrap();
+    assert_eq!(response.status(), CommandStatus::Successful);
+    assert_eq!(response.payload.renewed, vec!["c8y".to_string()]);
+}
+
+#[tokio::test]
+async fn unknown_clouds_are_rejected() {
+    let ttd = TempTedgeDir::new();
+    let cert_path = device_certificate(&ttd, time::Duration::days(1));
+    let (mut workflow, _mqtt) = spawn_cert_renewal(&ttd, &cert_path, None);
+
+    let mut command = scheduled_command(false);
+    command.payload.clouds = vec!["aws".to_string()];
+    workflow.send(command).await.unwrap();
+    workflow.skip(1).await;
+
+    assert_eq!(
+        workflow.recv().await.unwrap().status(),
+        CommandStatus::Failed {
+            reason: "Certificate renewal is not supported for aws".to_string()
+        }
+    );
+}
+
+#[tokio::test]
+async fn periodic_checks_trigger_cert_renew_commands() {
+    let ttd = TempTedgeDir::new();
+    let cert_path = device_certificate(&ttd, time::Duration::days(1));
+    let (_workflow, mut mqtt) =
+        spawn_cert_renewal(&ttd, &cert_path, Some(Duration::from_secs(3600)));
+
+    let message = mqtt.recv().await.expect("a cert_renew command");
+    assert!(message
+        .topic
+        .name
+        .starts_with("te/device/main///cmd/cert_renew/cert-renewal-"));
+    assert!(message.retain);
+    let payload: Value = serde_json::from_slice(message.payload_bytes()).unwrap();
+    assert_eq!(
+        payload,
+        json!({"status": "init", "clouds": ["c8y"], "force": false})
+    );
+}
+
+#[tokio::test]
+async fn a_new_renewal_is_triggered_when_the_pending_one_has_failed() {
+    let ttd = TempTedgeDir::new();
+    let cert_path = device_certificate(&ttd, time::Duration::days(1));
+    let (_workflow, mut mqtt) =
+        spawn_cert_renewal(&ttd, &cert_path, Some(Duration::from_millis(100)));
+
+    let message = mqtt.recv().await.expect("a cert_renew command");
+
+    // No new command is triggered while the first one is pending
+    assert!(
+        tokio::time::timeout(Duration::from_millis(300), mqtt.recv())
+            .await
+            .is_err()
+    );
+
+    // The command goes straight to failed, e.g. rejected by a custom workflow
+    let failed = MqttMessage::new(
+        &message.topic,
+        json!({"status": "failed", "reason": "rejected", "clouds": ["c8y"]}).to_string(),
+    )
+    .with_retain();
+    mqtt.send(failed).await.unwrap();
+
+    let message = mqtt.recv().await.expect("a new cert_renew command");
+    let payload: Value = serde_json::from_slice(message.payload_bytes()).unwrap();
+    assert_eq!(payload["status"], "init");
+}
+
+/// Create a device certificate valid for the given duration, along the c8y bridge config
+fn device_certificate(ttd: &TempTedgeDir, validity: time::Duration) -> camino::Utf8PathBuf {
+    ttd.dir("mosquitto-conf").file("c8y-bridge.conf");
+    let cert_path = ttd.utf8_path().join("tedge-certificate.pem");
+    std::fs::write(&cert_path, self_signed_certificate(validity)).unwrap();
+    cert_path
+}
+
+fn self_signed_certificate(validity: time::Duration) -> String {
+    let key = rcgen::KeyPair::generate().unwrap();
+    let mut params = rcgen::CertificateParams::new(vec!["test-device".to_string()]).unwrap();
+    params.not_before = OffsetDateTime::now_utc() - time::Duration::days(1);
+    params.not_after = OffsetDateTime::now_utc() + validity;
+    params.self_signed(&key).unwrap().pem()
+}
+
+fn scheduled_command(force: bool) -> CertRenewCommand {
+    CertRenewCommand {
+        target: EntityTopicId::default_main_device(),
+        cmd_id: "1234".to_string(),
+        payload: CertRenewCmdPayload {
+            status: CommandStatus::Scheduled,
+            force,
+            ..Default::default()
+        },
+    }
+}
+
+fn tedge_calls(ttd: &TempTedgeDir) -> Vec<String> {
+    std::fs::read_to_string(ttd.path().join("tedge.log"))
+        .unwrap_or_default()
+        .lines()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn spawn_cert_renewal(
+    ttd: &TempTedgeDir,
+    cert_path: &camino::Utf8Path,
+    check_interval: Option<Duration>,
+) -> (
+    TimedMessageBox<SimpleMessageBox<CertRenewCommand, CertRenewCommand>>,
+    TimedMessageBox<SimpleMessageBox<MqttMessage, MqttMessage>>,
+) {
+    // A fake `tedge` command logging its arguments and creating a new certificate on `tedge cert renew`
+    let tedge_bin = ttd.utf8_path().join("tedge");
+    let new_cert_path = ttd.utf8_path().join("new-certificate.pem");
+    std::fs::write(
+        &new_cert_path,
+        self_signed_certificate(time::Duration::days(365)),
+    )
+    .unwrap();
+    with_exec_permission(
+        &tedge_bin,
+        &format!(
+            r#"#!/bin/sh
+echo "$@" >> {log}
+if [ "$1" = cert ]; then
+    cp {new_cert_path} {cert_path}.new
+fi
+"#,
+            log = ttd.utf8_path().join("tedge.log"),
+        ),
+    );
+
+    let config = CertRenewalConfig {
+        mqtt_schema: MqttSchema::default(),
+        device_topic_id: EntityTopicId::default_main_device(),
+        config_dir: ttd.utf8_path_buf(),
+        clouds: vec![CloudCertificate {
+            cloud: "c8y".to_string(),
+            profile: None,
+            cert_path: cert_path.to_owned(),
+            use_est: false,
+        }],
+        minimum_validity: Duration::from_secs(30 * 24 * 3600),
+        check_interval,
+        tedge_bin,
+        sudo: SudoCommandBuilder::enabled(false),
+    };
+
+    let mut workflow_builder: SimpleMessageBoxBuilder<CertRenewCommand, CertRenewCommand> =
+        SimpleMessageBoxBuilder::new("Workflow", 5);
+    let mut mqtt_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage> =
+        SimpleMessageBoxBuilder::new("MQTT", 5);
+    let mut cert_renewal_builder = CertRenewalBuilder::new(config, &mut mqtt_builder);
+    workflow_builder.connect_sink(NoConfig, &cert_renewal_builder);
+    cert_renewal_builder.connect_sink(NoConfig, &workflow_builder);
+
+    let workflow = workflow_builder.build().with_timeout(TEST_TIMEOUT);
+    let mqtt = mqtt_builder.build().with_timeout(TEST_TIMEOUT);
+    let actor = cert_renewal_builder.build();
+    tokio::spawn(async move { actor.run().await });
+
+    (workflow, mqtt)
+}
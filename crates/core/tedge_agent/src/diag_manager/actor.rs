@@ -0,0 +1,230 @@
+use crate::diag_manager::config::DiagCollectConfig;
+use async_trait::async_trait;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use tedge_actors::Actor;
+use tedge_actors::ClientMessageBox;
+use tedge_actors::MessageReceiver;
+use tedge_actors::RuntimeError;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_api::commands::CommandStatus;
+use tedge_api::commands::DiagCollectCmdPayload;
+use tedge_api::commands::DiagCollectCommand;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_uploader_ext::UploadRequest;
+use tedge_uploader_ext::UploadResult;
+use tokio::process::Command;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+pub type DiagUploadRequest = (String, UploadRequest);
+pub type DiagUploadResult = (String, UploadResult);
+
+/// Exit status of `tedge diag collect` when no diagnostic plugins are found
+const NO_PLUGINS_EXIT_CODE: i32 = 2;
+
+pub struct DiagCollectActor {
+    config: DiagCollectConfig,
+    message_box: SimpleMessageBox<DiagCollectCommand, DiagCollectCommand>,
+    uploader: ClientMessageBox<DiagUploadRequest, DiagUploadResult>,
+}
+
+#[async_trait]
+impl Actor for DiagCollectActor {
+    fn name(&self) -> &str {
+        "DiagCollectActor"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        while let Some(request) = self.message_box.recv().await {
+            self.process_command(request).await?;
+        }
+        Ok(())
+    }
+}
+
+impl DiagCollectActor {
+    pub fn new(
+        config: DiagCollectConfig,
+        message_box: SimpleMessageBox<DiagCollectCommand, DiagCollectCommand>,
+        uploader: ClientMessageBox<DiagUploadRequest, DiagUploadResult>,
+    ) -> Self {
+        DiagCollectActor {
+            config,
+            message_box,
+            uploader,
+        }
+    }
+
+    /// Run the diagnostic plugins and upload the resulting tarball to the file-transfer service
+    async fn process_command(
+        &mut self,
+        mut command: DiagCollectCommand,
+    ) -> Result<(), RuntimeError> {
+        if command.status() != CommandStatus::Scheduled {
+            // Only handle commands in the scheduled state
+            return Ok(());
+        }
+
+        let tedge_url = match &command.payload.tedge_url {
+            Some(tedge_url) => Ok(tedge_url.clone()),
+            None => self.tedge_url(&command),
+        };
+        if let Ok(tedge_url) = &tedge_url {
+            command.payload.tedge_url = Some(tedge_url.clone());
+        }
+        command.executing();
+        self.message_box.send(command.clone()).await?;
+
+        // The output directory is owned by the agent,
+        // so the tarball can be removed even if created by `sudo tedge diag collect`
+        let output_dir = self
+            .config
+            .tmp_dir
+            .join(format!("tedge-diag-{}", command.cmd_id));
+        let result = match tedge_url {
+            Ok(tedge_url) => {
+                self.collect_and_upload(&command, &output_dir, &tedge_url)
+                    .await
+            }
+            Err(reason) => Err(reason),
+        };
+        if let Err(err) = tokio::fs::remove_dir_all(&output_dir).await {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to remove {output_dir}: {err}");
+            }
+        }
+
+        match result {
+            Ok(()) => {
+                info!("Diagnostic information collected for {}", command.cmd_id);
+                command.successful();
+            }
+            Err(reason) => {
+                error!(reason);
+                command.failed(reason);
+            }
+        }
+        self.message_box.send(command).await?;
+        Ok(())
+    }
+
+    /// The file-transfer service URL used when none is provided by the requester
+    fn tedge_url(&self, command: &DiagCollectCommand) -> Result<String, String> {
+        let device_name = command.target.default_device_name().ok_or_else(|| {
+            format!(
+                "A tedgeUrl has to be provided for {}: not using the default topic scheme",
+                command.target
+            )
+        })?;
+        Ok(format!(
+            "http://{}/te/v1/files/{device_name}/{}/{}.tar.gz",
+            self.config.tedge_http_host,
+            OperationType::DiagCollect,
+            command.cmd_id
+        ))
+    }
+
+    async fn collect_and_upload(
+        &mut self,
+        command: &DiagCollectCommand,
+        output_dir: &Utf8Path,
+        tedge_url: &str,
+    ) -> Result<(), String> {
+        tokio::fs::create_dir_all(output_dir)
+            .await
+            .map_err(|err| format!("Failed to create {output_dir}: {err}"))?;
+        let tarball = self
+            .run_diag_collect(&command.payload, output_dir, &command.cmd_id)
+            .await?;
+
+        info!("Uploading {tarball} to {tedge_url}");
+        let upload_request = UploadRequest::new(tedge_url, &tarball);
+        let (_, upload_result) = self
+            .uploader
+            .await_response((command.cmd_id.clone(), upload_request))
+            .await
+            .map_err(|err| format!("Failed to upload {tarball}: {err}"))?;
+        upload_result.map_err(|err| format!("Failed to upload {tarball} to {tedge_url}: {err}"))?;
+        Ok(())
+    }
+
+    /// Run `tedge diag collect`, returning the path to the tarball
+    ///
+    /// As the tarball is created even if some plugins fail,
+    /// the command is only considered failed if no tarball has been created.
+    async fn run_diag_collect(
+        &self,
+        payload: &DiagCollectCmdPayload,
+        output_dir: &Utf8Path,
+        cmd_id: &str,
+    ) -> Result<Utf8PathBuf, String> {
+        let args = diag_collect_args(payload, output_dir, cmd_id);
+        let command_line = format!("{} {}", self.config.tedge_bin, args.join(" "));
+        let mut command: Command = self.config.sudo.command(&self.config.tedge_bin).into();
+        command.args(&args).kill_on_drop(true);
+
+        let output = match tokio::time::timeout(self.config.collect_timeout, command.output()).await
+        {
+            Ok(Ok(output)) => output,
+            Ok(Err(err)) => return Err(format!("`{command_line}` failed to execute: {err}")),
+            Err(_) => return Err(format!("`{command_line}` timed out")),
+        };
+        if output.status.code() == Some(NO_PLUGINS_EXIT_CODE) {
+            return Err("No diagnostic plugins were found".to_string());
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        match stdout
+            .lines()
+            .last()
+            .map(|line| Utf8PathBuf::from(line.trim()))
+        {
+            Some(tarball) if tarball.is_file() => {
+                if !output.status.success() {
+                    warn!("Some diagnostic plugins failed, see summary.log in {tarball}");
+                }
+                Ok(tarball)
+            }
+            _ => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                Err(format!(
+                    "`{command_line}` failed with {}: {}",
+                    output.status,
+                    stderr.trim()
+                ))
+            }
+        }
+    }
+}
+
+/// The `tedge` arguments to run the diagnostic plugins requested by a command
+fn diag_collect_args(
+    payload: &DiagCollectCmdPayload,
+    output_dir: &Utf8Path,
+    cmd_id: &str,
+) -> Vec<String> {
+    let mut args = vec![
+        "diag".to_string(),
+        "collect".to_string(),
+        "--output-dir".to_string(),
+        output_dir.to_string(),
+        "--name".to_string(),
+        format!("tedge-diag-{cmd_id}"),
+    ];
+    if !payload.plugins.is_empty() {
+        args.extend(["--plugins".to_string(), payload.plugins.join(",")]);
+    }
+    if let Some(timeout) = payload.timeout {
+        args.extend(["--timeout".to_string(), format!("{timeout}s")]);
+    }
+    if let Some(forceful_timeout) = payload.forceful_timeout {
+        args.extend([
+            "--forceful-timeout".to_string(),
+            format!("{forceful_timeout}s"),
+        ]);
+    }
+    args
+}
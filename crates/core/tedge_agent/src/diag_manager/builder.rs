@@ -0,0 +1,92 @@
+use crate::diag_manager::actor::DiagCollectActor;
+use crate::diag_manager::actor::DiagUploadRequest;
+use crate::diag_manager::actor::DiagUploadResult;
+use crate::diag_manager::config::DiagCollectConfig;
+use tedge_actors::Builder;
+use tedge_actors::ClientMessageBox;
+use tedge_actors::DynSender;
+use tedge_actors::LinkError;
+use tedge_actors::MappingSender;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::Service;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::commands::DiagCollectCommand;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_api::workflow::GenericCommandData;
+use tedge_api::workflow::GenericCommandState;
+use tedge_api::workflow::OperationName;
+
+pub struct DiagCollectBuilder {
+    config: DiagCollectConfig,
+    message_box: SimpleMessageBoxBuilder<DiagCollectCommand, DiagCollectCommand>,
+    uploader: ClientMessageBox<DiagUploadRequest, DiagUploadResult>,
+}
+
+impl DiagCollectBuilder {
+    pub fn new(
+        config: DiagCollectConfig,
+        uploader_actor: &mut impl Service<DiagUploadRequest, DiagUploadResult>,
+    ) -> Self {
+        let message_box = SimpleMessageBoxBuilder::new("DiagCollect", 10);
+        let uploader = ClientMessageBox::new(uploader_actor);
+
+        Self {
+            config,
+            message_box,
+            uploader,
+        }
+    }
+}
+
+impl MessageSink<DiagCollectCommand> for DiagCollectBuilder {
+    fn get_sender(&self) -> DynSender<DiagCollectCommand> {
+        self.message_box.get_sender()
+    }
+}
+
+impl MessageSource<DiagCollectCommand, NoConfig> for DiagCollectBuilder {
+    fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<DiagCollectCommand>) {
+        self.message_box.connect_sink(config, peer)
+    }
+}
+
+impl MessageSource<GenericCommandData, NoConfig> for DiagCollectBuilder {
+    fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<GenericCommandData>) {
+        self.message_box.connect_sink(config, &peer.get_sender())
+    }
+}
+
+impl IntoIterator for &DiagCollectBuilder {
+    type Item = (OperationName, DynSender<GenericCommandState>);
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let sender =
+            MappingSender::new(self.message_box.get_sender(), |msg: GenericCommandState| {
+                msg.try_into().ok()
+            });
+        vec![(OperationType::DiagCollect.to_string(), sender.into())].into_iter()
+    }
+}
+
+impl RuntimeRequestSink for DiagCollectBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.message_box.get_signal_sender()
+    }
+}
+
+impl Builder<DiagCollectActor> for DiagCollectBuilder {
+    type Error = LinkError;
+
+    fn try_build(self) -> Result<DiagCollectActor, Self::Error> {
+        Ok(self.build())
+    }
+
+    fn build(self) -> DiagCollectActor {
+        DiagCollectActor::new(self.config, self.message_box.build(), self.uploader)
+    }
+}
@@ -0,0 +1,35 @@
+use camino::Utf8PathBuf;
+use std::time::Duration;
+use tedge_config::SudoCommandBuilder;
+use tedge_config::TEdgeConfig;
+
+#[derive(Debug, Clone)]
+pub struct DiagCollectConfig {
+    /// The directory where the diagnostic tarballs are created, before being uploaded
+    pub tmp_dir: Utf8PathBuf,
+
+    /// The host and port of the file-transfer service
+    pub tedge_http_host: String,
+
+    /// Maximum duration to run all the diagnostic plugins
+    pub collect_timeout: Duration,
+
+    /// The `tedge` command used to run the diagnostic plugins
+    pub tedge_bin: Utf8PathBuf,
+    pub sudo: SudoCommandBuilder,
+}
+
+impl DiagCollectConfig {
+    pub fn from_tedge_config(tedge_config: &TEdgeConfig) -> Self {
+        DiagCollectConfig {
+            tmp_dir: tedge_config.tmp.path.clone().into(),
+            tedge_http_host: format!(
+                "{}:{}",
+                tedge_config.http.client.host, tedge_config.http.client.port
+            ),
+            collect_timeout: Duration::from_secs(3600),
+            tedge_bin: "tedge".into(),
+            sudo: SudoCommandBuilder::new(tedge_config),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use crate::diag_manager::actor::DiagUploadRequest;
+use crate::diag_manager::actor::DiagUploadResult;
+use crate::diag_manager::builder::DiagCollectBuilder;
+use crate::diag_manager::config::DiagCollectConfig;
+use std::time::Duration;
+use tedge_actors::test_helpers::FakeServerBox;
+use tedge_actors::test_helpers::FakeServerBoxBuilder;
+use tedge_actors::test_helpers::MessageReceiverExt;
+use tedge_actors::test_helpers::TimedMessageBox;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::MessageReceiver;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::commands::CommandStatus;
+use tedge_api::commands::DiagCollectCmdPayload;
+use tedge_api::commands::DiagCollectCommand;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_config::SudoCommandBuilder;
+use tedge_test_utils::fs::with_exec_permission;
+use tedge_test_utils::fs::TempTedgeDir;
+use tedge_uploader_ext::UploadResponse;
+
+const TEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+type WorkflowBox = TimedMessageBox<SimpleMessageBox<DiagCollectCommand, DiagCollectCommand>>;
+type UploaderBox = TimedMessageBox<FakeServerBox<DiagUploadRequest, DiagUploadResult>>;
+
+#[tokio::test]
+async fn diagnostic_tarball_is_uploaded_to_the_file_transfer_service() {
+    let ttd = TempTedgeDir::new();
+    let (mut workflow, mut uploader) = spawn_diag_collect(&ttd, 0);
+
+    workflow.send(scheduled_command()).await.unwrap();
+
+    let executing = workflow.recv().await.unwrap();
+    assert_eq!(executing.status(), CommandStatus::Executing);
+    let tedge_url = "http://127.0.0.1:8000/te/v1/files/main/diag_collect/1234.tar.gz";
+    assert_eq!(executing.payload.tedge_url.as_deref(), Some(tedge_url));
+
+    let (cmd_id, upload_request) = uploader.recv().await.unwrap();
+    assert_eq!(upload_request.url, tedge_url);
+    assert_eq!(
+        upload_request.file_path,
+        ttd.utf8_path()
+            .join("tmp/tedge-diag-1234/tedge-diag-1234.tar.gz")
+    );
+    let upload_response = UploadResponse::new(&upload_request.url, upload_request.file_path);
+    uploader.send((cmd_id, Ok(upload_response))).await.unwrap();
+
+    assert_eq!(
+        workflow.recv().await.unwrap().status(),
+        CommandStatus::Successful
+    );
+    assert_eq!(
+        tedge_calls(&ttd),
+        vec![format!(
+            "diag collect --output-dir {} --name tedge-diag-1234",
+            ttd.utf8_path().join("tmp/tedge-diag-1234")
+        )]
+    );
+    assert!(!ttd.utf8_path().join("tmp/tedge-diag-1234").exists());
+}
+
+#[tokio::test]
+async fn plugin_selection_and_timeouts_are_given_to_tedge_diag() {
+    let ttd = TempTedgeDir::new();
+    let (mut workflow, mut uploader) = spawn_diag_collect(&ttd, 0);
+
+    let mut command = scheduled_command();
+    command.payload.tedge_url = Some("http://127.0.0.1:8000/te/v1/files/diag.tar.gz".to_string());
+    command.payload.plugins = vec!["01_tedge".to_string(), "08_mosquitto".to_string()];
+    command.payload.timeout = Some(10);
+    command.payload.forceful_timeout = Some(5);
+    workflow.send(command).await.unwrap();
+    workflow.skip(1).await;
+
+    let (cmd_id, upload_request) = uploader.recv().await.unwrap();
+    assert_eq!(
+        upload_request.url,
+        "http://127.0.0.1:8000/te/v1/files/diag.tar.gz"
+    );
+    let upload_response = UploadResponse::new(&upload_request.url, upload_request.file_path);
+    uploader.send((cmd_id, Ok(upload_response))).await.unwrap();
+
+    assert_eq!(
+        workflow.recv().await.unwrap().status(),
+        CommandStatus::Successful
+    );
+    assert_eq!(
+        tedge_calls(&ttd),
+        vec![format!(
+            "diag collect --output-dir {} --name tedge-diag-1234 --plugins 01_tedge,08_mosquitto --timeout 10s --forceful-timeout 5s",
+            ttd.utf8_path().join("tmp/tedge-diag-1234")
+        )]
+    );
+}
+
+#[tokio::test]
+async fn diagnostics_are_uploaded_even_if_some_plugins_fail() {
+    let ttd = TempTedgeDir::new();
+    let (mut workflow, mut uploader) = spawn_diag_collect(&ttd, 1);
+
+    workflow.send(scheduled_command()).await.unwrap();
+    workflow.skip(1).await;
+
+    let (cmd_id, upload_request) = uploader.recv().await.unwrap();
+    let upload_response = UploadResponse::new(&upload_request.url, upload_request.file_path);
+    uploader.send((cmd_id, Ok(upload_response))).await.unwrap();
+
+    assert_eq!(
+        workflow.recv().await.unwrap().status(),
+        CommandStatus::Successful
+    );
+}
+
+#[tokio::test]
+async fn collection_fails_when_no_plugins_are_found() {
+    let ttd = TempTedgeDir::new();
+    let (mut workflow, _uploader) = spawn_diag_collect(&ttd, 2);
+
+    workflow.send(scheduled_command()).await.unwrap();
+    workflow.skip(1).await;
+
+    assert_eq!(
+        workflow.recv().await.unwrap().status(),
+        CommandStatus::Failed {
+            reason: "No diagnostic plugins were found".to_string()
+        }
+    );
+}
+
+fn scheduled_command() -> DiagCollectCommand {
+    DiagCollectCommand {
+        target: EntityTopicId::default_main_device(),
+        cmd_id: "1234".to_string(),
+        payload: DiagCollectCmdPayload {
+            status: CommandStatus::Scheduled,
+            ..Default::default()
+        },
+    }
+}
+
+fn tedge_calls(ttd: &TempTedgeDir) -> Vec<String> {
+    std::fs::read_to_string(ttd.path().join("tedge.log"))
+        .unwrap_or_default()
+        .lines()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn spawn_diag_collect(ttd: &TempTedgeDir, exit_code: i32) -> (WorkflowBox, UploaderBox) {
+    // A fake `tedge` command logging its arguments and creating a tarball, unless no plugins are found
+    let tedge_bin = ttd.utf8_path().join("tedge");
+    with_exec_permission(
+        &tedge_bin,
+        &format!(
+            r#"#!/bin/sh
+echo "$@" >> {log}
+if [ {exit_code} -eq 2 ]; then
+    echo "No diagnostic plugins were found" >&2
+    exit 2
+fi
+while [ $# -gt 0 ]; do
+    case "$1" in
+        --output-dir) dir="$2"; shift;;
+        --name) name="$2"; shift;;
+    esac
+    shift
+done
+touch "$dir/$name.tar.gz"
+echo "$dir/$name.tar.gz"
+exit {exit_code}
+"#,
+            log = ttd.utf8_path().join("tedge.log"),
+        ),
+    );
+
+    let config = DiagCollectConfig {
+        tmp_dir: ttd.utf8_path().join("tmp"),
+        tedge_http_host: "127.0.0.1:8000".to_string(),
+        collect_timeout: TEST_TIMEOUT,
+        tedge_bin,
+        sudo: SudoCommandBuilder::enabled(false),
+    };
+
+    let mut workflow_builder: SimpleMessageBoxBuilder<DiagCollectCommand, DiagCollectCommand> =
+        SimpleMessageBoxBuilder::new("Workflow", 5);
+    let mut uploader_builder: FakeServerBoxBuilder<DiagUploadRequest, DiagUploadResult> =
+        FakeServerBoxBuilder::default();
+    let mut diag_builder = DiagCollectBuilder::new(config, &mut uploader_builder);
+    workflow_builder.connect_sink(NoConfig, &diag_builder);
+    diag_builder.connect_sink(NoConfig, &workflow_builder);
+
+    let workflow = workflow_builder.build().with_timeout(TEST_TIMEOUT);
+    let uploader = uploader_builder.build().with_timeout(TEST_TIMEOUT);
+    let actor = diag_builder.build();
+    tokio::spawn(async move { actor.run().await });
+
+    (workflow, uploader)
+}
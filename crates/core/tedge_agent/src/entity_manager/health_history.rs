@@ -0,0 +1,463 @@
+//! Record the health status changes of the services registered with the entity store.
+//!
+//! The `te/+/+/+/+/status/health` messages only carry the latest status of a service.
+//! By recording the status changes, the agent can tell for how long a service has been up,
+//! how often it went down and whether it is flapping, i.e. repeatedly going up and down.
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tedge_api::health::Status;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_config::TEdgeConfig;
+use tedge_utils::fs::atomically_write_file_async;
+use time::OffsetDateTime;
+use tracing::error;
+use tracing::warn;
+
+/// The file used to persist the health history, in the agent state directory
+pub const HEALTH_HISTORY_FILE: &str = "health-history.json";
+
+#[derive(Clone, Debug)]
+pub struct HealthHistoryConfig {
+    /// The file where the history is persisted, if any
+    pub path: Option<Utf8PathBuf>,
+
+    /// The maximum number of status changes kept for each service
+    pub max_entries: usize,
+
+    /// The number of times a service can go down within the flapping window
+    /// before being flagged as flapping (0 to never flag a service as flapping)
+    pub flapping_threshold: usize,
+
+    /// The time window over which the down transitions of a service are counted
+    pub flapping_window: Duration,
+}
+
+impl Default for HealthHistoryConfig {
+    fn default() -> Self {
+        HealthHistoryConfig {
+            path: None,
+            max_entries: 100,
+            flapping_threshold: 5,
+            flapping_window: Duration::from_secs(600),
+        }
+    }
+}
+
+impl HealthHistoryConfig {
+    pub fn from_tedge_config(tedge_config: &TEdgeConfig) -> Self {
+        let config = &tedge_config.agent.health_history;
+        HealthHistoryConfig {
+            path: None,
+            max_entries: config.max_entries as usize,
+            flapping_threshold: config.flapping.threshold as usize,
+            flapping_window: config.flapping.window.duration(),
+        }
+    }
+
+    pub fn with_path(self, path: impl Into<Utf8PathBuf>) -> Self {
+        HealthHistoryConfig {
+            path: Some(path.into()),
+            ..self
+        }
+    }
+}
+
+/// A change of the health status of a service
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthChange {
+    pub status: Status,
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+}
+
+/// The health statistics of a service, as exposed over HTTP
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct HealthStats {
+    /// The current status of the service
+    pub status: Status,
+
+    /// The number of seconds since the service is up, 0 if the service is not up
+    pub uptime: u64,
+
+    /// The number of times the service went down since its status is recorded
+    pub down_count: u64,
+
+    /// When the status of the service last changed
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_change: OffsetDateTime,
+
+    /// Whether the service is flagged as flapping
+    pub flapping: bool,
+
+    /// The latest status changes, the most recent last
+    pub history: Vec<HealthChange>,
+}
+
+/// A change of the flapping state of a service
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Flapping {
+    /// The service went down `down_count` times within the flapping window
+    Started { down_count: usize },
+
+    /// The service is no longer going down repeatedly
+    Stopped,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct ServiceHistory {
+    down_count: u64,
+    #[serde(default)]
+    flapping: bool,
+    changes: VecDeque<HealthChange>,
+}
+
+pub struct HealthHistory {
+    config: HealthHistoryConfig,
+    services: BTreeMap<EntityTopicId, ServiceHistory>,
+    /// Whether the history has changed since last persisted
+    dirty: bool,
+}
+
+impl HealthHistory {
+    /// Load the history persisted by a previous run, if any
+    pub fn load(config: HealthHistoryConfig) -> Self {
+        let services = config
+            .path
+            .as_ref()
+            .and_then(|path| match std::fs::read(path) {
+                Ok(content) => serde_json::from_slice(&content)
+                    .map_err(|err| warn!("Ignoring invalid health history {path}: {err}"))
+                    .ok(),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                Err(err) => {
+                    warn!("Cannot read the health history {path}: {err}");
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        HealthHistory {
+            config,
+            services,
+            dirty: false,
+        }
+    }
+
+    /// Record the latest health status of a service
+    ///
+    /// Return the change of the flapping state of the service, if any.
+    pub fn record(
+        &mut self,
+        topic_id: &EntityTopicId,
+        status: Status,
+        now: OffsetDateTime,
+    ) -> Option<Flapping> {
+        let service = self.services.entry(topic_id.clone()).or_default();
+        let previous = service.changes.back().map(|change| &change.status);
+        if previous == Some(&status) {
+            return None;
+        }
+
+        if previous.is_some() && status == Status::Down {
+            service.down_count += 1;
+        }
+        service
+            .changes
+            .push_back(HealthChange { status, time: now });
+        while service.changes.len() > self.config.max_entries.max(1) {
+            service.changes.pop_front();
+        }
+
+        self.dirty = true;
+        Self::update_flapping(&self.config, service, now)
+    }
+
+    /// The time of a health status change, given the time of the health message, if any
+    ///
+    /// The message time is ignored when older than the last recorded change of the service,
+    /// as for a message published with a skewed clock, so the changes are kept in chronological order.
+    pub fn change_time(
+        &self,
+        topic_id: &EntityTopicId,
+        message_time: Option<OffsetDateTime>,
+        received: OffsetDateTime,
+    ) -> OffsetDateTime {
+        let last_change = self
+            .services
+            .get(topic_id)
+            .and_then(|service| service.changes.back())
+            .map(|change| change.time);
+        match (message_time, last_change) {
+            (Some(time), Some(last_change)) if time >= last_change => time,
+            (Some(time), None) => time,
+            (_, Some(last_change)) => received.max(last_change),
+            (None, None) => received,
+        }
+    }
+
+    /// Check if the services flagged as flapping are now stable
+    pub fn check_flapping(&mut self, now: OffsetDateTime) -> Vec<(EntityTopicId, Flapping)> {
+        let mut updates = Vec::new();
+        for (topic_id, service) in self.services.iter_mut() {
+            if service.flapping {
+                if let Some(update) = Self::update_flapping(&self.config, service, now) {
+                    updates.push((topic_id.clone(), update));
+                }
+            }
+        }
+
+        if !updates.is_empty() {
+            self.dirty = true;
+        }
+        updates
+    }
+
+    /// The health statistics of a service, if any status has been recorded
+    pub fn stats(&self, topic_id: &EntityTopicId, now: OffsetDateTime) -> Option<HealthStats> {
+        let service = self.services.get(topic_id)?;
+        let last = service.changes.back()?;
+        let uptime = if last.status == Status::Up {
+            (now - last.time).whole_seconds().max(0) as u64
+        } else {
+            0
+        };
+
+        Some(HealthStats {
+            status: last.status.clone(),
+            uptime,
+            down_count: service.down_count,
+            last_change: last.time,
+            flapping: service.flapping,
+            history: service.changes.iter().cloned().collect(),
+        })
+    }
+
+    /// The services which health status is recorded
+    pub fn services(&self) -> impl Iterator<Item = &EntityTopicId> {
+        self.services.keys()
+    }
+
+    /// Forget a service, e.g. when deregistered
+    pub fn remove(&mut self, topic_id: &EntityTopicId) {
+        if self.services.remove(topic_id).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    fn update_flapping(
+        config: &HealthHistoryConfig,
+        service: &mut ServiceHistory,
+        now: OffsetDateTime,
+    ) -> Option<Flapping> {
+        if config.flapping_threshold == 0 {
+            return None;
+        }
+
+        let window_start = now - config.flapping_window;
+        let down_count = service
+            .changes
+            .iter()
+            .filter(|change| change.status == Status::Down && change.time >= window_start)
+            .count();
+        let is_flapping = down_count >= config.flapping_threshold;
+
+        match (service.flapping, is_flapping) {
+            (false, true) => {
+                service.flapping = true;
+                Some(Flapping::Started { down_count })
+            }
+            (true, false) => {
+                service.flapping = false;
+                Some(Flapping::Stopped)
+            }
+            _ => None,
+        }
+    }
+
+    /// Persist the history, if changed since last persisted
+    ///
+    /// The history is not persisted on each status change, but periodically,
+    /// so the changes recorded since the latest call are lost if the agent is killed.
+    pub async fn persist(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        let Some(path) = &self.config.path else {
+            self.dirty = false;
+            return;
+        };
+        let content = match serde_json::to_vec(&self.services) {
+            Ok(content) => content,
+            Err(err) => {
+                error!("Failed to serialize the health history: {err}");
+                return;
+            }
+        };
+        match atomically_write_file_async(path, &content).await {
+            Ok(()) => self.dirty = false,
+            Err(err) => error!("Failed to persist the health history to {path}: {err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[test]
+    fn only_status_changes_are_recorded() {
+        let mut history = HealthHistory::load(HealthHistoryConfig::default());
+        let service = service("foo");
+
+        history.record(&service, Status::Up, at(0));
+        history.record(&service, Status::Up, at(10));
+        history.record(&service, Status::Down, at(20));
+        history.record(&service, Status::Down, at(30));
+        history.record(&service, Status::Up, at(40));
+
+        let stats = history.stats(&service, at(100)).unwrap();
+        assert_eq!(stats.status, Status::Up);
+        assert_eq!(stats.uptime, 60);
+        assert_eq!(stats.down_count, 1);
+        assert_eq!(stats.last_change, at(40));
+        assert_eq!(
+            stats.history,
+            vec![
+                change(Status::Up, 0),
+                change(Status::Down, 20),
+                change(Status::Up, 40),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_service_that_is_down_has_no_uptime() {
+        let mut history = HealthHistory::load(HealthHistoryConfig::default());
+        let service = service("foo");
+
+        history.record(&service, Status::Down, at(0));
+
+        let stats = history.stats(&service, at(100)).unwrap();
+        assert_eq!(stats.status, Status::Down);
+        assert_eq!(stats.uptime, 0);
+        assert_eq!(stats.down_count, 0);
+        assert!(history.stats(&self::service("bar"), at(100)).is_none());
+    }
+
+    #[test]
+    fn the_history_is_bounded() {
+        let config = HealthHistoryConfig {
+            max_entries: 3,
+            flapping_threshold: 0,
+            ..HealthHistoryConfig::default()
+        };
+        let mut history = HealthHistory::load(config);
+        let service = service("foo");
+
+        for i in 0..10 {
+            let status = if i % 2 == 0 { Status::Up } else { Status::Down };
+            history.record(&service, status, at(i));
+        }
+
+        let stats = history.stats(&service, at(10)).unwrap();
+        assert_eq!(stats.down_count, 5);
+        assert_eq!(
+            stats.history,
+            vec![
+                change(Status::Down, 7),
+                change(Status::Up, 8),
+                change(Status::Down, 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_service_going_down_repeatedly_is_flagged_as_flapping() {
+        let config = HealthHistoryConfig {
+            flapping_threshold: 2,
+            flapping_window: Duration::from_secs(60),
+            ..HealthHistoryConfig::default()
+        };
+        let mut history = HealthHistory::load(config);
+        let service = service("foo");
+
+        assert_eq!(history.record(&service, Status::Up, at(0)), None);
+        assert_eq!(history.record(&service, Status::Down, at(10)), None);
+        assert_eq!(history.record(&service, Status::Up, at(20)), None);
+        assert_eq!(
+            history.record(&service, Status::Down, at(30)),
+            Some(Flapping::Started { down_count: 2 })
+        );
+        assert_eq!(history.record(&service, Status::Up, at(40)), None);
+        assert!(history.stats(&service, at(40)).unwrap().flapping);
+
+        // Still flapping as long as the down transitions are within the window
+        assert_eq!(history.check_flapping(at(69)), vec![]);
+        assert_eq!(
+            history.check_flapping(at(71)),
+            vec![(service.clone(), Flapping::Stopped)]
+        );
+        assert!(!history.stats(&service, at(71)).unwrap().flapping);
+    }
+
+    #[test]
+    fn changes_are_kept_in_chronological_order() {
+        let mut history = HealthHistory::load(HealthHistoryConfig::default());
+        let service = service("foo");
+
+        assert_eq!(history.change_time(&service, Some(at(10)), at(20)), at(10));
+        assert_eq!(history.change_time(&service, None, at(20)), at(20));
+
+        history.record(&service, Status::Up, at(10));
+        assert_eq!(history.change_time(&service, Some(at(15)), at(20)), at(15));
+        assert_eq!(history.change_time(&service, Some(at(5)), at(20)), at(20));
+        assert_eq!(history.change_time(&service, None, at(20)), at(20));
+
+        // Even when the receive time is older, as the last change was time-stamped ahead
+        assert_eq!(history.change_time(&service, Some(at(5)), at(8)), at(10));
+    }
+
+    #[tokio::test]
+    async fn the_history_is_persisted() {
+        let ttd = TempTedgeDir::new();
+        let config =
+            HealthHistoryConfig::default().with_path(ttd.utf8_path().join(HEALTH_HISTORY_FILE));
+        let service = service("foo");
+
+        let mut history = HealthHistory::load(config.clone());
+        history.record(&service, Status::Up, at(0));
+        history.record(&service, Status::Down, at(10));
+        assert!(HealthHistory::load(config.clone())
+            .stats(&service, at(20))
+            .is_none());
+
+        history.persist().await;
+        let history = HealthHistory::load(config);
+        let stats = history.stats(&service, at(20)).unwrap();
+        assert_eq!(stats.down_count, 1);
+        assert_eq!(
+            stats.history,
+            vec![change(Status::Up, 0), change(Status::Down, 10)]
+        );
+    }
+
+    fn service(name: &str) -> EntityTopicId {
+        EntityTopicId::default_main_service(name).unwrap()
+    }
+
+    fn at(seconds: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(1_700_000_000 + seconds).unwrap()
+    }
+
+    fn change(status: Status, seconds: i64) -> HealthChange {
+        HealthChange {
+            status,
+            time: at(seconds),
+        }
+    }
+}
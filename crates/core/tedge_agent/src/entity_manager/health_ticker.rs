@@ -0,0 +1,118 @@
+use crate::entity_manager::server::EntityStoreRequest;
+use crate::entity_manager::server::EntityStoreResponse;
+use async_trait::async_trait;
+use std::convert::Infallible;
+use std::time::Duration;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::ClientMessageBox;
+use tedge_actors::DynSender;
+use tedge_actors::MessageReceiver;
+use tedge_actors::NoMessage;
+use tedge_actors::RuntimeError;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::Service;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tokio::time::interval_at;
+use tokio::time::Instant;
+use tokio::time::MissedTickBehavior;
+
+/// Interval at which the health history is persisted, if changed
+const PERSIST_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Periodically request the entity store to publish the health statistics of the services
+/// and to persist their health history
+pub struct HealthTicker {
+    /// The interval at which the statistics are published, if any
+    interval: Option<Duration>,
+    signals: SimpleMessageBox<NoMessage, NoMessage>,
+    entity_store: ClientMessageBox<EntityStoreRequest, EntityStoreResponse>,
+}
+
+#[async_trait]
+impl Actor for HealthTicker {
+    fn name(&self) -> &str {
+        "HealthTicker"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        // The first statistics are published after a full interval,
+        // giving time to the entity store to process the retained health messages
+        let mut stats_ticker = self.interval.map(|interval| {
+            let mut ticker = interval_at(Instant::now() + interval, interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ticker
+        });
+        let mut persist_ticker = interval_at(Instant::now() + PERSIST_INTERVAL, PERSIST_INTERVAL);
+        persist_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            let stats_tick = async {
+                match stats_ticker.as_mut() {
+                    Some(ticker) => ticker.tick().await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = stats_tick => {
+                    self.entity_store
+                        .await_response(EntityStoreRequest::PublishHealthStats)
+                        .await?;
+                }
+                _ = persist_ticker.tick() => {
+                    self.entity_store
+                        .await_response(EntityStoreRequest::PersistHealthHistory)
+                        .await?;
+                }
+                _ = self.signals.recv() => {
+                    // Best effort: the entity store might be already stopped
+                    let _ = self
+                        .entity_store
+                        .await_response(EntityStoreRequest::PersistHealthHistory)
+                        .await;
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+pub struct HealthTickerBuilder {
+    interval: Option<Duration>,
+    box_builder: SimpleMessageBoxBuilder<NoMessage, NoMessage>,
+    entity_store: ClientMessageBox<EntityStoreRequest, EntityStoreResponse>,
+}
+
+impl HealthTickerBuilder {
+    /// Create a ticker publishing the health statistics at the given interval, `None` to never publish them
+    pub fn new(
+        interval: Option<Duration>,
+        entity_store: &mut impl Service<EntityStoreRequest, EntityStoreResponse>,
+    ) -> Self {
+        HealthTickerBuilder {
+            interval,
+            box_builder: SimpleMessageBoxBuilder::new("HealthTicker", 1),
+            entity_store: ClientMessageBox::new(entity_store),
+        }
+    }
+}
+
+impl RuntimeRequestSink for HealthTickerBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.box_builder.get_signal_sender()
+    }
+}
+
+impl Builder<HealthTicker> for HealthTickerBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<HealthTicker, Self::Error> {
+        Ok(HealthTicker {
+            interval: self.interval,
+            signals: self.box_builder.build(),
+            entity_store: self.entity_store,
+        })
+    }
+}
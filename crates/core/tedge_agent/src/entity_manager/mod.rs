@@ -1,3 +1,5 @@
+pub(crate) mod health_history;
+pub(crate) mod health_ticker;
 pub(crate) mod server;
 
 #[cfg(test)]
@@ -1,6 +1,11 @@
+use super::health_history::Flapping;
+use super::health_history::HealthHistory;
+use super::health_history::HealthHistoryConfig;
+use super::health_history::HealthStats;
 use async_trait::async_trait;
 use futures::channel::mpsc;
 use futures::StreamExt as _;
+use serde_json::json;
 use serde_json::Map;
 use serde_json::Value;
 use tedge_actors::LoggingSender;
@@ -9,11 +14,13 @@ use tedge_actors::MessageSink;
 use tedge_actors::Sender;
 use tedge_actors::Server;
 use tedge_api::entity::EntityMetadata;
+use tedge_api::entity::EntityType;
 use tedge_api::entity_store;
 use tedge_api::entity_store::EntityRegistrationMessage;
 use tedge_api::entity_store::EntityTwinMessage;
 use tedge_api::entity_store::EntityUpdateMessage;
 use tedge_api::entity_store::ListFilters;
+use tedge_api::health::HealthStatus;
 use tedge_api::mqtt_topics::Channel;
 use tedge_api::mqtt_topics::ChannelFilter;
 use tedge_api::mqtt_topics::EntityFilter;
@@ -24,7 +31,14 @@ use tedge_api::EntityStore;
 use tedge_mqtt_ext::MqttMessage;
 use tedge_mqtt_ext::MqttRequest;
 use tedge_mqtt_ext::TopicFilter;
+use tedge_utils::timestamp::IsoOrUnix;
+use tedge_utils::timestamp::TimeFormat;
+use time::OffsetDateTime;
 use tracing::error;
+use tracing::warn;
+
+/// The alarm raised on a service which health status is flapping
+const FLAPPING_ALARM_TYPE: &str = "health_flapping";
 
 #[derive(Debug)]
 pub enum EntityStoreRequest {
@@ -38,6 +52,9 @@ pub enum EntityStoreRequest {
     SetTwinFragment(EntityTwinMessage),
     GetTwinFragments(EntityTopicId),
     SetTwinFragments(EntityTopicId, Map<String, Value>),
+    GetHealth(EntityTopicId),
+    PublishHealthStats,
+    PersistHealthHistory,
 }
 
 #[derive(Debug)]
@@ -52,11 +69,13 @@ pub enum EntityStoreResponse {
     SetTwinFragment(Result<bool, entity_store::Error>),
     GetTwinFragments(Result<Map<String, Value>, entity_store::Error>),
     SetTwinFragments(Result<(), entity_store::Error>),
+    GetHealth(Option<HealthStats>),
 }
 
 pub struct EntityStoreServer {
     config: EntityStoreServerConfig,
     entity_store: EntityStore,
+    health_history: HealthHistory,
     mqtt_publisher: LoggingSender<MqttMessage>,
     retain_requests: LoggingSender<(mpsc::UnboundedSender<MqttMessage>, TopicFilter)>,
 }
@@ -64,6 +83,8 @@ pub struct EntityStoreServer {
 pub struct EntityStoreServerConfig {
     pub mqtt_schema: MqttSchema,
     pub entity_auto_register: bool,
+    pub health_history: HealthHistoryConfig,
+    pub time_format: TimeFormat,
 }
 
 impl EntityStoreServerConfig {
@@ -71,6 +92,22 @@ impl EntityStoreServerConfig {
         Self {
             mqtt_schema,
             entity_auto_register,
+            health_history: HealthHistoryConfig::default(),
+            time_format: TimeFormat::Unix,
+        }
+    }
+
+    pub fn with_health_history(self, health_history: HealthHistoryConfig) -> Self {
+        Self {
+            health_history,
+            ..self
+        }
+    }
+
+    pub fn with_time_format(self, time_format: TimeFormat) -> Self {
+        Self {
+            time_format,
+            ..self
         }
     }
 }
@@ -94,9 +131,12 @@ impl EntityStoreServer {
             )),
         );
 
+        let health_history = HealthHistory::load(config.health_history.clone());
+
         Self {
             config,
             entity_store,
+            health_history,
             mqtt_publisher,
             retain_requests,
         }
@@ -162,6 +202,20 @@ impl Server for EntityStoreServer {
                 let res = self.set_entity_twin_fragments(&topic_id, fragments).await;
                 EntityStoreResponse::SetTwinFragments(res)
             }
+            EntityStoreRequest::GetHealth(topic_id) => {
+                let stats = self
+                    .health_history
+                    .stats(&topic_id, OffsetDateTime::now_utc());
+                EntityStoreResponse::GetHealth(stats)
+            }
+            EntityStoreRequest::PublishHealthStats => {
+                self.publish_health_stats().await;
+                EntityStoreResponse::Ok
+            }
+            EntityStoreRequest::PersistHealthHistory => {
+                self.health_history.persist().await;
+                EntityStoreResponse::Ok
+            }
             EntityStoreRequest::MqttMessage(mqtt_message) => {
                 self.process_mqtt_message(mqtt_message).await;
                 EntityStoreResponse::Ok
@@ -242,6 +296,10 @@ impl EntityStoreServer {
             }
         }
 
+        if let Channel::Health = channel {
+            self.record_health_status(&topic_id, &message).await;
+        }
+
         if let Channel::EntityTwinData { fragment_key } = channel {
             let fragment_value = if message.payload().is_empty() {
                 Value::Null
@@ -255,6 +313,108 @@ impl EntityStoreServer {
         Ok(())
     }
 
+    async fn record_health_status(&mut self, topic_id: &EntityTopicId, message: &MqttMessage) {
+        let is_service = self
+            .entity_store
+            .get(topic_id)
+            .is_some_and(|entity| entity.r#type == EntityType::Service);
+        if !is_service || message.payload_bytes().is_empty() {
+            return;
+        }
+
+        let Ok(health) =
+            HealthStatus::try_from_health_status_message(message, &self.config.mqtt_schema)
+        else {
+            return;
+        };
+        if !health.is_valid() {
+            return;
+        }
+
+        // The status changed when the message was published, not when received
+        let message_time = serde_json::from_slice::<Value>(message.payload_bytes())
+            .ok()
+            .and_then(|payload| IsoOrUnix::try_from(payload.get("time")?).ok())
+            .map(IsoOrUnix::into_inner);
+        let time =
+            self.health_history
+                .change_time(topic_id, message_time, OffsetDateTime::now_utc());
+        if let Some(flapping) = self.health_history.record(topic_id, health.status, time) {
+            self.publish_flapping_alarm(topic_id, flapping, time).await;
+        }
+    }
+
+    /// Publish the uptime statistics of the services as `health` measurements
+    async fn publish_health_stats(&mut self) {
+        let now = OffsetDateTime::now_utc();
+        for (topic_id, flapping) in self.health_history.check_flapping(now) {
+            self.publish_flapping_alarm(&topic_id, flapping, now).await;
+        }
+
+        let stats: Vec<_> = self
+            .health_history
+            .services()
+            .filter_map(|topic_id| {
+                let stats = self.health_history.stats(topic_id, now)?;
+                Some((topic_id.clone(), stats))
+            })
+            .collect();
+        for (topic_id, stats) in stats {
+            let topic = self.config.mqtt_schema.topic_for(
+                &topic_id,
+                &Channel::Measurement {
+                    measurement_type: "health".to_string(),
+                },
+            );
+            let since_last_change = (now - stats.last_change).whole_seconds().max(0);
+            let payload = json!({
+                "health": {
+                    "uptime": stats.uptime,
+                    "down_count": stats.down_count,
+                    "since_last_change": since_last_change,
+                }
+            });
+            self.publish_message(MqttMessage::new(&topic, payload.to_string()))
+                .await;
+        }
+    }
+
+    async fn publish_flapping_alarm(
+        &mut self,
+        topic_id: &EntityTopicId,
+        flapping: Flapping,
+        now: OffsetDateTime,
+    ) {
+        let topic = self.config.mqtt_schema.topic_for(
+            topic_id,
+            &Channel::Alarm {
+                alarm_type: FLAPPING_ALARM_TYPE.to_string(),
+            },
+        );
+        let payload = match flapping {
+            Flapping::Started { down_count } => {
+                let name = topic_id.default_service_name().unwrap_or(topic_id.as_str());
+                let window = humantime::format_duration(self.config.health_history.flapping_window);
+                let text = format!("{name} went down {down_count} times within {window}");
+                warn!("{text}");
+                let time_format = self.config.time_format;
+                let time = time_format.to_json(now).unwrap_or_else(|err| {
+                    error!("Failed to convert timestamp to {time_format} format due to: {err}");
+                    now.unix_timestamp().into()
+                });
+                json!({
+                    "text": text,
+                    "severity": "major",
+                    "time": time,
+                })
+                .to_string()
+            }
+            Flapping::Stopped => "".to_string(),
+        };
+        self.publish_message(MqttMessage::new(&topic, payload).with_retain())
+            .await;
+    }
+
     async fn set_twin_fragment(
         &mut self,
         twin_message: EntityTwinMessage,
@@ -344,6 +504,9 @@ impl EntityStoreServer {
         if deleted.is_empty() {
             return deleted;
         }
+        for entity in deleted.iter() {
+            self.health_history.remove(&entity.topic_id);
+        }
 
         let mut topics = TopicFilter::empty();
         for entity in deleted.iter() {
@@ -1,3 +1,4 @@
+use crate::entity_manager::server::EntityStoreRequest;
 use crate::entity_manager::server::EntityStoreResponse;
 use crate::entity_manager::tests::model::Action;
 use crate::entity_manager::tests::model::Action::AddDevice;
@@ -14,6 +15,7 @@ use tedge_actors::Sender;
 use tedge_actors::Server;
 use tedge_api::entity::EntityMetadata;
 use tedge_api::entity::EntityType;
+use tedge_api::health::Status;
 use tedge_api::mqtt_topics::EntityTopicId;
 use tedge_mqtt_ext::test_helpers::assert_received_contains_str;
 use tedge_mqtt_ext::MqttMessage;
@@ -222,6 +224,119 @@ async fn clear_entity_twin_data() {
     assert_eq!(entity.twin_data.get("x"), None);
 }
 
+#[tokio::test]
+async fn health_status_changes_are_recorded() {
+    let handle = entity::server("device-under-test");
+    let (mut entity_store, mut mqtt_output) = (handle.entity_store, handle.mqtt_output);
+
+    for status in [
+        "up", "up", "down", "up", "down", "up", "down", "up", "down", "up", "down",
+    ] {
+        let payload = json!({ "status": status }).to_string();
+        entity_store
+            .process_mqtt_message(
+                MqttMessage::from(("te/device/main/service/foo/status/health", payload))
+                    .with_retain(),
+            )
+            .await;
+    }
+
+    let stats = entity::get_health(&mut entity_store, "device/main/service/foo")
+        .await
+        .unwrap();
+    assert_eq!(stats.status, Status::Down);
+    assert_eq!(stats.uptime, 0);
+    assert_eq!(stats.down_count, 5);
+    assert_eq!(stats.history.len(), 10);
+    assert!(stats.flapping);
+
+    mqtt_output.skip(1).await; // Skip the auto-registration message
+    assert_received_contains_str(
+        &mut mqtt_output,
+        [(
+            "te/device/main/service/foo/a/health_flapping",
+            "foo went down 5 times within 10m",
+        )],
+    )
+    .await;
+
+    entity_store
+        .handle(EntityStoreRequest::PublishHealthStats)
+        .await;
+    assert_received_contains_str(
+        &mut mqtt_output,
+        [("te/device/main/service/foo/m/health", r#""down_count":5"#)],
+    )
+    .await;
+
+    entity::delete_entity(&mut entity_store, "device/main/service/foo")
+        .await
+        .unwrap();
+    assert_eq!(
+        entity::get_health(&mut entity_store, "device/main/service/foo").await,
+        None
+    );
+}
+
+#[tokio::test]
+async fn health_status_changes_are_recorded_at_the_message_time() {
+    let handle = entity::server("device-under-test");
+    let mut entity_store = handle.entity_store;
+
+    entity_store
+        .process_mqtt_message(
+            MqttMessage::from((
+                "te/device/main/service/foo/status/health",
+                r#"{"status":"up","time":1700000000}"#,
+            ))
+            .with_retain(),
+        )
+        .await;
+
+    let stats = entity::get_health(&mut entity_store, "device/main/service/foo")
+        .await
+        .unwrap();
+    assert_eq!(
+        stats.last_change,
+        time::OffsetDateTime::from_unix_timestamp(1700000000).unwrap()
+    );
+
+    // A message time older than the last change is ignored
+    entity_store
+        .process_mqtt_message(
+            MqttMessage::from((
+                "te/device/main/service/foo/status/health",
+                r#"{"status":"down","time":1600000000}"#,
+            ))
+            .with_retain(),
+        )
+        .await;
+
+    let stats = entity::get_health(&mut entity_store, "device/main/service/foo")
+        .await
+        .unwrap();
+    assert_eq!(stats.status, Status::Down);
+    assert!(stats.history[0].time < stats.history[1].time);
+}
+
+#[tokio::test]
+async fn health_status_of_devices_is_not_recorded() {
+    let handle = entity::server("device-under-test");
+    let mut entity_store = handle.entity_store;
+
+    entity_store
+        .process_mqtt_message(
+            MqttMessage::from(("te/device/main///status/health", r#"{"status":"up"}"#))
+                .with_retain(),
+        )
+        .await;
+
+    assert_eq!(
+        entity::get_health(&mut entity_store, "device/main//").await,
+        None
+    );
+}
+
 proptest! {
     //#![proptest_config(proptest::prelude::ProptestConfig::with_cases(1000))]
     #[test]
@@ -329,6 +444,7 @@ async fn check_registrations_from_user_pov(registrations: Commands) {
 }
 
 mod entity {
+    use crate::entity_manager::health_history::HealthStats;
     use crate::entity_manager::server::EntityStoreRequest;
     use crate::entity_manager::server::EntityStoreResponse;
     use crate::entity_manager::server::EntityStoreServer;
@@ -371,6 +487,20 @@ mod entity {
         None
     }
 
+    pub async fn get_health(
+        entity_store: &mut EntityStoreServer,
+        topic_id: &str,
+    ) -> Option<HealthStats> {
+        let topic_id = EntityTopicId::from_str(topic_id).unwrap();
+        if let EntityStoreResponse::GetHealth(stats) = entity_store
+            .handle(EntityStoreRequest::GetHealth(topic_id))
+            .await
+        {
+            return stats;
+        };
+        None
+    }
+
     pub async fn set_twin_fragments(
         entity_store: &mut EntityStoreServer,
         topic_id: &str,
@@ -734,6 +864,7 @@ mod model {
                 r#type: action.target_type(),
                 parent: action.parent_topic_id(),
                 health_endpoint: None,
+                process: None,
                 twin_data: action.properties(),
             }
         }
@@ -13,7 +13,7 @@ use rustls::ServerConfig;
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use tedge_actors::futures::channel::mpsc;
-use tedge_actors::futures::StreamExt;
+use tedge_actors::message_boxes::next_signal;
 use tedge_actors::Actor;
 use tedge_actors::Builder;
 use tedge_actors::ClientMessageBox;
@@ -62,7 +62,7 @@ impl Actor for HttpServerActor {
                 info!("Done");
                 return Ok(result.map_err(HttpServerError::FromIo)?);
             }
-            Some(RuntimeRequest::Shutdown) = self.signal_receiver.next() => {
+            Some(RuntimeRequest::Shutdown) = next_signal(&mut self.signal_receiver) => {
                 info!("Shutdown");
                 return Ok(());
             }
@@ -4,6 +4,7 @@
 //! - `POST /v1/entities`: Registers a new entity.
 //! - `GET /v1/entities/*path`: Retrieves an existing entity.
 //! - `DELETE /v1/entities/*path`: Deregisters an existing entity.
+//! - `GET /v1/entities/*path/health`: Retrieves the health status history of an existing service.
 //!
 //! References:
 //!
@@ -132,6 +133,9 @@ enum Error {
 
     #[error("Actions on channel: {0} are not supported")]
     UnsupportedChannel(String),
+
+    #[error("No health status recorded for entity: {0}")]
+    EntityHealthNotFound(EntityTopicId),
 }
 
 impl IntoResponse for Error {
@@ -154,6 +158,7 @@ impl IntoResponse for Error {
             Error::ResourceNotFound => StatusCode::NOT_FOUND,
             Error::EntityTwinDataNotFound(_, _) => StatusCode::NOT_FOUND,
             Error::UnsupportedChannel(_) => StatusCode::NOT_FOUND,
+            Error::EntityHealthNotFound(_) => StatusCode::NOT_FOUND,
             Error::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
         };
         let error_message = self.to_string();
@@ -192,6 +197,7 @@ impl From<EntityRegistrationHttpPayload> for EntityRegistrationMessage {
             r#type: value.others.r#type,
             parent: value.others.parent,
             health_endpoint: value.others.health_endpoint,
+            process: value.others.process,
             twin_data: value.others.twin_data,
         }
     }
@@ -239,6 +245,7 @@ async fn get_resource(
                     .into_response(),
             )
         }
+        Channel::Health => Ok(get_entity_health(state, topic_id).await.into_response()),
         _ => Err(Error::MethodNotAllowed),
     }
 }
@@ -339,6 +346,10 @@ fn parse_path(path: &str) -> Result<(EntityTopicId, Channel), Error> {
                 },
             ))
         }
+        [seg1, seg2, seg3, seg4, "health"] => {
+            let topic_id = topic_id_from_path_segments(seg1, Some(seg2), Some(seg3), Some(seg4))?;
+            Ok((topic_id, Channel::Health))
+        }
         [_, _, _, _, "twin", keys @ ..] => Err(Error::EntityStoreError(
             entity_store::Error::InvalidTwinData(keys.join("/")),
         )),
@@ -518,6 +529,26 @@ async fn get_entity_twin_fragments(
     Ok(Json(twin_data?))
 }
 
+async fn get_entity_health(
+    state: AgentState,
+    topic_id: EntityTopicId,
+) -> Result<impl IntoResponse, Error> {
+    let response = state
+        .entity_store_handle
+        .clone()
+        .await_response(EntityStoreRequest::GetHealth(topic_id.clone()))
+        .await?;
+
+    let EntityStoreResponse::GetHealth(stats) = response else {
+        return Err(Error::InvalidEntityStoreResponse);
+    };
+
+    match stats {
+        Some(stats) => Ok(Json(stats)),
+        None => Err(Error::EntityHealthNotFound(topic_id)),
+    }
+}
+
 async fn set_entity_twin_fragments(
     state: AgentState,
     topic_id: EntityTopicId,
@@ -559,6 +590,8 @@ async fn delete_entity_twin_fragments(
 #[cfg(test)]
 mod tests {
     use super::AgentState;
+    use crate::entity_manager::health_history::HealthChange;
+    use crate::entity_manager::health_history::HealthStats;
     use crate::entity_manager::server::EntityStoreRequest;
     use crate::entity_manager::server::EntityStoreResponse;
     use crate::http_server::entity_store::entity_store_router;
@@ -581,9 +614,11 @@ mod tests {
     use tedge_api::entity::EntityMetadata;
     use tedge_api::entity::EntityType;
     use tedge_api::entity_store;
+    use tedge_api::health::Status;
     use tedge_api::mqtt_topics::EntityTopicId;
     use tedge_test_utils::fs::TempTedgeDir;
     use test_case::test_case;
+    use time::OffsetDateTime;
     use tower::Service;
 
     #[tokio::test]
@@ -1546,6 +1581,101 @@ mod tests {
         assert_non_existent_entity_response(response).await;
     }
 
+    #[tokio::test]
+    async fn get_service_health() {
+        let TestHandle {
+            mut app,
+            mut entity_store_box,
+        } = setup();
+
+        // Mock entity store actor response
+        tokio::spawn(async move {
+            if let Some(mut req) = entity_store_box.recv().await {
+                if let EntityStoreRequest::GetHealth(topic_id) = req.request {
+                    if topic_id == EntityTopicId::default_main_service("foo").unwrap() {
+                        let went_up = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+                        let stats = HealthStats {
+                            status: Status::Up,
+                            uptime: 60,
+                            down_count: 0,
+                            last_change: went_up,
+                            flapping: false,
+                            history: vec![HealthChange {
+                                status: Status::Up,
+                                time: went_up,
+                            }],
+                        };
+                        req.reply_to
+                            .send(EntityStoreResponse::GetHealth(Some(stats)))
+                            .await
+                            .unwrap();
+                    }
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/v1/entities/device/main/service/foo/health")
+            .body(Body::empty())
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let health: Value = serde_json::from_slice(&body).unwrap();
+        assert_json_eq!(
+            health,
+            json!({
+                "status": "up",
+                "uptime": 60,
+                "down_count": 0,
+                "last_change": "2023-11-14T22:13:20Z",
+                "flapping": false,
+                "history": [
+                    {"status": "up", "time": "2023-11-14T22:13:20Z"}
+                ]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn get_unknown_service_health() {
+        let TestHandle {
+            mut app,
+            mut entity_store_box,
+        } = setup();
+
+        // Mock entity store actor response
+        tokio::spawn(async move {
+            if let Some(mut req) = entity_store_box.recv().await {
+                if let EntityStoreRequest::GetHealth(_) = req.request {
+                    req.reply_to
+                        .send(EntityStoreResponse::GetHealth(None))
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/v1/entities/device/main/service/foo/health")
+            .body(Body::empty())
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let error: Value = serde_json::from_slice(&body).unwrap();
+        assert_json_eq!(
+            error,
+            json!({"error":"No health status recorded for entity: device/main/service/foo"})
+        );
+    }
+
     #[tokio::test]
     async fn set_twin_fragments() {
         let TestHandle {
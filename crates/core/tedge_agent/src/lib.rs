@@ -19,11 +19,14 @@ use tedge_config::log_init;
 use tracing::log::warn;
 
 mod agent;
+mod cert_renewal_manager;
 mod device_profile_manager;
+mod diag_manager;
 mod entity_manager;
 mod http_server;
 mod operation_file_cache;
 mod operation_workflows;
+mod remote_access_manager;
 mod restart_manager;
 mod software_manager;
 mod state_repository;
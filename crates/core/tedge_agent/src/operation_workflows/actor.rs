@@ -6,6 +6,7 @@ use camino::Utf8PathBuf;
 use log::error;
 use log::info;
 use std::process::Output;
+use std::sync::Arc;
 use std::time::Duration;
 use tedge_actors::fan_in_message_type;
 use tedge_actors::Actor;
@@ -28,14 +29,23 @@ use tedge_api::workflow::GenericCommandData;
 use tedge_api::workflow::GenericCommandMetadata;
 use tedge_api::workflow::GenericCommandState;
 use tedge_api::workflow::GenericStateUpdate;
+use tedge_api::workflow::HttpBody;
+use tedge_api::workflow::HttpMethod;
+use tedge_api::workflow::HttpRequestTemplate;
 use tedge_api::workflow::OperationAction;
 use tedge_api::workflow::OperationName;
 use tedge_api::workflow::WorkflowExecutionError;
 use tedge_api::CommandLog;
 use tedge_file_system_ext::FsWatchEvent;
+use tedge_http_ext::HttpRequest;
+use tedge_http_ext::HttpRequestBuilder;
+use tedge_http_ext::HttpResponseExt;
+use tedge_http_ext::HttpResult;
 use tedge_mqtt_ext::MqttMessage;
 use tedge_mqtt_ext::QoS;
 use tedge_script_ext::Execute;
+use tedge_timer_ext::SetTimeout;
+use tedge_timer_ext::Timeout;
 use tokio::time::sleep;
 
 /// A generic command state that is published by the [TedgeOperationConverterActor]
@@ -43,7 +53,14 @@ use tokio::time::sleep;
 #[derive(Debug)]
 pub struct InternalCommandState(GenericCommandState);
 
-fan_in_message_type!(AgentInput[MqttMessage, InternalCommandState, GenericCommandData, FsWatchEvent] : Debug);
+/// A command state that is sent back to the [WorkflowActor] by the timer actor once a retry delay is over
+#[derive(Debug)]
+pub struct RetryCommandState(GenericCommandState);
+
+pub type RetryTimerStart = SetTimeout<RetryCommandState>;
+pub type RetryTimerComplete = Timeout<RetryCommandState>;
+
+fan_in_message_type!(AgentInput[MqttMessage, InternalCommandState, RetryTimerComplete, GenericCommandData, FsWatchEvent] : Debug);
 
 pub struct WorkflowActor {
     pub(crate) mqtt_schema: MqttSchema,
@@ -54,8 +71,12 @@ pub struct WorkflowActor {
     pub(crate) input_receiver: UnboundedLoggingReceiver<AgentInput>,
     pub(crate) builtin_command_dispatcher: CommandDispatcher,
     pub(crate) command_sender: DynSender<InternalCommandState>,
+    pub(crate) retry_timer: LoggingSender<RetryTimerStart>,
     pub(crate) mqtt_publisher: LoggingSender<MqttMessage>,
     pub(crate) script_runner: ClientMessageBox<Execute, std::io::Result<Output>>,
+    pub(crate) http_client: ClientMessageBox<HttpRequest, HttpResult>,
+    pub(crate) tedge_http_url: Arc<str>,
+    pub(crate) c8y_proxy_url: Option<Arc<str>>,
 }
 
 #[async_trait]
@@ -77,6 +98,11 @@ impl Actor for WorkflowActor {
                 AgentInput::InternalCommandState(InternalCommandState(command_state)) => {
                     self.process_command_update(command_state).await?;
                 }
+                AgentInput::RetryTimerComplete(Timeout {
+                    event: RetryCommandState(command_state),
+                }) => {
+                    self.process_command_retry(command_state).await?;
+                }
                 AgentInput::GenericCommandData(GenericCommandData::State(new_state)) => {
                     self.process_builtin_command_update(new_state).await?;
                 }
@@ -287,9 +313,87 @@ impl WorkflowActor {
                 let output = self.script_runner.await_response(command).await?;
                 log_file.log_script_output(&output).await;
 
+                let attempt = state.retry_attempt();
+                if let Some(delay) = handlers.retry_delay(attempt, &output) {
+                    let attempt = attempt + 1;
+                    let max_retries = handlers
+                        .retry_policy()
+                        .map(|retry| retry.max_retries)
+                        .unwrap_or_default();
+                    info!(
+                        "Retrying {operation} operation {step} step in {delay:?} (attempt {attempt}/{max_retries})"
+                    );
+                    log_file
+                        .log_info(&format!(
+                            "=> retrying {script_name} in {delay:?} (attempt {attempt}/{max_retries})"
+                        ))
+                        .await;
+                    // The delay must not block the other commands processed by this actor
+                    let new_state = state.with_retry_attempt(attempt);
+                    self.retry_timer
+                        .send(SetTimeout::new(delay, RetryCommandState(new_state)))
+                        .await?;
+                    return Ok(());
+                }
+
                 let new_state = state.update_with_script_output(script_name, output, handlers);
                 self.publish_command_state(new_state, &mut log_file).await
             }
+            OperationAction::Http(request, handlers) => {
+                let step = &state.status;
+                info!("Processing {operation} operation {step} step with HTTP request: {request}");
+
+                let http_request = match self.http_request(&request) {
+                    Ok(http_request) => http_request,
+                    Err(reason) => {
+                        let new_state = state.update(GenericStateUpdate::failed(reason));
+                        return self.publish_command_state(new_state, &mut log_file).await;
+                    }
+                };
+                let response = match handlers.timeout {
+                    None => self.http_client.await_response(http_request).await?,
+                    Some(timeout) => {
+                        match tokio::time::timeout(
+                            timeout,
+                            self.http_client.await_response(http_request),
+                        )
+                        .await
+                        {
+                            Ok(response) => response?,
+                            Err(_) => {
+                                log_file
+                                    .log_info(&format!("=> {request} timed out after {timeout:?}"))
+                                    .await;
+                                let new_state = state.update(handlers.on_timeout);
+                                return self.publish_command_state(new_state, &mut log_file).await;
+                            }
+                        }
+                    }
+                };
+
+                let new_state = match response {
+                    Ok(response) => {
+                        let status = response.status().as_u16();
+                        match response.text().await {
+                            Ok(body) => {
+                                log_file
+                                    .log_info(&format!("=> {request} returned status {status}"))
+                                    .await;
+                                state.update_with_http_response(status, body, &handlers)
+                            }
+                            Err(err) => state.update(GenericStateUpdate::failed(format!(
+                                "Failed to read the response to {request}: {err}"
+                            ))),
+                        }
+                    }
+                    Err(err) => {
+                        let mut on_error = handlers.on_error;
+                        on_error.reason = Some(format!("Failed to send {request}: {err}"));
+                        state.update(on_error)
+                    }
+                };
+                self.publish_command_state(new_state, &mut log_file).await
+            }
             OperationAction::BgScript(script, handlers) => {
                 let next_state = &handlers.on_exec.status;
                 info!(
@@ -458,6 +562,58 @@ impl WorkflowActor {
         self.process_command_update(adapted_state).await
     }
 
+    /// Build the HTTP request sent by a workflow `http` action
+    ///
+    /// Relative urls are resolved as for `tedge http`:
+    /// - `/c8y/...` urls are sent to the local Cumulocity proxy
+    /// - `/te/...` and `/tedge/...` urls are sent to the local thin-edge HTTP service
+    fn http_request(&self, request: &HttpRequestTemplate) -> Result<HttpRequest, String> {
+        let url = &request.url;
+        let url = if url.starts_with("/c8y") {
+            let Some(c8y_proxy_url) = &self.c8y_proxy_url else {
+                return Err(format!(
+                    "No Cumulocity proxy is configured to send {request}"
+                ));
+            };
+            format!("{c8y_proxy_url}{url}")
+        } else if url.starts_with("/te") || url.starts_with("/tedge") {
+            format!("{}{url}", self.tedge_http_url)
+        } else {
+            url.to_string()
+        };
+
+        let mut builder = match request.method {
+            HttpMethod::Get => HttpRequestBuilder::get(url),
+            HttpMethod::Post => HttpRequestBuilder::post(url),
+            HttpMethod::Put => HttpRequestBuilder::put(url),
+            HttpMethod::Delete => HttpRequestBuilder::delete(url),
+        };
+        builder = match &request.body {
+            HttpBody::Empty => builder,
+            HttpBody::Text(text) => builder.text(text.clone()),
+            HttpBody::Json(json) => {
+                let has_content_type = request
+                    .headers
+                    .iter()
+                    .any(|(key, _)| key.eq_ignore_ascii_case("content-type"));
+                if has_content_type {
+                    builder.json(json)
+                } else {
+                    builder
+                        .json(json)
+                        .header("Content-Type", "application/json")
+                }
+            }
+        };
+        for (key, value) in request.headers.iter() {
+            builder = builder.header(key.as_str(), value.as_str());
+        }
+
+        builder
+            .build()
+            .map_err(|err| format!("Invalid HTTP request {request}: {err}"))
+    }
+
     fn open_command_log(
         &mut self,
         state: &GenericCommandState,
@@ -484,6 +640,32 @@ impl WorkflowActor {
         )
     }
 
+    /// Resume a command which step is retried, unless the command moved on in the meantime
+    async fn process_command_retry(
+        &mut self,
+        new_state: GenericCommandState,
+    ) -> Result<(), RuntimeError> {
+        let Ok((operation, cmd_id)) = self.extract_command_identifiers(&new_state.topic.name)
+        else {
+            return Ok(());
+        };
+        let still_pending = self
+            .workflow_repository
+            .pending_commands()
+            .get_state(new_state.topic.name.as_str())
+            .is_some_and(|(_, state)| state.status == new_state.status);
+        if !still_pending {
+            info!(
+                "Not retrying {operation} operation {} step, as the command moved on",
+                new_state.status
+            );
+            return Ok(());
+        }
+
+        let mut log_file = self.open_command_log(&new_state, &operation, &cmd_id);
+        self.publish_command_state(new_state, &mut log_file).await
+    }
+
     async fn publish_command_state(
         &mut self,
         new_state: GenericCommandState,
@@ -1,5 +1,7 @@
 use crate::operation_workflows::actor::AgentInput;
 use crate::operation_workflows::actor::InternalCommandState;
+use crate::operation_workflows::actor::RetryTimerComplete;
+use crate::operation_workflows::actor::RetryTimerStart;
 use crate::operation_workflows::actor::WorkflowActor;
 use crate::operation_workflows::config::OperationConfig;
 use crate::operation_workflows::message_box::CommandDispatcher;
@@ -30,6 +32,8 @@ use tedge_api::workflow::GenericCommandData;
 use tedge_api::workflow::GenericCommandState;
 use tedge_api::workflow::OperationName;
 use tedge_file_system_ext::FsWatchEvent;
+use tedge_http_ext::HttpRequest;
+use tedge_http_ext::HttpResult;
 use tedge_mqtt_ext::MqttMessage;
 use tedge_mqtt_ext::TopicFilter;
 use tedge_script_ext::Execute;
@@ -40,8 +44,10 @@ pub struct WorkflowActorBuilder {
     input_receiver: UnboundedLoggingReceiver<AgentInput>,
     command_dispatcher: CommandDispatcher,
     command_sender: DynSender<InternalCommandState>,
+    retry_timer: LoggingSender<RetryTimerStart>,
     mqtt_publisher: LoggingSender<MqttMessage>,
     script_runner: ClientMessageBox<Execute, std::io::Result<Output>>,
+    http_client: ClientMessageBox<HttpRequest, HttpResult>,
     signal_sender: mpsc::Sender<RuntimeRequest>,
 }
 
@@ -50,7 +56,9 @@ impl WorkflowActorBuilder {
         config: OperationConfig,
         mqtt_actor: &mut (impl MessageSource<MqttMessage, TopicFilter> + MessageSink<MqttMessage>),
         script_runner: &mut impl Service<Execute, std::io::Result<Output>>,
+        http_client: &mut impl Service<HttpRequest, HttpResult>,
         fs_notify: &mut impl MessageSource<FsWatchEvent, PathBuf>,
+        timer: &mut impl Service<RetryTimerStart, RetryTimerComplete>,
     ) -> Self {
         let (input_sender, input_receiver) = mpsc::unbounded();
         let (signal_sender, signal_receiver) = mpsc::channel(10);
@@ -64,6 +72,8 @@ impl WorkflowActorBuilder {
 
         let command_dispatcher = CommandDispatcher::default();
         let command_sender = input_sender.sender_clone();
+        let retry_timer = timer.connect_client(input_sender.sender_clone());
+        let retry_timer = LoggingSender::new("RetryTimer".into(), retry_timer);
 
         let mqtt_publisher = mqtt_actor.get_sender();
         mqtt_actor.connect_sink(
@@ -73,6 +83,7 @@ impl WorkflowActorBuilder {
         let mqtt_publisher = LoggingSender::new("MqttPublisher".into(), mqtt_publisher);
 
         let script_runner = ClientMessageBox::new(script_runner);
+        let http_client = ClientMessageBox::new(http_client);
 
         fs_notify.connect_sink(config.operations_dir.clone().into(), &input_sender);
 
@@ -82,9 +93,11 @@ impl WorkflowActorBuilder {
             input_receiver,
             command_dispatcher,
             command_sender,
+            retry_timer,
             mqtt_publisher,
             signal_sender,
             script_runner,
+            http_client,
         }
     }
 
@@ -131,6 +144,8 @@ impl Builder<WorkflowActor> for WorkflowActorBuilder {
         WorkflowActor {
             mqtt_schema: self.config.mqtt_schema,
             device_topic_id: self.config.device_topic_id,
+            tedge_http_url: self.config.tedge_http_url,
+            c8y_proxy_url: self.config.c8y_proxy_url,
             workflow_repository,
             state_repository,
             log_dir: self.config.log_dir,
@@ -138,7 +153,9 @@ impl Builder<WorkflowActor> for WorkflowActorBuilder {
             builtin_command_dispatcher: self.command_dispatcher,
             mqtt_publisher: self.mqtt_publisher,
             command_sender: self.command_sender,
+            retry_timer: self.retry_timer,
             script_runner: self.script_runner,
+            http_client: self.http_client,
         }
     }
 }
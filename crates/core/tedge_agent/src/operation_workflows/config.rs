@@ -1,4 +1,5 @@
 use camino::Utf8PathBuf;
+use std::sync::Arc;
 use tedge_api::mqtt_topics::EntityTopicId;
 use tedge_api::mqtt_topics::MqttSchema;
 use tedge_config::TEdgeConfig;
@@ -11,6 +12,8 @@ pub struct OperationConfig {
     pub config_dir: Utf8PathBuf,
     pub state_dir: Utf8PathBuf,
     pub operations_dir: Utf8PathBuf,
+    pub tedge_http_url: Arc<str>,
+    pub c8y_proxy_url: Option<Arc<str>>,
 }
 
 impl OperationConfig {
@@ -21,6 +24,24 @@ impl OperationConfig {
     ) -> Result<OperationConfig, tedge_config::TEdgeConfigError> {
         let config_dir = tedge_config.root_dir();
 
+        let tedge_http_protocol = tedge_config
+            .http
+            .cert_path
+            .or_none()
+            .map_or("http", |_| "https");
+        let tedge_http_url = format!(
+            "{tedge_http_protocol}://{}:{}",
+            tedge_config.http.client.host, tedge_config.http.client.port
+        )
+        .into();
+
+        // HTTP requests sent by workflows to `/c8y` are forwarded to the default Cumulocity proxy, if any
+        let c8y_proxy_url = tedge_config.c8y.try_get::<str>(None).ok().map(|c8y| {
+            let protocol = c8y.proxy.cert_path.or_none().map_or("http", |_| "https");
+            let client = &c8y.proxy.client;
+            format!("{protocol}://{}:{}", client.host, client.port).into()
+        });
+
         Ok(OperationConfig {
             mqtt_schema: MqttSchema::with_root(topic_root),
             device_topic_id: device_topic_id.clone(),
@@ -28,6 +49,8 @@ impl OperationConfig {
             config_dir: config_dir.to_owned(),
             state_dir: tedge_config.agent.state.path.clone().into(),
             operations_dir: config_dir.join("operations"),
+            tedge_http_url,
+            c8y_proxy_url,
         })
     }
 }
@@ -1,3 +1,5 @@
+use crate::operation_workflows::actor::RetryTimerComplete;
+use crate::operation_workflows::actor::RetryTimerStart;
 use crate::operation_workflows::builder::WorkflowActorBuilder;
 use crate::operation_workflows::config::OperationConfig;
 use crate::software_manager::actor::SoftwareCommand;
@@ -39,6 +41,8 @@ use tedge_api::workflow::OperationName;
 use tedge_api::RestartCommand;
 use tedge_api::SoftwareUpdateCommand;
 use tedge_file_system_ext::FsWatchEvent;
+use tedge_http_ext::HttpRequest;
+use tedge_http_ext::HttpResult;
 use tedge_mqtt_ext::test_helpers::assert_received_contains_str;
 use tedge_mqtt_ext::MqttMessage;
 use tedge_mqtt_ext::Topic;
@@ -356,8 +360,16 @@ async fn spawn_mqtt_operation_converter(device_topic_id: &str) -> Result<TestHan
         RequestEnvelope<Execute, std::io::Result<Output>>,
         NoMessage,
     > = SimpleMessageBoxBuilder::new("Script", 5);
+    let mut http_builder: SimpleMessageBoxBuilder<
+        RequestEnvelope<HttpRequest, HttpResult>,
+        NoMessage,
+    > = SimpleMessageBoxBuilder::new("HTTP", 5);
     let mut inotify_builder: SimpleMessageBoxBuilder<NoMessage, FsWatchEvent> =
         SimpleMessageBoxBuilder::new("Inotify", 5);
+    let mut timer_builder: SimpleMessageBoxBuilder<
+        RequestEnvelope<RetryTimerStart, RetryTimerComplete>,
+        NoMessage,
+    > = SimpleMessageBoxBuilder::new("Timer", 5);
 
     let tmp_dir = tempfile::TempDir::new().unwrap();
     let tmp_path = Utf8Path::from_path(tmp_dir.path()).unwrap();
@@ -368,12 +380,16 @@ async fn spawn_mqtt_operation_converter(device_topic_id: &str) -> Result<TestHan
         config_dir: tmp_path.into(),
         state_dir: tmp_path.join("running-operations"),
         operations_dir: tmp_path.join("operations"),
+        tedge_http_url: "http://127.0.0.1:8000".into(),
+        c8y_proxy_url: Some("http://127.0.0.1:8001".into()),
     };
     let mut converter_actor_builder = WorkflowActorBuilder::new(
         config,
         &mut mqtt_builder,
         &mut script_builder,
+        &mut http_builder,
         &mut inotify_builder,
+        &mut timer_builder,
     );
     converter_actor_builder.register_builtin_operation(&mut restart_builder);
     converter_actor_builder.register_builtin_operation(&mut software_builder);
@@ -0,0 +1,128 @@
+use crate::remote_access_manager::config::RemoteAccessConfig;
+use async_trait::async_trait;
+use http::HeaderValue;
+use remote_access::WebsocketSocketProxy;
+use tedge_actors::Actor;
+use tedge_actors::MessageReceiver;
+use tedge_actors::RuntimeError;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_api::commands::CommandStatus;
+use tedge_api::commands::RemoteAccessCmdPayload;
+use tedge_api::commands::RemoteAccessCommand;
+use tracing::error;
+use tracing::info;
+use url::Url;
+
+pub struct RemoteAccessActor {
+    config: RemoteAccessConfig,
+    message_box: SimpleMessageBox<RemoteAccessCommand, RemoteAccessCommand>,
+}
+
+#[async_trait]
+impl Actor for RemoteAccessActor {
+    fn name(&self) -> &str {
+        "RemoteAccessActor"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        while let Some(request) = self.message_box.recv().await {
+            self.process_command(request).await?;
+        }
+        Ok(())
+    }
+}
+
+impl RemoteAccessActor {
+    pub fn new(
+        config: RemoteAccessConfig,
+        message_box: SimpleMessageBox<RemoteAccessCommand, RemoteAccessCommand>,
+    ) -> Self {
+        RemoteAccessActor {
+            config,
+            message_box,
+        }
+    }
+
+    /// Open a tunnel, the command being successful as soon as both ends are connected
+    ///
+    /// The data is then copied in the background, until either end of the tunnel is closed.
+    async fn process_command(
+        &mut self,
+        mut command: RemoteAccessCommand,
+    ) -> Result<(), RuntimeError> {
+        if command.status() != CommandStatus::Scheduled {
+            // Only handle commands in the scheduled state
+            return Ok(());
+        }
+
+        // The token is only used to open the tunnel and must not be republished along the command status
+        let token = command.payload.token.take();
+        command.executing();
+        self.message_box.send(command.clone()).await?;
+
+        match self.open_tunnel(&command.payload, token).await {
+            Ok(tunnel) => {
+                let target = format!("{}:{}", command.payload.host, command.payload.port);
+                info!("Remote access tunnel to {target} opened");
+                tokio::spawn(async move {
+                    tunnel.run().await;
+                    info!("Remote access tunnel to {target} closed");
+                });
+                command.successful();
+            }
+            Err(reason) => {
+                error!(reason);
+                command.failed(reason);
+            }
+        }
+        self.message_box.send(command).await?;
+        Ok(())
+    }
+
+    async fn open_tunnel(
+        &self,
+        payload: &RemoteAccessCmdPayload,
+        token: Option<String>,
+    ) -> Result<WebsocketSocketProxy, String> {
+        let target = format!("{}:{}", payload.host, payload.port);
+        if !self.config.allowed_targets.contains(&target) {
+            return Err(format!(
+                "Remote access to {target} is not allowed: not listed in agent.remote_access.allowed_targets"
+            ));
+        }
+
+        let url: Url = payload
+            .url
+            .parse()
+            .map_err(|err| format!("Invalid websocket URL {}: {err}", payload.url))?;
+        let authorization = token
+            .map(|token| HeaderValue::from_str(&format!("Bearer {token}")))
+            .transpose()
+            .map_err(|err| format!("Invalid token: {err}"))?;
+
+        let connect = WebsocketSocketProxy::connect(
+            &url,
+            (payload.host.as_str(), payload.port),
+            authorization,
+            Some(self.config.tls_config.clone()),
+            &self.config.proxy,
+        );
+        match tokio::time::timeout(self.config.connect_timeout, connect).await {
+            Ok(Ok(tunnel)) => Ok(tunnel),
+            Ok(Err(err)) => Err(format!(
+                "Fail to open a tunnel from {url} to {}:{}: {}",
+                payload.host,
+                payload.port,
+                err.chain()
+                    .map(|err| err.to_string())
+                    .collect::<Vec<_>>()
+                    .join(": ")
+            )),
+            Err(_) => Err(format!(
+                "Fail to open a tunnel from {url} to {}:{}: timeout after {:?}",
+                payload.host, payload.port, self.config.connect_timeout
+            )),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::remote_access_manager::actor::RemoteAccessActor;
+use crate::remote_access_manager::config::RemoteAccessConfig;
+use tedge_actors::Builder;
+use tedge_actors::DynSender;
+use tedge_actors::LinkError;
+use tedge_actors::MappingSender;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::commands::RemoteAccessCommand;
+use tedge_api::commands::REMOTE_ACCESS_OPERATION;
+use tedge_api::workflow::GenericCommandData;
+use tedge_api::workflow::GenericCommandState;
+use tedge_api::workflow::OperationName;
+
+pub struct RemoteAccessBuilder {
+    config: RemoteAccessConfig,
+    message_box: SimpleMessageBoxBuilder<RemoteAccessCommand, RemoteAccessCommand>,
+}
+
+impl RemoteAccessBuilder {
+    pub fn new(config: RemoteAccessConfig) -> Self {
+        let message_box = SimpleMessageBoxBuilder::new("RemoteAccess", 10);
+
+        Self {
+            config,
+            message_box,
+        }
+    }
+}
+
+impl MessageSink<RemoteAccessCommand> for RemoteAccessBuilder {
+    fn get_sender(&self) -> DynSender<RemoteAccessCommand> {
+        self.message_box.get_sender()
+    }
+}
+
+impl MessageSource<RemoteAccessCommand, NoConfig> for RemoteAccessBuilder {
+    fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<RemoteAccessCommand>) {
+        self.message_box.connect_sink(config, peer)
+    }
+}
+
+impl MessageSource<GenericCommandData, NoConfig> for RemoteAccessBuilder {
+    fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<GenericCommandData>) {
+        self.message_box.connect_sink(config, &peer.get_sender())
+    }
+}
+
+impl IntoIterator for &RemoteAccessBuilder {
+    type Item = (OperationName, DynSender<GenericCommandState>);
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let sender =
+            MappingSender::new(self.message_box.get_sender(), |msg: GenericCommandState| {
+                msg.try_into().ok()
+            });
+        vec![(REMOTE_ACCESS_OPERATION.to_string(), sender.into())].into_iter()
+    }
+}
+
+impl RuntimeRequestSink for RemoteAccessBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.message_box.get_signal_sender()
+    }
+}
+
+impl Builder<RemoteAccessActor> for RemoteAccessBuilder {
+    type Error = LinkError;
+
+    fn try_build(self) -> Result<RemoteAccessActor, Self::Error> {
+        Ok(self.build())
+    }
+
+    fn build(self) -> RemoteAccessActor {
+        RemoteAccessActor::new(self.config, self.message_box.build())
+    }
+}
@@ -0,0 +1,29 @@
+use std::time::Duration;
+use tedge_config::tedge_toml::TEdgeConfigReaderProxy;
+use tedge_config::TEdgeConfig;
+
+#[derive(Debug, Clone)]
+pub struct RemoteAccessConfig {
+    /// The TLS configuration used to connect `wss://` websockets
+    pub tls_config: rustls::ClientConfig,
+
+    /// The HTTP proxy, if any, used to connect the websockets
+    pub proxy: TEdgeConfigReaderProxy,
+
+    /// Maximum duration to open both ends of a tunnel
+    pub connect_timeout: Duration,
+
+    /// The `host:port` targets to which a tunnel can be opened
+    pub allowed_targets: Vec<String>,
+}
+
+impl RemoteAccessConfig {
+    pub fn from_tedge_config(tedge_config: &TEdgeConfig) -> Self {
+        RemoteAccessConfig {
+            tls_config: tedge_config.cloud_client_tls_config(),
+            proxy: tedge_config.proxy.clone(),
+            connect_timeout: Duration::from_secs(30),
+            allowed_targets: tedge_config.agent.remote_access.allowed_targets.0.clone(),
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use crate::remote_access_manager::builder::RemoteAccessBuilder;
+use crate::remote_access_manager::config::RemoteAccessConfig;
+use axum::extract::ws::Message;
+use axum::extract::ws::WebSocket;
+use axum::extract::WebSocketUpgrade;
+use axum::response::Response;
+use axum::routing::any;
+use axum::Router;
+use http::HeaderMap;
+use std::time::Duration;
+use tedge_actors::test_helpers::MessageReceiverExt;
+use tedge_actors::test_helpers::TimedMessageBox;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::MessageReceiver;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::commands::CommandStatus;
+use tedge_api::commands::RemoteAccessCmdPayload;
+use tedge_api::commands::RemoteAccessCommand;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_config::TEdgeConfig;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+const TEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[tokio::test]
+async fn open_a_tunnel_from_a_websocket_to_a_local_socket() {
+    let (ws_url, mut authorizations) = spawn_websocket_server().await;
+    let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = target.local_addr().unwrap().port();
+    let mut workflow = spawn_remote_access(vec![format!("127.0.0.1:{port}")]);
+
+    workflow
+        .send(scheduled_command(port, &ws_url, Some("secret")))
+        .await
+        .unwrap();
+
+    let executing = workflow.recv().await.unwrap();
+    assert_eq!(executing.status(), CommandStatus::Executing);
+    assert_eq!(executing.payload.token, None);
+    let response = workflow.recv().await.unwrap();
+    assert_eq!(response.status(), CommandStatus::Successful);
+    assert_eq!(response.payload.token, None);
+    assert_eq!(
+        authorizations.recv().await,
+        Some(Some("Bearer secret".to_string()))
+    );
+
+    // The websocket server echoes in upper case what is received from the local socket
+    let (mut socket, _) = target.accept().await.unwrap();
+    socket.write_all(b"ping").await.unwrap();
+    let mut echo = [0u8; 4];
+    tokio::time::timeout(TEST_TIMEOUT, socket.read_exact(&mut echo))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&echo, b"PING");
+}
+
+#[tokio::test]
+async fn the_token_is_optional() {
+    let (ws_url, mut authorizations) = spawn_websocket_server().await;
+    let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = target.local_addr().unwrap().port();
+    let mut workflow = spawn_remote_access(vec![format!("127.0.0.1:{port}")]);
+
+    workflow
+        .send(scheduled_command(port, &ws_url, None))
+        .await
+        .unwrap();
+
+    workflow.skip(1).await;
+    let response = workflow.recv().await.unwrap();
+    assert_eq!(response.status(), CommandStatus::Successful);
+    assert_eq!(authorizations.recv().await, Some(None));
+}
+
+#[tokio::test]
+async fn fail_when_the_local_service_cannot_be_reached() {
+    let (ws_url, _authorizations) = spawn_websocket_server().await;
+    let unused_port = {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    let mut workflow = spawn_remote_access(vec![format!("127.0.0.1:{unused_port}")]);
+
+    workflow
+        .send(scheduled_command(unused_port, &ws_url, None))
+        .await
+        .unwrap();
+
+    workflow.skip(1).await;
+    let response = workflow.recv().await.unwrap();
+    assert!(
+        matches!(response.status(), CommandStatus::Failed { reason } if reason.contains("TCP socket"))
+    );
+}
+
+#[tokio::test]
+async fn fail_on_invalid_websocket_url() {
+    let mut workflow = spawn_remote_access(vec!["127.0.0.1:22".to_string()]);
+
+    workflow
+        .send(scheduled_command(22, "not a url", None))
+        .await
+        .unwrap();
+
+    workflow.skip(1).await;
+    let response = workflow.recv().await.unwrap();
+    assert!(
+        matches!(response.status(), CommandStatus::Failed { reason } if reason.starts_with("Invalid websocket URL"))
+    );
+}
+
+#[tokio::test]
+async fn fail_on_a_target_not_allowed() {
+    let (ws_url, mut authorizations) = spawn_websocket_server().await;
+    let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = target.local_addr().unwrap().port();
+    let mut workflow = spawn_remote_access(vec!["127.0.0.1:22".to_string()]);
+
+    workflow
+        .send(scheduled_command(port, &ws_url, Some("secret")))
+        .await
+        .unwrap();
+
+    workflow.skip(1).await;
+    let response = workflow.recv().await.unwrap();
+    assert!(
+        matches!(response.status(), CommandStatus::Failed { reason } if reason.contains("is not allowed"))
+    );
+    // The websocket has not even been opened
+    assert!(authorizations.try_recv().is_err());
+}
+
+fn scheduled_command(port: u16, url: &str, token: Option<&str>) -> RemoteAccessCommand {
+    RemoteAccessCommand {
+        target: EntityTopicId::default_main_device(),
+        cmd_id: "1234".to_string(),
+        payload: RemoteAccessCmdPayload {
+            status: CommandStatus::Scheduled,
+            host: "127.0.0.1".to_string(),
+            port,
+            url: url.to_string(),
+            token: token.map(str::to_string),
+        },
+    }
+}
+
+/// Spawn a websocket server echoing in upper case the data it receives
+///
+/// Return the websocket URL and a receiver of the authorization headers sent by the clients.
+async fn spawn_websocket_server() -> (String, mpsc::UnboundedReceiver<Option<String>>) {
+    let (authorizations, receiver) = mpsc::unbounded_channel();
+    let app = Router::new().route(
+        "/tunnel",
+        any(move |headers: HeaderMap, ws: WebSocketUpgrade| {
+            let authorization = headers
+                .get("authorization")
+                .map(|value| value.to_str().unwrap().to_string());
+            authorizations.send(authorization).unwrap();
+            async move { upgrade(ws) }
+        }),
+    );
+
+    fn upgrade(ws: WebSocketUpgrade) -> Response {
+        ws.protocols(["binary"]).on_upgrade(echo_upper_case)
+    }
+
+    async fn echo_upper_case(mut socket: WebSocket) {
+        while let Some(Ok(Message::Binary(data))) = socket.recv().await {
+            let data = data.to_ascii_uppercase();
+            if socket.send(Message::Binary(data.into())).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("ws://127.0.0.1:{port}/tunnel"), receiver)
+}
+
+fn spawn_remote_access(
+    allowed_targets: Vec<String>,
+) -> TimedMessageBox<SimpleMessageBox<RemoteAccessCommand, RemoteAccessCommand>> {
+    let tedge_config = TEdgeConfig::load_toml_str("");
+    let config = RemoteAccessConfig {
+        tls_config: tedge_config.cloud_client_tls_config(),
+        proxy: tedge_config.proxy.clone(),
+        connect_timeout: TEST_TIMEOUT,
+        allowed_targets,
+    };
+
+    let mut workflow_builder: SimpleMessageBoxBuilder<RemoteAccessCommand, RemoteAccessCommand> =
+        SimpleMessageBoxBuilder::new("Workflow", 5);
+    let mut remote_access_builder = RemoteAccessBuilder::new(config);
+    workflow_builder.connect_sink(NoConfig, &remote_access_builder);
+    remote_access_builder.connect_sink(NoConfig, &workflow_builder);
+
+    let workflow = workflow_builder.build().with_timeout(TEST_TIMEOUT);
+    let actor = remote_access_builder.build();
+    tokio::spawn(async move { actor.run().await });
+
+    workflow
+}
@@ -83,7 +83,7 @@ impl Actor for RestartManagerActor {
                             info!("As requested, a shutdown has been triggered");
                             return Ok(());
                         }
-                        Ok(None) | Err(_ /* timeout */) => {
+                        Ok(_) | Err(_ /* timeout */) => {
                             // Something went wrong. The process should have been shutdown by the restart.
                             let error = "No shutdown has been triggered".to_string();
                             error!(error);
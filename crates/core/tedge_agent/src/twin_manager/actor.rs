@@ -1,7 +1,11 @@
 use crate::twin_manager::builder::TwinManagerConfig;
+use crate::twin_manager::collectors::run_collector_scripts;
+use crate::twin_manager::collectors::BuiltinCollectors;
+use crate::twin_manager::collectors::INVENTORY_COLLECTORS_DIR;
 use async_trait::async_trait;
 use serde_json::Map;
 use serde_json::Value;
+use std::collections::HashMap;
 use std::fs::File;
 use std::time::Duration;
 use tedge_actors::Actor;
@@ -11,9 +15,11 @@ use tedge_actors::RuntimeError;
 use tedge_actors::Sender;
 use tedge_actors::SimpleMessageBox;
 use tedge_api::mqtt_topics::Channel;
-use tedge_api::mqtt_topics::EntityTopicId;
 use tedge_mqtt_ext::MqttMessage;
+use tokio::time::interval_at;
 use tokio::time::timeout;
+use tokio::time::Instant;
+use tokio::time::MissedTickBehavior;
 use tracing::error;
 
 const INVENTORY_FRAGMENTS_FILE_LOCATION: &str = "device/inventory.json";
@@ -22,6 +28,7 @@ pub struct TwinManagerActor {
     config: TwinManagerConfig,
     messages: SimpleMessageBox<MqttMessage, MqttMessage>,
     mqtt_publisher: LoggingSender<MqttMessage>,
+    twin_data: HashMap<String, Value>,
 }
 
 #[async_trait]
@@ -35,11 +42,7 @@ impl Actor for TwinManagerActor {
         // Wait until the very fist message is received (at least the agent health status is guaranteed)
         if let Some(mut msg) = self.messages.recv().await {
             loop {
-                if let Ok((_, Channel::EntityTwinData { fragment_key })) = self
-                    .config
-                    .mqtt_schema
-                    .entity_channel_of(msg.topic.as_ref())
-                {
+                if let Some(fragment_key) = self.update_twin_data(&msg) {
                     // If a twin data message for the same key is available,
                     // ignore the value in inventory JSON
                     inventory_map.remove(&fragment_key);
@@ -55,15 +58,34 @@ impl Actor for TwinManagerActor {
         }
 
         // Publish any remaining twin data loaded from the inventory JSON file
-        let device_id = self.config.device_topic_id.clone();
         for (key, value) in inventory_map {
-            self.publish_twin_data(&device_id, key, value).await;
+            self.publish_twin_data(key, value).await;
         }
 
-        // This is to prevent the MQTT actor from crashing while trying to send a twin message to this actor later
-        // after this actor has finished and closed its message box, but the MQTT actor still holds the sender half of it.
-        while self.messages.recv().await.is_some() {
-            // Do nothing
+        self.collect_inventory().await;
+
+        let inventory_interval = self.config.inventory_interval;
+        if inventory_interval.is_zero() {
+            // This is to prevent the MQTT actor from crashing while trying to send a twin message to this actor later
+            // after this actor has finished and closed its message box, but the MQTT actor still holds the sender half of it.
+            while let Some(msg) = self.messages.recv().await {
+                self.update_twin_data(&msg);
+            }
+            return Ok(());
+        }
+
+        let mut ticker = interval_at(Instant::now() + inventory_interval, inventory_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                msg = self.messages.recv() => match msg {
+                    Some(msg) => {
+                        self.update_twin_data(&msg);
+                    }
+                    None => break,
+                },
+                _ = ticker.tick() => self.collect_inventory().await,
+            }
         }
         Ok(())
     }
@@ -79,6 +101,50 @@ impl TwinManagerActor {
             config,
             messages,
             mqtt_publisher,
+            twin_data: HashMap::new(),
+        }
+    }
+
+    /// Keep track of the twin data published for the main device
+    ///
+    /// Return the fragment key, if the message is a twin data message
+    fn update_twin_data(&mut self, msg: &MqttMessage) -> Option<String> {
+        let Ok((topic_id, Channel::EntityTwinData { fragment_key })) = self
+            .config
+            .mqtt_schema
+            .entity_channel_of(msg.topic.as_ref())
+        else {
+            return None;
+        };
+        if topic_id != self.config.device_topic_id {
+            return None;
+        }
+
+        let payload = msg.payload_str().unwrap_or_default();
+        if payload.is_empty() {
+            self.twin_data.remove(&fragment_key);
+        } else if let Ok(value) = serde_json::from_str(payload) {
+            self.twin_data.insert(fragment_key.clone(), value);
+        }
+        Some(fragment_key)
+    }
+
+    /// Run the inventory collectors and publish the fragments which values have changed
+    async fn collect_inventory(&mut self) {
+        let mut fragments = Map::new();
+        if self.config.builtin_collectors {
+            match tokio::task::spawn_blocking(|| BuiltinCollectors::default().collect()).await {
+                Ok(builtin_fragments) => fragments.extend(builtin_fragments),
+                Err(err) => error!("Failed to run the builtin inventory collectors: {err}"),
+            }
+        }
+        let collectors_dir = self.config.config_dir.join(INVENTORY_COLLECTORS_DIR);
+        fragments.extend(run_collector_scripts(&collectors_dir).await);
+
+        for (key, value) in fragments {
+            if self.twin_data.get(&key) != Some(&value) {
+                self.publish_twin_data(key, value).await;
+            }
         }
     }
 
@@ -123,14 +189,18 @@ impl TwinManagerActor {
         Ok(twin_map)
     }
 
-    async fn publish_twin_data(
-        &mut self,
-        topic_id: &EntityTopicId,
-        fragment_key: String,
-        fragment_value: Value,
-    ) {
+    async fn publish_twin_data(&mut self, fragment_key: String, fragment_value: Value) {
+        if fragment_value.is_null() {
+            self.twin_data.remove(&fragment_key);
+        } else {
+            self.twin_data
+                .insert(fragment_key.clone(), fragment_value.clone());
+        }
         let twin_channel = Channel::EntityTwinData { fragment_key };
-        let topic = self.config.mqtt_schema.topic_for(topic_id, &twin_channel);
+        let topic = self
+            .config
+            .mqtt_schema
+            .topic_for(&self.config.device_topic_id, &twin_channel);
         let payload = if fragment_value.is_null() {
             "".to_string()
         } else {
@@ -1,6 +1,7 @@
 use crate::twin_manager::actor::TwinManagerActor;
 use camino::Utf8PathBuf;
 use std::convert::Infallible;
+use std::time::Duration;
 use tedge_actors::Builder;
 use tedge_actors::DynSender;
 use tedge_actors::LoggingSender;
@@ -21,6 +22,8 @@ pub struct TwinManagerConfig {
     pub mqtt_schema: MqttSchema,
     pub device_topic_id: EntityTopicId,
     pub agent_topic_id: EntityTopicId,
+    pub inventory_interval: Duration,
+    pub builtin_collectors: bool,
 }
 
 impl TwinManagerConfig {
@@ -35,6 +38,24 @@ impl TwinManagerConfig {
             mqtt_schema,
             device_topic_id,
             agent_topic_id,
+            inventory_interval: Duration::ZERO,
+            builtin_collectors: false,
+        }
+    }
+
+    /// Run the inventory collectors on the given interval, in addition to start-up
+    pub fn with_inventory_interval(self, inventory_interval: Duration) -> Self {
+        Self {
+            inventory_interval,
+            ..self
+        }
+    }
+
+    /// Enable or disable the builtin inventory collectors
+    pub fn with_builtin_collectors(self, builtin_collectors: bool) -> Self {
+        Self {
+            builtin_collectors,
+            ..self
         }
     }
 
@@ -0,0 +1,264 @@
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::os::unix::fs::PermissionsExt;
+use std::time::Duration;
+use tedge_utils::resources::DiskUsage;
+use tedge_utils::resources::MemoryUsage;
+use tokio::process::Command;
+use tokio::time::timeout;
+use tracing::warn;
+
+/// Directory, relative to the config dir, where user-provided inventory collectors are looked up
+pub const INVENTORY_COLLECTORS_DIR: &str = "device/inventory.d";
+
+const COLLECTOR_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Builtin inventory collectors
+///
+/// These collectors read the system information from `/proc`, `/sys` and `/etc/os-release`,
+/// using blocking calls: they have to be run with `spawn_blocking` from an async context.
+/// All the paths are relative to a `host_root` directory, which is `/` except for tests.
+pub struct BuiltinCollectors {
+    host_root: Utf8PathBuf,
+}
+
+impl Default for BuiltinCollectors {
+    fn default() -> Self {
+        BuiltinCollectors {
+            host_root: "/".into(),
+        }
+    }
+}
+
+impl BuiltinCollectors {
+    pub fn with_host_root(host_root: impl Into<Utf8PathBuf>) -> Self {
+        BuiltinCollectors {
+            host_root: host_root.into(),
+        }
+    }
+
+    /// Collect all the builtin fragments, skipping those for which no information is available
+    pub fn collect(&self) -> Map<String, Value> {
+        let collectors: [(&str, fn(&Self) -> Option<Value>); 6] = [
+            ("os_release", Self::os_release),
+            ("kernel", Self::kernel),
+            ("cpu", Self::cpu),
+            ("memory", Self::memory),
+            ("disks", Self::disks),
+            ("network", Self::network),
+        ];
+
+        collectors
+            .into_iter()
+            .filter_map(|(fragment, collector)| Some((fragment.to_string(), collector(self)?)))
+            .collect()
+    }
+
+    fn read(&self, path: &str) -> Option<String> {
+        std::fs::read_to_string(self.host_root.join(path)).ok()
+    }
+
+    fn os_release(&self) -> Option<Value> {
+        let content = self
+            .read("etc/os-release")
+            .or_else(|| self.read("usr/lib/os-release"))?;
+        let fields: Map<String, Value> = content
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| {
+                let value = value.trim().trim_matches('"').trim_matches('\'');
+                (key.trim().to_lowercase(), Value::from(value))
+            })
+            .collect();
+        Some(Value::Object(fields))
+    }
+
+    fn kernel(&self) -> Option<Value> {
+        let read_field = |name: &str| {
+            self.read(&format!("proc/sys/kernel/{name}"))
+                .map(|value| value.trim().to_string())
+        };
+        Some(json!({
+            "name": read_field("ostype")?,
+            "release": read_field("osrelease")?,
+            "version": read_field("version")?,
+            "architecture": std::env::consts::ARCH,
+        }))
+    }
+
+    fn cpu(&self) -> Option<Value> {
+        let cpuinfo = self.read("proc/cpuinfo")?;
+        let mut cores = 0;
+        let mut model = None;
+        for (key, value) in cpuinfo.lines().filter_map(|line| line.split_once(':')) {
+            match key.trim() {
+                "processor" => cores += 1,
+                "model name" | "Model" if model.is_none() => model = Some(value.trim()),
+                _ => {}
+            }
+        }
+        let mut cpu = json!({ "cores": cores });
+        if let Some(model) = model {
+            cpu["model"] = model.into();
+        }
+        Some(cpu)
+    }
+
+    fn memory(&self) -> Option<Value> {
+        let memory = MemoryUsage::parse(&self.read("proc/meminfo")?)?;
+        Some(json!({
+            "total": memory.total,
+            "swap": memory.swap_total.unwrap_or(0),
+        }))
+    }
+
+    fn disks(&self) -> Option<Value> {
+        let mounts = self.read("proc/mounts")?;
+        let disks: Vec<Value> = mounts
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let device = fields.next()?;
+                let mount_point = fields.next()?;
+                let filesystem = fields.next()?;
+                if !device.starts_with("/dev/") {
+                    return None;
+                }
+                let path = self.host_root.join(mount_point.trim_start_matches('/'));
+                let size = DiskUsage::of(path).ok().map(|usage| usage.total);
+                Some(json!({
+                    "device": device,
+                    "mount_point": mount_point,
+                    "filesystem": filesystem,
+                    "size": size,
+                }))
+            })
+            .collect();
+        Some(Value::Array(disks))
+    }
+
+    fn network(&self) -> Option<Value> {
+        let net_dir = self.host_root.join("sys/class/net");
+        let interfaces: BTreeMap<String, Value> = std::fs::read_dir(net_dir)
+            .ok()?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name != "lo")
+            .map(|name| {
+                let read_field = |field: &str| {
+                    self.read(&format!("sys/class/net/{name}/{field}"))
+                        .map(|value| value.trim().to_string())
+                };
+                let interface = json!({
+                    "mac": read_field("address"),
+                    "mtu": read_field("mtu").and_then(|mtu| mtu.parse::<u32>().ok()),
+                    "state": read_field("operstate"),
+                });
+                (name, interface)
+            })
+            .collect();
+        Some(json!(interfaces))
+    }
+}
+
+/// Run all the executable scripts found in the given directory, in alphabetical order
+///
+/// Each script is expected to print on its stdout a JSON object,
+/// each key of which being published as a separate twin fragment.
+pub async fn run_collector_scripts(collectors_dir: &Utf8Path) -> Map<String, Value> {
+    let mut fragments = Map::new();
+    let Ok(mut entries) = tokio::fs::read_dir(collectors_dir).await else {
+        return fragments;
+    };
+
+    let mut scripts = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let is_executable = tokio::fs::metadata(entry.path())
+            .await
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0);
+        if let (true, Ok(script)) = (is_executable, Utf8PathBuf::try_from(entry.path())) {
+            scripts.push(script);
+        }
+    }
+    scripts.sort();
+
+    for script in scripts {
+        match run_collector_script(&script).await {
+            Ok(values) => fragments.extend(values),
+            Err(err) => warn!("Ignoring the output of the inventory collector {script}: {err}"),
+        }
+    }
+
+    fragments
+}
+
+async fn run_collector_script(script: &Utf8Path) -> Result<Map<String, Value>, String> {
+    let mut command = Command::new(script);
+    command.kill_on_drop(true);
+    let output = match timeout(COLLECTOR_TIMEOUT, command.output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(err)) => return Err(format!("failed to execute: {err}")),
+        Err(_) => return Err(format!("timed out after {COLLECTOR_TIMEOUT:?}")),
+    };
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{}: {}", output.status, stderr.trim()));
+    }
+
+    match serde_json::from_slice(&output.stdout) {
+        Ok(Value::Object(fragments)) => Ok(fragments),
+        Ok(_) => Err("expected a JSON object on stdout".to_string()),
+        Err(err) => Err(format!("invalid JSON on stdout: {err}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[test]
+    fn collect_system_info_from_proc_and_sys() {
+        let root = TempTedgeDir::new();
+        root.dir("etc")
+            .file("os-release")
+            .with_raw_content("NAME=\"Debian GNU/Linux\"\nVERSION_ID=\"12\"\nID=debian\n");
+        root.dir("proc").file("cpuinfo").with_raw_content(
+            "processor\t: 0\nmodel name\t: ARMv7\n\nprocessor\t: 1\nmodel name\t: ARMv7\n",
+        );
+        root.dir("proc").file("meminfo").with_raw_content(
+            "MemTotal:        1024 kB\nMemFree:          512 kB\nSwapTotal:         0 kB\n",
+        );
+        root.dir("sys")
+            .dir("class")
+            .dir("net")
+            .dir("eth0")
+            .file("address")
+            .with_raw_content("dc:a6:32:00:00:01\n");
+
+        let fragments = BuiltinCollectors::with_host_root(root.utf8_path()).collect();
+
+        assert_eq!(
+            fragments.get("os_release"),
+            Some(&json!({"name": "Debian GNU/Linux", "version_id": "12", "id": "debian"}))
+        );
+        assert_eq!(
+            fragments.get("cpu"),
+            Some(&json!({"cores": 2, "model": "ARMv7"}))
+        );
+        assert_eq!(
+            fragments.get("memory"),
+            Some(&json!({"total": 1024 * 1024, "swap": 0}))
+        );
+        assert_eq!(
+            fragments.get("network"),
+            Some(&json!({"eth0": {"mac": "dc:a6:32:00:00:01", "mtu": null, "state": null}}))
+        );
+        assert_eq!(fragments.get("kernel"), None);
+        assert_eq!(fragments.get("disks"), None);
+    }
+}
@@ -1,5 +1,6 @@
 pub(crate) mod actor;
 pub(crate) mod builder;
+pub(crate) mod collectors;
 
 #[cfg(test)]
 mod tests;
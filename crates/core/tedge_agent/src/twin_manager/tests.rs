@@ -2,6 +2,7 @@ use crate::twin_manager::builder::TwinManagerActorBuilder;
 use crate::twin_manager::builder::TwinManagerConfig;
 use serde_json::json;
 use serde_json::Value;
+use std::time::Duration;
 use tedge_actors::test_helpers::MessageReceiverExt;
 use tedge_actors::Actor;
 use tedge_actors::Builder;
@@ -11,8 +12,11 @@ use tedge_actors::SimpleMessageBoxBuilder;
 use tedge_api::mqtt_topics::EntityTopicId;
 use tedge_api::mqtt_topics::MqttSchema;
 use tedge_mqtt_ext::MqttMessage;
+use tedge_test_utils::fs::with_exec_permission;
 use tedge_test_utils::fs::TempTedgeDir;
 
+const TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[tokio::test]
 async fn process_inventory_json_content_on_init() {
     let inventory_json = json!({
@@ -62,16 +66,86 @@ async fn inventory_json_value_ignored_if_twin_data_present() {
         .await;
 }
 
+#[tokio::test]
+async fn publish_fragments_returned_by_collector_scripts() {
+    let tmp_dir = TempTedgeDir::default();
+    create_collector_script(
+        &tmp_dir,
+        "hardware",
+        r#"{"hardware":{"model":"RPi 4"},"serial":"1234"}"#,
+    );
+    let handle = spawn_twin_manager(tmp_dir, Duration::ZERO);
+    let mut mqtt_box = handle.mqtt_box;
+
+    mqtt_box
+        .send(MqttMessage::from(("te/device/main///twin/serial", r#""1234""#)).with_retain())
+        .await
+        .unwrap();
+
+    // The serial fragment is not published again as unchanged
+    mqtt_box
+        .assert_received([MqttMessage::from((
+            "te/device/main///twin/hardware",
+            r#"{"model":"RPi 4"}"#,
+        ))
+        .with_retain()])
+        .await;
+}
+
+#[tokio::test]
+async fn collectors_are_run_periodically_and_only_changes_are_published() {
+    let tmp_dir = TempTedgeDir::default();
+    let value_file = tmp_dir.file("inventory_values.json");
+    let value_path = value_file.utf8_path_buf();
+    value_file.with_raw_content(r#"{"location":"A","firmware":"1.0"}"#);
+    with_exec_permission(
+        tmp_dir
+            .dir("device")
+            .dir("inventory.d")
+            .utf8_path()
+            .join("dynamic"),
+        &format!("#!/bin/sh\ncat {value_path}\n"),
+    );
+    let handle = spawn_twin_manager(tmp_dir, Duration::from_millis(100));
+    // Wait for the collectors to be run again, whatever the load of the test machine
+    let mut mqtt_box = handle.mqtt_box.with_timeout(TEST_TIMEOUT);
+
+    mqtt_box
+        .send(
+            MqttMessage::from(("te/device/main/service/tedge-agent/status/health", "1"))
+                .with_retain(),
+        )
+        .await
+        .unwrap();
+    mqtt_box
+        .assert_received_unordered([
+            MqttMessage::from(("te/device/main///twin/location", r#""A""#)).with_retain(),
+            MqttMessage::from(("te/device/main///twin/firmware", r#""1.0""#)).with_retain(),
+        ])
+        .await;
+
+    std::fs::write(&value_path, r#"{"location":"B","firmware":"1.0"}"#).unwrap();
+    mqtt_box
+        .assert_received([
+            MqttMessage::from(("te/device/main///twin/location", r#""B""#)).with_retain(),
+        ])
+        .await;
+}
+
 pub(crate) struct TestHandle {
     pub _tmp_dir: TempTedgeDir,
     pub mqtt_box: SimpleMessageBox<MqttMessage, MqttMessage>,
 }
 
 pub fn setup(inventory_json: Value) -> TestHandle {
-    let mqtt_schema = MqttSchema::default();
     let tmp_dir = TempTedgeDir::default();
-    let config_dir = tmp_dir.utf8_path_buf();
     create_inventory_json_file_with_content(&tmp_dir, &inventory_json.to_string());
+    spawn_twin_manager(tmp_dir, Duration::ZERO)
+}
+
+fn spawn_twin_manager(tmp_dir: TempTedgeDir, inventory_interval: Duration) -> TestHandle {
+    let mqtt_schema = MqttSchema::default();
+    let config_dir = tmp_dir.utf8_path_buf();
 
     let main_device_id = EntityTopicId::default_main_device();
     let config = TwinManagerConfig::new(
@@ -81,7 +155,8 @@ pub fn setup(inventory_json: Value) -> TestHandle {
         main_device_id
             .default_service_for_device("tedge-agent")
             .unwrap(),
-    );
+    )
+    .with_inventory_interval(inventory_interval);
 
     let mut mqtt_actor = SimpleMessageBoxBuilder::new("MQTT", 64);
     let actor = TwinManagerActorBuilder::new(config, &mut mqtt_actor).build();
@@ -99,3 +174,8 @@ fn create_inventory_json_file_with_content(ttd: &TempTedgeDir, content: &str) {
     let file = ttd.dir("device").file("inventory.json");
     file.with_raw_content(content);
 }
+
+fn create_collector_script(ttd: &TempTedgeDir, name: &str, output: &str) {
+    let script = ttd.dir("device").dir("inventory.d").utf8_path().join(name);
+    with_exec_permission(script, &format!("#!/bin/sh\necho '{output}'\n"));
+}
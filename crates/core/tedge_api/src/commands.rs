@@ -710,6 +710,147 @@ impl CommandPayload for RestartCommandPayload {
     }
 }
 
+/// The name of the operation renewing the device certificates
+pub const CERT_RENEW_OPERATION: &str = "cert_renew";
+
+/// Command to renew the device certificate used to connect the cloud profiles
+pub type CertRenewCommand = Command<CertRenewCmdPayload>;
+
+/// Command to renew the device certificate used to connect the cloud profiles
+#[derive(Debug, Clone, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CertRenewCmdPayload {
+    #[serde(flatten)]
+    pub status: CommandStatus,
+
+    /// The cloud profiles for which the certificate has to be renewed, e.g. `c8y`, `c8y@eu` or `aws`
+    ///
+    /// If none is provided, the certificates of all the connected cloud profiles are considered.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clouds: Vec<String>,
+
+    /// Renew the certificates even if they are not about to expire
+    #[serde(default)]
+    pub force: bool,
+
+    /// The cloud profiles for which a new certificate has been installed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renewed: Vec<String>,
+}
+
+impl Jsonify for CertRenewCmdPayload {}
+
+impl CommandPayload for CertRenewCmdPayload {
+    fn operation_type() -> OperationType {
+        OperationType::Custom(CERT_RENEW_OPERATION.to_string())
+    }
+
+    fn status(&self) -> CommandStatus {
+        self.status.clone()
+    }
+
+    fn set_status(&mut self, status: CommandStatus) {
+        self.status = status
+    }
+}
+
+/// The name of the operation opening a remote access tunnel
+pub const REMOTE_ACCESS_OPERATION: &str = "remote_access";
+
+/// Command to open a tunnel from a remote access service to a local TCP socket
+pub type RemoteAccessCommand = Command<RemoteAccessCmdPayload>;
+
+/// Command to open a tunnel from a remote access service to a local TCP socket
+///
+/// The data received from the websocket is forwarded to `host:port` and vice versa,
+/// until either end of the tunnel is closed.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteAccessCmdPayload {
+    #[serde(flatten)]
+    pub status: CommandStatus,
+
+    /// The host of the local service to connect, e.g. `127.0.0.1`
+    pub host: String,
+
+    /// The port of the local service to connect, e.g. `22` for SSH
+    pub port: u16,
+
+    /// The websocket URL of the remote access service, e.g. `wss://example.com/tunnel/1234`
+    pub url: String,
+
+    /// A bearer token used to authenticate the websocket connection
+    ///
+    /// The token is removed from the command, once the tunnel is opened.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl Jsonify for RemoteAccessCmdPayload {}
+
+impl CommandPayload for RemoteAccessCmdPayload {
+    fn operation_type() -> OperationType {
+        OperationType::Custom(REMOTE_ACCESS_OPERATION.to_string())
+    }
+
+    fn status(&self) -> CommandStatus {
+        self.status.clone()
+    }
+
+    fn set_status(&mut self, status: CommandStatus) {
+        self.status = status
+    }
+}
+
+/// Command to collect diagnostic information using the `tedge diag` plugins
+pub type DiagCollectCommand = Command<DiagCollectCmdPayload>;
+
+/// Command to collect diagnostic information using the `tedge diag` plugins
+///
+/// The resulting tarball is uploaded to the file-transfer service.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagCollectCmdPayload {
+    #[serde(flatten)]
+    pub status: CommandStatus,
+
+    /// The file-transfer service URL where the tarball is uploaded
+    ///
+    /// If none is provided, the agent picks a URL and adds it to the command when executing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tedge_url: Option<String>,
+
+    /// The names of the diagnostic plugins to run, without their file extension
+    ///
+    /// If none is provided, all the plugins are run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<String>,
+
+    /// Timeout in seconds for a graceful plugin shutdown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+
+    /// Timeout in seconds for a forced plugin termination, starting after the graceful timeout expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forceful_timeout: Option<u64>,
+}
+
+impl Jsonify for DiagCollectCmdPayload {}
+
+impl CommandPayload for DiagCollectCmdPayload {
+    fn operation_type() -> OperationType {
+        OperationType::DiagCollect
+    }
+
+    fn status(&self) -> CommandStatus {
+        self.status.clone()
+    }
+
+    fn set_status(&mut self, status: CommandStatus) {
+        self.status = status
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq, Clone)]
 #[serde(rename_all = "camelCase", tag = "status")]
 pub enum CommandStatus {
@@ -884,6 +1025,9 @@ pub struct ConfigUpdateCmdPayload {
     pub path: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub log_path: Option<Utf8PathBuf>,
+    /// Apply the update even if it changes keys that are unsafe to change remotely
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub force: bool,
 }
 
 impl Jsonify for ConfigUpdateCmdPayload {}
@@ -1,5 +1,6 @@
 use crate::mqtt_topics::EntityTopicId;
 use crate::mqtt_topics::TopicIdError;
+use camino::Utf8PathBuf;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::Map;
@@ -57,6 +58,19 @@ impl From<&EntityExternalId> for String {
     }
 }
 
+/// The process running a service, as given by the `@process` property of a service registration
+///
+/// When provided, the process resource usage is monitored by the agent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceProcess {
+    /// The main process of a systemd unit, e.g. `{"systemd_unit": "mosquitto.service"}`
+    SystemdUnit(String),
+
+    /// The process which pid is stored in a file, e.g. `{"pid_file": "/run/mosquitto.pid"}`
+    PidFile(Utf8PathBuf),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EntityMetadata {
     #[serde(rename = "@topic-id")]
@@ -69,6 +83,8 @@ pub struct EntityMetadata {
     pub external_id: Option<EntityExternalId>,
     #[serde(rename = "@health", skip_serializing_if = "Option::is_none")]
     pub health_endpoint: Option<EntityTopicId>,
+    #[serde(rename = "@process", skip_serializing_if = "Option::is_none")]
+    pub process: Option<ServiceProcess>,
 
     #[serde(skip)]
     pub twin_data: Map<String, JsonValue>,
@@ -82,6 +98,7 @@ impl EntityMetadata {
             external_id: None,
             parent: None,
             health_endpoint: None,
+            process: None,
             twin_data: Map::new(),
         }
     }
@@ -109,6 +126,7 @@ impl EntityMetadata {
             r#type: EntityType::MainDevice,
             parent: None,
             health_endpoint: None,
+            process: None,
             twin_data: Map::new(),
         }
     }
@@ -121,6 +139,7 @@ impl EntityMetadata {
             r#type: EntityType::ChildDevice,
             parent: Some(EntityTopicId::default_main_device()),
             health_endpoint: None,
+            process: None,
             twin_data: Map::new(),
         })
     }
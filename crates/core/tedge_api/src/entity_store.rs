@@ -11,6 +11,7 @@ use crate::entity::EntityExternalId;
 use crate::entity::EntityMetadata;
 use crate::entity::EntityType;
 use crate::entity::InsertOutcome;
+use crate::entity::ServiceProcess;
 use crate::entity_store;
 use crate::mqtt_topics::default_topic_schema;
 use crate::mqtt_topics::Channel;
@@ -28,6 +29,7 @@ use mqtt_channel::MqttMessage;
 use mqtt_channel::QoS;
 use serde::Deserialize;
 use serde::Serialize;
+use serde_json::json;
 use serde_json::Map;
 use serde_json::Value as JsonValue;
 use std::collections::hash_map::Entry;
@@ -105,6 +107,7 @@ impl EntityStore {
             r#type: main_device.r#type,
             parent: None,
             health_endpoint: None,
+            process: None,
             twin_data: main_device.twin_data,
         };
 
@@ -366,6 +369,7 @@ impl EntityStore {
             external_id: message.external_id,
             parent,
             health_endpoint: message.health_endpoint,
+            process: message.process,
             twin_data: message.twin_data,
         };
 
@@ -953,6 +957,8 @@ pub struct EntityRegistrationPayload {
     pub parent: Option<EntityTopicId>,
     #[serde(rename = "@health", skip_serializing_if = "Option::is_none")]
     pub health_endpoint: Option<EntityTopicId>,
+    #[serde(rename = "@process", skip_serializing_if = "Option::is_none")]
+    pub process: Option<ServiceProcess>,
 
     #[serde(flatten)]
     pub twin_data: Map<String, JsonValue>,
@@ -970,6 +976,7 @@ pub struct EntityRegistrationMessage {
     pub r#type: EntityType,
     pub parent: Option<EntityTopicId>,
     pub health_endpoint: Option<EntityTopicId>,
+    pub process: Option<ServiceProcess>,
 
     pub twin_data: Map<String, JsonValue>,
 }
@@ -984,6 +991,7 @@ impl EntityRegistrationMessage {
             r#type: payload.r#type,
             parent: payload.parent,
             health_endpoint: payload.health_endpoint,
+            process: payload.process,
             twin_data: payload.twin_data,
         })
     }
@@ -995,6 +1003,7 @@ impl EntityRegistrationMessage {
             external_id: None,
             parent: None,
             health_endpoint: None,
+            process: None,
             twin_data: Map::new(),
         }
     }
@@ -1027,6 +1036,7 @@ impl EntityRegistrationMessage {
             r#type: EntityType::MainDevice,
             parent: None,
             health_endpoint: None,
+            process: None,
             twin_data: Map::new(),
         }
     }
@@ -1049,6 +1059,10 @@ impl EntityRegistrationMessage {
             props.insert("@health".to_string(), health_endpoint.to_string().into());
         }
 
+        if let Some(process) = self.process {
+            props.insert("@process".to_string(), json!(process));
+        }
+
         props.append(&mut self.twin_data);
 
         let message = serde_json::to_string(&props).unwrap();
@@ -1066,6 +1080,7 @@ impl From<&EntityMetadata> for EntityRegistrationMessage {
             external_id: value.external_id.clone(),
             parent: value.parent.clone(),
             health_endpoint: value.health_endpoint.clone(),
+            process: value.process.clone(),
             twin_data: Map::new(),
         }
     }
@@ -1162,6 +1177,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_service_process_reference() {
+        let parsed = EntityRegistrationMessage::try_from(
+            "device/main/service/mosquitto".parse().unwrap(),
+            json!({
+                "@type" : "service",
+                "@process": { "systemd_unit": "mosquitto.service" },
+            })
+            .to_string()
+            .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.process,
+            Some(ServiceProcess::SystemdUnit("mosquitto.service".to_string()))
+        );
+        assert!(parsed.twin_data.is_empty());
+
+        let message = parsed.to_mqtt_message(&MqttSchema::default());
+        let payload: JsonValue = serde_json::from_slice(message.payload_bytes()).unwrap();
+        assert_eq!(
+            payload,
+            json!({
+                "@type" : "service",
+                "@process": { "systemd_unit": "mosquitto.service" },
+            })
+        );
+    }
+
     #[test_case(
         json!({
             "@type" : "main-device",
@@ -1545,6 +1589,7 @@ mod tests {
                     external_id: None,
                     parent,
                     health_endpoint: None,
+                    process: None,
                     twin_data: Map::new(),
                 })
                 .unwrap();
@@ -1564,6 +1609,7 @@ mod tests {
                 topic_id: EntityTopicId::default_main_service("service1").unwrap(),
                 parent: None,
                 health_endpoint: None,
+                process: None,
                 twin_data: Map::new(),
             })
             .unwrap();
@@ -1584,6 +1630,7 @@ mod tests {
                 topic_id: EntityTopicId::default_main_service("service2").unwrap(),
                 parent: None,
                 health_endpoint: None,
+                process: None,
                 twin_data: Map::new(),
             })
             .unwrap();
@@ -1617,6 +1664,7 @@ mod tests {
                     external_id: None,
                     parent: Some(EntityTopicId::from_str("device/main//").unwrap()),
                     health_endpoint: None,
+                    process: None,
                     twin_data: json!({ "name": "child1" }).as_object().unwrap().to_owned(),
                 },
                 EntityRegistrationMessage {
@@ -1625,6 +1673,7 @@ mod tests {
                     external_id: None,
                     parent: Some(EntityTopicId::from_str("device/child1//").unwrap()),
                     health_endpoint: None,
+                    process: None,
                     twin_data: json!({ "name": "service1" })
                         .as_object()
                         .unwrap()
@@ -1650,6 +1699,7 @@ mod tests {
                 external_id: None,
                 parent: Some(EntityTopicId::from_str("device/main//").unwrap()),
                 health_endpoint: None,
+                process: None,
                 twin_data: json!({ "name": "child2" }).as_object().unwrap().to_owned(),
             },]
         );
@@ -1679,6 +1729,7 @@ mod tests {
                 external_id: None,
                 parent: None,
                 health_endpoint: None,
+                process: None,
                 twin_data: json!({}).as_object().unwrap().to_owned(),
             })
             .unwrap();
@@ -1689,6 +1740,7 @@ mod tests {
             r#type: EntityType::MainDevice,
             external_id: None,
             health_endpoint: None,
+            process: None,
             twin_data: Map::new(),
         };
         // Assert main device registered with custom topic scheme
@@ -1706,6 +1758,7 @@ mod tests {
                 external_id: None,
                 parent: Some(main_topic_id.clone()),
                 health_endpoint: None,
+                process: None,
                 twin_data: Map::new(),
             })
             .unwrap();
@@ -1716,6 +1769,7 @@ mod tests {
             r#type: EntityType::Service,
             external_id: None,
             health_endpoint: None,
+            process: None,
             twin_data: Map::new(),
         };
         // Assert service registered under main device with custom topic scheme
@@ -1827,6 +1881,7 @@ mod tests {
                 r#type: EntityType::MainDevice,
                 parent: None,
                 health_endpoint: None,
+                process: None,
                 twin_data: json!({ "name" : "test-name", "type": "test-type" })
                     .as_object()
                     .unwrap()
@@ -1854,6 +1909,7 @@ mod tests {
             r#type: EntityType::MainDevice,
             parent: None,
             health_endpoint: None,
+            process: None,
             twin_data: json!({ "name" : "new-test-device" })
                 .as_object()
                 .unwrap()
@@ -1891,6 +1947,7 @@ mod tests {
             external_id: Some("child1".into()),
             parent: None,
             health_endpoint: None,
+            process: None,
             twin_data: Map::new(),
         };
 
@@ -1920,6 +1977,7 @@ mod tests {
             external_id: Some("child1".into()),
             parent: None,
             health_endpoint: None,
+            process: None,
             twin_data: Map::new(),
         };
 
@@ -2368,6 +2426,7 @@ mod tests {
                 r#type: EntityType::MainDevice,
                 parent: None,
                 health_endpoint: None,
+                process: None,
                 twin_data: Map::new(),
             },
             0,
@@ -469,6 +469,7 @@ pub mod default_topic_schema {
                 r#type: EntityType::MainDevice,
                 parent: None,
                 health_endpoint: None,
+                process: None,
                 twin_data: Default::default(),
             }],
             ["device", child, "", ""] if !child.is_empty() => vec![EntityRegistrationMessage {
@@ -477,6 +478,7 @@ pub mod default_topic_schema {
                 r#type: EntityType::ChildDevice,
                 parent: Some(EntityTopicId::default_main_device()),
                 health_endpoint: None,
+                process: None,
                 twin_data: json!({ "name": child }).as_object().unwrap().to_owned(),
             }],
             ["device", device, "service", service] if !device.is_empty() && !service.is_empty() => {
@@ -491,6 +493,7 @@ pub mod default_topic_schema {
                     r#type: EntityType::Service,
                     parent: Some(device_topic_id),
                     health_endpoint: None,
+                    process: None,
                     twin_data: json!({ "name": service }).as_object().unwrap().to_owned(),
                 });
                 registrations
@@ -716,6 +719,7 @@ pub enum OperationType {
     FirmwareUpdate,
     Health,
     DeviceProfile,
+    DiagCollect,
     Custom(String),
 }
 
@@ -758,6 +762,7 @@ impl<'a> From<&'a str> for OperationType {
             "config_update" => OperationType::ConfigUpdate,
             "firmware_update" => OperationType::FirmwareUpdate,
             "device_profile" => OperationType::DeviceProfile,
+            "diag_collect" => OperationType::DiagCollect,
             operation => OperationType::Custom(operation.to_string()),
         }
     }
@@ -775,6 +780,7 @@ impl Display for OperationType {
             OperationType::FirmwareUpdate => write!(f, "firmware_update"),
             OperationType::Health => write!(f, "health"),
             OperationType::DeviceProfile => write!(f, "device_profile"),
+            OperationType::DiagCollect => write!(f, "diag_collect"),
             OperationType::Custom(operation) => write!(f, "{operation}"),
         }
     }
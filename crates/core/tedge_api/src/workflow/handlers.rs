@@ -15,6 +15,7 @@ pub struct ExitHandlers {
     on_exit: Vec<(u8, u8, GenericStateUpdate)>,
     on_stdout: Vec<String>,
     timeout: Option<Duration>,
+    retry: Option<RetryPolicy>,
 }
 
 impl ExitHandlers {
@@ -79,9 +80,36 @@ impl ExitHandlers {
             on_exit,
             on_stdout,
             timeout,
+            retry: None,
         })
     }
 
+    pub fn with_retry_policy(self, retry: Option<RetryPolicy>) -> Self {
+        ExitHandlers { retry, ..self }
+    }
+
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry.as_ref()
+    }
+
+    /// Return the delay to wait before running again a script that failed, if it has to be retried
+    ///
+    /// - `attempt` is the number of retries already done for the current state
+    /// - a script is retried only when it returned an exit code selected by the retry policy,
+    ///   i.e. launch errors and killed scripts are not retried
+    pub fn retry_delay(
+        &self,
+        attempt: u32,
+        outcome: &std::io::Result<std::process::Output>,
+    ) -> Option<Duration> {
+        let retry = self.retry.as_ref()?;
+        let code = outcome.as_ref().ok()?.status.code()?;
+        if code == 0 || attempt >= retry.max_retries || !retry.applies_to(code as u8) {
+            return None;
+        }
+        Some(retry.delay_before(attempt + 1))
+    }
+
     pub fn state_update(
         &self,
         program: &str,
@@ -190,6 +218,63 @@ impl ExitHandlers {
     }
 }
 
+/// Define how a script that failed has to be retried before moving to the error state
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// The maximum number of retries
+    pub max_retries: u32,
+
+    /// The delay before the first retry
+    pub delay: Duration,
+
+    /// The factor applied to the delay after each retry
+    pub backoff: u32,
+
+    /// The exit code ranges for which the script is retried (any error, if empty)
+    pub retry_on: Vec<(u8, u8)>,
+}
+
+impl RetryPolicy {
+    pub fn try_new(
+        max_retries: u32,
+        delay: Duration,
+        backoff: u32,
+        retry_on: Vec<(u8, u8)>,
+    ) -> Result<Self, ScriptDefinitionError> {
+        for (from, to) in retry_on.iter() {
+            if to < from {
+                return Err(ScriptDefinitionError::IncorrectRange {
+                    from: *from,
+                    to: *to,
+                });
+            }
+        }
+
+        Ok(RetryPolicy {
+            max_retries,
+            delay,
+            backoff: max(1, backoff),
+            retry_on,
+        })
+    }
+
+    /// Return true if a script returning this exit code has to be retried
+    pub fn applies_to(&self, code: u8) -> bool {
+        code != 0
+            && (self.retry_on.is_empty()
+                || self
+                    .retry_on
+                    .iter()
+                    .any(|(from, to)| *from <= code && code <= *to))
+    }
+
+    /// The delay to wait before the given retry (starting at 1)
+    pub fn delay_before(&self, retry: u32) -> Duration {
+        let factor = self.backoff.saturating_pow(retry.saturating_sub(1));
+        self.delay.saturating_mul(factor)
+    }
+}
+
 /// Extract the json output of a script outcome
 pub fn extract_json_output(
     program: &str,
@@ -764,6 +849,72 @@ timeout_second = 15
         );
     }
 
+    #[test]
+    fn failing_script_is_retried_with_backoff() {
+        let file = r#"
+script = "sh -c 'exit 3'"
+retries = 3
+retry_delay_second = 5
+retry_backoff = 2
+"#;
+        let (script, handlers) = script_from_toml(file);
+        let output = script.output();
+        assert_eq!(
+            handlers.retry_delay(0, &output),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            handlers.retry_delay(1, &output),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            handlers.retry_delay(2, &output),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(handlers.retry_delay(3, &output), None);
+    }
+
+    #[test]
+    fn only_selected_exit_codes_are_retried() {
+        let file = r#"
+script = "sh -c 'exit 3'"
+retries = 3
+retry_on = [1, "5-7"]
+"#;
+        let (script, handlers) = script_from_toml(file);
+        assert_eq!(handlers.retry_delay(0, &script.output()), None);
+
+        let file = r#"
+script = "sh -c 'exit 6'"
+retries = 3
+retry_on = [1, "5-7"]
+"#;
+        let (script, handlers) = script_from_toml(file);
+        assert_eq!(
+            handlers.retry_delay(0, &script.output()),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn successful_script_is_not_retried() {
+        let file = r#"
+script = "sh -c 'exit 0'"
+retries = 3
+"#;
+        let (script, handlers) = script_from_toml(file);
+        assert_eq!(handlers.retry_delay(0, &script.output()), None);
+    }
+
+    #[test]
+    fn scripts_are_not_retried_by_default() {
+        let file = r#"
+script = "sh -c 'exit 1'"
+"#;
+        let (script, handlers) = script_from_toml(file);
+        assert_eq!(handlers.retry_delay(0, &script.output()), None);
+    }
+
     impl ShellScript {
         pub fn output(&self) -> std::io::Result<std::process::Output> {
             Command::new(self.command.clone())
@@ -0,0 +1,221 @@
+use crate::substitution::Record;
+use crate::workflow::AwaitHandlers;
+use crate::workflow::GenericCommandState;
+use crate::workflow::StateExcerpt;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Value;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+const OP_RESPONSE_KEY: &str = "@response";
+
+/// An HTTP request sent by a workflow step
+///
+/// The url, the header values and the body are templates,
+/// into which values extracted from the command state are injected.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HttpRequestTemplate {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: HttpBody,
+}
+
+/// The HTTP methods supported by the workflow `http` action
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    #[default]
+    Get,
+    Post,
+    Put,
+    Delete,
+}
+
+/// The body of an HTTP request sent by a workflow step
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HttpBody {
+    /// No body
+    Empty,
+
+    /// A text body, e.g. `body = "id=${.payload.id}"`
+    Text(String),
+
+    /// A JSON body, e.g. `body = { id = "${.payload.id}" }`
+    Json(Value),
+}
+
+impl From<Option<Value>> for HttpBody {
+    fn from(value: Option<Value>) -> Self {
+        match value {
+            None | Some(Value::Null) => HttpBody::Empty,
+            Some(Value::String(text)) => HttpBody::Text(text),
+            Some(json) => HttpBody::Json(json),
+        }
+    }
+}
+
+impl HttpRequestTemplate {
+    /// Inject values extracted from the command state into this request
+    pub fn inject_values(&self, state: &GenericCommandState) -> Self {
+        let url = state.inject_values_into_template(&self.url);
+        let headers = self
+            .headers
+            .iter()
+            .map(|(key, value)| (key.clone(), state.inject_values_into_template(value)))
+            .collect();
+        let body = match &self.body {
+            HttpBody::Empty => HttpBody::Empty,
+            HttpBody::Text(text) => HttpBody::Text(state.inject_values_into_template(text)),
+            HttpBody::Json(json) => {
+                HttpBody::Json(StateExcerpt::from(json.clone()).extract_value_from(state))
+            }
+        };
+
+        HttpRequestTemplate {
+            method: self.method,
+            url,
+            headers,
+            body,
+        }
+    }
+}
+
+impl Display for HttpMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let method = match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Delete => "DELETE",
+        };
+        f.write_str(method)
+    }
+}
+
+impl Display for HttpRequestTemplate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.method, self.url)
+    }
+}
+
+impl GenericCommandState {
+    /// Update the command state with the response to an HTTP request
+    ///
+    /// The response status and body are captured into a `@response` fragment of the state payload,
+    /// the body being parsed as JSON if possible, and kept as a string otherwise.
+    /// The next state is `on_success` for a 2xx status, and `on_error` otherwise.
+    pub fn update_with_http_response(
+        self,
+        status: u16,
+        body: String,
+        handlers: &AwaitHandlers,
+    ) -> Self {
+        let body = serde_json::from_str(&body).unwrap_or(Value::String(body));
+        let new_state = self.update_with_json(json!({
+            OP_RESPONSE_KEY: {
+                "status": status,
+                "body": body,
+            }
+        }));
+
+        if (200..300).contains(&status) {
+            new_state.update(handlers.on_success.clone())
+        } else {
+            let mut on_error = handlers.on_error.clone();
+            if on_error.reason.is_none() {
+                on_error.reason = Some(format!("HTTP request failed with status {status}"));
+            }
+            new_state.update(on_error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::GenericStateUpdate;
+    use mqtt_channel::MqttMessage;
+    use mqtt_channel::Topic;
+
+    #[test]
+    fn inject_state_values_into_request() {
+        let state = command_state(r#"{"status":"fetch", "id":"1234", "name":"foo"}"#);
+        let request = HttpRequestTemplate {
+            method: HttpMethod::Put,
+            url: "/c8y/inventory/managedObjects/${.payload.id}".to_string(),
+            headers: vec![("X-Name".to_string(), "${.payload.name}".to_string())],
+            body: Some(json!({"name": "${.payload.name}", "type": "device"})).into(),
+        };
+
+        let request = request.inject_values(&state);
+        assert_eq!(request.url, "/c8y/inventory/managedObjects/1234");
+        assert_eq!(
+            request.headers,
+            vec![("X-Name".to_string(), "foo".to_string())]
+        );
+        assert_eq!(
+            request.body,
+            HttpBody::Json(json!({"name": "foo", "type": "device"}))
+        );
+    }
+
+    #[test]
+    fn successful_response_is_injected_into_next_state() {
+        let state = command_state(r#"{"status":"fetch"}"#);
+        let handlers = handlers();
+
+        let new_state =
+            state.update_with_http_response(200, r#"{"id":"5678"}"#.to_string(), &handlers);
+        assert_eq!(new_state.status, "fetched");
+        assert_eq!(
+            new_state.payload,
+            json!({
+                "status": "fetched",
+                "@response": {
+                    "status": 200,
+                    "body": {"id": "5678"}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn error_status_moves_to_error_state() {
+        let state = command_state(r#"{"status":"fetch"}"#);
+        let handlers = handlers();
+
+        let new_state = state.update_with_http_response(404, "Not Found".to_string(), &handlers);
+        assert_eq!(new_state.status, "failed");
+        assert_eq!(
+            new_state.payload,
+            json!({
+                "status": "failed",
+                "reason": "HTTP request failed with status 404",
+                "@response": {
+                    "status": 404,
+                    "body": "Not Found"
+                }
+            })
+        );
+    }
+
+    fn command_state(payload: &str) -> GenericCommandState {
+        let topic = Topic::new_unchecked("te/device/main///cmd/fetch_it/123");
+        let message = MqttMessage::new(&topic, payload);
+        GenericCommandState::from_command_message(&message).unwrap()
+    }
+
+    fn handlers() -> AwaitHandlers {
+        AwaitHandlers {
+            timeout: None,
+            on_success: "fetched".into(),
+            on_error: GenericStateUpdate {
+                status: "failed".to_string(),
+                reason: None,
+            },
+            on_timeout: GenericStateUpdate::timeout(),
+        }
+    }
+}
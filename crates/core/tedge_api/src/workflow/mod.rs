@@ -1,5 +1,6 @@
 pub mod error;
 pub mod handlers;
+pub mod http;
 pub(crate) mod log;
 mod on_disk;
 pub mod state;
@@ -14,6 +15,7 @@ use crate::substitution::Record;
 use ::log::info;
 pub use error::*;
 pub use handlers::*;
+pub use http::*;
 use mqtt_channel::MqttMessage;
 use mqtt_channel::QoS;
 use serde::Deserialize;
@@ -93,6 +95,20 @@ pub enum OperationAction {
     /// A script has to be executed
     Script(ShellScript, ExitHandlers),
 
+    /// Send an HTTP request and move to the next state depending on the response status
+    ///
+    /// The response status and body are captured into a `@response` fragment of the state payload.
+    ///
+    /// ```toml
+    /// http.method = "POST"
+    /// http.url = "/c8y/event/events"
+    /// http.headers.Accept = "application/json"
+    /// http.body = { type = "te_event", text = "${.payload.text}" }
+    /// on_success = "<state>"
+    /// on_error = "<state>"
+    /// ```
+    Http(HttpRequestTemplate, AwaitHandlers),
+
     /// Executes a script but move to the next state without waiting for that script to return
     ///
     /// Notably such a script can trigger a device reboot or an agent restart.
@@ -163,6 +179,7 @@ impl Display for OperationAction {
             OperationAction::AwaitingAgentRestart { .. } => "await agent restart".to_string(),
             OperationAction::Script(script, _) => script.to_string(),
             OperationAction::BgScript(script, _) => script.to_string(),
+            OperationAction::Http(request, _) => format!("send HTTP request {request}"),
             OperationAction::Operation(operation, maybe_script, _, _) => match maybe_script {
                 None => format!("execute {operation} as sub-operation"),
                 Some(script) => format!(
@@ -314,6 +331,7 @@ impl OperationWorkflow {
             OperationType::Custom(_)
             | OperationType::Restart
             | OperationType::DeviceProfile
+            | OperationType::DiagCollect
             | OperationType::FirmwareUpdate => {
                 let meta_topic = schema.capability_topic_for(target, self.operation.clone());
                 let payload = "{}".to_string();
@@ -358,6 +376,9 @@ impl OperationAction {
             OperationAction::BgScript(script, handlers) => {
                 OperationAction::BgScript(script.inject_values(state), handlers.clone())
             }
+            OperationAction::Http(request, handlers) => {
+                OperationAction::Http(request.inject_values(state), handlers.clone())
+            }
             OperationAction::Operation(operation_expr, optional_script, input, handlers) => {
                 let operation = state.inject_values_into_template(operation_expr);
                 let optional_script = optional_script
@@ -23,6 +23,7 @@ use std::fmt::Display;
 
 const OP_LOG_PATH_KEY: &str = "logPath";
 const OP_WORKFLOW_VERSION_KEY: &str = "@version";
+const OP_RETRY_KEY: &str = "@retry";
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum GenericCommandData {
@@ -231,13 +232,47 @@ impl GenericCommandState {
         self.set_key_value(OP_WORKFLOW_VERSION_KEY, version)
     }
 
+    /// The number of times the action of the current state has been retried
+    ///
+    /// The counter is attached to a state, and removed once the script of this state is no more retried.
+    pub fn retry_attempt(&self) -> u32 {
+        self.payload
+            .get(OP_RETRY_KEY)
+            .filter(|retry| {
+                retry.get("state").and_then(|s| s.as_str()) == Some(self.status.as_str())
+            })
+            .and_then(|retry| retry.get("attempt"))
+            .and_then(|attempt| attempt.as_u64())
+            .unwrap_or_default() as u32
+    }
+
+    /// Record that the action of the current state is retried for the given attempt
+    pub fn with_retry_attempt(mut self, attempt: u32) -> Self {
+        if let Some(o) = self.payload.as_object_mut() {
+            o.insert(
+                OP_RETRY_KEY.to_string(),
+                json!({
+                    "state": self.status,
+                    "attempt": attempt,
+                }),
+            );
+        }
+        self
+    }
+
     /// Update the command state with the outcome of a script
+    ///
+    /// The script being no more retried, the retry counter is removed,
+    /// so a later loop back to the same state starts with a fresh counter.
     pub fn update_with_script_output(
-        self,
+        mut self,
         script: String,
         output: std::io::Result<std::process::Output>,
         handlers: ExitHandlers,
     ) -> Self {
+        if let Some(o) = self.payload.as_object_mut() {
+            o.remove(OP_RETRY_KEY);
+        }
         let json_update = handlers.state_update(&script, output);
         self.update_with_json(json_update)
     }
@@ -722,6 +757,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn the_retry_counter_is_removed_when_the_script_completes() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let topic = Topic::new_unchecked("te/device/main///cmd/make_it/123");
+        let state = GenericCommandState::new(topic, "download".to_string(), json!({}))
+            .with_retry_attempt(2);
+        assert_eq!(state.retry_attempt(), 2);
+
+        let output = std::process::Output {
+            status: std::process::ExitStatus::from_raw(0),
+            stdout: b":::begin-tedge:::\n{\"status\":\"install\"}\n:::end-tedge:::\n".to_vec(),
+            stderr: vec![],
+        };
+        let state = state.update_with_script_output(
+            "download.sh".to_string(),
+            Ok(output),
+            ExitHandlers::default(),
+        );
+        assert_eq!(state.status, "install");
+        assert_eq!(state.payload.get(OP_RETRY_KEY), None);
+        assert_eq!(state.move_to("download".into()).retry_attempt(), 0);
+    }
+
     #[test]
     fn retrieve_invoking_command() {
         let topic = Topic::new_unchecked("te/device/main///cmd/do_it/sub:make_it:456");
@@ -7,9 +7,12 @@ use crate::workflow::ExecHandlers;
 use crate::workflow::ExitHandlers;
 use crate::workflow::GenericCommandState;
 use crate::workflow::GenericStateUpdate;
+use crate::workflow::HttpMethod;
+use crate::workflow::HttpRequestTemplate;
 use crate::workflow::IterateHandlers;
 use crate::workflow::OperationAction;
 use crate::workflow::OperationWorkflow;
+use crate::workflow::RetryPolicy;
 use crate::workflow::ScriptDefinitionError;
 use crate::workflow::WorkflowDefinitionError;
 use serde::de::Error;
@@ -18,6 +21,7 @@ use serde::Deserializer;
 use serde::Serialize;
 use serde::Serializer;
 use serde_json::Value;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::fmt::Formatter;
@@ -65,6 +69,45 @@ pub struct TomlOperationState {
     /// Values to be extracted from the sub-operation final state
     #[serde(default)]
     pub output: Option<Value>,
+
+    /// Number of times a failing script is retried before moving to the error state
+    #[serde(default)]
+    pub retries: Option<u32>,
+
+    /// Delay before the first retry of a failing script
+    #[serde(default)]
+    pub retry_delay_second: Option<u64>,
+
+    /// Factor by which the retry delay is multiplied after each attempt
+    #[serde(default)]
+    pub retry_backoff: Option<u32>,
+
+    /// Exit codes for which a failing script is retried (any non-zero exit code, if not provided)
+    #[serde(default)]
+    pub retry_on: Vec<ExitCodes>,
+}
+
+impl TomlOperationState {
+    fn retry_policy(&self) -> Result<Option<RetryPolicy>, ScriptDefinitionError> {
+        let Some(max_retries) = self.retries else {
+            return Ok(None);
+        };
+        let delay = Duration::from_secs(self.retry_delay_second.unwrap_or_default());
+        let backoff = self.retry_backoff.unwrap_or(1);
+        let mut retry_on = Vec::new();
+        for codes in self.retry_on.iter() {
+            match codes {
+                ExitCodes::Code(x) => retry_on.push((*x, *x)),
+                ExitCodes::Range { from, to } => retry_on.push((*from, *to)),
+                ExitCodes::AnyError => {
+                    retry_on.clear();
+                    break;
+                }
+            }
+        }
+
+        RetryPolicy::try_new(max_retries, delay, backoff, retry_on).map(Some)
+    }
 }
 
 /// User-friendly representation of an [OperationAction]
@@ -76,6 +119,37 @@ pub enum TomlOperationAction {
     Action(String),
     Operation(String),
     Iterate(String),
+    Http(TomlHttpRequest),
+}
+
+/// User-friendly representation of an [HttpRequestTemplate]
+#[derive(Clone, Debug, Deserialize)]
+pub struct TomlHttpRequest {
+    /// The HTTP method, GET by default
+    #[serde(default)]
+    pub method: HttpMethod,
+
+    /// The target url, possibly relative to the local thin-edge or Cumulocity proxy endpoints
+    pub url: String,
+
+    /// The HTTP headers
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// The request body, either a text or a JSON object
+    #[serde(default)]
+    pub body: Option<Value>,
+}
+
+impl From<TomlHttpRequest> for HttpRequestTemplate {
+    fn from(value: TomlHttpRequest) -> Self {
+        HttpRequestTemplate {
+            method: value.method,
+            url: value.url,
+            headers: value.headers.into_iter().collect(),
+            body: value.body.into(),
+        }
+    }
 }
 
 impl Default for TomlOperationAction {
@@ -125,9 +199,21 @@ impl TryFrom<(TomlOperationState, DefaultHandlers)> for OperationAction {
     ) -> Result<Self, Self::Error> {
         match input.action {
             TomlOperationAction::Script(script) => {
-                let handlers = ExitHandlers::try_from((input.handlers, defaults))?;
+                let retry_policy = input.retry_policy()?;
+                let handlers = ExitHandlers::try_from((input.handlers, defaults))?
+                    .with_retry_policy(retry_policy);
                 Ok(OperationAction::Script(script, handlers))
             }
+            TomlOperationAction::Http(request) => {
+                let http_defaults = AwaitHandlers {
+                    timeout: defaults.timeout,
+                    on_success: GenericStateUpdate::successful(),
+                    on_error: defaults.on_error,
+                    on_timeout: defaults.on_timeout,
+                };
+                let handlers = AwaitHandlers::try_from((input.handlers, http_defaults))?;
+                Ok(OperationAction::Http(request.into(), handlers))
+            }
             TomlOperationAction::BackgroundScript(script) => {
                 let handlers = ExecHandlers::try_from((input.handlers, defaults))?;
                 Ok(OperationAction::BgScript(script, handlers))
@@ -449,10 +535,19 @@ impl<'de> Deserialize<'de> for ExitCodes {
     where
         D: Deserializer<'de>,
     {
-        let exit_code = String::deserialize(deserializer)?;
-        exit_code
-            .parse()
-            .map_err(|err| D::Error::custom(format!("invalid exit: {exit_code}: {err}")))
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawExitCodes {
+            Code(u8),
+            Text(String),
+        }
+
+        match RawExitCodes::deserialize(deserializer)? {
+            RawExitCodes::Code(code) => Ok(ExitCodes::Code(code)),
+            RawExitCodes::Text(exit_code) => exit_code
+                .parse()
+                .map_err(|err| D::Error::custom(format!("invalid exit: {exit_code}: {err}"))),
+        }
     }
 }
 
@@ -501,6 +596,7 @@ impl FromStr for ExitCodes {
 mod tests {
     use super::*;
     use crate::workflow::GenericStateUpdate;
+    use crate::workflow::HttpBody;
     use assert_matches::assert_matches;
     use ExitCodes::*;
 
@@ -732,6 +828,121 @@ script = "/some/script/which/fails"
         )
     }
 
+    #[test]
+    fn parse_script_retry_policy() {
+        let file = r#"
+operation = "download"
+
+[init]
+action = "proceed"
+on_success = "fetch"
+
+[fetch]
+script = "/usr/bin/fetch.sh ${.payload.url}"
+retries = 5
+retry_delay_second = 10
+retry_backoff = 2
+retry_on = [1, "6-7"]
+on_success = "successful"
+on_error = "failed"
+
+[successful]
+action = "cleanup"
+
+[failed]
+action = "cleanup"
+"#;
+        let input: TomlOperationWorkflow = toml::from_str(file).unwrap();
+        let workflow = OperationWorkflow::try_from(input).unwrap();
+
+        match workflow.states.get("fetch").unwrap() {
+            OperationAction::Script(_, handlers) => {
+                assert_eq!(
+                    handlers.retry_policy(),
+                    Some(&RetryPolicy {
+                        max_retries: 5,
+                        delay: Duration::from_secs(10),
+                        backoff: 2,
+                        retry_on: vec![(1, 1), (6, 7)],
+                    })
+                );
+            }
+            other => panic!("Expected script action, but got {other}"),
+        }
+    }
+
+    #[test]
+    fn reject_ill_defined_retry_range() {
+        let file = r#"
+script = "/usr/bin/fetch.sh"
+retries = 5
+retry_on = ["7-6"]
+"#;
+        let input: TomlOperationState = toml::from_str(file).unwrap();
+        let error = OperationAction::try_from(input).unwrap_err();
+        assert_eq!(
+            error,
+            WorkflowDefinitionError::ScriptDefinitionError(ScriptDefinitionError::IncorrectRange {
+                from: 7,
+                to: 6
+            })
+        );
+    }
+
+    #[test]
+    fn parse_http_toml() {
+        let file = r#"
+operation = "custom_operation"
+timeout_second = 60
+
+[init]
+http.method = "POST"
+http.url = "/c8y/event/events"
+http.headers.Accept = "application/json"
+http.body = { type = "custom_event", text = "${.payload.text}" }
+on_success = "successful"
+
+[successful]
+action = "cleanup"
+
+[failed]
+action = "cleanup"
+"#;
+        let input: TomlOperationWorkflow = toml::from_str(file).unwrap();
+        let workflow = OperationWorkflow::try_from(input).unwrap();
+
+        match workflow.states.get("init").unwrap() {
+            OperationAction::Http(request, handlers) => {
+                assert_eq!(request.method, HttpMethod::Post);
+                assert_eq!(request.url, "/c8y/event/events");
+                assert_eq!(
+                    request.headers,
+                    vec![("Accept".to_string(), "application/json".to_string())]
+                );
+                assert_eq!(
+                    request.body,
+                    HttpBody::from(Some(serde_json::json!({
+                        "type": "custom_event",
+                        "text": "${.payload.text}"
+                    })))
+                );
+                assert_eq!(handlers.timeout, Some(Duration::from_secs(60)));
+                assert_eq!(handlers.on_success, "successful".into());
+                assert_eq!(handlers.on_error, GenericStateUpdate::unknown_error());
+            }
+            other => panic!("Expected http action, but got {other}"),
+        }
+    }
+
+    #[test]
+    fn parse_fails_on_unknown_http_method() {
+        let file = r#"
+http.method = "PATCH"
+http.url = "/c8y/event/events"
+"#;
+        assert!(toml::from_str::<TomlOperationState>(file).is_err());
+    }
+
     #[test]
     fn parse_iterate_toml() {
         let file = r#"
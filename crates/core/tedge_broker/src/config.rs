@@ -0,0 +1,305 @@
+use crate::error::BrokerError;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use rumqttd::ConnectionSettings;
+use rumqttd::RouterConfig;
+use rumqttd::ServerSettings;
+use rumqttd::TlsConfig;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use tedge_config::TEdgeConfig;
+use tracing::warn;
+
+/// The maximum size of an MQTT message, as for the mosquitto configuration generated by `tedge`
+pub(crate) const MAX_PAYLOAD_SIZE: usize = 256 * 1024 * 1024 - 1;
+
+/// The sub-directory of `data.path` where the broker stores its state
+const BROKER_DATA_DIR: &str = "broker";
+
+/// The file used to bundle the CA certificates of a `mqtt.external.ca_path` directory
+const CA_BUNDLE_FILE: &str = "ca-certificates.pem";
+
+/// The listeners of the embedded broker, as configured by the `mqtt.bind` and `mqtt.external` settings
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BrokerConfig {
+    /// The listener used by the thin-edge components, without any authentication
+    pub internal: SocketAddr,
+
+    /// The listener opened to the other devices and the local processes, if any
+    pub external: Option<ExternalListener>,
+
+    /// The directory where the broker persists the retained messages
+    pub data_dir: Utf8PathBuf,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExternalListener {
+    pub listen: SocketAddr,
+    pub tls: Option<ListenerTls>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ListenerTls {
+    pub cert_file: Utf8PathBuf,
+    pub key_file: Utf8PathBuf,
+
+    /// The CA certificates trusted to authenticate the clients, if client authentication is required
+    ///
+    /// This is either a PEM file or a directory of PEM files, as for mosquitto's `capath`.
+    pub ca_path: Option<Utf8PathBuf>,
+}
+
+impl BrokerConfig {
+    pub fn from_tedge_config(config: &TEdgeConfig) -> Result<Self, BrokerError> {
+        let internal = SocketAddr::new(config.mqtt.bind.address, config.mqtt.bind.port.into());
+        let data_dir = config.data.path.join(BROKER_DATA_DIR);
+
+        let external_config = &config.mqtt.external;
+        let Some(port) = external_config.bind.port.or_none().cloned() else {
+            return Ok(BrokerConfig {
+                internal,
+                external: None,
+                data_dir,
+            });
+        };
+
+        if let Some(interface) = external_config.bind.interface.or_none() {
+            warn!("Ignoring mqtt.external.bind.interface = {interface:?}: not supported by the embedded broker");
+        }
+
+        // As mosquitto, listen on all the interfaces when no address is given
+        let address = external_config
+            .bind
+            .address
+            .or_none()
+            .cloned()
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+        let cert_file = external_config.cert_file.or_none().cloned();
+        let key_file = external_config.key_file.or_none().cloned();
+        let ca_path = external_config.ca_path.or_none().cloned();
+        let tls = match (cert_file, key_file, ca_path) {
+            (Some(cert_file), Some(key_file), ca_path) => Some(ListenerTls {
+                cert_file: cert_file.into(),
+                key_file: key_file.into(),
+                ca_path: ca_path.map(Utf8PathBuf::from),
+            }),
+            (None, None, None) => None,
+            (None, None, Some(_)) => {
+                return Err(BrokerError::IncompleteTlsConfig(
+                    "mqtt.external.ca_path is set but not mqtt.external.cert_file and mqtt.external.key_file",
+                ))
+            }
+            (Some(_), None, _) => {
+                return Err(BrokerError::IncompleteTlsConfig(
+                    "mqtt.external.cert_file is set but not mqtt.external.key_file",
+                ))
+            }
+            (None, Some(_), _) => {
+                return Err(BrokerError::IncompleteTlsConfig(
+                    "mqtt.external.key_file is set but not mqtt.external.cert_file",
+                ))
+            }
+        };
+
+        Ok(BrokerConfig {
+            internal,
+            external: Some(ExternalListener {
+                listen: SocketAddr::new(address, port),
+                tls,
+            }),
+            data_dir,
+        })
+    }
+
+    /// The address to be used by the broker itself to connect the internal listener
+    pub fn internal_client_address(&self) -> SocketAddr {
+        let mut address = self.internal;
+        if address.ip().is_unspecified() {
+            address.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        }
+        address
+    }
+
+    /// The file where the retained messages are persisted
+    pub fn retained_messages_file(&self) -> Utf8PathBuf {
+        self.data_dir.join("retained-messages.json")
+    }
+
+    /// Build the rumqttd configuration
+    ///
+    /// If the trusted CA certificates are given as a directory,
+    /// these certificates are bundled into a single file under the broker data directory.
+    pub fn rumqttd_config(&self) -> Result<rumqttd::Config, BrokerError> {
+        let mut servers = HashMap::new();
+        servers.insert(
+            "internal".to_string(),
+            server_settings("internal", self.internal, None),
+        );
+
+        if let Some(external) = &self.external {
+            let tls = match &external.tls {
+                None => None,
+                Some(tls) => {
+                    let capath = match &tls.ca_path {
+                        None => None,
+                        Some(ca_path) => Some(ca_file(ca_path, &self.data_dir)?.to_string()),
+                    };
+                    Some(TlsConfig::Rustls {
+                        capath,
+                        certpath: tls.cert_file.to_string(),
+                        keypath: tls.key_file.to_string(),
+                    })
+                }
+            };
+            servers.insert(
+                "external".to_string(),
+                server_settings("external", external.listen, tls),
+            );
+        }
+
+        let router = RouterConfig {
+            max_segment_size: 100 * 1024 * 1024,
+            max_segment_count: 10,
+            max_connections: 1000,
+            initialized_filters: None,
+            ..Default::default()
+        };
+
+        Ok(rumqttd::Config {
+            id: 0,
+            router,
+            cluster: None,
+            console: None,
+            v4: Some(servers),
+            ws: None,
+            v5: None,
+            bridge: None,
+            prometheus: None,
+            metrics: None,
+        })
+    }
+}
+
+fn server_settings(name: &str, listen: SocketAddr, tls: Option<TlsConfig>) -> ServerSettings {
+    ServerSettings {
+        name: name.to_string(),
+        listen,
+        tls,
+        next_connection_delay_ms: 1,
+        connections: ConnectionSettings {
+            connection_timeout_ms: 5000,
+            max_payload_size: MAX_PAYLOAD_SIZE,
+            max_inflight_count: 200,
+            auth: None,
+            dynamic_filters: true,
+            external_auth: None,
+        },
+    }
+}
+
+/// The PEM file of the CA certificates trusted to authenticate the clients
+///
+/// rumqttd expects a single file, while `mqtt.external.ca_path` can be a directory,
+/// in which case the `.pem` and `.crt` files of the directory are bundled together.
+fn ca_file(ca_path: &Utf8Path, data_dir: &Utf8Path) -> Result<Utf8PathBuf, BrokerError> {
+    if !ca_path.is_dir() {
+        return Ok(ca_path.to_owned());
+    }
+
+    let mut certificates = Vec::new();
+    let mut entries = ca_path
+        .read_dir_utf8()
+        .map_err(|err| BrokerError::CaCertificates(ca_path.to_owned(), err))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| matches!(path.extension(), Some("pem" | "crt")))
+        .collect::<Vec<_>>();
+    entries.sort();
+    for path in entries {
+        let certificate = std::fs::read_to_string(&path)
+            .map_err(|err| BrokerError::CaCertificates(path.clone(), err))?;
+        certificates.push(certificate);
+    }
+
+    let bundle = data_dir.join(CA_BUNDLE_FILE);
+    std::fs::create_dir_all(data_dir)
+        .and_then(|()| std::fs::write(&bundle, certificates.join("\n")))
+        .map_err(|err| BrokerError::CaCertificates(bundle.clone(), err))?;
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[test]
+    fn only_the_internal_listener_is_opened_by_default() {
+        let config = TEdgeConfig::load_toml_str("");
+        let broker = BrokerConfig::from_tedge_config(&config).unwrap();
+
+        assert_eq!(broker.internal, "127.0.0.1:1883".parse().unwrap());
+        assert_eq!(broker.external, None);
+        assert_eq!(broker.data_dir, "/var/tedge/broker");
+    }
+
+    #[test]
+    fn external_listener_requires_client_certificates_when_a_ca_is_given() {
+        let config = TEdgeConfig::load_toml_str(
+            r#"
+            [mqtt.external]
+            bind.port = 8883
+            ca_path = "/etc/ssl/certs/ca.pem"
+            cert_file = "/etc/tedge/device-certs/tedge-certificate.pem"
+            key_file = "/etc/tedge/device-certs/tedge-private-key.pem"
+            "#,
+        );
+        let broker = BrokerConfig::from_tedge_config(&config).unwrap();
+
+        assert_eq!(
+            broker.external,
+            Some(ExternalListener {
+                listen: "0.0.0.0:8883".parse().unwrap(),
+                tls: Some(ListenerTls {
+                    cert_file: "/etc/tedge/device-certs/tedge-certificate.pem".into(),
+                    key_file: "/etc/tedge/device-certs/tedge-private-key.pem".into(),
+                    ca_path: Some("/etc/ssl/certs/ca.pem".into()),
+                })
+            })
+        );
+    }
+
+    #[test]
+    fn a_certificate_without_private_key_is_rejected() {
+        let config = TEdgeConfig::load_toml_str(
+            r#"
+            [mqtt.external]
+            bind.port = 8883
+            cert_file = "/etc/tedge/device-certs/tedge-certificate.pem"
+            "#,
+        );
+
+        assert!(matches!(
+            BrokerConfig::from_tedge_config(&config),
+            Err(BrokerError::IncompleteTlsConfig(_))
+        ));
+    }
+
+    #[test]
+    fn ca_directories_are_bundled_into_a_single_file() {
+        let ttd = TempTedgeDir::new();
+        let certs = ttd.dir("certs");
+        certs.file("a.pem").with_raw_content("CERT A");
+        certs.file("b.crt").with_raw_content("CERT B");
+        certs.file("5a1f2c3b.0").with_raw_content("CERT A");
+        let data_dir = ttd.utf8_path().join("broker");
+
+        let bundle = ca_file(certs.utf8_path(), &data_dir).unwrap();
+
+        assert_eq!(bundle, data_dir.join(CA_BUNDLE_FILE));
+        assert_eq!(std::fs::read_to_string(bundle).unwrap(), "CERT A\nCERT B");
+    }
+}
@@ -0,0 +1,16 @@
+use camino::Utf8PathBuf;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BrokerError {
+    #[error("Invalid external listener configuration: {0}")]
+    IncompleteTlsConfig(&'static str),
+
+    #[error("Fail to read the CA certificates from {0}: {1}")]
+    CaCertificates(Utf8PathBuf, std::io::Error),
+
+    #[error("Fail to persist the retained messages into {0}: {1}")]
+    PersistRetainedMessages(Utf8PathBuf, String),
+
+    #[error("The MQTT broker failed: {0}")]
+    BrokerFailure(String),
+}
@@ -0,0 +1,138 @@
+//! An MQTT broker embedded into thin-edge.io, built on rumqttd
+//!
+//! This broker is an alternative to mosquitto for minimal and container deployments.
+//! It opens the same listeners as the mosquitto configuration generated by `tedge`:
+//! - an internal listener on `mqtt.bind.address` and `mqtt.bind.port`, without authentication,
+//! - an external listener, if `mqtt.external.bind.port` is set, using TLS if a certificate is given
+//!   and requiring client certificates if `mqtt.external.ca_path` is set.
+//!
+//! The retained messages are persisted under `data.path`, so they survive a restart of the broker.
+mod config;
+mod error;
+mod retained;
+
+pub use config::BrokerConfig;
+pub use error::BrokerError;
+
+use camino::Utf8Path;
+use config::MAX_PAYLOAD_SIZE;
+use mqtt_channel::MqttMessage;
+use std::time::Duration;
+use tedge_config::cli::CommonArgs;
+use tedge_config::log_init;
+use tedge_config::TEdgeConfig;
+use tokio::signal::unix::signal;
+use tokio::signal::unix::SignalKind;
+use tokio::sync::oneshot;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+/// Interval at which the retained messages are persisted
+///
+/// The retained messages are also persisted on SIGTERM and SIGINT, but those changed
+/// within the last interval are lost if the broker is killed or the device loses power.
+/// The file is only rewritten when the retained messages have changed.
+const PERSISTENCE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, clap::Parser)]
+#[clap(
+name = clap::crate_name!(),
+version = clap::crate_version!(),
+about = clap::crate_description!()
+)]
+pub struct BrokerOpt {
+    #[command(flatten)]
+    pub common: CommonArgs,
+}
+
+pub async fn run(broker_opt: BrokerOpt) -> Result<(), anyhow::Error> {
+    log_init(
+        "tedge-broker",
+        &broker_opt.common.log_args,
+        &broker_opt.common.config_dir,
+    )?;
+
+    let tedge_config = TEdgeConfig::load(&broker_opt.common.config_dir).await?;
+    let config = BrokerConfig::from_tedge_config(&tedge_config)?;
+    let mut broker = rumqttd::Broker::new(config.rumqttd_config()?);
+
+    // The broker runs its own threads and never returns unless it fails to start.
+    // A plain thread is used, so the process doesn't wait for the broker on shutdown.
+    let (broker_stopped, mut broker_result) = oneshot::channel();
+    std::thread::spawn(move || {
+        let _ = broker_stopped.send(broker.start().map_err(|err| err.to_string()));
+    });
+
+    let address = config.internal_client_address();
+    let mqtt_config = mqtt_channel::Config::default()
+        .with_host(address.ip().to_string())
+        .with_port(address.port())
+        .with_max_packet_size(MAX_PAYLOAD_SIZE);
+
+    let retained_messages_file = config.retained_messages_file();
+    let mut retained_messages = retained::load(&retained_messages_file).await;
+    info!(
+        "Restoring {} retained messages from {retained_messages_file}",
+        retained_messages.len()
+    );
+    tokio::select! {
+        result = &mut broker_result => return broker_stopped_error(result),
+        result = retained::restore(&mqtt_config, retained_messages.clone()) => result?,
+    }
+
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut persistence_interval = tokio::time::interval(PERSISTENCE_INTERVAL);
+    persistence_interval.tick().await;
+    loop {
+        tokio::select! {
+            result = &mut broker_result => return broker_stopped_error(result),
+            _ = persistence_interval.tick() => {
+                persist_retained_messages(&mqtt_config, &retained_messages_file, &mut retained_messages).await;
+            }
+            _ = sigterm.recv() => break,
+            _ = sigint.recv() => break,
+        }
+    }
+
+    info!("Persisting the retained messages before shutdown");
+    persist_retained_messages(
+        &mqtt_config,
+        &retained_messages_file,
+        &mut retained_messages,
+    )
+    .await;
+    Ok(())
+}
+
+async fn persist_retained_messages(
+    mqtt_config: &mqtt_channel::Config,
+    path: &Utf8Path,
+    persisted: &mut Vec<MqttMessage>,
+) {
+    let retained_messages = match retained::snapshot(mqtt_config).await {
+        Ok(messages) => messages,
+        Err(err) => {
+            warn!("Fail to collect the retained messages: {err}");
+            return;
+        }
+    };
+    if retained_messages == *persisted {
+        return;
+    }
+    match retained::save(path, &retained_messages).await {
+        Ok(()) => *persisted = retained_messages,
+        Err(err) => error!("{err}"),
+    }
+}
+
+fn broker_stopped_error(
+    result: Result<Result<(), String>, oneshot::error::RecvError>,
+) -> Result<(), anyhow::Error> {
+    let reason = match result {
+        Ok(Err(err)) => err,
+        _ => "stopped unexpectedly".to_string(),
+    };
+    Err(BrokerError::BrokerFailure(reason).into())
+}
@@ -0,0 +1,140 @@
+//! Persistence of the retained messages
+//!
+//! rumqttd keeps the retained messages in memory only. To survive a restart,
+//! these messages are periodically saved into a file under `data.path`
+//! and published again when the broker starts.
+//!
+//! The current set of retained messages is captured by a short-lived client
+//! subscribing to all the topics: on subscription, the broker sends the retained messages
+//! with the retain flag set, while the messages published live are forwarded with the flag cleared.
+use crate::error::BrokerError;
+use camino::Utf8Path;
+use mqtt_channel::Connection;
+use mqtt_channel::MqttError;
+use mqtt_channel::MqttMessage;
+use mqtt_channel::SinkExt;
+use mqtt_channel::StreamExt;
+use mqtt_channel::TopicFilter;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tedge_utils::fs::atomically_write_file_async;
+use tokio::time::Instant;
+use tracing::warn;
+
+/// How long to wait for a new retained message before considering all of them have been received
+const IDLE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Maximum time spent to collect the retained messages, even if live messages keep flowing
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Load the retained messages persisted by a previous run
+///
+/// A missing or unreadable file is not an error, the broker simply starts without retained messages.
+pub async fn load(path: &Utf8Path) -> Vec<MqttMessage> {
+    let content = match tokio::fs::read(path).await {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return vec![],
+        Err(err) => {
+            warn!("Fail to read the retained messages from {path}: {err}");
+            return vec![];
+        }
+    };
+    serde_json::from_slice(&content).unwrap_or_else(|err| {
+        warn!("Ignoring the retained messages persisted in {path}: {err}");
+        vec![]
+    })
+}
+
+/// Persist the retained messages, replacing any previous content
+pub async fn save(path: &Utf8Path, messages: &[MqttMessage]) -> Result<(), BrokerError> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await.map_err(|err| {
+            BrokerError::PersistRetainedMessages(path.to_owned(), err.to_string())
+        })?;
+    }
+    // MqttMessage serialization cannot fail
+    let content = serde_json::to_vec(messages).unwrap_or_default();
+    atomically_write_file_async(path, &content)
+        .await
+        .map_err(|err| BrokerError::PersistRetainedMessages(path.to_owned(), err.to_string()))
+}
+
+/// Collect the messages currently retained by the broker, sorted by topic
+pub async fn snapshot(mqtt_config: &mqtt_channel::Config) -> Result<Vec<MqttMessage>, MqttError> {
+    let mqtt_config = mqtt_config
+        .clone()
+        .with_no_session()
+        .with_subscriptions(TopicFilter::new_unchecked("#"));
+    let mut connection = Connection::new(&mqtt_config).await?;
+
+    let deadline = Instant::now() + SNAPSHOT_TIMEOUT;
+    let mut retained = BTreeMap::new();
+    while let Ok(Some(message)) =
+        tokio::time::timeout(IDLE_TIMEOUT, connection.received.next()).await
+    {
+        if message.retain {
+            retained.insert(message.topic.name.clone(), message);
+        }
+        if Instant::now() > deadline {
+            warn!("Incomplete snapshot of the retained messages: too many messages are published");
+            break;
+        }
+    }
+
+    connection.close().await;
+    Ok(retained.into_values().collect())
+}
+
+/// Publish again the retained messages persisted by a previous run
+pub async fn restore(
+    mqtt_config: &mqtt_channel::Config,
+    messages: Vec<MqttMessage>,
+) -> Result<(), MqttError> {
+    if messages.is_empty() {
+        return Ok(());
+    }
+
+    let mut connection = Connection::new(&mqtt_config.clone().with_no_session()).await?;
+    for message in messages {
+        connection.published.send(message.with_retain()).await?;
+    }
+    connection.close().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mqtt_channel::Topic;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[tokio::test]
+    async fn persisted_messages_are_loaded_back() {
+        let ttd = TempTedgeDir::new();
+        let path = ttd.utf8_path().join("broker/retained-messages.json");
+        let messages = vec![
+            MqttMessage::new(
+                &Topic::new_unchecked("te/device/main//"),
+                r#"{"@type":"device"}"#,
+            )
+            .with_retain(),
+            MqttMessage::new(&Topic::new_unchecked("te/device/main///twin/os"), "linux")
+                .with_retain(),
+        ];
+
+        save(&path, &messages).await.unwrap();
+
+        assert_eq!(load(&path).await, messages);
+    }
+
+    #[tokio::test]
+    async fn missing_or_corrupted_files_are_ignored() {
+        let ttd = TempTedgeDir::new();
+        let path = ttd.utf8_path().join("retained-messages.json");
+        assert_eq!(load(&path).await, vec![]);
+
+        ttd.file("retained-messages.json")
+            .with_raw_content("not json");
+        assert_eq!(load(&path).await, vec![]);
+    }
+}
@@ -32,6 +32,7 @@ pub async fn start_basic_actors(
         &mut mqtt_actor,
         &mqtt_schema,
         &config.service,
+        &runtime.get_handle(),
     );
 
     // Shutdown on SIGINT
@@ -4,6 +4,10 @@ pub async fn start_watchdog(_: tedge_config::TEdgeConfig) -> Result<(), anyhow::
     Err(anyhow::Error::from(WatchdogError::WatchdogNotAvailable))
 }
 
+pub async fn notify_systemd_if_supervised() -> Result<(), anyhow::Error> {
+    Ok(())
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum WatchdogError {
     #[error("The watchdog is not available on this platform")]
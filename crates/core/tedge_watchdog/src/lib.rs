@@ -1,5 +1,11 @@
+use std::sync::Arc;
 use tedge_config::cli::CommonArgs;
 use tedge_config::log_init;
+use tedge_config::models::WatchdogMode;
+
+// on all systems, the watchdog can restart the services itself
+mod restart_watchdog;
+pub use restart_watchdog::ServiceRestarter;
 
 // on linux, we use systemd
 #[cfg(target_os = "linux")]
@@ -26,7 +32,14 @@ pub struct WatchdogOpt {
     pub common: CommonArgs,
 }
 
-pub async fn run(watchdog_opt: WatchdogOpt) -> Result<(), anyhow::Error> {
+/// Run the watchdog
+///
+/// The `restarter` is only built when the services are restarted by the watchdog itself,
+/// i.e. when `watchdog.mode` is `restart`.
+pub async fn run(
+    watchdog_opt: WatchdogOpt,
+    restarter: impl FnOnce() -> Result<Arc<dyn ServiceRestarter>, anyhow::Error>,
+) -> Result<(), anyhow::Error> {
     log_init(
         "tedge-watchdog",
         &watchdog_opt.common.log_args,
@@ -34,5 +47,13 @@ pub async fn run(watchdog_opt: WatchdogOpt) -> Result<(), anyhow::Error> {
     )?;
 
     let tedge_config = tedge_config::TEdgeConfig::load(&watchdog_opt.common.config_dir).await?;
-    watchdog::start_watchdog(tedge_config).await
+    match tedge_config.watchdog.mode {
+        WatchdogMode::Systemd => watchdog::start_watchdog(tedge_config).await,
+        WatchdogMode::Restart => {
+            let restarter = restarter()?;
+            // The systemd unit is of `Type=notify` with a `WatchdogSec`, whatever the mode
+            watchdog::notify_systemd_if_supervised().await?;
+            restart_watchdog::start_watchdog(tedge_config, restarter).await
+        }
+    }
 }
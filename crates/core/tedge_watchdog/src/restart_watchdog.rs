@@ -0,0 +1,577 @@
+//! A watchdog that restarts unresponsive services itself,
+//! for systems where the services are not managed by systemd (OpenRC, s6, containers).
+//!
+//! Every `watchdog.interval`, a health check request is sent to each monitored service.
+//! A service that misses `watchdog.restart.missed_checks` consecutive checks is restarted
+//! using the system service manager, at most `watchdog.restart.max_attempts` times within
+//! `watchdog.restart.window`. When this limit is reached, the device is rebooted
+//! if `watchdog.reboot.enable` is set, at most `watchdog.reboot.max_reboots` times in a row.
+//! All these steps are reported as alarms on the main device.
+use anyhow::Context;
+use camino::Utf8PathBuf;
+use futures::SinkExt;
+use futures::StreamExt;
+use mqtt_channel::MqttMessage;
+use mqtt_channel::QoS;
+use mqtt_channel::Topic;
+use mqtt_channel::TopicFilter;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Value as JsonValue;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tedge_api::health::ServiceHealthTopic;
+use tedge_api::mqtt_topics::Channel;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_config::SudoCommandBuilder;
+use tedge_config::SystemConfig;
+use tedge_config::TEdgeConfig;
+use tedge_utils::timestamp::IsoOrUnix;
+use time::OffsetDateTime;
+use tokio::time::Instant;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+const SERVICE_NAME: &str = "tedge-watchdog";
+
+/// The file, in `data.path`, where the number of consecutive reboots is persisted
+const REBOOT_COUNT_FILE: &str = "watchdog-reboots";
+
+/// Restart the system services on behalf of the watchdog
+///
+/// This is implemented by the `tedge` crate using the service manager configured in `system.toml`.
+#[async_trait::async_trait]
+pub trait ServiceRestarter: Send + Sync {
+    /// Check that the service is known and can be restarted
+    fn check_service(&self, service: &str) -> Result<(), anyhow::Error>;
+
+    async fn restart_service(&self, service: &str) -> Result<(), anyhow::Error>;
+}
+
+/// When unresponsive services have to be restarted, and when to give up
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    pub interval: Duration,
+    pub missed_checks: u32,
+    pub max_attempts: u32,
+    pub window: Duration,
+    pub reboot: bool,
+    pub max_reboots: u32,
+}
+
+impl RestartPolicy {
+    pub fn from_tedge_config(tedge_config: &TEdgeConfig) -> Self {
+        let watchdog = &tedge_config.watchdog;
+        RestartPolicy {
+            interval: watchdog.interval.duration(),
+            missed_checks: watchdog.restart.missed_checks.max(1),
+            max_attempts: watchdog.restart.max_attempts,
+            window: watchdog.restart.window.duration(),
+            reboot: watchdog.reboot.enable,
+            max_reboots: watchdog.reboot.max_reboots,
+        }
+    }
+}
+
+/// What the watchdog has to do after a health check of a service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    /// Nothing, the service is healthy or has not missed enough checks yet
+    None,
+    /// Restart the service, this being the given attempt within the restart window
+    Restart { attempt: u32 },
+    /// The service is still unresponsive after the maximum number of restarts
+    Escalate,
+    /// The service responds again after having been restarted
+    Recovered,
+}
+
+/// Tracks the missed health checks and restarts of a service
+#[derive(Debug)]
+struct ServiceMonitor {
+    policy: RestartPolicy,
+    missed: u32,
+    restarts: VecDeque<Instant>,
+    alarm_raised: bool,
+    escalated: bool,
+}
+
+impl ServiceMonitor {
+    fn new(policy: RestartPolicy) -> Self {
+        ServiceMonitor {
+            policy,
+            missed: 0,
+            restarts: VecDeque::new(),
+            alarm_raised: false,
+            escalated: false,
+        }
+    }
+
+    fn on_response(&mut self) -> Action {
+        self.missed = 0;
+        self.escalated = false;
+        if std::mem::take(&mut self.alarm_raised) {
+            Action::Recovered
+        } else {
+            Action::None
+        }
+    }
+
+    fn on_missed_check(&mut self, now: Instant) -> Action {
+        self.missed += 1;
+        if self.missed < self.policy.missed_checks {
+            return Action::None;
+        }
+        self.missed = 0;
+
+        while let Some(restart) = self.restarts.front() {
+            if now.duration_since(*restart) < self.policy.window {
+                break;
+            }
+            self.restarts.pop_front();
+        }
+
+        if (self.restarts.len() as u32) < self.policy.max_attempts {
+            self.restarts.push_back(now);
+            self.alarm_raised = true;
+            Action::Restart {
+                attempt: self.restarts.len() as u32,
+            }
+        } else if !self.escalated {
+            self.escalated = true;
+            self.alarm_raised = true;
+            Action::Escalate
+        } else {
+            Action::None
+        }
+    }
+}
+
+/// The number of consecutive reboots triggered by the watchdog
+///
+/// This count is persisted, so the watchdog doesn't reboot the device forever
+/// when a service is still unresponsive after each reboot.
+#[derive(Debug)]
+struct RebootCounter {
+    path: Utf8PathBuf,
+    count: u32,
+}
+
+impl RebootCounter {
+    async fn load(path: Utf8PathBuf) -> Self {
+        let count = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => content.trim().parse().unwrap_or_else(|_| {
+                warn!("Ignoring invalid reboot count in {path}");
+                0
+            }),
+            Err(_) => 0,
+        };
+        RebootCounter { path, count }
+    }
+
+    async fn increment(&mut self) {
+        self.count += 1;
+        if let Err(err) = tokio::fs::write(&self.path, self.count.to_string()).await {
+            error!("Failed to persist the reboot count to {}: {err}", self.path);
+        }
+    }
+
+    async fn reset(&mut self) {
+        if self.count > 0 {
+            self.count = 0;
+            if let Err(err) = tokio::fs::remove_file(&self.path).await {
+                error!("Failed to reset the reboot count in {}: {err}", self.path);
+            }
+        }
+    }
+}
+
+struct MonitoredService {
+    name: String,
+    check_topic: Topic,
+    health_topic: Topic,
+    alarm_topic: Topic,
+    monitor: ServiceMonitor,
+}
+
+pub async fn start_watchdog(
+    tedge_config: TEdgeConfig,
+    restarter: Arc<dyn ServiceRestarter>,
+) -> Result<(), anyhow::Error> {
+    let policy = RestartPolicy::from_tedge_config(&tedge_config);
+    let mqtt_topic_root = &tedge_config.mqtt.topic_root;
+    let mqtt_schema = MqttSchema::with_root(mqtt_topic_root.clone());
+    let device_topic_id: EntityTopicId = tedge_config
+        .mqtt
+        .device_topic_id
+        .parse()
+        .context("Can't parse as device topic id")?;
+
+    let mut services = Vec::new();
+    for name in tedge_config.watchdog.services.iter() {
+        restarter
+            .check_service(name)
+            .context("Invalid watchdog.services")?;
+        let service = device_topic_id
+            .default_service_for_device(name)
+            .with_context(|| format!("Services not in default scheme unsupported: {name}"))?;
+        services.push(MonitoredService {
+            name: name.clone(),
+            check_topic: mqtt_schema.topic_for(
+                &service,
+                &Channel::Command {
+                    operation: OperationType::Health,
+                    cmd_id: "check".to_string(),
+                },
+            ),
+            health_topic: mqtt_schema.topic_for(&service, &Channel::Health),
+            alarm_topic: mqtt_schema.topic_for(
+                &device_topic_id,
+                &Channel::Alarm {
+                    alarm_type: format!("{name}_unresponsive"),
+                },
+            ),
+            monitor: ServiceMonitor::new(policy.clone()),
+        });
+    }
+    if services.is_empty() {
+        warn!("tedge watchdog not started because no services to monitor");
+        return Ok(());
+    }
+
+    let watchdog_topic_id = device_topic_id
+        .default_service_for_device(SERVICE_NAME)
+        .context("Services not in default scheme unsupported")?;
+    let service_health_topic = ServiceHealthTopic::from_new_topic(
+        &watchdog_topic_id.into(),
+        &mqtt_schema,
+        tedge_config.service.timestamp_format,
+    );
+    let up_message = service_health_topic.clone();
+
+    let mut health_topics = TopicFilter::empty();
+    for service in &services {
+        health_topics.add_all(service.health_topic.clone().into());
+    }
+    let mqtt_config = tedge_config
+        .mqtt_config()?
+        .with_session_name(format!(
+            "{SERVICE_NAME}#{mqtt_topic_root}/{device_topic_id}"
+        ))
+        .with_subscriptions(health_topics)
+        .with_initial_message(move || up_message.up_message())
+        .with_last_will_message(service_health_topic.down_message());
+    let client = mqtt_channel::Connection::new(&mqtt_config).await?;
+    let mut received = client.received;
+    let mut publisher = client.published;
+    let mut reboots =
+        RebootCounter::load(tedge_config.data.path.as_path().join(REBOOT_COUNT_FILE)).await;
+
+    info!(
+        "Starting watchdog for {}, restarting the services which miss {} health checks",
+        services
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        policy.missed_checks
+    );
+
+    loop {
+        let request_time = OffsetDateTime::now_utc();
+        for service in &services {
+            publisher
+                .send(MqttMessage::new(&service.check_topic, ""))
+                .await
+                .context("Could not send health check request")?;
+        }
+
+        let mut responded = vec![false; services.len()];
+        let deadline = Instant::now() + policy.interval;
+        loop {
+            match tokio::time::timeout_at(deadline, received.next()).await {
+                Ok(Some(message)) => {
+                    let Some(index) = services
+                        .iter()
+                        .position(|s| s.health_topic == message.topic)
+                    else {
+                        continue;
+                    };
+                    if is_up_since(&message, request_time) {
+                        responded[index] = true;
+                    }
+                }
+                Ok(None) => anyhow::bail!("MQTT receiver closed"),
+                Err(_) => break,
+            }
+        }
+
+        if responded.iter().all(|responded| *responded) {
+            reboots.reset().await;
+        }
+
+        let now = Instant::now();
+        for (service, responded) in services.iter_mut().zip(responded) {
+            let action = if responded {
+                service.monitor.on_response()
+            } else {
+                warn!(
+                    "No health check response received from {} in time",
+                    service.name
+                );
+                service.monitor.on_missed_check(now)
+            };
+            handle_action(
+                &tedge_config,
+                &*restarter,
+                &mut publisher,
+                &mut reboots,
+                service,
+                action,
+            )
+            .await?;
+        }
+    }
+}
+
+async fn handle_action(
+    tedge_config: &TEdgeConfig,
+    restarter: &dyn ServiceRestarter,
+    publisher: &mut futures::channel::mpsc::UnboundedSender<MqttMessage>,
+    reboots: &mut RebootCounter,
+    service: &MonitoredService,
+    action: Action,
+) -> Result<(), anyhow::Error> {
+    let name = &service.name;
+    let policy = &service.monitor.policy;
+    let reboot = action == Action::Escalate && policy.reboot && reboots.count < policy.max_reboots;
+    let alarm = match action {
+        Action::None => return Ok(()),
+        Action::Recovered => {
+            info!("{name} is responsive again");
+            MqttMessage::new(&service.alarm_topic, "")
+        }
+        Action::Restart { attempt } => {
+            warn!(
+                "Restarting {name} (attempt {attempt}/{})",
+                policy.max_attempts
+            );
+            let text = match restarter.restart_service(name).await {
+                Ok(()) => format!(
+                    "{name} missed {} health checks and has been restarted (attempt {attempt}/{})",
+                    policy.missed_checks, policy.max_attempts
+                ),
+                Err(err) => {
+                    error!("Failed to restart {name}: {err:#}");
+                    format!(
+                        "{name} missed {} health checks and could not be restarted: {err:#}",
+                        policy.missed_checks
+                    )
+                }
+            };
+            alarm_message(tedge_config, &service.alarm_topic, "major", text)?
+        }
+        Action::Escalate => {
+            let window = humantime::format_duration(policy.window);
+            let text = if reboot {
+                format!("{name} is still unresponsive after {} restarts within {window}, rebooting the device (reboot {}/{})", policy.max_attempts, reboots.count + 1, policy.max_reboots)
+            } else if policy.reboot {
+                format!("{name} is still unresponsive after {} restarts within {window} and {} reboots, giving up rebooting the device", policy.max_attempts, reboots.count)
+            } else {
+                format!("{name} is still unresponsive after {} restarts within {window}, giving up restarting it", policy.max_attempts)
+            };
+            error!("{text}");
+            alarm_message(tedge_config, &service.alarm_topic, "critical", text)?
+        }
+    };
+
+    publisher
+        .send(alarm)
+        .await
+        .context("Could not send alarm")?;
+
+    if reboot {
+        reboots.increment().await;
+        if let Err(err) = reboot_device(tedge_config).await {
+            error!("Failed to reboot the device: {err:#}");
+        }
+    }
+    Ok(())
+}
+
+fn alarm_message(
+    tedge_config: &TEdgeConfig,
+    topic: &Topic,
+    severity: &str,
+    text: String,
+) -> Result<MqttMessage, anyhow::Error> {
+    let time = tedge_config
+        .service
+        .timestamp_format
+        .to_json(OffsetDateTime::now_utc())?;
+    let payload = json!({
+        "text": text,
+        "severity": severity,
+        "time": time,
+    });
+    Ok(MqttMessage::new(topic, payload.to_string())
+        .with_retain()
+        .with_qos(QoS::AtLeastOnce))
+}
+
+/// Reboot the device using the command configured in `system.toml`
+async fn reboot_device(tedge_config: &TEdgeConfig) -> Result<(), anyhow::Error> {
+    let system_config = SystemConfig::try_new(tedge_config.root_dir())?;
+    let Some((reboot, args)) = system_config.system.reboot.split_first() else {
+        anyhow::bail!("`system.reboot` is empty");
+    };
+    let mut command: tokio::process::Command =
+        SudoCommandBuilder::new(tedge_config).command(reboot).into();
+    let status = command
+        .args(args)
+        .status()
+        .await
+        .with_context(|| format!("Fail to run `{reboot}`"))?;
+    if !status.success() {
+        anyhow::bail!("`{reboot}` failed with {status}");
+    }
+    Ok(())
+}
+
+/// A subset of fields of health status payload required by the watchdog.
+#[derive(Debug, Deserialize)]
+struct HealthStatus {
+    status: Option<String>,
+    time: Option<JsonValue>,
+}
+
+/// Tell if the message reports the service as up, in response to a request sent at the given time
+fn is_up_since(message: &MqttMessage, request_time: OffsetDateTime) -> bool {
+    let Ok(health) = serde_json::from_slice::<HealthStatus>(message.payload_bytes()) else {
+        return false;
+    };
+    if health.status.as_deref() != Some("up") {
+        return false;
+    }
+    let Some(Ok(time)) = health.time.as_ref().map(IsoOrUnix::try_from) else {
+        return false;
+    };
+
+    // Compare with a 1s precision, as the unix timestamps can be integers
+    time.into_inner().unix_timestamp() >= request_time.unix_timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_utils::timestamp::TimeFormat;
+
+    fn policy() -> RestartPolicy {
+        RestartPolicy {
+            interval: Duration::from_secs(30),
+            missed_checks: 2,
+            max_attempts: 2,
+            window: Duration::from_secs(3600),
+            reboot: true,
+            max_reboots: 2,
+        }
+    }
+
+    #[test]
+    fn a_service_is_restarted_after_missing_consecutive_checks() {
+        let mut monitor = ServiceMonitor::new(policy());
+        let now = Instant::now();
+
+        assert_eq!(monitor.on_missed_check(now), Action::None);
+        assert_eq!(monitor.on_response(), Action::None);
+        assert_eq!(monitor.on_missed_check(now), Action::None);
+        assert_eq!(monitor.on_missed_check(now), Action::Restart { attempt: 1 });
+        assert_eq!(monitor.on_response(), Action::Recovered);
+        assert_eq!(monitor.on_response(), Action::None);
+    }
+
+    #[test]
+    fn restarts_are_escalated_once_the_limit_is_reached() {
+        let mut monitor = ServiceMonitor::new(policy());
+        let now = Instant::now();
+
+        let mut actions = vec![];
+        for _ in 0..8 {
+            actions.push(monitor.on_missed_check(now));
+        }
+        assert_eq!(
+            actions,
+            vec![
+                Action::None,
+                Action::Restart { attempt: 1 },
+                Action::None,
+                Action::Restart { attempt: 2 },
+                Action::None,
+                Action::Escalate,
+                Action::None,
+                Action::None,
+            ]
+        );
+    }
+
+    #[test]
+    fn restarts_older_than_the_window_are_not_counted() {
+        let mut monitor = ServiceMonitor::new(policy());
+        let start = Instant::now();
+        let later = start + Duration::from_secs(3601);
+
+        monitor.on_missed_check(start);
+        monitor.on_missed_check(start);
+        monitor.on_missed_check(start);
+        monitor.on_missed_check(start);
+
+        monitor.on_missed_check(later);
+        assert_eq!(
+            monitor.on_missed_check(later),
+            Action::Restart { attempt: 1 }
+        );
+    }
+
+    #[tokio::test]
+    async fn the_reboot_count_is_persisted() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = Utf8PathBuf::try_from(dir.path().join(REBOOT_COUNT_FILE)).unwrap();
+
+        let mut reboots = RebootCounter::load(path.clone()).await;
+        assert_eq!(reboots.count, 0);
+        reboots.increment().await;
+        reboots.increment().await;
+
+        let mut reboots = RebootCounter::load(path.clone()).await;
+        assert_eq!(reboots.count, 2);
+        reboots.reset().await;
+
+        let reboots = RebootCounter::load(path).await;
+        assert_eq!(reboots.count, 0);
+    }
+
+    #[test]
+    fn only_fresh_up_messages_are_accepted_as_responses() {
+        let topic = Topic::new_unchecked("te/device/main/service/tedge-agent/status/health");
+        let request_time = OffsetDateTime::now_utc();
+        let response = |status: &str, time: OffsetDateTime| {
+            let time = TimeFormat::Unix.to_json(time).unwrap();
+            MqttMessage::new(&topic, json!({"status": status, "time": time}).to_string())
+        };
+
+        assert!(is_up_since(&response("up", request_time), request_time));
+        assert!(!is_up_since(&response("down", request_time), request_time));
+        assert!(!is_up_since(
+            &response("up", request_time - Duration::from_secs(60)),
+            request_time
+        ));
+        assert!(!is_up_since(
+            &MqttMessage::new(&topic, r#"{"status":"up"}"#),
+            request_time
+        ));
+    }
+}
@@ -63,6 +63,19 @@ pub async fn start_watchdog(tedge_config: TEdgeConfig) -> Result<(), anyhow::Err
     Ok(())
 }
 
+/// Notify systemd about the health of tedge-watchdog, when started by systemd
+///
+/// This is used in `restart` mode, where the services are not monitored on behalf of systemd,
+/// but `tedge-watchdog` itself can still be a systemd `Type=notify` service with a `WatchdogSec`.
+pub async fn notify_systemd_if_supervised() -> Result<(), anyhow::Error> {
+    if std::env::var_os("NOTIFY_SOCKET").is_none() {
+        return Ok(());
+    }
+    notify_systemd(process::id(), "--ready")?;
+    start_watchdog_for_self().await?;
+    Ok(())
+}
+
 async fn start_watchdog_for_self() -> Result<(), WatchdogError> {
     match get_watchdog_sec("/lib/systemd/system/tedge-watchdog.service") {
         Ok(interval) => {
@@ -1,3 +1,5 @@
+use crate::cache::CacheConfig;
+use crate::policy::AccessPolicy;
 use crate::server::AppData;
 use crate::server::Server;
 use crate::tokens::C8yTokenManager;
@@ -5,9 +7,9 @@ use async_trait::async_trait;
 use c8y_api::http_proxy::C8yAuthRetriever;
 use camino::Utf8PathBuf;
 use futures::channel::mpsc;
-use futures::StreamExt;
 use std::convert::Infallible;
 use std::net::IpAddr;
+use tedge_actors::message_boxes::next_signal;
 use tedge_actors::Actor;
 use tedge_actors::Builder;
 use tedge_actors::DynSender;
@@ -39,11 +41,31 @@ impl C8yAuthProxyBuilder {
         let reqwest_client = config.cloud_root_certs()?.client();
         let c8y = config.c8y.try_get(c8y_profile)?;
         let auth_retriever = C8yAuthRetriever::from_tedge_config(config, c8y_profile)?;
+        let policy = c8y
+            .proxy
+            .policy_path
+            .or_none()
+            .map(|path| AccessPolicy::from_file(path.as_std_path()))
+            .transpose()?;
+        if policy.is_none() {
+            info!("No policy configured in `c8y.proxy.policy_path`: any local client can use the Cumulocity proxy");
+        }
+        let cache = c8y.proxy.cache.enable.then(|| CacheConfig {
+            // Each profile has its own cache, as the cached responses are specific to a tenant
+            dir: match c8y_profile {
+                Some(profile) => c8y.proxy.cache.path.join(profile),
+                None => c8y.proxy.cache.path.clone().into(),
+            },
+            max_size: c8y.proxy.cache.max_size,
+            queue_max_size: c8y.proxy.cache.queue_max_size,
+        });
         let app_data = AppData {
             is_https: true,
             host: c8y.http.or_config_not_set()?.to_string(),
             token_manager: C8yTokenManager::new(auth_retriever).shared(),
             client: reqwest_client,
+            policy,
+            cache,
         };
         let bind = &c8y.proxy.bind;
         let (signal_sender, signal_receiver) = mpsc::channel(10);
@@ -118,7 +140,7 @@ impl Actor for C8yAuthProxy {
                 info!("Done");
                 Ok(result.map_err(BoxError::from)?)
             },
-            Some(RuntimeRequest::Shutdown) = self.signal_receiver.next() => {
+            Some(RuntimeRequest::Shutdown) = next_signal(&mut self.signal_receiver) => {
                 info!("Shutdown");
                 Ok(())
             }
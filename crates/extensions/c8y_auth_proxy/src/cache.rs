@@ -0,0 +1,622 @@
+//! On-disk cache used by the proxy while Cumulocity cannot be reached.
+//!
+//! - The responses to the GET requests made with the device identity are stored on disk.
+//!   A response is served from the cache without contacting Cumulocity while fresh, as told by its `Cache-Control: max-age`.
+//!   Otherwise, the request is forwarded to Cumulocity, with an `If-None-Match` header if the cached response has an `ETag`,
+//!   so a `304 Not Modified` response can be answered with the cached content.
+//!   When Cumulocity cannot be reached, the cached content is served even if stale, with a `Warning` header.
+//! - The POST requests creating events, alarms and measurements that cannot be sent to Cumulocity are queued on disk
+//!   and forwarded later, in order, when Cumulocity can be reached again. Such requests are answered with a
+//!   `202 Accepted` response with a `Warning` header.
+//!
+//! Both the cache and the queue are bounded by size. Cached responses are evicted, least recently stored first,
+//! while new requests are rejected when the queue is full.
+
+use crate::tokens::SharedTokenManager;
+use anyhow::Context;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use hyper::body::Bytes;
+use hyper::header::AUTHORIZATION;
+use hyper::header::CACHE_CONTROL;
+use hyper::header::CONTENT_DISPOSITION;
+use hyper::header::CONTENT_LANGUAGE;
+use hyper::header::CONTENT_TYPE;
+use hyper::header::ETAG;
+use hyper::header::LAST_MODIFIED;
+use hyper::header::WARNING;
+use hyper::HeaderMap;
+use hyper::StatusCode;
+use reqwest::header::HeaderName;
+use reqwest::header::HeaderValue;
+use serde::Deserialize;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+use tracing::info;
+use tracing::warn;
+
+/// The `Warning` header added to cached responses served while Cumulocity cannot be reached
+pub(crate) const STALE_WARNING: &str = "110 - \"Response is Stale\"";
+
+/// The `Warning` header added to the responses of the requests queued while Cumulocity cannot be reached
+pub(crate) const QUEUED_WARNING: &str = "199 - \"Queued while Cumulocity is unreachable\"";
+
+/// The paths of the POST requests that can be queued while Cumulocity cannot be reached
+const QUEUEABLE_PATHS: [&str; 3] = [
+    "/event/events",
+    "/alarm/alarms",
+    "/measurement/measurements",
+];
+
+/// Interval between two attempts to forward the queued requests
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Headers of the queued requests that are forwarded to Cumulocity
+const QUEUED_HEADERS: [HeaderName; 2] = [CONTENT_TYPE, hyper::header::ACCEPT];
+
+/// Headers of the responses that are stored along the cached content
+///
+/// Only the headers describing the content are kept,
+/// so headers specific to a response, as `Set-Cookie`, are never replayed.
+const CACHED_HEADERS: [HeaderName; 5] = [
+    CONTENT_TYPE,
+    CONTENT_DISPOSITION,
+    CONTENT_LANGUAGE,
+    ETAG,
+    LAST_MODIFIED,
+];
+
+/// Settings of the proxy cache
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// The directory where responses and queued requests are stored
+    pub dir: Utf8PathBuf,
+
+    /// Maximum size in bytes of the cached responses
+    pub max_size: u64,
+
+    /// Maximum size in bytes of the queued requests
+    pub queue_max_size: u64,
+}
+
+#[derive(Clone)]
+pub(crate) struct ResponseCache {
+    config: Arc<CacheConfig>,
+    lock: Arc<Mutex<()>>,
+}
+
+/// A response stored in the cache
+#[derive(Debug, Clone)]
+pub(crate) struct CachedResponse {
+    metadata: ResponseMetadata,
+    body: Bytes,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ResponseMetadata {
+    url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    etag: Option<String>,
+    stored_at: u64,
+    max_age: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueuedRequest {
+    path_and_query: String,
+    headers: Vec<(String, String)>,
+}
+
+impl ResponseCache {
+    pub fn new(config: CacheConfig) -> Self {
+        ResponseCache {
+            config: Arc::new(config),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    fn responses_dir(&self) -> Utf8PathBuf {
+        self.config.dir.join("responses")
+    }
+
+    fn queue_dir(&self) -> Utf8PathBuf {
+        self.config.dir.join("queue")
+    }
+
+    /// Responses larger than this size are not cached
+    pub fn max_entry_size(&self) -> usize {
+        (self.config.max_size / 4) as usize
+    }
+
+    /// Tell if a GET request can be served from the cache
+    ///
+    /// Conditional and range requests are left to Cumulocity.
+    pub fn is_cacheable(headers: &HeaderMap) -> bool {
+        !headers.contains_key(AUTHORIZATION)
+            && !headers.contains_key(hyper::header::IF_NONE_MATCH)
+            && !headers.contains_key(hyper::header::IF_MODIFIED_SINCE)
+            && !headers.contains_key(hyper::header::RANGE)
+    }
+
+    /// Tell if a POST request on the given path can be queued
+    pub fn is_queueable(path: &str) -> bool {
+        QUEUEABLE_PATHS.contains(&path)
+    }
+
+    /// Return the cached response for a URL, if any
+    pub async fn get(&self, url: &str) -> Option<CachedResponse> {
+        let (metadata_path, body_path) = self.entry_paths(url);
+        let metadata = tokio::fs::read(&metadata_path).await.ok()?;
+        let metadata: ResponseMetadata = serde_json::from_slice(&metadata).ok()?;
+        if metadata.url != url {
+            return None;
+        }
+        let body = tokio::fs::read(&body_path).await.ok()?;
+        Some(CachedResponse {
+            metadata,
+            body: body.into(),
+        })
+    }
+
+    /// Store a successful response
+    pub async fn store(&self, url: &str, headers: &HeaderMap, body: Bytes) {
+        if body.len() > self.max_entry_size() {
+            return;
+        }
+        let metadata = ResponseMetadata {
+            url: url.to_string(),
+            status: StatusCode::OK.as_u16(),
+            headers: headers
+                .iter()
+                .filter(|(name, _)| CACHED_HEADERS.contains(name))
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            etag: headers
+                .get(ETAG)
+                .and_then(|etag| etag.to_str().ok())
+                .map(str::to_string),
+            stored_at: now(),
+            max_age: max_age(headers),
+        };
+        let _lock = self.lock.lock().await;
+        if let Err(err) = self.write_entry(&metadata, &body).await {
+            warn!("Failed to cache the response of {url}: {err:#}");
+            return;
+        }
+        if let Err(err) = self.evict().await {
+            warn!("Failed to evict cached responses: {err:#}");
+        }
+    }
+
+    /// Mark a cached response as fresh again, Cumulocity having responded `304 Not Modified`
+    pub async fn revalidated(&self, mut cached: CachedResponse, headers: &HeaderMap) {
+        cached.metadata.stored_at = now();
+        if headers.contains_key(CACHE_CONTROL) {
+            cached.metadata.max_age = max_age(headers);
+        }
+        let _lock = self.lock.lock().await;
+        if let Err(err) = self.write_entry(&cached.metadata, &cached.body).await {
+            warn!(
+                "Failed to update the cached response of {}: {err:#}",
+                cached.metadata.url
+            );
+        }
+    }
+
+    async fn write_entry(&self, metadata: &ResponseMetadata, body: &[u8]) -> anyhow::Result<()> {
+        let (metadata_path, body_path) = self.entry_paths(&metadata.url);
+        tokio::fs::create_dir_all(self.responses_dir()).await?;
+        write_file(&body_path, body).await?;
+        write_file(&metadata_path, &serde_json::to_vec(metadata)?).await?;
+        Ok(())
+    }
+
+    /// Remove the least recently stored responses till the cache size is under the limit
+    async fn evict(&self) -> anyhow::Result<()> {
+        let mut entries = Vec::new();
+        let mut total_size = 0;
+        let mut dir = tokio::fs::read_dir(self.responses_dir()).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            total_size += metadata.len();
+            let path = Utf8PathBuf::try_from(entry.path())?;
+            if path.extension() == Some("body") {
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                entries.push((modified, path));
+            }
+        }
+
+        entries.sort();
+        for (_, body_path) in entries {
+            if total_size <= self.config.max_size {
+                break;
+            }
+            let metadata_path = body_path.with_extension("json");
+            for path in [body_path, metadata_path] {
+                if let Ok(metadata) = tokio::fs::metadata(&path).await {
+                    tokio::fs::remove_file(&path).await?;
+                    total_size = total_size.saturating_sub(metadata.len());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn entry_paths(&self, url: &str) -> (Utf8PathBuf, Utf8PathBuf) {
+        let key = format!("{:016x}", fnv1a(url.as_bytes()));
+        let dir = self.responses_dir();
+        (
+            dir.join(format!("{key}.json")),
+            dir.join(format!("{key}.body")),
+        )
+    }
+
+    /// Queue a POST request to be forwarded later
+    pub async fn enqueue(
+        &self,
+        path_and_query: &str,
+        headers: &HeaderMap,
+        body: &Bytes,
+    ) -> anyhow::Result<()> {
+        let request = QueuedRequest {
+            path_and_query: path_and_query.to_string(),
+            headers: QUEUED_HEADERS
+                .iter()
+                .filter_map(|name| {
+                    let value = headers.get(name)?.to_str().ok()?;
+                    Some((name.to_string(), value.to_string()))
+                })
+                .collect(),
+        };
+        let request = serde_json::to_vec(&request)?;
+
+        let _lock = self.lock.lock().await;
+        let queue_dir = self.queue_dir();
+        tokio::fs::create_dir_all(&queue_dir).await?;
+        let queue_size = dir_size(&queue_dir).await?;
+        let request_size = (request.len() + body.len()) as u64;
+        if queue_size + request_size > self.config.queue_max_size {
+            anyhow::bail!("the queue is full");
+        }
+
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let id = format!("{nanos:032}");
+        write_file(&queue_dir.join(format!("{id}.body")), body).await?;
+        write_file(&queue_dir.join(format!("{id}.json")), &request).await?;
+        Ok(())
+    }
+
+    /// Forward the queued requests to Cumulocity, periodically
+    pub async fn flush_queue_periodically(
+        self,
+        client: reqwest::Client,
+        host: Arc<str>,
+        token_manager: SharedTokenManager,
+    ) {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(err) = self.flush_queue(&client, &host, &token_manager).await {
+                warn!("Failed to forward the queued requests to Cumulocity: {err:#}");
+            }
+        }
+    }
+
+    /// Forward the queued requests to Cumulocity, in order, stopping on the first connection error
+    ///
+    /// The lock is only held to list and to remove the queued requests,
+    /// not while requests are sent to Cumulocity, so the proxy can still cache responses
+    /// and queue new requests in the meantime.
+    pub async fn flush_queue(
+        &self,
+        client: &reqwest::Client,
+        host: &str,
+        token_manager: &SharedTokenManager,
+    ) -> anyhow::Result<()> {
+        let queue_dir = self.queue_dir();
+        let ids = {
+            let _lock = self.lock.lock().await;
+            let mut ids = Vec::new();
+            let Ok(mut dir) = tokio::fs::read_dir(&queue_dir).await else {
+                return Ok(());
+            };
+            while let Some(entry) = dir.next_entry().await? {
+                let path = Utf8PathBuf::try_from(entry.path())?;
+                if path.extension() == Some("json") {
+                    if let Some(id) = path.file_stem() {
+                        ids.push(id.to_string());
+                    }
+                }
+            }
+            ids.sort();
+            ids
+        };
+
+        for id in ids {
+            let metadata_path = queue_dir.join(format!("{id}.json"));
+            let body_path = queue_dir.join(format!("{id}.body"));
+            let request: QueuedRequest =
+                serde_json::from_slice(&tokio::fs::read(&metadata_path).await?)
+                    .with_context(|| format!("invalid queued request {metadata_path}"))?;
+            let body = tokio::fs::read(&body_path).await.unwrap_or_default();
+            let destination = format!("{host}{}", request.path_and_query);
+
+            let send = |token: Arc<str>| {
+                let mut req = client
+                    .post(&destination)
+                    .header(AUTHORIZATION, token.as_ref());
+                for (name, value) in &request.headers {
+                    req = req.header(name, value);
+                }
+                req.body(body.clone()).send()
+            };
+            let token = token_manager.not_matching(None).await?;
+            let mut res = send(token.clone()).await;
+            if matches!(&res, Ok(res) if res.status() == StatusCode::UNAUTHORIZED) {
+                let token = token_manager.not_matching(Some(&token)).await?;
+                res = send(token).await;
+            }
+            match res {
+                // Cumulocity is still unreachable: keep the requests for a later attempt
+                Err(err) if err.is_connect() || err.is_timeout() => return Ok(()),
+                Err(err) => warn!("Failed to forward the queued request to {destination}: {err}"),
+                Ok(res) if !res.status().is_success() => warn!(
+                    "Cumulocity rejected the queued request to {destination} with {}",
+                    res.status()
+                ),
+                Ok(_) => info!("Forwarded the queued request to {destination}"),
+            }
+            let _lock = self.lock.lock().await;
+            let _ = tokio::fs::remove_file(&metadata_path).await;
+            let _ = tokio::fs::remove_file(&body_path).await;
+        }
+        Ok(())
+    }
+}
+
+impl CachedResponse {
+    /// Tell if the response can be served without being revalidated by Cumulocity
+    pub fn is_fresh(&self) -> bool {
+        self.metadata
+            .max_age
+            .is_some_and(|max_age| now() < self.metadata.stored_at.saturating_add(max_age))
+    }
+
+    pub fn etag(&self) -> Option<HeaderValue> {
+        self.metadata
+            .etag
+            .as_deref()
+            .and_then(|etag| HeaderValue::from_str(etag).ok())
+    }
+
+    pub fn to_response(&self, warning: Option<&'static str>) -> Response {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.metadata.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                // Responses cached by previous versions were stored with all their headers
+                if CACHED_HEADERS.contains(&name) {
+                    headers.append(name, value);
+                }
+            }
+        }
+        if let Some(warning) = warning {
+            headers.insert(WARNING, HeaderValue::from_static(warning));
+        }
+        let status = StatusCode::from_u16(self.metadata.status).unwrap_or(StatusCode::OK);
+        (status, headers, axum::body::Body::from(self.body.clone())).into_response()
+    }
+}
+
+/// The freshness lifetime of a response, in seconds
+///
+/// Responses without `max-age` or with `no-cache` or `no-store` are always revalidated by Cumulocity,
+/// and only served from the cache when Cumulocity cannot be reached.
+fn max_age(headers: &HeaderMap) -> Option<u64> {
+    let cache_control = headers.get(CACHE_CONTROL)?.to_str().ok()?;
+    let directives: Vec<&str> = cache_control
+        .split(',')
+        .map(|directive| directive.trim())
+        .collect();
+    if directives.iter().any(|directive| {
+        directive.eq_ignore_ascii_case("no-cache") || directive.eq_ignore_ascii_case("no-store")
+    }) {
+        return None;
+    }
+    directives
+        .iter()
+        .find_map(|directive| directive.strip_prefix("max-age="))
+        .and_then(|max_age| max_age.parse().ok())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// FNV-1a hash, used to derive stable file names from URLs
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+async fn write_file(path: &Utf8Path, content: &[u8]) -> std::io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    tokio::fs::write(&tmp_path, content).await?;
+    tokio::fs::rename(&tmp_path, path).await
+}
+
+async fn dir_size(dir: &Utf8Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        size += entry.metadata().await?.len();
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn cache(dir: &TempDir, max_size: u64, queue_max_size: u64) -> ResponseCache {
+        ResponseCache::new(CacheConfig {
+            dir: Utf8PathBuf::try_from(dir.path().to_path_buf()).unwrap(),
+            max_size,
+            queue_max_size,
+        })
+    }
+
+    fn headers(cache_control: Option<&'static str>, etag: Option<&'static str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if let Some(cache_control) = cache_control {
+            headers.insert(CACHE_CONTROL, HeaderValue::from_static(cache_control));
+        }
+        if let Some(etag) = etag {
+            headers.insert(ETAG, HeaderValue::from_static(etag));
+        }
+        headers
+    }
+
+    #[tokio::test]
+    async fn responses_are_cached() {
+        let dir = TempDir::new().unwrap();
+        let cache = cache(&dir, 1024 * 1024, 1024);
+        let url = "https://tenant.example.com/inventory/managedObjects/1234";
+
+        assert!(cache.get(url).await.is_none());
+        cache
+            .store(
+                url,
+                &headers(Some("max-age=60"), Some("\"v1\"")),
+                Bytes::from("{}"),
+            )
+            .await;
+
+        let cached = cache.get(url).await.unwrap();
+        assert!(cached.is_fresh());
+        assert_eq!(cached.etag().unwrap(), "\"v1\"");
+        assert_eq!(cached.body, Bytes::from("{}"));
+    }
+
+    #[tokio::test]
+    async fn only_content_headers_are_replayed() {
+        let dir = TempDir::new().unwrap();
+        let cache = cache(&dir, 1024 * 1024, 1024);
+        let url = "https://tenant.example.com/inventory/managedObjects/1234";
+
+        let mut headers = headers(Some("max-age=60"), Some("\"v1\""));
+        headers.insert(
+            hyper::header::SET_COOKIE,
+            HeaderValue::from_static("XSRF-TOKEN=secret"),
+        );
+        cache.store(url, &headers, Bytes::from("{}")).await;
+
+        let response = cache.get(url).await.unwrap().to_response(None);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(response.headers()[ETAG], "\"v1\"");
+        assert!(!response.headers().contains_key(hyper::header::SET_COOKIE));
+    }
+
+    #[tokio::test]
+    async fn responses_without_max_age_are_revalidated() {
+        let dir = TempDir::new().unwrap();
+        let cache = cache(&dir, 1024 * 1024, 1024);
+        let url = "https://tenant.example.com/inventory/managedObjects/1234";
+
+        cache
+            .store(
+                url,
+                &headers(Some("no-cache, max-age=60"), None),
+                Bytes::from("{}"),
+            )
+            .await;
+        assert!(!cache.get(url).await.unwrap().is_fresh());
+
+        cache
+            .store(url, &headers(None, None), Bytes::from("{}"))
+            .await;
+        assert!(!cache.get(url).await.unwrap().is_fresh());
+    }
+
+    #[tokio::test]
+    async fn oldest_responses_are_evicted() {
+        let dir = TempDir::new().unwrap();
+        let cache = cache(&dir, 2048, 1024);
+        let body = Bytes::from(vec![b'x'; 500]);
+
+        for i in 0..4 {
+            cache
+                .store(
+                    &format!("https://tenant/binaries/{i}"),
+                    &headers(None, None),
+                    body.clone(),
+                )
+                .await;
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert!(cache.get("https://tenant/binaries/0").await.is_none());
+        assert!(cache.get("https://tenant/binaries/3").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn large_responses_are_not_cached() {
+        let dir = TempDir::new().unwrap();
+        let cache = cache(&dir, 2048, 1024);
+
+        cache
+            .store(
+                "https://tenant/binaries/1",
+                &headers(None, None),
+                Bytes::from(vec![b'x'; 1024]),
+            )
+            .await;
+        assert!(cache.get("https://tenant/binaries/1").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn queue_is_bounded_by_size() {
+        let dir = TempDir::new().unwrap();
+        let cache = cache(&dir, 2048, 1024);
+        let body = Bytes::from(vec![b'x'; 400]);
+
+        assert!(cache
+            .enqueue("/event/events", &headers(None, None), &body)
+            .await
+            .is_ok());
+        assert!(cache
+            .enqueue("/event/events", &headers(None, None), &body)
+            .await
+            .is_ok());
+        assert!(cache
+            .enqueue("/event/events", &headers(None, None), &body)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn only_requests_creating_data_can_be_queued() {
+        assert!(ResponseCache::is_queueable("/event/events"));
+        assert!(ResponseCache::is_queueable("/measurement/measurements"));
+        assert!(!ResponseCache::is_queueable("/inventory/managedObjects"));
+    }
+}
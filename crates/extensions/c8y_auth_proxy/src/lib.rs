@@ -1,4 +1,6 @@
 pub mod actor;
 mod body;
+pub mod cache;
+pub mod policy;
 mod server;
 mod tokens;
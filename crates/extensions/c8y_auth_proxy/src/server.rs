@@ -1,3 +1,9 @@
+use crate::cache::CacheConfig;
+use crate::cache::CachedResponse;
+use crate::cache::ResponseCache;
+use crate::cache::QUEUED_WARNING;
+use crate::cache::STALE_WARNING;
+use crate::policy::AccessPolicy;
 use crate::tokens::*;
 use anyhow::Context;
 use axum::extract::ws::rejection::WebSocketUpgradeRejection;
@@ -10,11 +16,13 @@ use axum::http::HeaderValue;
 use axum::response::IntoResponse;
 use axum::response::Response;
 use axum::routing::get;
+use axum::Extension;
 use axum::Router;
 use axum_tls::config::load_ssl_config;
 use axum_tls::config::PemReader;
 use axum_tls::config::TrustStoreLoader;
 use axum_tls::start_tls_server;
+use axum_tls::TlsData;
 use futures::future::BoxFuture;
 use futures::FutureExt;
 use futures::Sink;
@@ -24,8 +32,11 @@ use futures::StreamExt;
 use http_body::Frame;
 use http_body_util::Full;
 use http_body_util::StreamBody;
+use hyper::body::Bytes;
 use hyper::header::AUTHORIZATION;
 use hyper::header::HOST;
+use hyper::header::IF_NONE_MATCH;
+use hyper::header::WARNING;
 use hyper::HeaderMap;
 use reqwest::Method;
 use reqwest::StatusCode;
@@ -45,6 +56,7 @@ use tokio_tungstenite::MaybeTlsStream;
 use tokio_tungstenite::WebSocketStream;
 use tracing::error;
 use tracing::info;
+use tracing::warn;
 
 pub struct Server {
     fut: BoxFuture<'static, std::io::Result<()>>,
@@ -59,6 +71,14 @@ impl Server {
         key_path: OptionalConfig<impl PemReader>,
         ca_path: OptionalConfig<impl TrustStoreLoader>,
     ) -> anyhow::Result<Self> {
+        let state = AppState::from(state);
+        let queue_flush = state.cache.clone().map(|cache| {
+            cache.flush_queue_periodically(
+                state.client.clone(),
+                state.target_host.http.clone(),
+                state.token_manager.clone(),
+            )
+        });
         let app = create_app(state);
         let server_config = load_ssl_config(cert_path, key_path, ca_path, "Cumulocity proxy")?;
         let fut = if let Some(server_config) = server_config {
@@ -66,6 +86,16 @@ impl Server {
         } else {
             try_bind_insecure(app, address, port)?.boxed()
         };
+        let fut = match queue_flush {
+            Some(queue_flush) => async move {
+                tokio::select! {
+                    res = fut => res,
+                    () = queue_flush => Ok(()),
+                }
+            }
+            .boxed(),
+            None => fut,
+        };
 
         Ok(Server { fut })
     }
@@ -94,7 +124,7 @@ impl IntoResponse for ProxyError {
     }
 }
 
-fn create_app(state: AppData) -> Router<()> {
+fn create_app(state: AppState) -> Router<()> {
     let handle = get(respond_to)
         .post(respond_to)
         .put(respond_to)
@@ -105,7 +135,7 @@ fn create_app(state: AppData) -> Router<()> {
         .route("/c8y", handle.clone())
         .route("/c8y/", handle.clone())
         .route("/c8y/{*path}", handle)
-        .with_state(AppState::from(state))
+        .with_state(state)
 }
 
 fn try_bind_insecure(
@@ -136,6 +166,8 @@ pub(crate) struct AppData {
     pub host: String,
     pub token_manager: SharedTokenManager,
     pub client: reqwest::Client,
+    pub policy: Option<AccessPolicy>,
+    pub cache: Option<CacheConfig>,
 }
 
 #[derive(Clone)]
@@ -143,6 +175,8 @@ struct AppState {
     target_host: TargetHost,
     client: reqwest::Client,
     token_manager: SharedTokenManager,
+    policy: Option<Arc<AccessPolicy>>,
+    cache: Option<ResponseCache>,
 }
 
 impl From<AppData> for AppState {
@@ -161,6 +195,8 @@ impl From<AppData> for AppState {
             },
             token_manager: value.token_manager,
             client: value.client,
+            policy: value.policy.map(Arc::new),
+            cache: value.cache.map(ResponseCache::new),
         }
     }
 }
@@ -183,6 +219,18 @@ impl FromRef<AppState> for reqwest::Client {
     }
 }
 
+impl FromRef<AppState> for Option<Arc<AccessPolicy>> {
+    fn from_ref(input: &AppState) -> Self {
+        input.policy.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<ResponseCache> {
+    fn from_ref(input: &AppState) -> Self {
+        input.cache.clone()
+    }
+}
+
 #[derive(Clone)]
 struct TargetHost {
     http: Arc<str>,
@@ -394,7 +442,10 @@ where
 async fn respond_to(
     State(host): State<TargetHost>,
     State(client): State<reqwest::Client>,
+    State(policy): State<Option<Arc<AccessPolicy>>>,
+    State(cache): State<Option<ResponseCache>>,
     retrieve_token: State<SharedTokenManager>,
+    tls_data: Option<Extension<TlsData>>,
     path: Option<Path<String>>,
     uri: hyper::Uri,
     method: Method,
@@ -406,6 +457,20 @@ async fn respond_to(
         Some(Path(p)) => p.as_str(),
         None => "",
     };
+
+    // Only the requests allowed by the policy can use the device identity
+    if let Some(policy) = &policy {
+        let common_name = tls_data
+            .as_ref()
+            .and_then(|Extension(tls_data)| tls_data.common_name.as_deref());
+        if policy
+            .authorize(common_name, &method, &format!("/{path}"), &mut headers)
+            .is_err()
+        {
+            return Ok(StatusCode::FORBIDDEN.into_response());
+        }
+    }
+
     let auth: fn(reqwest::RequestBuilder, &str) -> reqwest::RequestBuilder =
         if headers.contains_key(AUTHORIZATION) {
             |req, _auth_value| req
@@ -425,16 +490,54 @@ async fn respond_to(
         destination += query;
     }
 
-    let mut token = retrieve_token
-        .not_matching(None)
-        .await
-        .with_context(|| "failed to retrieve JWT token")?;
+    let (body, body_clone) = small_body.try_clone();
+
+    // With the cache enabled, the requests made with the device identity
+    // can be served while Cumulocity cannot be reached
+    let cache = cache.filter(|_| ws.is_err() && !headers.contains_key(AUTHORIZATION));
+    let cacheable =
+        cache.is_some() && method == Method::GET && ResponseCache::is_cacheable(&headers);
+    let cached = match &cache {
+        Some(cache) if cacheable => cache.get(&destination).await,
+        _ => None,
+    };
+    if let Some(cached) = &cached {
+        if cached.is_fresh() {
+            return Ok(cached.to_response(None));
+        }
+        if let Some(etag) = cached.etag() {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+    }
+    let queueable_request = match (&body_clone, uri.path_and_query()) {
+        (Some(body), Some(path_and_query))
+            if cache.is_some()
+                && method == Method::POST
+                && ResponseCache::is_queueable(&format!("/{path}")) =>
+        {
+            let path_and_query = path_and_query.as_str().trim_start_matches("/c8y");
+            Some((path_and_query.to_string(), body.clone()))
+        }
+        _ => None,
+    };
+    let fallback = OfflineFallback {
+        cache: cache.as_ref(),
+        cached: cached.as_ref(),
+        queueable_request,
+    };
+
+    let mut token = match retrieve_token.not_matching(None).await {
+        Ok(token) => token,
+        Err(err) => {
+            let err = err.context("failed to retrieve JWT token");
+            return fallback.respond(&headers, err, true).await;
+        }
+    };
 
     if let Ok(ws) = ws {
         let path = path.to_owned();
         return Ok(ws.on_upgrade(|socket| proxy_ws(socket, host, retrieve_token, headers, path)));
     }
-    let (body, body_clone) = small_body.try_clone();
     if body_clone.is_none() {
         let destination = format!("{}/tenant/currentTenant", host.http);
         let response = client
@@ -461,9 +564,16 @@ async fn respond_to(
         .body(body)
         .send()
     };
-    let mut res = send_request(reqwest::Body::wrap(body), &token)
-        .await
-        .with_context(|| format!("making proxied request to {destination}"))?;
+    let mut res = match send_request(reqwest::Body::wrap(body), &token).await {
+        Ok(res) => res,
+        Err(err) => {
+            // Only the requests that have not been sent can be queued
+            let not_sent = err.is_connect();
+            let err = anyhow::Error::from(err)
+                .context(format!("making proxied request to {destination}"));
+            return fallback.respond(&headers, err, not_sent).await;
+        }
+    };
 
     if res.status() == StatusCode::UNAUTHORIZED {
         token = retrieve_token
@@ -480,7 +590,34 @@ async fn respond_to(
     let status = res.status();
     let headers = std::mem::take(res.headers_mut());
 
-    let body = if te_header.is_some_and(|h| h.to_str().unwrap_or_default().contains("chunked")) {
+    if let (Some(cache), Some(cached), StatusCode::NOT_MODIFIED) = (&cache, &cached, status) {
+        cache.revalidated(cached.clone(), &headers).await;
+        return Ok(cached.to_response(None));
+    }
+
+    let body = if let (Some(cache), true) = (&cache, cacheable && status == StatusCode::OK) {
+        // Responses too large to be cached are streamed
+        let mut stream = res.bytes_stream();
+        let mut buffer = Vec::new();
+        let mut complete = true;
+        while let Some(chunk) = stream.next().await {
+            buffer.extend_from_slice(&chunk.context("reading proxy response bytes")?);
+            if buffer.len() > cache.max_entry_size() {
+                complete = false;
+                break;
+            }
+        }
+        let bytes = Bytes::from(buffer);
+        if complete {
+            cache.store(&destination, &headers, bytes.clone()).await;
+            axum::body::Body::new(Full::new(bytes))
+        } else {
+            let head = futures::stream::once(std::future::ready(Ok(bytes)));
+            axum::body::Body::new(StreamBody::new(
+                head.chain(stream).map(|b| b.map(Frame::data)),
+            ))
+        }
+    } else if te_header.is_some_and(|h| h.to_str().unwrap_or_default().contains("chunked")) {
         axum::body::Body::new(StreamBody::new(
             res.bytes_stream().map(|b| b.map(Frame::data)),
         ))
@@ -493,6 +630,45 @@ async fn respond_to(
     Ok((status, headers, body).into_response())
 }
 
+/// How to respond to a request when Cumulocity cannot be reached
+struct OfflineFallback<'a> {
+    cache: Option<&'a ResponseCache>,
+    cached: Option<&'a CachedResponse>,
+    queueable_request: Option<(String, Bytes)>,
+}
+
+impl OfflineFallback<'_> {
+    async fn respond(
+        self,
+        headers: &HeaderMap<HeaderValue>,
+        err: anyhow::Error,
+        can_queue: bool,
+    ) -> Result<Response, ProxyError> {
+        if let Some(cached) = self.cached {
+            warn!("Serving a cached response as Cumulocity cannot be reached: {err:#}");
+            return Ok(cached.to_response(Some(STALE_WARNING)));
+        }
+        if let (Some(cache), Some((path_and_query, body)), true) =
+            (self.cache, &self.queueable_request, can_queue)
+        {
+            match cache.enqueue(path_and_query, headers, body).await {
+                Ok(()) => {
+                    warn!("Queued the request to {path_and_query} as Cumulocity cannot be reached: {err:#}");
+                    return Ok((
+                        StatusCode::ACCEPTED,
+                        [(WARNING, HeaderValue::from_static(QUEUED_WARNING))],
+                    )
+                        .into_response());
+                }
+                Err(queue_err) => {
+                    warn!("Failed to queue the request to {path_and_query}: {queue_err:#}")
+                }
+            }
+        }
+        Err(err.into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use axum::body::Bytes;
@@ -955,6 +1131,8 @@ mod tests {
             vec!["test-token"],
             rcgen::generate_simple_self_signed(["localhost".to_owned()]).unwrap(),
             None,
+            None,
+            None,
         );
 
         let res = reqwest_client()
@@ -1113,6 +1291,228 @@ mod tests {
         assert_eq!(res.bytes().await.unwrap(), Bytes::from("Succeeded"));
     }
 
+    #[tokio::test]
+    async fn forwards_requests_allowed_by_the_policy_with_the_device_token() {
+        let _ = env_logger::try_init();
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/inventory/managedObjects")
+            .match_header("Authorization", "Bearer test-token")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let port = start_server_with_policy(&server, vec!["test-token"], TEST_POLICY);
+
+        let res = reqwest_client()
+            .get(format!(
+                "https://localhost:{port}/c8y/inventory/managedObjects"
+            ))
+            .bearer_auth("local-secret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_denied_by_the_policy() {
+        let _ = env_logger::try_init();
+        let mut server = mockito::Server::new_async().await;
+        let forwarded_get = server
+            .mock("GET", mockito::Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+        let forwarded_delete = server
+            .mock("DELETE", mockito::Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let port = start_server_with_policy(&server, vec!["test-token"], TEST_POLICY);
+
+        // Not an allowed method
+        let res = reqwest_client()
+            .delete(format!(
+                "https://localhost:{port}/c8y/inventory/managedObjects/1234"
+            ))
+            .bearer_auth("local-secret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 403);
+
+        // Not an allowed path
+        let res = reqwest_client()
+            .get(format!("https://localhost:{port}/c8y/user/users"))
+            .bearer_auth("local-secret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 403);
+
+        // Not an authenticated client
+        let res = reqwest_client()
+            .get(format!(
+                "https://localhost:{port}/c8y/inventory/managedObjects"
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 403);
+
+        forwarded_get.assert_async().await;
+        forwarded_delete.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn serves_cached_responses_when_cumulocity_is_unreachable() {
+        let _ = env_logger::try_init();
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let cache = test_cache(&cache_dir);
+        let unreachable = "127.0.0.1:0";
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        ResponseCache::new(cache.clone())
+            .store(
+                &format!("http://{unreachable}/inventory/managedObjects/1234"),
+                &headers,
+                Bytes::from(r#"{"id":"1234"}"#),
+            )
+            .await;
+
+        let port = start_proxy_to_url(
+            unreachable,
+            vec!["test-token"],
+            rcgen::generate_simple_self_signed(["localhost".to_owned()]).unwrap(),
+            None,
+            None,
+            Some(cache),
+        );
+
+        let res = reqwest_client()
+            .get(format!(
+                "https://localhost:{port}/c8y/inventory/managedObjects/1234"
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()["warning"], STALE_WARNING);
+        assert_eq!(res.bytes().await.unwrap(), Bytes::from(r#"{"id":"1234"}"#));
+
+        let res = reqwest_client()
+            .get(format!(
+                "https://localhost:{port}/c8y/inventory/managedObjects/5678"
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 502);
+    }
+
+    #[tokio::test]
+    async fn revalidates_cached_responses_with_their_etag() {
+        let _ = env_logger::try_init();
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/inventory/managedObjects/1234")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .create_async()
+            .await;
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let cache = test_cache(&cache_dir);
+        let mut headers = HeaderMap::new();
+        headers.insert("etag", HeaderValue::from_static("\"v1\""));
+        ResponseCache::new(cache.clone())
+            .store(
+                &format!("{}/inventory/managedObjects/1234", server.url()),
+                &headers,
+                Bytes::from(r#"{"id":"1234"}"#),
+            )
+            .await;
+
+        let url = server.url();
+        let (_scheme, host) = url.split_once("://").unwrap();
+        let port = start_proxy_to_url(
+            host,
+            vec!["test-token"],
+            rcgen::generate_simple_self_signed(["localhost".to_owned()]).unwrap(),
+            None,
+            None,
+            Some(cache),
+        );
+
+        let res = reqwest_client()
+            .get(format!(
+                "https://localhost:{port}/c8y/inventory/managedObjects/1234"
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+        assert!(res.headers().get("warning").is_none());
+        assert_eq!(res.bytes().await.unwrap(), Bytes::from(r#"{"id":"1234"}"#));
+    }
+
+    #[tokio::test]
+    async fn queues_events_when_cumulocity_is_unreachable() {
+        let _ = env_logger::try_init();
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let port = start_proxy_to_url(
+            "127.0.0.1:0",
+            vec!["test-token"],
+            rcgen::generate_simple_self_signed(["localhost".to_owned()]).unwrap(),
+            None,
+            None,
+            Some(test_cache(&cache_dir)),
+        );
+
+        let body = r#"{"type":"test","text":"offline event"}"#;
+        let res = reqwest_client()
+            .post(format!("https://localhost:{port}/c8y/event/events"))
+            .header("Content-Length", body.len())
+            .body(body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 202);
+        assert_eq!(res.headers()["warning"], QUEUED_WARNING);
+        let queued = std::fs::read_dir(cache_dir.path().join("queue"))
+            .unwrap()
+            .count();
+        assert_eq!(queued, 2, "the request and its body are queued");
+
+        // Only the requests creating data are queued
+        let res = reqwest_client()
+            .put(format!(
+                "https://localhost:{port}/c8y/inventory/managedObjects/1234"
+            ))
+            .header("Content-Length", body.len())
+            .body(body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 502);
+    }
+
+    fn test_cache(dir: &tempfile::TempDir) -> CacheConfig {
+        CacheConfig {
+            dir: Utf8PathBuf::try_from(dir.path().to_path_buf()).unwrap(),
+            max_size: 1024 * 1024,
+            queue_max_size: 1024 * 1024,
+        }
+    }
+
+    const TEST_POLICY: &str = r#"
+[[allow]]
+token = "local-secret"
+methods = ["GET"]
+paths = ["/inventory/*"]
+"#;
+
     #[allow(clippy::disallowed_methods)]
     fn reqwest_client() -> reqwest::Client {
         reqwest::Client::builder()
@@ -1136,6 +1536,25 @@ mod tests {
             tokens,
             rcgen::generate_simple_self_signed(["localhost".to_owned()]).unwrap(),
             None,
+            None,
+            None,
+        )
+    }
+
+    fn start_server_with_policy(
+        target_host: &mockito::Server,
+        tokens: Vec<impl Into<Cow<'static, str>>>,
+        policy: &str,
+    ) -> u16 {
+        let url = target_host.url();
+        let (_scheme, host) = url.split_once("://").unwrap();
+        start_proxy_to_url(
+            host,
+            tokens,
+            rcgen::generate_simple_self_signed(["localhost".to_owned()]).unwrap(),
+            None,
+            Some(AccessPolicy::from_toml(policy).unwrap()),
+            None,
         )
     }
 
@@ -1147,7 +1566,7 @@ mod tests {
     ) -> u16 {
         let url = target_host.url();
         let (_scheme, host) = url.split_once("://").unwrap();
-        start_proxy_to_url(host, tokens, certificate, ca_dir)
+        start_proxy_to_url(host, tokens, certificate, ca_dir, None, None)
     }
 
     #[allow(clippy::disallowed_methods)]
@@ -1156,6 +1575,8 @@ mod tests {
         tokens: Vec<impl Into<Cow<'static, str>>>,
         certificate: rcgen::CertifiedKey<rcgen::KeyPair>,
         ca_dir: Option<Utf8PathBuf>,
+        policy: Option<AccessPolicy>,
+        cache: Option<CacheConfig>,
     ) -> u16 {
         let jwt_retriever = IterJwtRetriever::new(tokens).shared();
         let mut last_error = None;
@@ -1165,6 +1586,8 @@ mod tests {
                 host: target_host.into(),
                 token_manager: jwt_retriever.clone(),
                 client: reqwest::Client::new(),
+                policy: policy.clone(),
+                cache: cache.clone(),
             };
             let trust_store = ca_dir
                 .as_ref()
@@ -1176,7 +1599,7 @@ mod tests {
                 trust_store,
             )
             .unwrap();
-            let app = create_app(state);
+            let app = create_app(state.into());
             let res = try_bind_with_tls(app, Ipv4Addr::LOCALHOST.into(), port, config);
             match res {
                 Ok(server) => {
@@ -14,6 +14,7 @@ use crate::error::MessageConversionError;
 use crate::json;
 use crate::operations;
 use crate::operations::OperationHandler;
+use crate::operations::SupportedLogTypes;
 use crate::supported_operations::operation::get_child_ops;
 use crate::supported_operations::operation::Operation;
 use crate::supported_operations::operation::ResultFormat;
@@ -189,6 +190,7 @@ pub struct CumulocityConverter {
     active_commands_last_cleared: Instant,
 
     supported_operations: SupportedOperations,
+    supported_log_types: HashMap<EntityTopicId, SupportedLogTypes>,
     pub operation_handler: OperationHandler,
 }
 
@@ -266,6 +268,7 @@ impl CumulocityConverter {
             device_name: device_id,
             alarm_converter,
             supported_operations: operation_manager,
+            supported_log_types: HashMap::new(),
             operation_logs,
             http_proxy,
             mqtt_publisher,
@@ -1281,6 +1284,7 @@ impl CumulocityConverter {
                         self.register_software_update_operation(&source).await
                     }
                     OperationType::LogUpload => self.convert_log_metadata(&source, message).await,
+                    OperationType::DiagCollect => self.convert_diag_collect_metadata(&source).await,
                     OperationType::ConfigSnapshot => {
                         self.convert_config_snapshot_metadata(&source, message)
                             .await
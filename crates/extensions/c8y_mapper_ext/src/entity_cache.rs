@@ -208,6 +208,7 @@ impl EntityCache {
             r#type: entity.r#type,
             parent,
             health_endpoint: entity.health_endpoint,
+            process: entity.process,
             twin_data: entity.twin_data,
         };
 
@@ -257,6 +258,10 @@ impl EntityCache {
                 .health_endpoint
                 .clone()
                 .or_else(|| existing_entity.health_endpoint.clone()),
+            process: entity
+                .process
+                .clone()
+                .or_else(|| existing_entity.process.clone()),
             twin_data: existing_entity.twin_data.clone(),
         };
 
@@ -435,6 +440,7 @@ mod tests {
             external_id: Some("bad+id".into()),
             parent: None,
             health_endpoint: None,
+            process: None,
             twin_data: Map::new(),
         });
 
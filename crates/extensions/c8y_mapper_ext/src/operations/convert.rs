@@ -14,6 +14,7 @@ use tedge_api::commands::CommandStatus;
 use tedge_api::commands::ConfigMetadata;
 use tedge_api::commands::ConfigSnapshotCmdPayload;
 use tedge_api::commands::ConfigUpdateCmdPayload;
+use tedge_api::commands::DiagCollectCmdPayload;
 use tedge_api::commands::FirmwareUpdateCmdPayload;
 use tedge_api::commands::LogMetadata;
 use tedge_api::commands::LogUploadCmdPayload;
@@ -34,6 +35,28 @@ use crate::converter::CumulocityConverter;
 use crate::error::ConversionError;
 use crate::error::CumulocityMapperError;
 
+/// The log type used to request diagnostic information from the devices supporting `diag_collect`
+pub(crate) const DIAGNOSTICS_LOG_TYPE: &str = "diagnostics";
+
+/// The log types supported by an entity, as advertised by its `log_upload` and `diag_collect` capabilities
+#[derive(Debug, Default)]
+pub(crate) struct SupportedLogTypes {
+    log_upload: Vec<String>,
+    diag_collect: bool,
+}
+
+impl SupportedLogTypes {
+    fn smartrest_payload(&self) -> String {
+        let mut types = self.log_upload.clone();
+        if self.diag_collect && !types.iter().any(|t| t == DIAGNOSTICS_LOG_TYPE) {
+            types.push(DIAGNOSTICS_LOG_TYPE.to_string());
+        }
+        types.sort();
+        let supported_log_types = types.join(",");
+        format!("{SET_SUPPORTED_LOGS},{supported_log_types}")
+    }
+}
+
 impl CumulocityConverter {
     /// Converts a config_snapshot metadata message to
     /// - supported operation "c8y_UploadConfigFile"
@@ -102,6 +125,7 @@ impl CumulocityConverter {
             config_type: config_upload_request.config_type,
             path: None,
             log_path: None,
+            force: false,
         };
 
         // Command messages must be retained
@@ -121,6 +145,35 @@ impl CumulocityConverter {
             .entity_cache
             .try_get_by_external_id(&device_xid.into())?;
 
+        // Diagnostic information is collected by a `diag_collect` command, if supported by the device
+        if log_request.log_file == DIAGNOSTICS_LOG_TYPE
+            && self
+                .supported_log_types
+                .get(&target.metadata.topic_id)
+                .is_some_and(|log_types| log_types.diag_collect)
+        {
+            let channel = Channel::Command {
+                operation: OperationType::DiagCollect,
+                cmd_id: cmd_id.clone(),
+            };
+            let topic = self
+                .mqtt_schema
+                .topic_for(&target.metadata.topic_id, &channel);
+            let tedge_url = format!(
+                "http://{}/te/v1/files/{}/diag_collect/{DIAGNOSTICS_LOG_TYPE}-{cmd_id}",
+                &self.config.tedge_http_host,
+                target.external_id.as_ref(),
+            );
+            let request = DiagCollectCmdPayload {
+                status: CommandStatus::Init,
+                tedge_url: Some(tedge_url),
+                ..Default::default()
+            };
+            return Ok(vec![
+                MqttMessage::new(&topic, request.to_json()).with_retain()
+            ]);
+        }
+
         let channel = Channel::Command {
             operation: OperationType::LogUpload,
             cmd_id: cmd_id.clone(),
@@ -182,10 +235,49 @@ impl CumulocityConverter {
 
         // To SmartREST supported log types
         let metadata = LogMetadata::from_json(message.payload_str()?)?;
-        let mut types = metadata.types;
-        types.sort();
-        let supported_log_types = types.join(",");
-        let payload = format!("{SET_SUPPORTED_LOGS},{supported_log_types}");
+        let log_types = self
+            .supported_log_types
+            .entry(topic_id.clone())
+            .or_default();
+        log_types.log_upload = metadata.types;
+        let payload = log_types.smartrest_payload();
+        let c8y_topic = self.smartrest_publish_topic_for_entity(topic_id)?;
+        messages.push(MqttMessage::new(&c8y_topic, payload));
+
+        Ok(messages)
+    }
+
+    /// Converts a diag_collect metadata message to
+    /// - supported operation "c8y_LogfileRequest"
+    /// - supported log types, adding the "diagnostics" log type
+    pub async fn convert_diag_collect_metadata(
+        &mut self,
+        topic_id: &EntityTopicId,
+    ) -> Result<Vec<MqttMessage>, ConversionError> {
+        if !self.config.capabilities.log_upload {
+            warn!("Received diag_collect metadata, however, log_upload feature is disabled");
+            return Ok(vec![]);
+        }
+
+        let mut messages = match self
+            .register_operation(topic_id, "c8y_LogfileRequest")
+            .await
+        {
+            Err(err) => {
+                error!(
+                    "Failed to register `c8y_LogfileRequest` operation for {topic_id} due to: {err}"
+                );
+                return Ok(vec![]);
+            }
+            Ok(messages) => messages,
+        };
+
+        let log_types = self
+            .supported_log_types
+            .entry(topic_id.clone())
+            .or_default();
+        log_types.diag_collect = true;
+        let payload = log_types.smartrest_payload();
         let c8y_topic = self.smartrest_publish_topic_for_entity(topic_id)?;
         messages.push(MqttMessage::new(&c8y_topic, payload));
 
@@ -298,6 +390,7 @@ impl CumulocityConverter {
             config_type: config_download_request.config_type.clone(),
             path: None,
             log_path: None,
+            force: false,
         };
 
         // Command messages must be retained
@@ -224,6 +224,8 @@ impl OperationHandler {
             topics.extend([
                 (AnyEntity, Command(OperationType::LogUpload)),
                 (AnyEntity, CommandMetadata(OperationType::LogUpload)),
+                (AnyEntity, Command(OperationType::DiagCollect)),
+                (AnyEntity, CommandMetadata(OperationType::DiagCollect)),
             ]);
         }
         if capabilities.config_snapshot {
@@ -0,0 +1,268 @@
+use super::error::OperationError;
+use super::EntityTarget;
+use super::OperationContext;
+use super::OperationOutcome;
+use crate::operations::convert::DIAGNOSTICS_LOG_TYPE;
+use anyhow::Context;
+use c8y_api::smartrest::smartrest_serializer::CumulocitySupportedOperations;
+use camino::Utf8PathBuf;
+use tedge_api::commands::CommandStatus;
+use tedge_api::commands::DiagCollectCommand;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_downloader_ext::DownloadRequest;
+use tedge_mqtt_ext::MqttMessage;
+use tracing::log::warn;
+
+impl OperationContext {
+    /// Address a received diag_collect command, triggered by a c8y_LogfileRequest for diagnostics. If its status is
+    /// - "executing", it converts the message to SmartREST "Executing".
+    /// - "successful", it uploads the diagnostic tarball to c8y as a "diagnostics" log file.
+    /// - "failed", it converts the message to SmartREST "Failed".
+    pub async fn handle_diag_collect_state_change(
+        &self,
+        target: &EntityTarget,
+        cmd_id: &str,
+        message: &MqttMessage,
+    ) -> Result<OperationOutcome, OperationError> {
+        if !self.capabilities.log_upload {
+            warn!("Received a diag_collect command, however, log_upload feature is disabled");
+            return Ok(OperationOutcome::Ignored);
+        }
+
+        let command = match DiagCollectCommand::try_from_bytes(
+            target.topic_id.clone(),
+            cmd_id.into(),
+            message.payload_bytes(),
+        )
+        .context("Could not parse command as a diag collect command")?
+        {
+            Some(command) => command,
+            None => {
+                // The command has been fully processed
+                return Ok(OperationOutcome::Ignored);
+            }
+        };
+
+        let smartrest_topic = &target.smartrest_publish_topic;
+
+        match command.status() {
+            CommandStatus::Executing => Ok(OperationOutcome::Executing {
+                extra_messages: vec![],
+            }),
+            CommandStatus::Successful => {
+                let tedge_file_url = command
+                    .payload
+                    .tedge_url
+                    .as_ref()
+                    .context("No tedgeUrl in the successful diag_collect command")?;
+
+                // Send a request to the Downloader to download the tarball from FTS
+                let tarball_name = format!("{DIAGNOSTICS_LOG_TYPE}-{cmd_id}.tar.gz");
+                let destination_dir = tempfile::tempdir_in(self.tmp_dir.as_std_path())
+                    .context("Failed to create a temporary directory")?;
+                let destination_path = destination_dir.path().join(&tarball_name);
+
+                let download_request = DownloadRequest::new(tedge_file_url, &destination_path);
+                let (_, download_result) = self
+                    .downloader
+                    .clone()
+                    .await_response((cmd_id.into(), download_request))
+                    .await
+                    .context("Unexpected ChannelError")?;
+
+                let download_response = download_result.context(
+                    "tedge-mapper-c8y failed to download diagnostics from file transfer service",
+                )?;
+
+                let file_path = Utf8PathBuf::try_from(download_response.file_path)
+                    .map_err(|e| e.into_io_error())
+                    .context("Could not parse file path as Utf-8")?;
+
+                let (binary_upload_event_url, upload_result) = self
+                    .upload_file(
+                        &target.external_id,
+                        &file_path,
+                        Some(format!("{}_{tarball_name}", target.external_id.as_ref())),
+                        Some(mime::APPLICATION_OCTET_STREAM),
+                        cmd_id,
+                        DIAGNOSTICS_LOG_TYPE.to_string(),
+                        None,
+                    )
+                    .await
+                    .context("Could not upload diagnostics to C8y")?;
+
+                let smartrest_response = super::get_smartrest_response_for_upload_result(
+                    upload_result,
+                    binary_upload_event_url.as_str(),
+                    CumulocitySupportedOperations::C8yLogFileRequest,
+                    self.smart_rest_use_operation_id,
+                    self.get_operation_id(cmd_id),
+                );
+
+                let c8y_notification = MqttMessage::new(smartrest_topic, smartrest_response);
+
+                self.upload_operation_log(
+                    &target.external_id,
+                    cmd_id,
+                    &OperationType::DiagCollect,
+                    &command.clone().into_generic_command(&self.mqtt_schema),
+                )
+                .await
+                .context("Could not upload operation log")?;
+
+                Ok(OperationOutcome::Finished {
+                    messages: vec![c8y_notification],
+                })
+            }
+            CommandStatus::Failed { reason } => Err(anyhow::anyhow!(reason).into()),
+            _ => {
+                // Do nothing as other components might handle those states
+                Ok(OperationOutcome::Ignored)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::*;
+    use c8y_api::json_c8y_deserializer::C8yDeviceControlTopic;
+    use serde_json::json;
+    use std::time::Duration;
+    use tedge_actors::test_helpers::MessageReceiverExt;
+    use tedge_actors::Sender;
+    use tedge_mqtt_ext::test_helpers::assert_received_contains_str;
+    use tedge_mqtt_ext::test_helpers::assert_received_includes_json;
+    use tedge_mqtt_ext::MqttMessage;
+    use tedge_mqtt_ext::Topic;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    const TEST_TIMEOUT_MS: Duration = Duration::from_millis(3000);
+
+    #[tokio::test]
+    async fn diagnostics_are_added_to_the_supported_log_types() {
+        let ttd = TempTedgeDir::new();
+        let test_handle = spawn_c8y_mapper_actor(&ttd, true).await;
+        let TestHandle { mqtt, .. } = test_handle;
+        let mut mqtt = mqtt.with_timeout(TEST_TIMEOUT_MS);
+
+        skip_init_messages(&mut mqtt).await;
+
+        mqtt.send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/diag_collect"),
+            "{}",
+        ))
+        .await
+        .expect("Send failed");
+        assert_received_contains_str(
+            &mut mqtt,
+            [
+                ("c8y/s/us", "114,c8y_LogfileRequest"),
+                ("c8y/s/us", "118,diagnostics"),
+            ],
+        )
+        .await;
+
+        mqtt.send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/log_upload"),
+            r#"{"types" : [ "typeA", "typeB" ]}"#,
+        ))
+        .await
+        .expect("Send failed");
+        assert_received_contains_str(&mut mqtt, [("c8y/s/us", "118,diagnostics,typeA,typeB")])
+            .await;
+    }
+
+    #[tokio::test]
+    async fn mapper_converts_diagnostics_logfile_req_to_diag_collect_cmd() {
+        let ttd = TempTedgeDir::new();
+        let test_handle = spawn_c8y_mapper_actor(&ttd, true).await;
+        let TestHandle { mqtt, .. } = test_handle;
+        let mut mqtt = mqtt.with_timeout(TEST_TIMEOUT_MS);
+
+        skip_init_messages(&mut mqtt).await;
+
+        mqtt.send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/diag_collect"),
+            "{}",
+        ))
+        .await
+        .expect("Send failed");
+        mqtt.skip(2).await;
+
+        mqtt.send(MqttMessage::new(
+            &C8yDeviceControlTopic::topic(&"c8y".try_into().unwrap()),
+            json!({
+                "id": "123456",
+                "c8y_LogfileRequest": {
+                    "searchText": "",
+                    "logFile": "diagnostics",
+                    "dateTo": "2023-11-29T16:33:50+0100",
+                    "dateFrom": "2023-11-28T16:33:50+0100",
+                    "maximumLines": 1000
+                },
+                "externalSource": {
+                    "externalId": "test-device",
+                    "type": "c8y_Serial"
+                 }
+            })
+            .to_string(),
+        ))
+        .await
+        .expect("Send failed");
+
+        assert_received_includes_json(
+            &mut mqtt,
+            [(
+                "te/device/main///cmd/diag_collect/c8y-mapper-123456",
+                json!({
+                    "status": "init",
+                    "tedgeUrl": "http://localhost:8888/te/v1/files/test-device/diag_collect/diagnostics-c8y-mapper-123456",
+                }),
+            )],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn handle_diag_collect_executing_and_failed_cmd() {
+        let ttd = TempTedgeDir::new();
+        let test_handle = spawn_c8y_mapper_actor(&ttd, true).await;
+        let TestHandle { mqtt, .. } = test_handle;
+        let mut mqtt = mqtt.with_timeout(TEST_TIMEOUT_MS);
+
+        skip_init_messages(&mut mqtt).await;
+
+        mqtt.send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/diag_collect/c8y-mapper-1234"),
+            json!({
+                "status": "executing",
+                "tedgeUrl": "http://localhost:8888/te/v1/files/test-device/diag_collect/diagnostics-c8y-mapper-1234",
+            })
+            .to_string(),
+        ))
+        .await
+        .expect("Send failed");
+        assert_received_contains_str(&mut mqtt, [("c8y/s/us", "501,c8y_LogfileRequest")]).await;
+
+        mqtt.send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/diag_collect/c8y-mapper-1234"),
+            json!({
+                "status": "failed",
+                "reason": "No diagnostic plugins were found",
+                "tedgeUrl": "http://localhost:8888/te/v1/files/test-device/diag_collect/diagnostics-c8y-mapper-1234",
+            })
+            .to_string(),
+        ))
+        .await
+        .expect("Send failed");
+        assert_received_contains_str(
+            &mut mqtt,
+            [(
+                "c8y/s/us",
+                "502,c8y_LogfileRequest,No diagnostic plugins were found",
+            )],
+        )
+        .await;
+    }
+}
@@ -4,6 +4,7 @@ mod config_snapshot;
 mod config_update;
 mod custom_operation;
 mod device_profile;
+mod diag_collect;
 mod firmware_update;
 mod log_upload;
 mod restart;
@@ -132,6 +133,10 @@ impl OperationContext {
                 self.handle_log_upload_state_change(&entity, &cmd_id, &message)
                     .await
             }
+            OperationType::DiagCollect => {
+                self.handle_diag_collect_state_change(&entity, &cmd_id, &message)
+                    .await
+            }
             OperationType::ConfigSnapshot => {
                 self.handle_config_snapshot_state_change(&entity, &cmd_id, &message)
                     .await
@@ -327,6 +332,8 @@ pub(super) enum OperationOutcome {
 fn to_c8y_operation(operation_type: &OperationType) -> Option<CumulocitySupportedOperations> {
     match operation_type {
         OperationType::LogUpload => Some(CumulocitySupportedOperations::C8yLogFileRequest),
+        // diag_collect commands are only created on c8y_LogfileRequest for diagnostics
+        OperationType::DiagCollect => Some(CumulocitySupportedOperations::C8yLogFileRequest),
         OperationType::Restart => Some(CumulocitySupportedOperations::C8yRestartRequest),
         OperationType::ConfigSnapshot => Some(CumulocitySupportedOperations::C8yUploadConfigFile),
         OperationType::ConfigUpdate => Some(CumulocitySupportedOperations::C8yDownloadConfigFile),
@@ -17,6 +17,7 @@
 //! https://thin-edge.github.io/thin-edge.io/operate/c8y/supported-operations/
 
 mod convert;
+pub(crate) use convert::SupportedLogTypes;
 mod error;
 
 mod handler;
@@ -206,6 +206,7 @@ mod tests {
                 r#type: EntityType::Service,
                 parent,
                 health_endpoint: None,
+                process: None,
                 twin_data: Map::new(),
             },
         );
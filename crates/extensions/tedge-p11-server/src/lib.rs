@@ -25,6 +25,7 @@ pub fn tedge_p11_service(config: CryptokiConfig) -> anyhow::Result<Arc<dyn Tedge
 
 /// A server listening on the UNIX domain socket, wrapping the service.
 mod proxy;
+pub use proxy::AccessPolicy;
 pub use proxy::TedgeP11Client;
 pub use proxy::TedgeP11Server;
 
@@ -20,6 +20,7 @@ use clap::command;
 use clap::Parser;
 use cryptoki::types::AuthPin;
 use serde::Deserialize;
+use tedge_p11_server::AccessPolicy;
 use tedge_p11_server::CryptokiConfigDirect;
 use tedge_p11_server::TedgeP11Server;
 use tokio::signal::unix::SignalKind;
@@ -60,6 +61,13 @@ pub struct Args {
     #[arg(long, env = "TEDGE_DEVICE_CRYPTOKI_URI", hide_env_values = true)]
     uri: Option<String>,
 
+    /// A path to the access policy, restricting the clients allowed to use the keys.
+    ///
+    /// If not provided, `<config-dir>/tedge-p11-server/policy.toml` is used if it exists.
+    /// Without a policy, any process that can connect to the socket can use any key on the token.
+    #[arg(long, env = "TEDGE_P11_SERVER_POLICY_PATH", hide_env_values = true)]
+    policy_path: Option<Utf8PathBuf>,
+
     /// Configures the logging level.
     ///
     /// One of error/warn/info/debug/trace. Logs with verbosity lower or equal to the selected level
@@ -181,6 +189,7 @@ struct ValidConfig {
     module_path: Utf8PathBuf,
     socket_path: Utf8PathBuf,
     uri: Option<String>,
+    policy_path: Option<Utf8PathBuf>,
 }
 async fn try_read_tedge_toml(
     toml_path: &Utf8PathBuf,
@@ -199,6 +208,10 @@ async fn try_read_tedge_toml(
 
 async fn try_read_config(args: Args) -> anyhow::Result<ValidConfig> {
     let toml_path = args.config_dir.join("tedge.toml");
+    let policy_path = args.policy_path.or_else(|| {
+        let default_path = args.config_dir.join("tedge-p11-server/policy.toml");
+        default_path.exists().then_some(default_path)
+    });
     let mut toml_config = TomlConfig::read_tedge_toml(&toml_path).await;
 
     let (pin, Some(module_path), socket_path, uri) = (
@@ -216,6 +229,7 @@ async fn try_read_config(args: Args) -> anyhow::Result<ValidConfig> {
         module_path,
         socket_path,
         uri,
+        policy_path,
     })
 }
 
@@ -249,6 +263,18 @@ async fn main() -> anyhow::Result<()> {
 
     info!(?cryptoki_config, "Using cryptoki configuration");
 
+    let policy = match &config.policy_path {
+        Some(policy_path) => {
+            info!(%policy_path, "Using access policy");
+            let default_uri = cryptoki_config.uri.as_deref().map(ToString::to_string);
+            AccessPolicy::from_file(policy_path.as_std_path(), default_uri)?
+        }
+        None => {
+            warn!("No access policy: any client connecting to the socket can use any key");
+            AccessPolicy::AllowAll
+        }
+    };
+
     // make sure that if we bind to unix socket in the program, it's removed on exit
     let (listener, _drop_guard) = {
         let mut systemd_listeners = sd_listen_fds::get()
@@ -283,7 +309,7 @@ async fn main() -> anyhow::Result<()> {
     let listener = tokio::net::UnixListener::from_std(listener)?;
     let service = tedge_p11_server::pkcs11::Cryptoki::new(cryptoki_config)
         .context("Failed to create the signing service")?;
-    let server = TedgeP11Server::new(service)?;
+    let server = TedgeP11Server::new(service)?.with_policy(policy);
     tokio::spawn(async move { server.serve(listener).await });
 
     // by capturing SIGINT and SIGERM, we allow owned socket drop guard to run before exit
@@ -25,6 +25,8 @@ use cryptoki::object::ObjectClass;
 use cryptoki::object::ObjectHandle;
 use cryptoki::session::Session;
 use cryptoki::session::UserType;
+use cryptoki::slot::Slot;
+use cryptoki::slot::TokenInfo;
 use rsa::pkcs1::EncodeRsaPublicKey;
 use rustls::sign::Signer;
 use rustls::sign::SigningKey;
@@ -45,11 +47,16 @@ pub use cryptoki::types::AuthPin;
 use crate::service;
 use crate::service::ChooseSchemeRequest;
 use crate::service::ChooseSchemeResponse;
+use crate::service::CreateCsrRequest;
+use crate::service::CreateCsrResponse;
+use crate::service::GenerateKeyRequest;
+use crate::service::GenerateKeyResponse;
+use crate::service::KeyTypeRequest;
 use crate::service::SignRequestWithSigScheme;
 use crate::service::SignResponse;
 use crate::service::TedgeP11Service;
 
-mod uri;
+pub(crate) mod uri;
 
 // oIDs for curves defined here: https://datatracker.ietf.org/doc/html/rfc5480#section-2.1.1.1
 // other can be browsed here: https://oid-base.com/get/1.3.132.0.34
@@ -57,6 +64,12 @@ const SECP256R1_OID: &str = "1.2.840.10045.3.1.7";
 const SECP384R1_OID: &str = "1.3.132.0.34";
 const SECP521R1_OID: &str = "1.3.132.0.35";
 
+// DER-encoded oIDs of the same curves, as expected by CKA_EC_PARAMS when generating a key
+const SECP256R1_EC_PARAMS: &[u8] = &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const SECP384R1_EC_PARAMS: &[u8] = &[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22];
+
+const RSA_PUBLIC_EXPONENT: [u8; 3] = [0x01, 0x00, 0x01];
+
 #[derive(Clone)]
 pub struct CryptokiConfigDirect {
     pub module_path: Utf8PathBuf,
@@ -107,6 +120,14 @@ impl TedgeP11Service for Cryptoki {
     fn get_public_key_pem(&self, uri: Option<&str>) -> anyhow::Result<String> {
         self.get_public_key_pem(uri)
     }
+
+    fn generate_key(&self, request: GenerateKeyRequest) -> anyhow::Result<GenerateKeyResponse> {
+        self.generate_key(request)
+    }
+
+    fn create_csr(&self, request: CreateCsrRequest) -> anyhow::Result<CreateCsrResponse> {
+        self.create_csr(request)
+    }
 }
 
 impl Cryptoki {
@@ -132,7 +153,7 @@ impl Cryptoki {
         })
     }
 
-    fn open_session(&self, uri_attributes: &uri::Pkcs11Uri) -> anyhow::Result<Session> {
+    fn select_slot(&self, uri_attributes: &uri::Pkcs11Uri) -> anyhow::Result<(Slot, TokenInfo)> {
         let wanted_label = uri_attributes.token.as_ref();
         let wanted_serial = uri_attributes.serial.as_ref();
 
@@ -155,14 +176,19 @@ impl Cryptoki {
             .filter(|(_, t)| {
                 wanted_serial.is_none() || wanted_serial.is_some_and(|s| t.serial_number() == s)
             });
-        let (slot, _) = tokens
+        let (slot, token_info) = tokens
             .next()
             .context("Didn't find a slot to use. The device may be disconnected.")?;
 
         let slot_info = self.context.get_slot_info(slot)?;
-        let token_info = self.context.get_token_info(slot)?;
         debug!(?slot_info, ?token_info, "Selected slot");
 
+        Ok((slot, token_info))
+    }
+
+    fn open_session(&self, uri_attributes: &uri::Pkcs11Uri) -> anyhow::Result<Session> {
+        let (slot, _) = self.select_slot(uri_attributes)?;
+
         let session = self.context.open_ro_session(slot)?;
         session.login(UserType::User, Some(&self.config.pin))?;
         let session_info = session.get_session_info()?;
@@ -240,6 +266,135 @@ impl Cryptoki {
         export_public_key_pem(&session, key)
     }
 
+    fn generate_key(&self, request: GenerateKeyRequest) -> anyhow::Result<GenerateKeyResponse> {
+        let uri_attributes = self.request_uri(request.uri.as_deref())?;
+        let (slot, token_info) = self.select_slot(&uri_attributes)?;
+
+        let session = self.context.open_rw_session(slot)?;
+        session.login(UserType::User, Some(&self.config.pin))?;
+        let session_info = session.get_session_info()?;
+        debug!(?session_info, "Opened a read-write session");
+
+        let key_uri = uri::Pkcs11Uri {
+            token: Some(token_info.label().into()),
+            object: Some(request.label.as_str().into()),
+            id: request.id.clone(),
+            ..Default::default()
+        };
+
+        // a second key with the same label and id would make the key selection ambiguous
+        if Self::find_key_by_attributes(&key_uri, &session, ObjectClass::PRIVATE_KEY).is_ok() {
+            anyhow::bail!(
+                "A private key with the same label and id already exists on the token: {key_uri}"
+            );
+        }
+
+        let (mechanism, mut public_template) = match request.key {
+            KeyTypeRequest::Rsa(bits @ (2048 | 3072 | 4096)) => (
+                Mechanism::RsaPkcsKeyPairGen,
+                vec![
+                    Attribute::ModulusBits(u64::from(bits).into()),
+                    Attribute::PublicExponent(RSA_PUBLIC_EXPONENT.to_vec()),
+                ],
+            ),
+            KeyTypeRequest::Ec(size) => {
+                let ec_params = match size {
+                    256 => SECP256R1_EC_PARAMS,
+                    384 => SECP384R1_EC_PARAMS,
+                    // No CSR can be created for a P521 key, which would be left orphaned on the token
+                    521 => anyhow::bail!(
+                        "P521 keys are not supported, as no CSR can be created for them"
+                    ),
+                    _ => anyhow::bail!("Unsupported EC key size: {size}"),
+                };
+                (
+                    Mechanism::EccKeyPairGen,
+                    vec![Attribute::EcParams(ec_params.to_vec())],
+                )
+            }
+            KeyTypeRequest::Rsa(bits) => anyhow::bail!("Unsupported RSA key size: {bits}"),
+        };
+
+        let mut key_attributes = vec![
+            Attribute::Token(true),
+            Attribute::Label(request.label.as_bytes().to_vec()),
+        ];
+        if let Some(id) = &request.id {
+            key_attributes.push(Attribute::Id(id.clone()));
+        }
+
+        public_template.push(Attribute::Verify(true));
+        public_template.extend(key_attributes.iter().cloned());
+
+        let mut private_template = vec![
+            Attribute::Private(true),
+            Attribute::Sensitive(true),
+            Attribute::Extractable(false),
+            Attribute::Sign(true),
+        ];
+        private_template.extend(key_attributes);
+
+        trace!(
+            ?mechanism,
+            ?public_template,
+            ?private_template,
+            "Generating a key pair"
+        );
+        let (public_key, _private_key) = session
+            .generate_key_pair(&mechanism, &public_template, &private_template)
+            .context("Failed to generate a key pair")?;
+
+        Ok(GenerateKeyResponse {
+            uri: key_uri.to_string(),
+            public_key_pem: export_public_key_pem(&session, public_key)?,
+        })
+    }
+
+    fn create_csr(&self, request: CreateCsrRequest) -> anyhow::Result<CreateCsrResponse> {
+        let mut signer = self
+            .signing_key(request.uri.as_deref())
+            .context("Failed to find a signing key")?;
+        let public_key_pem = self.get_public_key_pem(request.uri.as_deref())?;
+        let public_key = pem::parse(public_key_pem)
+            .context("Failed to parse the public key")?
+            .into_contents();
+
+        // rcgen doesn't support RSA-PSS for CSRs, nor P521 keys
+        let algorithm = match signer.sigscheme {
+            SigScheme::EcdsaNistp256Sha256 => &rcgen::PKCS_ECDSA_P256_SHA256,
+            SigScheme::EcdsaNistp384Sha384 => &rcgen::PKCS_ECDSA_P384_SHA384,
+            SigScheme::RsaPssSha256 | SigScheme::RsaPkcs1Sha256 => {
+                signer.sigscheme = SigScheme::RsaPkcs1Sha256;
+                &rcgen::PKCS_RSA_SHA256
+            }
+            SigScheme::EcdsaNistp521Sha512 => {
+                anyhow::bail!("Creating a CSR for a P521 key is not supported")
+            }
+        };
+        let key_pair = Pkcs11KeyPair {
+            signer,
+            public_key,
+            algorithm,
+        };
+
+        let mut distinguished_name = rcgen::DistinguishedName::new();
+        distinguished_name.push(rcgen::DnType::CommonName, request.common_name);
+        distinguished_name.push(rcgen::DnType::OrganizationName, request.organization_name);
+        distinguished_name.push(
+            rcgen::DnType::OrganizationalUnitName,
+            request.organizational_unit_name,
+        );
+        let mut params = rcgen::CertificateParams::default();
+        params.distinguished_name = distinguished_name;
+
+        let csr = params
+            .serialize_request(&key_pair)
+            .context("Failed to create the CSR")?;
+        let csr_pem = csr.pem().context("Failed to serialize the CSR")?;
+
+        Ok(CreateCsrResponse(csr_pem))
+    }
+
     fn find_key_by_attributes(
         uri: &uri::Pkcs11Uri,
         session: &Session,
@@ -435,6 +590,34 @@ impl Pkcs11Signer {
     }
 }
 
+/// A key pair on the token, used to sign a CSR.
+struct Pkcs11KeyPair {
+    signer: Pkcs11Signer,
+    public_key: Vec<u8>,
+    algorithm: &'static rcgen::SignatureAlgorithm,
+}
+
+impl rcgen::PublicKeyData for Pkcs11KeyPair {
+    fn der_bytes(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    fn algorithm(&self) -> &'static rcgen::SignatureAlgorithm {
+        self.algorithm
+    }
+}
+
+impl rcgen::SigningKey for Pkcs11KeyPair {
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, rcgen::Error> {
+        self.signer
+            .sign(msg, Some(self.signer.sigscheme))
+            .map_err(|err| {
+                warn!("Failed to sign the CSR: {err:#}");
+                rcgen::Error::RemoteKeyError
+            })
+    }
+}
+
 /// Currently supported signature schemes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SigScheme {
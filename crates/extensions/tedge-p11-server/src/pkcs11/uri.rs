@@ -1,5 +1,10 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt::Display;
+
+use percent_encoding::percent_encode;
+use percent_encoding::utf8_percent_encode;
+use percent_encoding::NON_ALPHANUMERIC;
 
 /// Attributes decoded from a PKCS #11 URL.
 ///
@@ -85,6 +90,55 @@ impl<'a> Pkcs11Uri<'a> {
     }
 }
 
+impl Pkcs11Uri<'_> {
+    /// Tell if all the attributes of this URI are also present, with the same values, in the given URI.
+    pub fn matches(&self, uri: &Pkcs11Uri) -> bool {
+        fn matches<T: PartialEq + ?Sized>(pattern: Option<&T>, value: Option<&T>) -> bool {
+            pattern.is_none_or(|pattern| value == Some(pattern))
+        }
+
+        matches(self.token.as_deref(), uri.token.as_deref())
+            && matches(self.serial.as_deref(), uri.serial.as_deref())
+            && matches(self.object.as_deref(), uri.object.as_deref())
+            && matches(self.id.as_deref(), uri.id.as_deref())
+            && self
+                .other
+                .iter()
+                .all(|(attribute, value)| uri.other.get(*attribute) == Some(value))
+    }
+}
+
+/// Formats the attributes relevant to us as a PKCS #11 URI, percent-encoding all the values.
+///
+/// Attributes in `other` are omitted.
+impl Display for Pkcs11Uri<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut attributes = Vec::new();
+        if let Some(token) = &self.token {
+            attributes.push(format!(
+                "token={}",
+                utf8_percent_encode(token, NON_ALPHANUMERIC)
+            ));
+        }
+        if let Some(serial) = &self.serial {
+            attributes.push(format!(
+                "serial={}",
+                utf8_percent_encode(serial, NON_ALPHANUMERIC)
+            ));
+        }
+        if let Some(object) = &self.object {
+            attributes.push(format!(
+                "object={}",
+                utf8_percent_encode(object, NON_ALPHANUMERIC)
+            ));
+        }
+        if let Some(id) = &self.id {
+            attributes.push(format!("id={}", percent_encode(id, NON_ALPHANUMERIC)));
+        }
+        write!(f, "pkcs11:{}", attributes.join(";"))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -188,4 +242,55 @@ mod tests {
         assert_eq!(uri1.other.get("key1").unwrap(), "value1");
         assert_eq!(uri1.other.get("key2").unwrap(), "value2");
     }
+
+    #[test]
+    fn formats_uri_that_can_be_parsed_back() {
+        let uri = Pkcs11Uri {
+            token: Some("my token".into()),
+            object: Some("tedge;key".into()),
+            id: Some(vec![0x01, 0xab]),
+            ..Default::default()
+        };
+
+        let formatted = uri.to_string();
+        assert_eq!(
+            formatted,
+            "pkcs11:token=my%20token;object=tedge%3Bkey;id=%01%AB"
+        );
+
+        let parsed = Pkcs11Uri::parse(&formatted).unwrap();
+        assert_eq!(parsed.token.as_deref(), Some("my token"));
+        assert_eq!(parsed.object.as_deref(), Some("tedge;key"));
+        assert_eq!(parsed.id, Some(vec![0x01, 0xab]));
+        assert!(parsed.serial.is_none());
+    }
+
+    #[test]
+    fn matches_uris_with_the_same_attributes() {
+        let uri = Pkcs11Uri::parse("pkcs11:token=token1;object=key1;id=%01;type=private").unwrap();
+
+        for pattern in [
+            "pkcs11:",
+            "pkcs11:token=token1",
+            "pkcs11:token=token1;object=key1;id=%01",
+            "pkcs11:type=private",
+        ] {
+            assert!(
+                Pkcs11Uri::parse(pattern).unwrap().matches(&uri),
+                "{pattern}"
+            );
+        }
+
+        for pattern in [
+            "pkcs11:token=token2",
+            "pkcs11:token=token1;object=key2",
+            "pkcs11:token=token1;serial=1234",
+            "pkcs11:type=public",
+        ] {
+            assert!(
+                !Pkcs11Uri::parse(pattern).unwrap().matches(&uri),
+                "{pattern}"
+            );
+        }
+    }
 }
@@ -12,6 +12,10 @@ use super::connection::Frame1;
 use crate::pkcs11::SigScheme;
 use crate::service::ChooseSchemeRequest;
 use crate::service::ChooseSchemeResponse;
+use crate::service::CreateCsrRequest;
+use crate::service::CreateCsrResponse;
+use crate::service::GenerateKeyRequest;
+use crate::service::GenerateKeyResponse;
 use crate::service::SignRequest;
 use crate::service::SignRequestWithSigScheme;
 use crate::service::TedgeP11Service;
@@ -59,6 +63,16 @@ impl TedgeP11Service for TedgeP11Client {
         let uri = uri.or(self.uri.as_deref()).map(ToString::to_string);
         self.get_public_key_pem(uri)
     }
+
+    fn generate_key(&self, mut request: GenerateKeyRequest) -> anyhow::Result<GenerateKeyResponse> {
+        request.uri = request.uri.or(self.uri.as_deref().map(ToString::to_string));
+        self.generate_key(request)
+    }
+
+    fn create_csr(&self, mut request: CreateCsrRequest) -> anyhow::Result<CreateCsrResponse> {
+        request.uri = request.uri.or(self.uri.as_deref().map(ToString::to_string));
+        self.create_csr(request)
+    }
 }
 
 impl TedgeP11Client {
@@ -190,6 +204,32 @@ impl TedgeP11Client {
         Ok(pubkey_pem)
     }
 
+    pub fn generate_key(&self, request: GenerateKeyRequest) -> anyhow::Result<GenerateKeyResponse> {
+        let request = Frame1::GenerateKeyRequest(request);
+        let response = self.do_request(request)?;
+
+        let Frame1::GenerateKeyResponse(response) = response else {
+            bail!("protocol error: bad response, expected generate_key, received: {response:?}");
+        };
+
+        debug!("Generate key complete");
+
+        Ok(response)
+    }
+
+    pub fn create_csr(&self, request: CreateCsrRequest) -> anyhow::Result<CreateCsrResponse> {
+        let request = Frame1::CreateCsrRequest(request);
+        let response = self.do_request(request)?;
+
+        let Frame1::CreateCsrResponse(response) = response else {
+            bail!("protocol error: bad response, expected create_csr, received: {response:?}");
+        };
+
+        debug!("Create CSR complete");
+
+        Ok(response)
+    }
+
     fn do_request(&self, request: Frame1) -> anyhow::Result<Frame1> {
         let stream = UnixStream::connect(&self.socket_path).with_context(|| {
             format!(
@@ -15,6 +15,10 @@ use tracing::warn;
 
 use crate::service::ChooseSchemeRequest;
 use crate::service::ChooseSchemeResponse;
+use crate::service::CreateCsrRequest;
+use crate::service::CreateCsrResponse;
+use crate::service::GenerateKeyRequest;
+use crate::service::GenerateKeyResponse;
 use crate::service::SignRequest;
 use crate::service::SignRequestWithSigScheme;
 use crate::service::SignResponse;
@@ -93,6 +97,10 @@ pub enum Frame1 {
     SignRequestWithSigScheme(SignRequestWithSigScheme),
     GetPublicKeyPemRequest(Option<String>),
     GetPublicKeyPemResponse(String),
+    GenerateKeyRequest(GenerateKeyRequest),
+    GenerateKeyResponse(GenerateKeyResponse),
+    CreateCsrRequest(CreateCsrRequest),
+    CreateCsrResponse(CreateCsrResponse),
 }
 
 /// An error that can be returned to the client by the server.
@@ -8,3 +8,7 @@ pub use client::TedgeP11Client;
 
 /// Serialization and framing of messages sent between the client and server.
 mod connection;
+
+/// Access control of the clients, based on the credentials of the connected processes.
+pub mod policy;
+pub use policy::AccessPolicy;
@@ -0,0 +1,346 @@
+//! Access control of the clients connecting to the server.
+//!
+//! The policy is a TOML file listing which local users and groups are allowed to perform which operations with which
+//! keys:
+//!
+//! ```toml
+//! # tedge user: full access to the key used to connect the cloud
+//! [[allow]]
+//! uid = 998
+//! operations = ["choose_scheme", "sign", "get_public_key", "create_csr"]
+//! keys = ["pkcs11:token=my-token;object=tedge"]
+//!
+//! # processes running with a given primary group: can only sign with their own key
+//! [[allow]]
+//! gid = 1001
+//! operations = ["choose_scheme", "sign"]
+//! keys = ["pkcs11:token=my-token;object=app-key"]
+//! ```
+//!
+//! The peer credentials are those provided by the kernel for the UNIX socket connection (`SO_PEERCRED`): the gid is the
+//! primary group of the client process, its supplementary groups being ignored. A request is
+//! allowed if at least one rule matches the uid/gid of the client, lists the requested operation and has a key pattern
+//! matching the key URI. A key pattern matches a URI when all the attributes of the pattern are also present with the
+//! same values in the URI; hence `pkcs11:` matches all the keys.
+//!
+//! The URI checked against the patterns is the URI of the request completed with the URI configured for the server,
+//! the latter taking precedence as done by the PKCS #11 service.
+
+use std::fmt::Display;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Deserialize;
+use tracing::info;
+use tracing::warn;
+
+use crate::pkcs11::uri::Pkcs11Uri;
+
+/// Target of the audit log events, to filter them from regular logs.
+pub const AUDIT_LOG_TARGET: &str = "tedge_p11_server::audit";
+
+/// The credentials of the process connected to the server socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: Option<i32>,
+}
+
+impl Display for PeerCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "uid={} gid={}", self.uid, self.gid)?;
+        if let Some(pid) = self.pid {
+            write!(f, " pid={pid}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The operations a client can be allowed to perform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, strum_macros::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Operation {
+    ChooseScheme,
+    Sign,
+    GetPublicKey,
+    GenerateKey,
+    CreateCsr,
+}
+
+/// The set of rules telling which clients can perform which operations.
+#[derive(Debug, Clone)]
+pub enum AccessPolicy {
+    /// Any client is allowed to perform any operation, as done when no policy file is provided.
+    AllowAll,
+
+    /// Only the clients matching a rule are allowed
+    Rules {
+        rules: Vec<AccessRule>,
+        /// The URI configured for the server, completing the request URIs
+        default_uri: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    allow: Vec<AccessRule>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccessRule {
+    /// The user id of the client, any user if not set
+    pub uid: Option<u32>,
+
+    /// The primary group id of the client process, any group if not set
+    ///
+    /// The supplementary groups of the client are not taken into account.
+    pub gid: Option<u32>,
+
+    /// The allowed operations
+    pub operations: Vec<Operation>,
+
+    /// PKCS #11 URI patterns of the keys that can be used
+    pub keys: Vec<String>,
+}
+
+impl AccessPolicy {
+    /// Read the policy from a TOML file
+    pub fn from_file(path: &Path, default_uri: Option<String>) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the access policy {}", path.display()))?;
+        Self::from_toml(&content, default_uri)
+            .with_context(|| format!("Invalid access policy {}", path.display()))
+    }
+
+    pub fn from_toml(content: &str, default_uri: Option<String>) -> anyhow::Result<Self> {
+        let policy: PolicyFile = toml::from_str(content)?;
+        for rule in &policy.allow {
+            for key in &rule.keys {
+                Pkcs11Uri::parse(key).with_context(|| format!("Invalid key pattern: {key}"))?;
+            }
+        }
+        if let Some(uri) = &default_uri {
+            Pkcs11Uri::parse(uri).context("Failed to parse config PKCS#11 URI")?;
+        }
+        Ok(AccessPolicy::Rules {
+            rules: policy.allow,
+            default_uri,
+        })
+    }
+
+    /// Check that a client is allowed to perform an operation with the key denoted by the uri
+    ///
+    /// The decision is logged in the audit log.
+    pub fn check(
+        &self,
+        peer: Option<PeerCredentials>,
+        operation: Operation,
+        uri: Option<&str>,
+    ) -> Result<(), AccessDenied> {
+        let decision = self.decide(peer, operation, uri);
+        let peer_str = peer.map_or_else(|| "unknown peer".to_string(), |peer| peer.to_string());
+        let uri_str = uri.unwrap_or("<default>");
+        match &decision {
+            Ok(()) => {
+                info!(target: AUDIT_LOG_TARGET, "allowed {operation} for {peer_str} with key {uri_str}")
+            }
+            Err(err) => {
+                warn!(target: AUDIT_LOG_TARGET, "denied {operation} for {peer_str} with key {uri_str}: {err}")
+            }
+        }
+        decision
+    }
+
+    fn decide(
+        &self,
+        peer: Option<PeerCredentials>,
+        operation: Operation,
+        uri: Option<&str>,
+    ) -> Result<(), AccessDenied> {
+        let AccessPolicy::Rules { rules, default_uri } = self else {
+            return Ok(());
+        };
+        let Some(peer) = peer else {
+            return Err(AccessDenied(
+                "the client credentials are unknown".to_string(),
+            ));
+        };
+
+        let mut key = default_uri
+            .as_deref()
+            .map(Pkcs11Uri::parse)
+            .transpose()
+            .map_err(|err| AccessDenied(format!("invalid server URI: {err}")))?
+            .unwrap_or_default();
+        let request_uri = uri
+            .map(Pkcs11Uri::parse)
+            .transpose()
+            .map_err(|err| AccessDenied(format!("invalid key URI: {err}")))?
+            .unwrap_or_default();
+        key.append_attributes(request_uri);
+
+        let allowed = rules
+            .iter()
+            .filter(|rule| rule.matches_peer(&peer))
+            .filter(|rule| rule.operations.contains(&operation))
+            .any(|rule| rule.matches_key(&key));
+        if allowed {
+            Ok(())
+        } else {
+            Err(AccessDenied(format!(
+                "no rule allows {operation} for {peer} with this key"
+            )))
+        }
+    }
+}
+
+impl AccessRule {
+    fn matches_peer(&self, peer: &PeerCredentials) -> bool {
+        self.uid.is_none_or(|uid| uid == peer.uid) && self.gid.is_none_or(|gid| gid == peer.gid)
+    }
+
+    fn matches_key(&self, key: &Pkcs11Uri) -> bool {
+        self.keys
+            .iter()
+            .filter_map(|pattern| Pkcs11Uri::parse(pattern).ok())
+            .any(|pattern| pattern.matches(key))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Access denied: {0}")]
+pub struct AccessDenied(String);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEDGE: PeerCredentials = PeerCredentials {
+        uid: 998,
+        gid: 998,
+        pid: Some(42),
+    };
+
+    const APP: PeerCredentials = PeerCredentials {
+        uid: 1000,
+        gid: 1001,
+        pid: None,
+    };
+
+    const POLICY: &str = r#"
+[[allow]]
+uid = 998
+operations = ["choose_scheme", "sign", "get_public_key"]
+keys = ["pkcs11:token=my-token;object=tedge"]
+
+[[allow]]
+gid = 1001
+operations = ["sign"]
+keys = ["pkcs11:token=my-token;object=app-key", "pkcs11:token=other-token"]
+"#;
+
+    #[test]
+    fn no_policy_allows_everything() {
+        let policy = AccessPolicy::AllowAll;
+        assert!(policy.check(None, Operation::GenerateKey, None).is_ok());
+    }
+
+    #[test]
+    fn rules_select_allowed_keys_and_operations() {
+        let policy = AccessPolicy::from_toml(POLICY, None).unwrap();
+
+        let tedge_key = Some("pkcs11:token=my-token;object=tedge");
+        let app_key = Some("pkcs11:token=my-token;object=app-key");
+        let other_key = Some("pkcs11:token=other-token;object=any-key;id=%01");
+
+        assert!(policy
+            .check(Some(TEDGE), Operation::Sign, tedge_key)
+            .is_ok());
+        assert!(policy
+            .check(Some(TEDGE), Operation::GetPublicKey, tedge_key)
+            .is_ok());
+        assert!(policy.check(Some(TEDGE), Operation::Sign, app_key).is_err());
+        assert!(policy
+            .check(Some(TEDGE), Operation::GenerateKey, tedge_key)
+            .is_err());
+
+        assert!(policy.check(Some(APP), Operation::Sign, app_key).is_ok());
+        assert!(policy.check(Some(APP), Operation::Sign, other_key).is_ok());
+        assert!(policy.check(Some(APP), Operation::Sign, tedge_key).is_err());
+        assert!(policy
+            .check(Some(APP), Operation::ChooseScheme, app_key)
+            .is_err());
+    }
+
+    #[test]
+    fn keys_have_to_be_fully_specified() {
+        let policy = AccessPolicy::from_toml(POLICY, None).unwrap();
+
+        // Without an object, any key of the token might be used
+        assert!(policy
+            .check(Some(TEDGE), Operation::Sign, Some("pkcs11:token=my-token"))
+            .is_err());
+        assert!(policy.check(Some(TEDGE), Operation::Sign, None).is_err());
+    }
+
+    #[test]
+    fn request_uri_is_completed_with_server_uri() {
+        let policy =
+            AccessPolicy::from_toml(POLICY, Some("pkcs11:token=my-token".to_string())).unwrap();
+
+        assert!(policy
+            .check(Some(TEDGE), Operation::Sign, Some("pkcs11:object=tedge"))
+            .is_ok());
+
+        // the server URI takes precedence
+        assert!(policy
+            .check(
+                Some(APP),
+                Operation::Sign,
+                Some("pkcs11:token=other-token;object=app-key")
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn unknown_peers_are_denied() {
+        let policy = AccessPolicy::from_toml(POLICY, None).unwrap();
+        assert!(policy
+            .check(
+                None,
+                Operation::Sign,
+                Some("pkcs11:token=my-token;object=tedge")
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected() {
+        assert!(AccessPolicy::from_toml(
+            r#"
+[[allow]]
+uid = 998
+operations = ["sign"]
+keys = ["token=my-token"]
+"#,
+            None
+        )
+        .is_err());
+
+        assert!(AccessPolicy::from_toml(
+            r#"
+[[allow]]
+uid = 998
+operations = ["delete_key"]
+keys = ["pkcs11:"]
+"#,
+            None
+        )
+        .is_err());
+    }
+}
@@ -3,17 +3,26 @@ use tokio::net::UnixListener;
 use anyhow::Context;
 use tracing::error;
 use tracing::info;
+use tracing::warn;
 
 use super::connection::Connection;
 use super::connection::Frame1;
 use super::connection::ProtocolError;
+use super::policy::AccessPolicy;
+use super::policy::Operation;
+use super::policy::PeerCredentials;
+use crate::pkcs11::uri::Pkcs11Uri;
+use crate::service::GenerateKeyRequest;
 use crate::service::SignRequestWithSigScheme;
 use crate::service::TedgeP11Service;
 
 /// Relays requests made by [`TedgeP11Client`](super::TedgeP11Client) to the inner PKCS #11 service and returns
 /// responses.
+///
+/// The requests are checked against the [`AccessPolicy`], using the credentials of the client process.
 pub struct TedgeP11Server {
     service: Box<dyn TedgeP11Service>,
+    policy: AccessPolicy,
 }
 
 impl TedgeP11Server {
@@ -23,9 +32,15 @@ impl TedgeP11Server {
     {
         Ok(Self {
             service: Box::new(service),
+            policy: AccessPolicy::AllowAll,
         })
     }
 
+    /// Restrict the clients allowed to use the service.
+    pub fn with_policy(self, policy: AccessPolicy) -> Self {
+        Self { policy, ..self }
+    }
+
     /// Handle multiple requests on a given listener.
     pub async fn serve(&self, listener: UnixListener) -> anyhow::Result<()> {
         // Accept a connection
@@ -35,27 +50,52 @@ impl TedgeP11Server {
                 .await
                 .context("Failed to accept connection")?;
 
+            let peer = match stream.peer_cred() {
+                Ok(credentials) => Some(PeerCredentials {
+                    uid: credentials.uid(),
+                    gid: credentials.gid(),
+                    pid: credentials.pid(),
+                }),
+                Err(err) => {
+                    warn!("Failed to get the credentials of the client: {err}");
+                    None
+                }
+            };
+
             let stream = stream.into_std()?;
             stream
                 .set_nonblocking(false)
                 .context("Failed to set nonblocking=false")?;
             let connection = Connection::new(stream);
 
-            match self.process(connection) {
+            match self.process(connection, peer) {
                 Ok(_) => info!("Incoming request successful"),
                 Err(e) => error!("Incoming request failed: {e:?}"),
             }
         }
     }
 
-    fn process(&self, mut connection: Connection) -> anyhow::Result<()> {
+    fn process(
+        &self,
+        mut connection: Connection,
+        peer: Option<PeerCredentials>,
+    ) -> anyhow::Result<()> {
         let request = connection.read_frame().context("read")?;
 
+        if let Some((operation, uri)) = requested_access(&request) {
+            if let Err(err) = self.policy.check(peer, operation, uri.as_deref()) {
+                let _ = connection.write_frame(&Frame1::Error(ProtocolError(err.to_string())));
+                anyhow::bail!(err);
+            }
+        }
+
         let response = match request {
             Frame1::Error(_)
             | Frame1::ChooseSchemeResponse { .. }
             | Frame1::SignResponse { .. }
-            | Frame1::GetPublicKeyPemResponse(_) => {
+            | Frame1::GetPublicKeyPemResponse(_)
+            | Frame1::GenerateKeyResponse(_)
+            | Frame1::CreateCsrResponse(_) => {
                 let error = ProtocolError("invalid request".to_string());
                 let _ = connection.write_frame(&Frame1::Error(error));
                 anyhow::bail!("protocol error: invalid request")
@@ -118,6 +158,34 @@ impl TedgeP11Server {
                     }
                 }
             }
+
+            Frame1::GenerateKeyRequest(request) => {
+                let response = self.service.generate_key(request);
+                match response {
+                    Ok(response) => Frame1::GenerateKeyResponse(response),
+                    Err(err) => {
+                        let response = Frame1::Error(ProtocolError(format!(
+                            "PKCS #11 service failed: {err:#}"
+                        )));
+                        connection.write_frame(&response)?;
+                        anyhow::bail!(err);
+                    }
+                }
+            }
+
+            Frame1::CreateCsrRequest(request) => {
+                let response = self.service.create_csr(request);
+                match response {
+                    Ok(response) => Frame1::CreateCsrResponse(response),
+                    Err(err) => {
+                        let response = Frame1::Error(ProtocolError(format!(
+                            "PKCS #11 service failed: {err:#}"
+                        )));
+                        connection.write_frame(&response)?;
+                        anyhow::bail!(err);
+                    }
+                }
+            }
         };
 
         connection.write_frame(&response).context("write")?;
@@ -126,6 +194,41 @@ impl TedgeP11Server {
     }
 }
 
+/// The operation and the key URI of a request, if a valid request
+fn requested_access(request: &Frame1) -> Option<(Operation, Option<String>)> {
+    match request {
+        Frame1::ChooseSchemeRequest(request) => {
+            Some((Operation::ChooseScheme, request.uri.clone()))
+        }
+        Frame1::SignRequest(request) => Some((Operation::Sign, request.uri.clone())),
+        Frame1::SignRequestWithSigScheme(request) => Some((Operation::Sign, request.uri.clone())),
+        Frame1::GetPublicKeyPemRequest(uri) => Some((Operation::GetPublicKey, uri.clone())),
+        Frame1::GenerateKeyRequest(request) => {
+            Some((Operation::GenerateKey, generated_key_uri(request)))
+        }
+        Frame1::CreateCsrRequest(request) => Some((Operation::CreateCsr, request.uri.clone())),
+        Frame1::Error(_)
+        | Frame1::ChooseSchemeResponse(_)
+        | Frame1::SignResponse(_)
+        | Frame1::GetPublicKeyPemResponse(_)
+        | Frame1::GenerateKeyResponse(_)
+        | Frame1::CreateCsrResponse(_) => None,
+    }
+}
+
+/// The URI of a key to be generated, i.e. the token URI completed with the key label and id
+fn generated_key_uri(request: &GenerateKeyRequest) -> Option<String> {
+    let mut uri = match request.uri.as_deref().map(Pkcs11Uri::parse) {
+        None => Pkcs11Uri::default(),
+        Some(Ok(uri)) => uri,
+        // let the service reject the invalid uri
+        Some(Err(_)) => return request.uri.clone(),
+    };
+    uri.object = Some(request.label.as_str().into());
+    uri.id = request.id.clone();
+    Some(uri.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,6 +242,9 @@ mod tests {
 
     const SCHEME: pkcs11::SigScheme = pkcs11::SigScheme::EcdsaNistp256Sha256;
     const SIGNATURE: [u8; 2] = [0x21, 0x37];
+    const PUBLIC_KEY_PEM: &str = "-----BEGIN PUBLIC KEY-----\n-----END PUBLIC KEY-----\n";
+    const CSR_PEM: &str =
+        "-----BEGIN CERTIFICATE REQUEST-----\n-----END CERTIFICATE REQUEST-----\n";
 
     struct TestSigningService;
 
@@ -160,6 +266,18 @@ mod tests {
         fn get_public_key_pem(&self, _uri: Option<&str>) -> anyhow::Result<String> {
             todo!()
         }
+
+        fn generate_key(&self, request: GenerateKeyRequest) -> anyhow::Result<GenerateKeyResponse> {
+            Ok(GenerateKeyResponse {
+                uri: format!("pkcs11:token=test;object={}", request.label),
+                public_key_pem: PUBLIC_KEY_PEM.to_string(),
+            })
+        }
+
+        fn create_csr(&self, request: CreateCsrRequest) -> anyhow::Result<CreateCsrResponse> {
+            anyhow::ensure!(request.uri.is_some(), "no key uri");
+            Ok(CreateCsrResponse(CSR_PEM.to_string()))
+        }
     }
 
     /// Check that client successfully receives responses from the server about the requests. Tests the
@@ -183,6 +301,79 @@ mod tests {
                 SCHEME.into()
             );
             assert_eq!(&client.sign2(&[], None, SCHEME).unwrap(), &SIGNATURE[..]);
+
+            let key = client
+                .generate_key(GenerateKeyRequest {
+                    key: KeyTypeRequest::Ec(256),
+                    label: "tedge".to_string(),
+                    id: None,
+                    uri: None,
+                })
+                .unwrap();
+            assert_eq!(key.uri, "pkcs11:token=test;object=tedge");
+            assert_eq!(key.public_key_pem, PUBLIC_KEY_PEM);
+
+            let csr = client
+                .create_csr(CreateCsrRequest {
+                    common_name: "test-device".to_string(),
+                    organization_name: "Thin Edge".to_string(),
+                    organizational_unit_name: "Test Device".to_string(),
+                    uri: Some(key.uri),
+                })
+                .unwrap();
+            assert_eq!(csr.0, CSR_PEM);
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn server_rejects_requests_denied_by_the_policy() {
+        use std::os::unix::fs::MetadataExt;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let metadata = std::fs::metadata(tmpdir.path()).unwrap();
+        let policy = format!(
+            r#"
+[[allow]]
+uid = {uid}
+gid = {gid}
+operations = ["sign"]
+keys = ["pkcs11:object=allowed"]
+"#,
+            uid = metadata.uid(),
+            gid = metadata.gid()
+        );
+        let policy = AccessPolicy::from_toml(&policy, None).unwrap();
+
+        let server = TedgeP11Server::new(TestSigningService)
+            .unwrap()
+            .with_policy(policy);
+        let socket_path = tmpdir.path().join("test_socket.sock");
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        tokio::spawn(async move { server.serve(listener).await });
+        // wait until the server calls accept()
+        tokio::time::sleep(Duration::from_millis(2)).await;
+
+        tokio::task::spawn_blocking(move || {
+            let client = TedgeP11Client::with_ready_check(socket_path.into());
+            assert_eq!(
+                &client
+                    .sign2(&[], Some("pkcs11:object=allowed".to_string()), SCHEME)
+                    .unwrap(),
+                &SIGNATURE[..]
+            );
+
+            let err = client
+                .sign2(&[], Some("pkcs11:object=other".to_string()), SCHEME)
+                .unwrap_err();
+            assert!(format!("{err:#}").contains("Access denied"), "{err:#}");
+
+            let err = client
+                .choose_scheme(&[], Some("pkcs11:object=allowed".to_string()))
+                .unwrap_err();
+            assert!(format!("{err:#}").contains("Access denied"), "{err:#}");
         })
         .await
         .unwrap();
@@ -19,6 +19,16 @@ pub trait TedgeP11Service: Send + Sync {
     /// Note: in some cases PKCS 11 RSA private key objects may also contain the public exponent attribute, allowing us
     /// to derive the public key from the private key object.
     fn get_public_key_pem(&self, uri: Option<&str>) -> anyhow::Result<String>;
+
+    /// Generates a new key pair on the token (denoted by uri), returning the URI and the public key of the new key.
+    ///
+    /// The private key is created as sensitive and non-extractable, so it never leaves the token.
+    fn generate_key(&self, request: GenerateKeyRequest) -> anyhow::Result<GenerateKeyResponse>;
+
+    /// Creates a certificate signing request for the key pair on the token (denoted by uri).
+    ///
+    /// The CSR is signed by the private key on the token, the public key being read from the token too.
+    fn create_csr(&self, request: CreateCsrRequest) -> anyhow::Result<CreateCsrResponse>;
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -49,6 +59,45 @@ pub struct SignRequestWithSigScheme {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SignResponse(pub Vec<u8>);
 
+/// The type and size of a key to be generated on the token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyTypeRequest {
+    /// An RSA key with the given modulus size in bits (2048, 3072 or 4096)
+    Rsa(u16),
+    /// An ECDSA key on the NIST curve with the given size in bits (256 or 384)
+    Ec(u16),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenerateKeyRequest {
+    pub key: KeyTypeRequest,
+    /// The label (`object` URI attribute) of the new key objects.
+    pub label: String,
+    /// The id (`id` URI attribute) of the new key objects.
+    pub id: Option<Vec<u8>>,
+    /// A URI selecting the token where the key has to be generated.
+    pub uri: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenerateKeyResponse {
+    /// A URI uniquely identifying the new key on the token
+    pub uri: String,
+    pub public_key_pem: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreateCsrRequest {
+    pub common_name: String,
+    pub organization_name: String,
+    pub organizational_unit_name: String,
+    pub uri: Option<String>,
+}
+
+/// A certificate signing request in PEM format.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreateCsrResponse(pub String);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SignatureScheme(pub rustls::SignatureScheme);
 
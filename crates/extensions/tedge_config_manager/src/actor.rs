@@ -5,8 +5,10 @@ use camino::Utf8PathBuf;
 use log::debug;
 use log::error;
 use log::info;
+use log::warn;
 use serde_json::json;
 use std::io::ErrorKind;
+use std::io::Write;
 use std::sync::Arc;
 use tedge_actors::fan_in_message_type;
 use tedge_actors::Actor;
@@ -36,8 +38,10 @@ use tedge_utils::atomic::MaybePermissions;
 use tedge_write::CopyOptions;
 use tedge_write::CreateDirsOptions;
 
+use crate::tedge_toml;
 use crate::FileEntry;
 use crate::TedgeWriteStatus;
+use crate::TEDGE_CONFIG_TYPE;
 
 use super::config::PluginConfig;
 use super::error::ConfigManagementError;
@@ -302,7 +306,7 @@ impl ConfigManagerWorker {
         mut request: ConfigUpdateCmdPayload,
     ) -> Result<(), ChannelError> {
         match self.execute_config_update_request(&topic, &request).await {
-            Ok(deployed_to_path) => {
+            Ok((deployed_to_path, services_to_restart)) => {
                 request.successful(deployed_to_path);
                 info!(
                     "Config Update request processed for config type: {}.",
@@ -310,6 +314,8 @@ impl ConfigManagerWorker {
                 );
                 self.publish_command_status(ConfigOperation::Update(topic, request))
                     .await?;
+                // The agent itself being possibly restarted, this is done once the command is completed
+                self.restart_services(services_to_restart).await;
             }
             Err(error) => {
                 request.failed(error.to_string());
@@ -321,11 +327,13 @@ impl ConfigManagerWorker {
         Ok(())
     }
 
+    /// Returns the path where the configuration file has been deployed,
+    /// along with the services that have to be restarted
     async fn execute_config_update_request(
         &mut self,
         topic: &Topic,
         request: &ConfigUpdateCmdPayload,
-    ) -> Result<Utf8PathBuf, ConfigManagementError> {
+    ) -> Result<(Utf8PathBuf, Vec<String>), ConfigManagementError> {
         let file_entry = self
             .plugin_config
             .get_file_entry_from_type(&request.config_type)?;
@@ -361,6 +369,11 @@ impl ConfigManagerWorker {
         let file_entry = self
             .plugin_config
             .get_file_entry_from_type(&request.config_type)?;
+
+        if file_entry.config_type == TEDGE_CONFIG_TYPE {
+            return self.apply_tedge_toml_update(from_path, file_entry, request.force);
+        }
+
         let to = Utf8PathBuf::from(&file_entry.path);
 
         if let Some(parent) = to.parent() {
@@ -373,7 +386,112 @@ impl ConfigManagerWorker {
             .deploy_config_file(from_path, file_entry)
             .context("failed to deploy configuration file")?;
 
-        Ok(deployed_to_path)
+        Ok((deployed_to_path, vec![]))
+    }
+
+    /// Applies the partial `tedge.toml` downloaded under `from` to `tedge.toml`
+    ///
+    /// The update is rejected if it sets keys that are unsafe to change remotely,
+    /// unless it is forced. Returns the services using the updated keys.
+    fn apply_tedge_toml_update(
+        &self,
+        from: &Utf8Path,
+        file_entry: &FileEntry,
+        force: bool,
+    ) -> Result<(Utf8PathBuf, Vec<String>), ConfigManagementError> {
+        let to = Utf8PathBuf::from(&file_entry.path);
+        let patch = std::fs::read_to_string(from)
+            .with_context(|| format!("failed to read the tedge.toml update '{from}'"))?;
+        let current = match std::fs::read_to_string(&to) {
+            Ok(current) => current,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let config_dir = Utf8Path::from_path(&self.config.config_dir).with_context(|| {
+            format!(
+                "path is not utf-8: '{}'",
+                self.config.config_dir.to_string_lossy()
+            )
+        })?;
+
+        let patched = tedge_config::patch_config_file(config_dir, &current, &patch)
+            .context("invalid tedge.toml update")?;
+
+        let unsafe_keys = tedge_toml::unsafe_keys(&patched.updated_keys);
+        if !unsafe_keys.is_empty() {
+            if !force {
+                return Err(ConfigManagementError::UnsafeConfigUpdate {
+                    keys: unsafe_keys.join(", "),
+                });
+            }
+            warn!("Forcing the update of {}", unsafe_keys.join(", "));
+        }
+
+        if patched.updated_keys.is_empty() {
+            info!("tedge.toml is left unchanged, as all the updated keys are already set");
+            return Ok((to, vec![]));
+        }
+
+        let mut new_content = tempfile::NamedTempFile::new_in(self.config.tmp_path.as_std_path())?;
+        new_content.write_all(patched.content.as_bytes())?;
+        let new_content_path = Utf8Path::from_path(new_content.path()).with_context(|| {
+            format!(
+                "path is not utf-8: '{}'",
+                new_content.path().to_string_lossy()
+            )
+        })?;
+
+        let deployed_to_path = self
+            .deploy_config_file(new_content_path, file_entry)
+            .context("failed to deploy configuration file")?;
+
+        Ok((
+            deployed_to_path,
+            tedge_toml::services_to_restart(&patched.updated_keys),
+        ))
+    }
+
+    /// Restarts the services that are running, among the given ones
+    async fn restart_services(&self, services: Vec<String>) {
+        for service in services {
+            let is_active =
+                self.service_command(&self.config.service_is_active_command, &service, false);
+            match run_service_command(is_active).await {
+                Ok(true) => (),
+                Ok(false) => {
+                    debug!("Not restarting {service}, as it is not running");
+                    continue;
+                }
+                Err(err) => {
+                    warn!("Failed to check if {service} is running: {err}");
+                    continue;
+                }
+            }
+
+            info!("Restarting {service} to apply the tedge.toml update");
+            let restart =
+                self.service_command(&self.config.service_restart_command, &service, true);
+            match run_service_command(restart).await {
+                Ok(true) => (),
+                Ok(false) => warn!("Failed to restart {service}"),
+                Err(err) => warn!("Failed to restart {service}: {err}"),
+            }
+        }
+    }
+
+    fn service_command(
+        &self,
+        command: &[String],
+        service: &str,
+        elevated: bool,
+    ) -> Option<std::process::Command> {
+        let (program, args) = command.split_first()?;
+        let mut command = match &self.config.use_tedge_write {
+            TedgeWriteStatus::Enabled { sudo } if elevated => sudo.command(program),
+            _ => std::process::Command::new(program),
+        };
+        command.args(args.iter().map(|arg| arg.replace("{}", service)));
+        Some(command)
     }
 
     /// Creates the parent directories of the target file if they are missing,
@@ -506,7 +624,8 @@ impl ConfigManagerWorker {
     }
 
     async fn reload_supported_config_types(&mut self) -> Result<(), ChannelError> {
-        self.plugin_config = PluginConfig::new(self.config.plugin_config_path.as_path());
+        self.plugin_config = PluginConfig::new(self.config.plugin_config_path.as_path())
+            .with_tedge_config_entry(&self.config.config_dir);
         self.publish_supported_config_types().await
     }
 
@@ -533,6 +652,15 @@ impl ConfigManagerWorker {
     }
 }
 
+/// Runs a service command, returning whether the command succeeded
+async fn run_service_command(command: Option<std::process::Command>) -> anyhow::Result<bool> {
+    let Some(mut command) = command else {
+        anyhow::bail!("the service command is empty");
+    };
+    let status = tokio::task::spawn_blocking(move || command.status()).await??;
+    Ok(status.success())
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ConfigOperation {
     Snapshot(Topic, ConfigSnapshotCmdPayload),
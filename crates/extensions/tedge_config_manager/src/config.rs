@@ -18,6 +18,7 @@ use tedge_api::mqtt_topics::MqttSchema;
 use tedge_api::mqtt_topics::OperationType;
 use tedge_config::tedge_toml::ReadError;
 use tedge_config::SudoCommandBuilder;
+use tedge_config::SystemConfig;
 use tedge_mqtt_ext::Topic;
 use tedge_mqtt_ext::TopicFilter;
 use tedge_utils::file::PermissionEntry;
@@ -27,6 +28,11 @@ use super::error::InvalidConfigTypeError;
 pub const DEFAULT_PLUGIN_CONFIG_FILE_NAME: &str = "tedge-configuration-plugin.toml";
 pub const DEFAULT_OPERATION_DIR_NAME: &str = "plugins/";
 pub const DEFAULT_PLUGIN_CONFIG_TYPE: &str = "tedge-configuration-plugin";
+/// Built-in config type applying partial updates to `tedge.toml`
+///
+/// This type is distinct from any user-declared `tedge.toml` entry,
+/// which is updated as any other configuration file, replacing its whole content.
+pub const TEDGE_CONFIG_TYPE: &str = "tedge-config";
 
 /// Configuration of the Configuration Manager
 #[derive(Clone, Debug)]
@@ -44,6 +50,11 @@ pub struct ConfigManagerConfig {
     pub use_tedge_write: TedgeWriteStatus,
 
     pub config_update_enabled: bool,
+
+    /// Commands checking if a service is running and restarting it after a `tedge.toml`
+    /// update, `{}` being replaced by the service name
+    pub service_is_active_command: Vec<String>,
+    pub service_restart_command: Vec<String>,
 }
 
 pub struct ConfigManagerOptions {
@@ -63,6 +74,15 @@ impl ConfigManagerConfig {
         let mqtt_device_topic_id = cliopts.mqtt_device_topic_id;
 
         let plugin_config_dir = config_dir.join(DEFAULT_OPERATION_DIR_NAME);
+        let init = Utf8Path::from_path(&config_dir)
+            .and_then(|config_dir| match SystemConfig::try_new(config_dir) {
+                Ok(system_config) => Some(system_config.init),
+                Err(err) => {
+                    warn!("Using the default service commands: {err}");
+                    None
+                }
+            })
+            .unwrap_or_default();
         let plugin_config_path = plugin_config_dir.join(DEFAULT_PLUGIN_CONFIG_FILE_NAME);
 
         let config_reload_topics = [OperationType::ConfigSnapshot, OperationType::ConfigUpdate]
@@ -94,6 +114,8 @@ impl ConfigManagerConfig {
                 sudo: SudoCommandBuilder::enabled(cliopts.is_sudo_enabled),
             },
             config_update_enabled: cliopts.config_update_enabled,
+            service_is_active_command: init.is_active,
+            service_restart_command: init.restart,
         })
     }
 }
@@ -210,6 +232,20 @@ impl PluginConfig {
         plugin_config.add_entries_from_raw_config(raw_config)
     }
 
+    /// Adds the built-in `tedge-config` config type, overriding any user-declared entry of this type
+    pub fn with_tedge_config_entry(mut self, config_dir: &Path) -> Self {
+        let builtin_entry = FileEntry::new(
+            config_dir.join("tedge.toml").display().to_string(),
+            TEDGE_CONFIG_TYPE,
+            PermissionEntry::default(),
+            PermissionEntry::default(),
+        );
+        if self.files.replace(builtin_entry).is_some() {
+            warn!("The config type '{TEDGE_CONFIG_TYPE}' is reserved for tedge.toml updates, ignoring the entry of the plugin config");
+        }
+        self
+    }
+
     fn new_with_config_file_entry(config_file_path: &Path) -> Self {
         let file_entry = FileEntry::new(
             config_file_path.display().to_string(),
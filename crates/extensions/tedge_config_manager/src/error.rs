@@ -35,6 +35,9 @@ pub enum ConfigManagementError {
     #[error(transparent)]
     FromAtomFileError(#[from] tedge_utils::fs::AtomFileError),
 
+    #[error("Refusing to update {keys} remotely, as a wrong value can make the device unreachable. Use `force` to update these keys anyway")]
+    UnsafeConfigUpdate { keys: String },
+
     #[error("{0:#}")]
     Other(#[from] anyhow::Error),
 }
@@ -1,6 +1,7 @@
 mod actor;
 mod config;
 mod error;
+mod tedge_toml;
 
 #[cfg(test)]
 mod tests;
@@ -62,7 +63,8 @@ impl ConfigManagerBuilder {
     ) -> Result<Self, FileError> {
         Self::init(&config).await?;
 
-        let plugin_config = PluginConfig::new(config.plugin_config_path.as_path());
+        let plugin_config = PluginConfig::new(config.plugin_config_path.as_path())
+            .with_tedge_config_entry(&config.config_dir);
         let box_builder = SimpleMessageBoxBuilder::new("Tedge-Config-Manager", 16);
 
         let downloader = ClientMessageBox::new(downloader_actor);
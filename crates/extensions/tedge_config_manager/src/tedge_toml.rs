@@ -0,0 +1,109 @@
+//! Rules applied when `tedge.toml` is updated remotely
+//!
+//! A `tedge.toml` config update is a partial `tedge.toml`, setting only the
+//! keys to be updated. The keys that can make the device unreachable if
+//! wrongly set are refused unless the update is forced, and only the services
+//! using the updated keys are restarted.
+use tedge_config::tedge_toml::WritableKey;
+
+/// Keys that can cut the device off from the cloud or from the local broker
+///
+/// A leading `*.` matches any prefix and a trailing `.*` any suffix.
+const UNSAFE_KEYS: &[&str] = &[
+    "config.*",
+    "device.*",
+    "mqtt.bind.*",
+    "mqtt.client.*",
+    "*.url",
+    "*.http",
+    "*.mqtt",
+    "*.bridge.*",
+    "*.root_cert_path",
+    "*.cert_path",
+    "*.key_path",
+    "*.cafile",
+    "*.capath",
+    "*.certfile",
+    "*.keyfile",
+    "*.auth_method",
+    "*.credentials_path",
+];
+
+/// The services reading the keys matching a pattern
+const SERVICES: &[(&str, &str)] = &[
+    ("c8y.*", "tedge-mapper-c8y"),
+    ("az.*", "tedge-mapper-az"),
+    ("aws.*", "tedge-mapper-aws"),
+    ("firmware.*", "c8y-firmware-plugin"),
+    ("agent.*", "tedge-agent"),
+    ("software.*", "tedge-agent"),
+    ("http.*", "tedge-agent"),
+];
+
+/// The service running the config manager, which has to be restarted last
+const AGENT_SERVICE: &str = "tedge-agent";
+
+/// The updated keys that cannot be changed remotely unless the update is forced
+pub fn unsafe_keys(updated_keys: &[WritableKey]) -> Vec<String> {
+    updated_keys
+        .iter()
+        .map(|key| key.to_cow_str())
+        .filter(|key| {
+            let (key, _) = split_profile(key);
+            UNSAFE_KEYS.iter().any(|pattern| key_matches(pattern, &key))
+        })
+        .map(|key| key.into_owned())
+        .collect()
+}
+
+/// The services to restart for the updated keys to be taken into account
+///
+/// The services of a cloud profile are named after the profile, e.g.
+/// `tedge-mapper-c8y@second`. The agent, if listed, comes last.
+pub fn services_to_restart(updated_keys: &[WritableKey]) -> Vec<String> {
+    let mut services = Vec::new();
+    for key in updated_keys {
+        let (key, profile) = split_profile(&key.to_cow_str());
+        for (_, service) in SERVICES
+            .iter()
+            .filter(|(pattern, _)| key_matches(pattern, &key))
+        {
+            let service = match profile {
+                Some(profile) => format!("{service}@{profile}"),
+                None => service.to_string(),
+            };
+            if !services.contains(&service) {
+                services.push(service);
+            }
+        }
+    }
+    services.sort_by_key(|service| *service == AGENT_SERVICE);
+    services
+}
+
+/// Splits a key such as `c8y.profiles.second.url` into `c8y.url` and the profile name
+fn split_profile(key: &str) -> (String, Option<String>) {
+    let mut fields = Vec::new();
+    let mut profile = None;
+    let mut iter = key.split('.');
+    while let Some(field) = iter.next() {
+        if field == "profiles" && profile.is_none() {
+            profile = iter.next().map(str::to_owned);
+        } else {
+            fields.push(field);
+        }
+    }
+    (fields.join("."), profile)
+}
+
+fn key_matches(pattern: &str, key: &str) -> bool {
+    let any_prefix = pattern.starts_with("*.");
+    let any_suffix = pattern.ends_with(".*");
+    let fields = pattern.trim_start_matches("*.").trim_end_matches(".*");
+    match (any_prefix, any_suffix) {
+        (false, false) => key == fields,
+        (false, true) => key.starts_with(&format!("{fields}.")),
+        (true, false) => key.ends_with(&format!(".{fields}")),
+        (true, true) => key.contains(&format!(".{fields}.")),
+    }
+}
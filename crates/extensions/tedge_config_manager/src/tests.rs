@@ -15,6 +15,7 @@ use tedge_actors::Sender;
 use tedge_actors::SimpleMessageBox;
 use tedge_actors::SimpleMessageBoxBuilder;
 use tedge_api::mqtt_topics::MqttSchema;
+use tedge_config::tedge_toml::WritableKey;
 use tedge_downloader_ext::DownloadResponse;
 use tedge_file_system_ext::FsWatchEvent;
 use tedge_mqtt_ext::MqttMessage;
@@ -29,6 +30,8 @@ use crate::actor::ConfigDownloadRequest;
 use crate::actor::ConfigDownloadResult;
 use crate::actor::ConfigUploadRequest;
 use crate::actor::ConfigUploadResult;
+use crate::tedge_toml::services_to_restart;
+use crate::tedge_toml::unsafe_keys;
 use crate::ConfigManagerBuilder;
 use crate::ConfigManagerConfig;
 use crate::TedgeWriteStatus;
@@ -94,6 +97,11 @@ async fn new_config_manager_builder(
         config_update_topic: TopicFilter::new_unchecked("te/device/main///cmd/config_update/+"),
         tedge_http_host: "127.0.0.1:3000".into(),
         config_update_enabled: true,
+        service_is_active_command: vec!["true".into()],
+        service_restart_command: vec![
+            "touch".into(),
+            format!("{}/{{}}.restarted", temp_dir.display()),
+        ],
     };
 
     let mut mqtt_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage> =
@@ -182,7 +190,7 @@ async fn config_manager_reloads_config_types() -> Result<(), anyhow::Error> {
         Some(
             MqttMessage::new(
                 &config_snapshot_reload_topic,
-                r#"{"types":["tedge-configuration-plugin","type_four","type_one","type_three","type_two"]}"#
+                r#"{"types":["tedge-config","tedge-configuration-plugin","type_four","type_one","type_three","type_two"]}"#
             )
             .with_retain()
         )
@@ -193,7 +201,7 @@ async fn config_manager_reloads_config_types() -> Result<(), anyhow::Error> {
         Some(
             MqttMessage::new(
                 &config_update_reload_topic,
-                r#"{"types":["tedge-configuration-plugin","type_four","type_one","type_three","type_two"]}"#
+                r#"{"types":["tedge-config","tedge-configuration-plugin","type_four","type_one","type_three","type_two"]}"#
             )
             .with_retain()
         )
@@ -606,3 +614,211 @@ async fn config_manager_processes_concurrently() -> Result<(), anyhow::Error> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn tedge_toml_updates_only_the_given_keys() -> Result<(), anyhow::Error> {
+    let tempdir = prepare()?;
+    tempdir
+        .file("tedge.toml")
+        .with_raw_content("config.version = \"2\"\n[c8y]\nurl = \"example.c8y.io\"\n");
+    let (mut mqtt, _fs, mut downloader, _uploader) =
+        spawn_config_manager_actor(tempdir.path()).await;
+    mqtt.skip(2).await;
+
+    let status = update_tedge_toml(
+        &mut mqtt,
+        &mut downloader,
+        &tempdir,
+        "[c8y.enable]\nlog_upload = false\n",
+        false,
+    )
+    .await?;
+    assert_eq!(status["status"], "successful");
+
+    let tedge_toml: Table = from_str(&read_to_string(tempdir.path().join("tedge.toml"))?)?;
+    assert_eq!(tedge_toml["c8y"]["url"].as_str(), Some("example.c8y.io"));
+    assert_eq!(
+        tedge_toml["c8y"]["enable"]["log_upload"].as_bool(),
+        Some(false)
+    );
+
+    // Only the services using the updated keys are restarted
+    let restarted = tempdir.path().join("tedge-mapper-c8y.restarted");
+    tokio::time::timeout(TEST_TIMEOUT_MS, async {
+        while !restarted.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await?;
+    assert!(!tempdir.path().join("tedge-agent.restarted").exists());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tedge_toml_unsafe_keys_are_only_updated_when_forced() -> Result<(), anyhow::Error> {
+    let tempdir = prepare()?;
+    let initial_content = "config.version = \"2\"\n[c8y]\nurl = \"example.c8y.io\"\n";
+    tempdir.file("tedge.toml").with_raw_content(initial_content);
+    let (mut mqtt, _fs, mut downloader, _uploader) =
+        spawn_config_manager_actor(tempdir.path()).await;
+    mqtt.skip(2).await;
+
+    let patch = "[c8y]\nurl = \"other.c8y.io\"\n";
+
+    let status = update_tedge_toml(&mut mqtt, &mut downloader, &tempdir, patch, false).await?;
+    assert_eq!(status["status"], "failed");
+    assert!(status["reason"].as_str().unwrap().contains("c8y.url"));
+    assert_eq!(
+        read_to_string(tempdir.path().join("tedge.toml"))?,
+        initial_content
+    );
+
+    let status = update_tedge_toml(&mut mqtt, &mut downloader, &tempdir, patch, true).await?;
+    assert_eq!(status["status"], "successful");
+    let tedge_toml: Table = from_str(&read_to_string(tempdir.path().join("tedge.toml"))?)?;
+    assert_eq!(tedge_toml["c8y"]["url"].as_str(), Some("other.c8y.io"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tedge_toml_update_with_invalid_values_is_rejected() -> Result<(), anyhow::Error> {
+    let tempdir = prepare()?;
+    let initial_content = "config.version = \"2\"\n";
+    tempdir.file("tedge.toml").with_raw_content(initial_content);
+    let (mut mqtt, _fs, mut downloader, _uploader) =
+        spawn_config_manager_actor(tempdir.path()).await;
+    mqtt.skip(2).await;
+
+    let patch = "[c8y.enable]\nlog_upload = \"maybe\"\n";
+
+    let status = update_tedge_toml(&mut mqtt, &mut downloader, &tempdir, patch, true).await?;
+    assert_eq!(status["status"], "failed");
+    assert!(status["reason"]
+        .as_str()
+        .unwrap()
+        .contains("c8y.enable.log_upload"));
+    assert_eq!(
+        read_to_string(tempdir.path().join("tedge.toml"))?,
+        initial_content
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_user_declared_tedge_toml_entry_is_replaced_as_a_whole() -> Result<(), anyhow::Error> {
+    let tempdir = TempTedgeDir::new();
+    let tedge_toml_path = tempdir.path().join("tedge.toml");
+    tempdir
+        .file("tedge-configuration-plugin.toml")
+        .with_raw_content(&format!(
+            r#"files = [{{ path = "{}", type = "tedge.toml" }}]"#,
+            tedge_toml_path.display()
+        ));
+    tempdir
+        .file("tedge.toml")
+        .with_raw_content("config.version = \"2\"\n[c8y]\nurl = \"example.c8y.io\"\n");
+    let (mut mqtt, _fs, mut downloader, _uploader) =
+        spawn_config_manager_actor(tempdir.path()).await;
+    mqtt.skip(2).await;
+
+    let new_content = "[c8y.enable]\nlog_upload = false\n";
+    let status = update_config(
+        &mut mqtt,
+        &mut downloader,
+        &tempdir,
+        "tedge.toml",
+        new_content,
+        false,
+    )
+    .await?;
+    assert_eq!(status["status"], "successful");
+    assert_eq!(read_to_string(tedge_toml_path)?, new_content);
+
+    Ok(())
+}
+
+#[test]
+fn tedge_toml_keys_that_can_make_the_device_unreachable_are_unsafe() {
+    let keys = [
+        "c8y.url",
+        "c8y.profiles.second.url",
+        "device.cert_path",
+        "mqtt.bind.port",
+        "c8y.enable.log_upload",
+        "agent.enable.config_update",
+    ]
+    .map(|key| key.parse::<WritableKey>().unwrap());
+
+    assert_eq!(
+        unsafe_keys(&keys),
+        [
+            "c8y.url",
+            "c8y.profiles.second.url",
+            "device.cert_path",
+            "mqtt.bind.port"
+        ]
+    );
+}
+
+#[test]
+fn tedge_toml_updates_restart_the_services_using_the_updated_keys() {
+    let keys = [
+        "agent.enable.config_update",
+        "c8y.enable.log_upload",
+        "c8y.profiles.second.enable.log_upload",
+        "c8y.enable.config_update",
+        "sudo.enable",
+    ]
+    .map(|key| key.parse::<WritableKey>().unwrap());
+
+    assert_eq!(
+        services_to_restart(&keys),
+        ["tedge-mapper-c8y", "tedge-mapper-c8y@second", "tedge-agent"]
+    );
+}
+
+/// Executes a `tedge-config` config update, returning the final state of the command
+async fn update_tedge_toml(
+    mqtt: &mut MqttMessageBox,
+    downloader: &mut DownloaderMessageBox,
+    tempdir: &TempTedgeDir,
+    patch: &str,
+    force: bool,
+) -> Result<serde_json::Value, anyhow::Error> {
+    update_config(mqtt, downloader, tempdir, "tedge-config", patch, force).await
+}
+
+/// Executes a config update of the given type, returning the final state of the command
+async fn update_config(
+    mqtt: &mut MqttMessageBox,
+    downloader: &mut DownloaderMessageBox,
+    tempdir: &TempTedgeDir,
+    config_type: &str,
+    patch: &str,
+    force: bool,
+) -> Result<serde_json::Value, anyhow::Error> {
+    let config_topic = Topic::new_unchecked("te/device/main///cmd/config_update/1234");
+    let update_request = serde_json::json!({
+        "status": "executing",
+        "tedgeUrl": format!("http://127.0.0.1:3000/te/v1/files/main/config_update/{config_type}-1234"),
+        "remoteUrl": "http://www.remote.url",
+        "serverUrl": "http://www.remote.url",
+        "type": config_type,
+        "force": force,
+    });
+    mqtt.send(MqttMessage::new(&config_topic, update_request.to_string()).with_retain())
+        .await?;
+
+    let (topic, download_request) = downloader.recv().await.unwrap();
+    let patch_path = tempdir.path().join("tedge.toml.patch");
+    std::fs::write(&patch_path, patch)?;
+    let download_response = DownloadResponse::new(&download_request.url, &patch_path);
+    downloader.send((topic, Ok(download_response))).await?;
+
+    let message = mqtt.recv().await.unwrap();
+    assert_eq!(message.topic, config_topic);
+    Ok(serde_json::from_str(message.payload_str()?)?)
+}
@@ -2,14 +2,16 @@ use async_trait::async_trait;
 use log::error;
 use std::path::PathBuf;
 use tedge_actors::futures::channel::mpsc;
-use tedge_actors::futures::StreamExt;
 use tedge_actors::message_boxes::log_message_sent;
+use tedge_actors::message_boxes::next_signal;
 use tedge_actors::Actor;
 use tedge_actors::Builder;
 use tedge_actors::ChannelError;
 use tedge_actors::DynSender;
+use tedge_actors::MessageBoxMetrics;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
+use tedge_actors::MetricsSignalSender;
 use tedge_actors::RuntimeError;
 use tedge_actors::RuntimeRequest;
 use tedge_actors::RuntimeRequestSink;
@@ -29,6 +31,7 @@ pub enum FsWatchEvent {
 pub struct FsWatchMessageBox {
     watch_dirs: Vec<(PathBuf, DynSender<FsWatchEvent>)>,
     signal_receiver: mpsc::Receiver<RuntimeRequest>,
+    metrics: MessageBoxMetrics,
 }
 
 impl FsWatchMessageBox {
@@ -49,6 +52,7 @@ impl FsWatchMessageBox {
 
         for (watch_path, sender) in self.watch_dirs.iter_mut() {
             if path.starts_with(watch_path) {
+                self.metrics.message_sent();
                 sender.send(message.clone()).await?;
             }
         }
@@ -57,7 +61,7 @@ impl FsWatchMessageBox {
     }
 
     async fn recv(&mut self) -> Option<RuntimeRequest> {
-        self.signal_receiver.next().await
+        next_signal(&mut self.signal_receiver).await
     }
 }
 
@@ -65,6 +69,7 @@ pub struct FsWatchActorBuilder {
     watch_dirs: Vec<(PathBuf, DynSender<FsWatchEvent>)>,
     signal_sender: mpsc::Sender<RuntimeRequest>,
     signal_receiver: mpsc::Receiver<RuntimeRequest>,
+    metrics: MessageBoxMetrics,
 }
 
 impl FsWatchActorBuilder {
@@ -74,6 +79,7 @@ impl FsWatchActorBuilder {
             watch_dirs: Vec::new(),
             signal_sender,
             signal_receiver,
+            metrics: MessageBoxMetrics::default(),
         }
     }
 }
@@ -93,7 +99,10 @@ impl MessageSource<FsWatchEvent, PathBuf> for FsWatchActorBuilder {
 
 impl RuntimeRequestSink for FsWatchActorBuilder {
     fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
-        Box::new(self.signal_sender.clone())
+        Box::new(MetricsSignalSender::new(
+            self.signal_sender.clone(),
+            self.metrics.clone(),
+        ))
     }
 }
 
@@ -108,6 +117,7 @@ impl Builder<FsWatchActor> for FsWatchActorBuilder {
         let messages = FsWatchMessageBox {
             watch_dirs: self.watch_dirs,
             signal_receiver: self.signal_receiver,
+            metrics: self.metrics,
         };
 
         FsWatchActor { messages }
@@ -1,4 +1,6 @@
+use crate::metrics::RuntimeMetricsPublisher;
 use async_trait::async_trait;
+use log::error;
 use tedge_actors::Actor;
 use tedge_actors::MessageReceiver;
 use tedge_actors::RuntimeError;
@@ -6,11 +8,13 @@ use tedge_actors::Sender;
 use tedge_actors::SimpleMessageBox;
 use tedge_api::health::ServiceHealthTopic;
 use tedge_mqtt_ext::MqttMessage;
+use tokio::time::Interval;
 
 pub struct HealthMonitorActor {
     // TODO(marcel): move this
     service_registration_message: Option<MqttMessage>,
     health_topic: ServiceHealthTopic,
+    metrics_publisher: RuntimeMetricsPublisher,
     messages: SimpleMessageBox<MqttMessage, MqttMessage>,
 }
 
@@ -18,11 +22,13 @@ impl HealthMonitorActor {
     pub fn new(
         service_registration_message: Option<MqttMessage>,
         health_topic: ServiceHealthTopic,
+        metrics_publisher: RuntimeMetricsPublisher,
         messages: SimpleMessageBox<MqttMessage, MqttMessage>,
     ) -> Self {
         Self {
             service_registration_message,
             health_topic,
+            metrics_publisher,
             messages,
         }
     }
@@ -34,6 +40,17 @@ impl HealthMonitorActor {
     pub fn down_health_status(&self) -> MqttMessage {
         self.health_topic.down_message()
     }
+
+    /// Publish the metrics of the running actors
+    ///
+    /// A failure to collect the metrics is not fatal: the health of the service is still reported.
+    async fn publish_metrics(&mut self) -> Result<(), RuntimeError> {
+        match self.metrics_publisher.metrics_message().await {
+            Ok(metrics) => self.messages.send(metrics).await?,
+            Err(err) => error!("Fail to collect the actors metrics: {err}"),
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -49,9 +66,33 @@ impl Actor for HealthMonitorActor {
 
         self.messages.send(self.up_health_status()).await?;
 
-        while let Some(_message) = self.messages.recv().await {
-            self.messages.send(self.up_health_status()).await?;
+        let mut metrics_interval = self.metrics_publisher.interval().map(|period| {
+            let start = tokio::time::Instant::now() + period;
+            tokio::time::interval_at(start, period)
+        });
+
+        loop {
+            tokio::select! {
+                message = self.messages.recv() => match message {
+                    None => break,
+                    Some(message) if self.metrics_publisher.is_request(&message) => {
+                        self.publish_metrics().await?
+                    }
+                    Some(_) => self.messages.send(self.up_health_status()).await?,
+                },
+                _ = next_tick(&mut metrics_interval) => self.publish_metrics().await?,
+            }
         }
         Ok(())
     }
 }
+
+/// Wait for the next tick of the interval, if any
+async fn next_tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
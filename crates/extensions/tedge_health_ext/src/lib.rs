@@ -1,9 +1,11 @@
 mod actor;
+mod metrics;
 
 #[cfg(test)]
 mod tests;
 
 use actor::HealthMonitorActor;
+use metrics::RuntimeMetricsPublisher;
 use serde_json::json;
 use serde_json::Map;
 use tedge_actors::Builder;
@@ -12,6 +14,7 @@ use tedge_actors::LinkError;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
 use tedge_actors::NoConfig;
+use tedge_actors::RuntimeHandle;
 use tedge_actors::RuntimeRequest;
 use tedge_actors::RuntimeRequestSink;
 use tedge_actors::SimpleMessageBoxBuilder;
@@ -30,12 +33,15 @@ use tedge_mqtt_ext::TopicFilter;
 pub struct HealthMonitorBuilder {
     registration_message: Option<MqttMessage>,
     health_topic: ServiceHealthTopic,
+    metrics_publisher: RuntimeMetricsPublisher,
     box_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage>,
 }
 
 impl HealthMonitorBuilder {
     /// Creates a HealthMonitorBuilder that creates a HealthMonitorActor with
     /// a new topic scheme.
+    ///
+    /// The actor also publishes the metrics of the actors of the given runtime.
     pub fn from_service_topic_id(
         service: Service,
         mqtt: &mut (impl MessageSource<MqttMessage, TopicFilter>
@@ -44,6 +50,7 @@ impl HealthMonitorBuilder {
         // TODO: pass it less annoying way
         mqtt_schema: &MqttSchema,
         service_config: &TEdgeConfigReaderService,
+        runtime: &RuntimeHandle,
     ) -> Self {
         let mut service_type = service_config.ty.as_str();
         let time_format = service_config.timestamp_format;
@@ -70,6 +77,8 @@ impl HealthMonitorBuilder {
                     },
                 )
                 .into(),
+            RuntimeMetricsPublisher::request_topic(mqtt_schema, service.service_topic_id.entity())
+                .into(),
         ]
         .into_iter()
         .collect();
@@ -93,6 +102,7 @@ impl HealthMonitorBuilder {
             r#type: EntityType::Service,
             parent: Some(service.device_topic_id.entity().clone()),
             health_endpoint: None,
+            process: None,
             twin_data,
         };
         let registration_message = registration_message.to_mqtt_message(mqtt_schema);
@@ -100,9 +110,17 @@ impl HealthMonitorBuilder {
         let health_topic =
             ServiceHealthTopic::from_new_topic(service_topic_id, mqtt_schema, time_format);
 
+        let metrics_publisher = RuntimeMetricsPublisher::new(
+            runtime.clone(),
+            mqtt_schema,
+            service_topic_id.entity(),
+            service_config.metrics.interval.duration(),
+        );
+
         let builder = HealthMonitorBuilder {
             health_topic,
             registration_message: Some(registration_message),
+            metrics_publisher,
             box_builder,
         };
 
@@ -137,8 +155,12 @@ impl Builder<HealthMonitorActor> for HealthMonitorBuilder {
     fn try_build(self) -> Result<HealthMonitorActor, Self::Error> {
         let message_box = self.box_builder.build();
 
-        let actor =
-            HealthMonitorActor::new(self.registration_message, self.health_topic, message_box);
+        let actor = HealthMonitorActor::new(
+            self.registration_message,
+            self.health_topic,
+            self.metrics_publisher,
+            message_box,
+        );
 
         Ok(actor)
     }
@@ -0,0 +1,130 @@
+use serde_json::json;
+use serde_json::Map;
+use std::time::Duration;
+use tedge_actors::RuntimeError;
+use tedge_actors::RuntimeHandle;
+use tedge_actors::RuntimeMetrics;
+use tedge_api::mqtt_topics::Channel;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::Topic;
+
+/// The type of the measurements reporting the metrics of the actors of a service
+pub const ACTORS_MEASUREMENT_TYPE: &str = "actors";
+
+/// Publishes the metrics of the actors of a service as measurements on the service topic
+///
+/// These metrics are published periodically, if an interval is configured,
+/// and on demand, when a message is received on `te/<service-topic-id>/cmd/metrics/check`.
+pub struct RuntimeMetricsPublisher {
+    runtime: RuntimeHandle,
+    request_topic: Topic,
+    measurement_topic: Topic,
+    interval: Option<Duration>,
+}
+
+impl RuntimeMetricsPublisher {
+    pub fn new(
+        runtime: RuntimeHandle,
+        mqtt_schema: &MqttSchema,
+        service: &EntityTopicId,
+        interval: Duration,
+    ) -> Self {
+        RuntimeMetricsPublisher {
+            runtime,
+            request_topic: Self::request_topic(mqtt_schema, service),
+            measurement_topic: mqtt_schema.topic_for(
+                service,
+                &Channel::Measurement {
+                    measurement_type: ACTORS_MEASUREMENT_TYPE.to_string(),
+                },
+            ),
+            interval: (!interval.is_zero()).then_some(interval),
+        }
+    }
+
+    /// The topic on which the metrics are requested on demand
+    pub fn request_topic(mqtt_schema: &MqttSchema, service: &EntityTopicId) -> Topic {
+        mqtt_schema.topic_for(
+            service,
+            &Channel::Command {
+                operation: OperationType::Custom("metrics".to_string()),
+                cmd_id: "check".to_string(),
+            },
+        )
+    }
+
+    /// The interval at which the metrics have to be published, if any
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    pub fn is_request(&self, message: &MqttMessage) -> bool {
+        message.topic == self.request_topic
+    }
+
+    /// Collect the metrics of the running actors and build the measurement message
+    pub async fn metrics_message(&mut self) -> Result<MqttMessage, RuntimeError> {
+        let metrics = self.runtime.metrics().await?;
+        let payload = measurement_payload(&metrics);
+        Ok(MqttMessage::new(
+            &self.measurement_topic,
+            payload.to_string(),
+        ))
+    }
+}
+
+/// A thin-edge JSON measurement with one group of values per actor
+///
+/// The handling times are given in seconds.
+fn measurement_payload(metrics: &RuntimeMetrics) -> serde_json::Value {
+    metrics
+        .iter()
+        .map(|(actor, metrics)| {
+            let values = json!({
+                "queue_depth": metrics.queue_depth,
+                "messages_in": metrics.messages_in,
+                "messages_out": metrics.messages_out,
+                "mean_handling_time": metrics.mean_handling_time.as_secs_f64(),
+                "max_handling_time": metrics.max_handling_time.as_secs_f64(),
+            });
+            (actor.clone(), values)
+        })
+        .collect::<Map<_, _>>()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_actors::ActorMetrics;
+
+    #[test]
+    fn metrics_are_grouped_by_actor() {
+        let metrics = RuntimeMetrics::from([(
+            "MQTT-1".to_string(),
+            ActorMetrics {
+                queue_depth: 3,
+                messages_in: 10,
+                messages_out: 7,
+                mean_handling_time: Duration::from_millis(2),
+                max_handling_time: Duration::from_millis(500),
+            },
+        )]);
+
+        assert_eq!(
+            measurement_payload(&metrics),
+            json!({
+                "MQTT-1": {
+                    "queue_depth": 3,
+                    "messages_in": 10,
+                    "messages_out": 7,
+                    "mean_handling_time": 0.002,
+                    "max_handling_time": 0.5,
+                }
+            })
+        );
+    }
+}
@@ -1,13 +1,19 @@
 use crate::HealthMonitorBuilder;
 use crate::TopicFilter;
+use std::convert::Infallible;
 use std::time::Duration;
 use tedge_actors::test_helpers::MessageReceiverExt;
 use tedge_actors::Actor;
 use tedge_actors::Builder;
+use tedge_actors::Converter;
+use tedge_actors::ConvertingActor;
 use tedge_actors::DynSender;
 use tedge_actors::MessageReceiver;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::Runtime;
+use tedge_actors::Sender;
 use tedge_actors::SimpleMessageBox;
 use tedge_actors::SimpleMessageBoxBuilder;
 use tedge_api::mqtt_topics::EntityTopicId;
@@ -84,9 +90,73 @@ async fn health_check_set_init_and_last_will_message() -> Result<(), anyhow::Err
     Ok(())
 }
 
+#[tokio::test]
+async fn metrics_are_published_on_demand() -> Result<(), anyhow::Error> {
+    let mut runtime = Runtime::new();
+    let mut echo_actor = ConvertingActor::builder("Echo", Echo);
+    let mut echo_box = SimpleMessageBoxBuilder::new("Test", 16)
+        .with_connection(NoConfig, &mut echo_actor)
+        .build();
+    runtime.spawn(echo_actor).await?;
+
+    let mut mqtt_config = MqttConfig::default();
+    let mut mqtt_box =
+        spawn_a_health_check_actor_in_runtime("test", &mut mqtt_config, runtime).await;
+
+    // skip registration and health status messages
+    mqtt_box.skip(2).await;
+
+    echo_box.send("hello".to_string()).await?;
+    assert_eq!(
+        timeout(TEST_TIMEOUT, echo_box.recv()).await?,
+        Some("hello".to_string())
+    );
+
+    mqtt_box
+        .send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main/service/test/cmd/metrics/check"),
+            "",
+        ))
+        .await?;
+
+    let message = timeout(TEST_TIMEOUT, mqtt_box.recv())
+        .await?
+        .expect("a metrics message");
+    assert_eq!(message.topic.name, "te/device/main/service/test/m/actors");
+    let payload: serde_json::Value = serde_json::from_str(message.payload_str()?)?;
+    let echo_metrics = &payload["Echo-0"];
+    assert_eq!(echo_metrics["queue_depth"], 0);
+    assert_eq!(echo_metrics["messages_in"], 1);
+    assert_eq!(echo_metrics["messages_out"], 1);
+    assert!(echo_metrics["max_handling_time"].is_f64());
+
+    Ok(())
+}
+
+struct Echo;
+
+impl Converter for Echo {
+    type Input = String;
+    type Output = String;
+    type Error = Infallible;
+
+    fn convert(&mut self, input: &Self::Input) -> Result<Vec<Self::Output>, Self::Error> {
+        Ok(vec![input.clone()])
+    }
+}
+
 async fn spawn_a_health_check_actor(
     service_to_be_monitored: &str,
     mqtt_config: &mut MqttConfig,
+) -> SimpleMessageBox<MqttMessage, MqttMessage> {
+    spawn_a_health_check_actor_in_runtime(service_to_be_monitored, mqtt_config, Runtime::new())
+        .await
+}
+
+async fn spawn_a_health_check_actor_in_runtime(
+    service_to_be_monitored: &str,
+    mqtt_config: &mut MqttConfig,
+    runtime: Runtime,
 ) -> SimpleMessageBox<MqttMessage, MqttMessage> {
     let mut health_mqtt_builder = MqttActorBuilder::new(mqtt_config);
 
@@ -104,10 +174,15 @@ async fn spawn_a_health_check_actor(
         &mut health_mqtt_builder,
         &mqtt_schema,
         &config.service,
+        &runtime.get_handle(),
     );
 
     let actor = health_actor.build();
-    tokio::spawn(async move { actor.run().await });
+    tokio::spawn(async move {
+        // The runtime is kept alive for the actor to collect its metrics
+        let _runtime = runtime;
+        actor.run().await
+    });
 
     health_mqtt_builder.build()
 }
@@ -154,6 +154,14 @@ impl HttpRequestBuilder {
         HttpRequestBuilder { body, ..self }
     }
 
+    /// Send a text body
+    pub fn text(self, text: impl Into<String>) -> Self {
+        let body = Ok(Full::new(Bytes::from(text.into()))
+            .map_err(infallible)
+            .boxed());
+        HttpRequestBuilder { body, ..self }
+    }
+
     /// Send a  body
     pub fn body(self, content: impl Into<Body>) -> Self {
         let body = Ok(content.into());
@@ -11,6 +11,7 @@ pub use config::*;
 use log::error;
 use manager::LogPluginConfig;
 use std::path::PathBuf;
+use tedge_actors::ActorRebuilder;
 use tedge_actors::Builder;
 use tedge_actors::CloneSender;
 use tedge_actors::DynSender;
@@ -19,6 +20,7 @@ use tedge_actors::MappingSender;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
 use tedge_actors::NoConfig;
+use tedge_actors::RestartableBuilder;
 use tedge_actors::RuntimeRequest;
 use tedge_actors::RuntimeRequestSink;
 use tedge_actors::Service;
@@ -196,6 +198,33 @@ impl Builder<LogManagerActor> for LogManagerBuilder {
     }
 }
 
+/// The log manager is restarted with a fresh copy of its plugin configuration
+/// when a log upload request makes it fail.
+impl RestartableBuilder<LogManagerActor> for LogManagerBuilder {
+    fn build_restartable(self) -> (LogManagerActor, ActorRebuilder<LogManagerActor>) {
+        let (message_box, mut rebuild_message_box) = self.box_builder.build_restartable();
+        let config = self.config;
+        let upload_sender = self.upload_sender;
+        let actor = LogManagerActor::new(
+            config.clone(),
+            self.plugin_config,
+            message_box,
+            upload_sender.sender_clone(),
+        );
+        let rebuild = move || {
+            let message_box = rebuild_message_box()?;
+            let plugin_config = LogPluginConfig::new(&config.plugin_config_path);
+            Some(LogManagerActor::new(
+                config.clone(),
+                plugin_config,
+                message_box,
+                upload_sender.sender_clone(),
+            ))
+        };
+        (actor, Box::new(rebuild))
+    }
+}
+
 impl MessageSource<GenericCommandData, NoConfig> for LogManagerBuilder {
     fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<GenericCommandData>) {
         self.box_builder
@@ -20,15 +20,19 @@ use std::time::Duration;
 use std::time::Instant;
 use tedge_actors::fan_in_message_type;
 use tedge_actors::futures::channel::mpsc;
+use tedge_actors::message_boxes::next_signal;
 use tedge_actors::Actor;
 use tedge_actors::Builder;
 use tedge_actors::ChannelError;
 use tedge_actors::ClientMessageBox;
 use tedge_actors::CloneSender;
 use tedge_actors::DynSender;
+use tedge_actors::MessageBoxMetrics;
 use tedge_actors::MessageReceiver;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
+use tedge_actors::MeteredSender;
+use tedge_actors::MetricsSignalSender;
 use tedge_actors::RuntimeError;
 use tedge_actors::RuntimeRequest;
 use tedge_actors::RuntimeRequestSink;
@@ -60,6 +64,7 @@ pub struct MqttActorBuilder {
         TrieInsertRequest,
         Box<dyn CloneSender<MqttMessage> + 'static>,
     )>,
+    metrics: MessageBoxMetrics,
 }
 
 impl MqttRequest {
@@ -71,6 +76,7 @@ impl MqttRequest {
 struct InputCombiner {
     signal_receiver: mpsc::Receiver<RuntimeRequest>,
     request_receiver: mpsc::Receiver<MqttRequest>,
+    metrics: MessageBoxMetrics,
 }
 
 impl MessageSource<MqttMessage, &mut DynSubscriptions> for MqttActorBuilder {
@@ -221,10 +227,11 @@ impl MessageReceiver<MqttRequest> for InputCombiner {
         tokio::select! {
             biased;
 
-            Some(runtime_request) = self.signal_receiver.next() => {
+            Some(runtime_request) = next_signal(&mut self.signal_receiver) => {
                 Err(runtime_request)
             }
             Some(request) = self.request_receiver.next() => {
+                self.metrics.message_received();
                 Ok(Some(request))
             }
             else => Ok(None)
@@ -239,7 +246,7 @@ impl MessageReceiver<MqttRequest> for InputCombiner {
     }
 
     async fn recv_signal(&mut self) -> Option<RuntimeRequest> {
-        self.signal_receiver.next().await
+        next_signal(&mut self.signal_receiver).await
     }
 }
 
@@ -249,9 +256,11 @@ impl MqttActorBuilder {
         let (signal_sender, signal_receiver) = mpsc::channel(10);
         let (dynamic_connect_sender, dynamic_connect_receiver) = mpsc::channel(10);
         let trie = TrieService::new(MqtTrie::default());
+        let metrics = MessageBoxMetrics::default();
         let input_receiver = InputCombiner {
             signal_receiver,
             request_receiver,
+            metrics: metrics.clone(),
         };
 
         MqttActorBuilder {
@@ -265,6 +274,7 @@ impl MqttActorBuilder {
             current_id: 0,
             dynamic_connect_sender,
             dynamic_connect_receiver,
+            metrics,
         }
     }
 
@@ -286,6 +296,7 @@ impl MqttActorBuilder {
             base_config,
             self.input_receiver,
             self.subscriber_addresses,
+            self.metrics,
             self.trie.builder(),
             DynamicMqttClientHandle {
                 current_id: Arc::new(tokio::sync::Mutex::new(self.current_id)),
@@ -334,7 +345,10 @@ impl MqttActorBuilder {
 
 impl MessageSink<MqttMessage> for MqttActorBuilder {
     fn get_sender(&self) -> DynSender<MqttMessage> {
-        Box::new(self.request_sender.clone())
+        Box::new(MeteredSender::new(
+            self.request_sender.clone(),
+            self.metrics.clone(),
+        ))
     }
 }
 
@@ -415,13 +429,19 @@ pub struct SubscriptionRequest {
 
 impl MessageSink<MqttRequest> for MqttActorBuilder {
     fn get_sender(&self) -> DynSender<MqttRequest> {
-        self.request_sender.clone().into()
+        Box::new(MeteredSender::new(
+            self.request_sender.clone(),
+            self.metrics.clone(),
+        ))
     }
 }
 
 impl RuntimeRequestSink for MqttActorBuilder {
     fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
-        Box::new(self.signal_sender.clone())
+        Box::new(MetricsSignalSender::new(
+            self.signal_sender.clone(),
+            self.metrics.clone(),
+        ))
     }
 }
 
@@ -446,6 +466,7 @@ pub struct FromPeers {
 pub struct ToPeers {
     peer_senders: Vec<DynSender<MqttMessage>>,
     subscriptions: ClientMessageBox<TrieRequest, TrieResponse>,
+    metrics: MessageBoxMetrics,
 }
 
 impl FromPeers {
@@ -467,6 +488,7 @@ impl FromPeers {
         rx_to_peers: &mut mpsc::UnboundedReceiver<MqttRequest>,
     ) -> Result<(), RuntimeError> {
         while let Ok(Some(message)) = self.try_recv(rx_to_peers).await {
+            let handling_since = Instant::now();
             match message {
                 MqttRequest::Publish(message) => {
                     tracing::debug!(target: "MQTT pub", "{message}");
@@ -521,6 +543,9 @@ impl FromPeers {
                     self.forward_retain_messages_to(tx, topics, move |msg| msg);
                 }
             }
+            self.input_receiver
+                .metrics
+                .message_handled(handling_since.elapsed());
         }
 
         // On shutdown, first close input so no new messages can be pushed
@@ -598,6 +623,7 @@ impl ToPeers {
                 message = rx_from_peers.next() => {
                     let Some((client, message)) = message else { break };
                     tracing::debug!(target: "MQTT recv", "{message}");
+                    self.metrics.message_sent();
                     self.sender_by_id(client).send(message.clone()).await?;
                 }
                 Some((insert_req, sender)) = dynamic_connection_request.next() => {
@@ -628,6 +654,7 @@ impl ToPeers {
             unreachable!("MatchRequest always returns Matched")
         };
         for client in matches {
+            self.metrics.message_sent();
             self.sender_by_id(client).send(message.clone()).await?;
         }
         Ok(())
@@ -666,11 +693,13 @@ pub struct MqttActor {
 }
 
 impl MqttActor {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         mqtt_config: mqtt_channel::Config,
         base_config: mqtt_channel::Config,
         input_receiver: InputCombiner,
         peer_senders: Vec<DynSender<MqttMessage>>,
+        metrics: MessageBoxMetrics,
         mut trie_service: ServerActorBuilder<TrieService, Sequential>,
         dynamic_client_handle: DynamicMqttClientHandle,
         dynamic_connect_receiver: mpsc::Receiver<(
@@ -688,6 +717,7 @@ impl MqttActor {
             to_peers: ToPeers {
                 peer_senders,
                 subscriptions: ClientMessageBox::new(&mut trie_service),
+                metrics,
             },
             trie_service,
             dynamic_client_handle,
@@ -1009,6 +1039,7 @@ mod unit_tests {
             let input_combiner = InputCombiner {
                 signal_receiver: sig_rx,
                 request_receiver: req_rx,
+                metrics: MessageBoxMetrics::default(),
             };
 
             let mut ts = TrieService::with_default_subscriptions(default_subscriptions);
@@ -1030,6 +1061,7 @@ mod unit_tests {
             let tp = ToPeers {
                 subscriptions: ClientMessageBox::new(&mut ts),
                 peer_senders,
+                metrics: MessageBoxMetrics::default(),
             };
             tokio::spawn(async move { ts.build().run().await });
 
@@ -0,0 +1,72 @@
+use crate::sampler::SystemSampler;
+use crate::ResourceMonitorConfig;
+use async_trait::async_trait;
+use serde_json::Value;
+use tedge_actors::Actor;
+use tedge_actors::MessageReceiver;
+use tedge_actors::NoMessage;
+use tedge_actors::RuntimeError;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::Topic;
+use tokio::time::interval;
+use tokio::time::MissedTickBehavior;
+
+/// Periodically sample the device resource usage and publish it as a measurement
+pub struct ResourceMonitorActor {
+    topic: Topic,
+    interval: std::time::Duration,
+    sampler: SystemSampler,
+    messages: SimpleMessageBox<NoMessage, MqttMessage>,
+}
+
+#[async_trait]
+impl Actor for ResourceMonitorActor {
+    fn name(&self) -> &str {
+        "ResourceMonitor"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        let mut ticker = interval(self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut sampler = self.sampler;
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    // Reading /proc and calling statvfs are blocking operations
+                    let measurement;
+                    (sampler, measurement) = tokio::task::spawn_blocking(move || {
+                        let measurement = sampler.sample();
+                        (sampler, measurement)
+                    })
+                    .await
+                    .map_err(|err| RuntimeError::ActorError(Box::new(err)))?;
+                    if !measurement.is_empty() {
+                        let payload = Value::Object(measurement).to_string();
+                        let message = MqttMessage::new(&self.topic, payload);
+                        self.messages.send(message).await?;
+                    }
+                }
+                None = self.messages.recv() => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ResourceMonitorActor {
+    pub fn new(
+        config: ResourceMonitorConfig,
+        messages: SimpleMessageBox<NoMessage, MqttMessage>,
+    ) -> Self {
+        let sampler = SystemSampler::new(config.host_root, config.groups, config.disks);
+        ResourceMonitorActor {
+            topic: config.topic,
+            interval: config.interval,
+            sampler,
+            messages,
+        }
+    }
+}
@@ -0,0 +1,210 @@
+mod actor;
+mod process;
+mod process_actor;
+mod sampler;
+
+#[cfg(test)]
+mod tests;
+
+pub use actor::ResourceMonitorActor;
+pub use process_actor::ProcessMonitorActor;
+pub use sampler::MetricGroup;
+pub use sampler::UnknownMetricGroup;
+
+use camino::Utf8PathBuf;
+use std::convert::Infallible;
+use std::time::Duration;
+use tedge_actors::Builder;
+use tedge_actors::DynSender;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::NoMessage;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::mqtt_topics::ChannelFilter;
+use tedge_api::mqtt_topics::EntityFilter;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::Topic;
+use tedge_mqtt_ext::TopicFilter;
+use tedge_utils::timestamp::TimeFormat;
+
+/// Configuration of the resource monitor
+#[derive(Clone, Debug)]
+pub struct ResourceMonitorConfig {
+    /// The topic on which the resource usage measurements are published
+    pub topic: Topic,
+
+    /// Interval between two samples
+    pub interval: Duration,
+
+    /// The metric groups to be published
+    pub groups: Vec<MetricGroup>,
+
+    /// The mount points which disk usage is published
+    pub disks: Vec<Utf8PathBuf>,
+
+    /// The root directory under which `/proc` is looked up, i.e. `/` except for tests
+    pub host_root: Utf8PathBuf,
+}
+
+impl ResourceMonitorConfig {
+    pub fn new(topic: Topic, interval: Duration) -> Self {
+        ResourceMonitorConfig {
+            topic,
+            interval,
+            groups: MetricGroup::ALL.to_vec(),
+            disks: vec!["/".into()],
+            host_root: "/".into(),
+        }
+    }
+
+    pub fn with_groups(self, groups: Vec<MetricGroup>) -> Self {
+        Self { groups, ..self }
+    }
+
+    pub fn with_disks(self, disks: Vec<Utf8PathBuf>) -> Self {
+        Self { disks, ..self }
+    }
+
+    pub fn with_host_root(self, host_root: impl Into<Utf8PathBuf>) -> Self {
+        Self {
+            host_root: host_root.into(),
+            ..self
+        }
+    }
+}
+
+pub struct ResourceMonitorBuilder {
+    config: ResourceMonitorConfig,
+    box_builder: SimpleMessageBoxBuilder<NoMessage, MqttMessage>,
+}
+
+impl ResourceMonitorBuilder {
+    pub fn new(config: ResourceMonitorConfig, mqtt: &impl MessageSink<MqttMessage>) -> Self {
+        let mut box_builder = SimpleMessageBoxBuilder::new("ResourceMonitor", 1);
+        box_builder.connect_sink(NoConfig, mqtt);
+        ResourceMonitorBuilder {
+            config,
+            box_builder,
+        }
+    }
+}
+
+impl RuntimeRequestSink for ResourceMonitorBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.box_builder.get_signal_sender()
+    }
+}
+
+impl Builder<ResourceMonitorActor> for ResourceMonitorBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<ResourceMonitorActor, Self::Error> {
+        Ok(ResourceMonitorActor::new(
+            self.config,
+            self.box_builder.build(),
+        ))
+    }
+}
+
+/// Configuration of the monitor of the service processes
+#[derive(Clone, Debug)]
+pub struct ProcessMonitorConfig {
+    pub mqtt_schema: MqttSchema,
+
+    /// The device which services are monitored, i.e. the device running this monitor
+    pub device_topic_id: EntityTopicId,
+
+    /// Interval between two samples
+    pub interval: Duration,
+
+    /// The format of the time of the health status published on behalf of a service
+    pub time_format: TimeFormat,
+
+    /// The root directory under which `/proc` is looked up, i.e. `/` except for tests
+    pub host_root: Utf8PathBuf,
+}
+
+impl ProcessMonitorConfig {
+    pub fn new(
+        mqtt_schema: MqttSchema,
+        device_topic_id: EntityTopicId,
+        interval: Duration,
+    ) -> Self {
+        ProcessMonitorConfig {
+            mqtt_schema,
+            device_topic_id,
+            interval,
+            time_format: TimeFormat::Unix,
+            host_root: "/".into(),
+        }
+    }
+
+    pub fn with_time_format(self, time_format: TimeFormat) -> Self {
+        Self {
+            time_format,
+            ..self
+        }
+    }
+
+    pub fn with_host_root(self, host_root: impl Into<Utf8PathBuf>) -> Self {
+        Self {
+            host_root: host_root.into(),
+            ..self
+        }
+    }
+
+    /// The service registration messages, which tell the process to monitor,
+    /// and the service health status messages, which override the status published on behalf of a service
+    pub fn subscriptions(&self) -> TopicFilter {
+        let mut topics = self
+            .mqtt_schema
+            .topics(EntityFilter::AnyEntity, ChannelFilter::EntityMetadata);
+        topics.add_all(
+            self.mqtt_schema
+                .topics(EntityFilter::AnyEntity, ChannelFilter::Health),
+        );
+        topics
+    }
+}
+
+pub struct ProcessMonitorBuilder {
+    config: ProcessMonitorConfig,
+    box_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage>,
+}
+
+impl ProcessMonitorBuilder {
+    pub fn new(
+        config: ProcessMonitorConfig,
+        mqtt: &mut (impl MessageSource<MqttMessage, TopicFilter> + MessageSink<MqttMessage>),
+    ) -> Self {
+        let mut box_builder = SimpleMessageBoxBuilder::new("ProcessMonitor", 16);
+        mqtt.connect_sink(config.subscriptions(), &box_builder);
+        box_builder.connect_sink(NoConfig, mqtt);
+        ProcessMonitorBuilder {
+            config,
+            box_builder,
+        }
+    }
+}
+
+impl RuntimeRequestSink for ProcessMonitorBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.box_builder.get_signal_sender()
+    }
+}
+
+impl Builder<ProcessMonitorActor> for ProcessMonitorBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<ProcessMonitorActor, Self::Error> {
+        Ok(ProcessMonitorActor::new(
+            self.config,
+            self.box_builder.build(),
+        ))
+    }
+}
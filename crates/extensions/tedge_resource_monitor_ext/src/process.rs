@@ -0,0 +1,252 @@
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use serde_json::json;
+use serde_json::Value;
+use std::time::Instant;
+use tedge_api::entity::ServiceProcess;
+use tokio::process::Command;
+
+/// The resource usage of a service process
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProcessStatus {
+    /// The process is running
+    Running { pid: u32, usage: Value },
+
+    /// The process is not running, or cannot be found
+    Gone,
+}
+
+/// Sample the resource usage of the process running a service
+pub struct ProcessSampler {
+    host_root: Utf8PathBuf,
+    process: ServiceProcess,
+    clock_ticks: f64,
+    last_sample: Option<ProcessSample>,
+    restarts: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ProcessSample {
+    pid: u32,
+    start_time: u64,
+    cpu_ticks: u64,
+    at: Instant,
+}
+
+impl ProcessSampler {
+    pub fn new(host_root: Utf8PathBuf, process: ServiceProcess) -> Self {
+        let clock_ticks = nix::unistd::sysconf(nix::unistd::SysconfVar::CLK_TCK)
+            .ok()
+            .flatten()
+            .map_or(100.0, |ticks| ticks as f64);
+        ProcessSampler {
+            host_root,
+            process,
+            clock_ticks,
+            last_sample: None,
+            restarts: 0,
+        }
+    }
+
+    pub fn process(&self) -> &ServiceProcess {
+        &self.process
+    }
+
+    pub async fn sample(&mut self) -> ProcessStatus {
+        let (pid, systemd_restarts) = match &self.process {
+            ServiceProcess::PidFile(pid_file) => (read_pid_file(pid_file), None),
+            ServiceProcess::SystemdUnit(unit) => match systemd_main_pid(unit).await {
+                Some((pid, restarts)) => (Some(pid), Some(restarts)),
+                None => (None, None),
+            },
+        };
+        let Some(pid) = pid.filter(|pid| *pid != 0) else {
+            return ProcessStatus::Gone;
+        };
+        let Some((start_time, cpu_ticks)) = self.read_stat(pid) else {
+            return ProcessStatus::Gone;
+        };
+
+        let now = Instant::now();
+        let mut usage = serde_json::Map::new();
+        if let Some(last) = self.last_sample {
+            if last.pid != pid || last.start_time != start_time {
+                self.restarts += 1;
+            } else {
+                let elapsed = now.duration_since(last.at).as_secs_f64();
+                if elapsed > 0.0 {
+                    let cpu_seconds =
+                        cpu_ticks.saturating_sub(last.cpu_ticks) as f64 / self.clock_ticks;
+                    usage.insert(
+                        "cpu".to_string(),
+                        json!(round(100.0 * cpu_seconds / elapsed)),
+                    );
+                }
+            }
+        }
+        self.last_sample = Some(ProcessSample {
+            pid,
+            start_time,
+            cpu_ticks,
+            at: now,
+        });
+
+        if let Some(rss) = self.read_rss(pid) {
+            usage.insert("rss".to_string(), json!(rss));
+        }
+        if let Some(fds) = self.count_fds(pid) {
+            usage.insert("fds".to_string(), json!(fds));
+        }
+        let restarts = systemd_restarts.unwrap_or(self.restarts);
+        usage.insert("restarts".to_string(), json!(restarts));
+
+        ProcessStatus::Running {
+            pid,
+            usage: Value::Object(usage),
+        }
+    }
+
+    fn proc_path(&self, pid: u32, file: &str) -> Utf8PathBuf {
+        self.host_root.join(format!("proc/{pid}/{file}"))
+    }
+
+    /// Read the start time and the CPU time (user + system) in clock ticks from `/proc/<pid>/stat`
+    fn read_stat(&self, pid: u32) -> Option<(u64, u64)> {
+        let stat = std::fs::read_to_string(self.proc_path(pid, "stat")).ok()?;
+        // The command name, in parentheses, can contain spaces
+        let (_, fields) = stat.rsplit_once(')')?;
+        let fields: Vec<&str> = fields.split_whitespace().collect();
+        let utime: u64 = fields.get(11)?.parse().ok()?;
+        let stime: u64 = fields.get(12)?.parse().ok()?;
+        let start_time: u64 = fields.get(19)?.parse().ok()?;
+        Some((start_time, utime + stime))
+    }
+
+    /// Read the resident set size in bytes from `/proc/<pid>/status`
+    fn read_rss(&self, pid: u32) -> Option<u64> {
+        let status = std::fs::read_to_string(self.proc_path(pid, "status")).ok()?;
+        status
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| *key == "VmRSS")
+            .and_then(|(_, value)| value.trim().trim_end_matches("kB").trim().parse().ok())
+            .map(|kb: u64| kb * 1024)
+    }
+
+    /// Count the open file descriptors, provided the process can be inspected
+    fn count_fds(&self, pid: u32) -> Option<usize> {
+        let fds = std::fs::read_dir(self.proc_path(pid, "fd")).ok()?;
+        Some(fds.count())
+    }
+}
+
+fn read_pid_file(pid_file: &Utf8Path) -> Option<u32> {
+    std::fs::read_to_string(pid_file).ok()?.trim().parse().ok()
+}
+
+/// Get the main pid and the restart count of a systemd unit
+async fn systemd_main_pid(unit: &str) -> Option<(u32, u64)> {
+    let output = Command::new("systemctl")
+        .args(["show", "--property=MainPID,NRestarts", unit])
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let output = String::from_utf8_lossy(&output.stdout);
+    let property = |name: &str| {
+        output
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .find(|(key, _)| *key == name)
+            .and_then(|(_, value)| value.trim().parse::<u64>().ok())
+    };
+    let pid = property("MainPID")?;
+    let restarts = property("NRestarts").unwrap_or(0);
+    Some((pid.try_into().ok()?, restarts))
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[tokio::test]
+    async fn sample_process_from_pid_file() {
+        let root = TempTedgeDir::new();
+        let pid_file = root.file("service.pid").with_raw_content("1234\n");
+        fake_process(&root, 1234, 5000, 150);
+
+        let mut sampler = ProcessSampler::new(
+            root.utf8_path_buf(),
+            ServiceProcess::PidFile(pid_file.utf8_path_buf()),
+        );
+
+        assert_eq!(
+            sampler.sample().await,
+            ProcessStatus::Running {
+                pid: 1234,
+                usage: json!({"rss": 2048 * 1024, "fds": 3, "restarts": 0}),
+            }
+        );
+
+        // CPU usage is only available from the second sample
+        let ProcessStatus::Running { usage, .. } = sampler.sample().await else {
+            panic!("Expected a running process")
+        };
+        assert_eq!(usage["cpu"], json!(0.0));
+    }
+
+    #[tokio::test]
+    async fn restarts_are_detected_from_pid_changes() {
+        let root = TempTedgeDir::new();
+        let pid_file = root.file("service.pid").with_raw_content("1234");
+        fake_process(&root, 1234, 5000, 150);
+
+        let mut sampler = ProcessSampler::new(
+            root.utf8_path_buf(),
+            ServiceProcess::PidFile(pid_file.utf8_path_buf()),
+        );
+        sampler.sample().await;
+
+        std::fs::write(pid_file.utf8_path(), "5678").unwrap();
+        assert_eq!(sampler.sample().await, ProcessStatus::Gone);
+
+        fake_process(&root, 5678, 9000, 10);
+        let ProcessStatus::Running { pid, usage } = sampler.sample().await else {
+            panic!("Expected a running process")
+        };
+        assert_eq!(pid, 5678);
+        assert_eq!(usage["restarts"], json!(1));
+    }
+
+    #[tokio::test]
+    async fn process_is_gone_when_pid_file_is_missing() {
+        let root = TempTedgeDir::new();
+        let mut sampler = ProcessSampler::new(
+            root.utf8_path_buf(),
+            ServiceProcess::PidFile(root.utf8_path().join("missing.pid")),
+        );
+        assert_eq!(sampler.sample().await, ProcessStatus::Gone);
+    }
+
+    fn fake_process(root: &TempTedgeDir, pid: u32, start_time: u64, cpu_ticks: u64) {
+        let process = root.dir("proc").dir(&pid.to_string());
+        process.file("stat").with_raw_content(&format!(
+            "{pid} (my service) S 1 {pid} {pid} 0 -1 4194560 100 0 0 0 {cpu_ticks} 0 0 0 20 0 1 0 {start_time} 1000 200\n"
+        ));
+        process
+            .file("status")
+            .with_raw_content("Name:\tmy service\nVmRSS:\t    2048 kB\n");
+        let fd = process.dir("fd");
+        for i in 0..3 {
+            fd.file(&i.to_string());
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use crate::process::ProcessSampler;
+use crate::process::ProcessStatus;
+use crate::ProcessMonitorConfig;
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use tedge_actors::Actor;
+use tedge_actors::MessageReceiver;
+use tedge_actors::RuntimeError;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_api::entity::EntityType;
+use tedge_api::entity_store::EntityRegistrationMessage;
+use tedge_api::mqtt_topics::Channel;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::Topic;
+use tedge_utils::timestamp::TimeFormat;
+use time::OffsetDateTime;
+use tokio::time::interval;
+use tokio::time::MissedTickBehavior;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+/// Monitor the processes of the services registered with a `@process` property
+///
+/// On each tick, the resource usage of each process is published as a `process` measurement
+/// on the service topic.
+///
+/// When the process cannot be found, a `down` health status is published on behalf of the service,
+/// as the service might have been killed before having a chance to publish its own status.
+/// This status is cleared when the process is back, unless the service has published its own status meanwhile.
+pub struct ProcessMonitorActor {
+    config: ProcessMonitorConfig,
+    messages: SimpleMessageBox<MqttMessage, MqttMessage>,
+    services: HashMap<EntityTopicId, MonitoredService>,
+}
+
+struct MonitoredService {
+    sampler: ProcessSampler,
+    not_running: bool,
+
+    /// The health status published on behalf of the service, as long as not overridden by the service
+    down_status: Option<String>,
+}
+
+#[async_trait]
+impl Actor for ProcessMonitorActor {
+    fn name(&self) -> &str {
+        "ProcessMonitor"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        let mut ticker = interval(self.config.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => self.sample_processes().await?,
+                message = self.messages.recv() => match message {
+                    Some(message) => self.process_message(message),
+                    None => break,
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ProcessMonitorActor {
+    pub fn new(
+        config: ProcessMonitorConfig,
+        messages: SimpleMessageBox<MqttMessage, MqttMessage>,
+    ) -> Self {
+        ProcessMonitorActor {
+            config,
+            messages,
+            services: HashMap::new(),
+        }
+    }
+
+    fn process_message(&mut self, message: MqttMessage) {
+        match self
+            .config
+            .mqtt_schema
+            .entity_channel_of(message.topic.as_ref())
+        {
+            Ok((topic_id, Channel::EntityMetadata)) => self.process_registration(topic_id, message),
+            Ok((topic_id, Channel::Health)) => self.process_health_status(topic_id, message),
+            _ => (),
+        }
+    }
+
+    fn process_health_status(&mut self, topic_id: EntityTopicId, message: MqttMessage) {
+        if let Some(service) = self.services.get_mut(&topic_id) {
+            let is_own_status = service
+                .down_status
+                .as_ref()
+                .is_some_and(|status| status.as_bytes() == message.payload_bytes());
+            if !is_own_status {
+                service.down_status = None;
+            }
+        }
+    }
+
+    fn process_registration(&mut self, topic_id: EntityTopicId, message: MqttMessage) {
+        if message.payload_bytes().is_empty() {
+            self.services.remove(&topic_id);
+            return;
+        }
+
+        let registration =
+            match EntityRegistrationMessage::try_from(topic_id.clone(), message.payload_bytes()) {
+                Ok(registration) => registration,
+                Err(err) => {
+                    warn!("Ignoring invalid registration message for {topic_id}: {err}");
+                    return;
+                }
+            };
+        // Only the services running on this device can be monitored
+        let parent = registration
+            .parent
+            .clone()
+            .or_else(|| topic_id.default_service_parent_identifier());
+        let is_local_service = registration.r#type == EntityType::Service
+            && parent.as_ref() == Some(&self.config.device_topic_id);
+
+        match registration.process {
+            Some(process) if is_local_service => {
+                let unchanged = self
+                    .services
+                    .get(&topic_id)
+                    .is_some_and(|service| service.sampler.process() == &process);
+                if !unchanged {
+                    info!("Monitoring the process of {topic_id}: {process:?}");
+                    let sampler = ProcessSampler::new(self.config.host_root.clone(), process);
+                    self.services.insert(
+                        topic_id,
+                        MonitoredService {
+                            sampler,
+                            not_running: false,
+                            down_status: None,
+                        },
+                    );
+                }
+            }
+            _ => {
+                self.services.remove(&topic_id);
+            }
+        }
+    }
+
+    async fn sample_processes(&mut self) -> Result<(), RuntimeError> {
+        let mut messages = vec![];
+        for (topic_id, service) in self.services.iter_mut() {
+            match service.sampler.sample().await {
+                ProcessStatus::Running { pid, usage } => {
+                    let measurement_topic = self.config.mqtt_schema.topic_for(
+                        topic_id,
+                        &Channel::Measurement {
+                            measurement_type: "process".to_string(),
+                        },
+                    );
+                    let payload = json!({ "process": usage }).to_string();
+                    messages.push(MqttMessage::new(&measurement_topic, payload));
+
+                    if service.not_running {
+                        service.not_running = false;
+                        info!("The process of {topic_id} is running again with pid {pid}");
+                        if service.down_status.take().is_some() {
+                            let health_topic = health_topic(&self.config, topic_id);
+                            messages.push(MqttMessage::new(&health_topic, "").with_retain());
+                        }
+                    }
+                }
+                ProcessStatus::Gone => {
+                    if !service.not_running {
+                        service.not_running = true;
+                        warn!("The process of {topic_id} is not running");
+                        let health_topic = health_topic(&self.config, topic_id);
+                        let payload = down_status(self.config.time_format).to_string();
+                        messages
+                            .push(MqttMessage::new(&health_topic, payload.as_str()).with_retain());
+                        service.down_status = Some(payload);
+                    }
+                }
+            }
+        }
+
+        for message in messages {
+            self.messages.send(message).await?;
+        }
+        Ok(())
+    }
+}
+
+fn health_topic(config: &ProcessMonitorConfig, topic_id: &EntityTopicId) -> Topic {
+    config.mqtt_schema.topic_for(topic_id, &Channel::Health)
+}
+
+/// The `down` health status published when the process of a service is not running
+///
+/// The time tells when this status has been observed on behalf of the service,
+/// even when the service never published its own status.
+fn down_status(time_format: TimeFormat) -> serde_json::Value {
+    let now = OffsetDateTime::now_utc();
+    let time = time_format.to_json(now).unwrap_or_else(|err| {
+        error!("Failed to convert timestamp to {time_format} format due to: {err}");
+        now.unix_timestamp().into()
+    });
+    json!({
+        "status": "down",
+        "reason": "process not running",
+        "time": time,
+    })
+}
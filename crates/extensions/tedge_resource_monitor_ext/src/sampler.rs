@@ -0,0 +1,385 @@
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+use tedge_utils::resources::DiskUsage;
+use tedge_utils::resources::MemoryUsage;
+
+/// The groups of metrics that can be published by the resource monitor
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MetricGroup {
+    Cpu,
+    Load,
+    Memory,
+    Disk,
+    Network,
+}
+
+impl MetricGroup {
+    pub const ALL: [MetricGroup; 5] = [
+        MetricGroup::Cpu,
+        MetricGroup::Load,
+        MetricGroup::Memory,
+        MetricGroup::Disk,
+        MetricGroup::Network,
+    ];
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Unknown metric group: {0}. Expected one of: cpu, load, memory, disk, network")]
+pub struct UnknownMetricGroup(String);
+
+impl FromStr for MetricGroup {
+    type Err = UnknownMetricGroup;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "cpu" => Ok(MetricGroup::Cpu),
+            "load" => Ok(MetricGroup::Load),
+            "memory" => Ok(MetricGroup::Memory),
+            "disk" => Ok(MetricGroup::Disk),
+            "network" => Ok(MetricGroup::Network),
+            unknown => Err(UnknownMetricGroup(unknown.to_string())),
+        }
+    }
+}
+
+impl Display for MetricGroup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MetricGroup::Cpu => "cpu",
+            MetricGroup::Load => "load",
+            MetricGroup::Memory => "memory",
+            MetricGroup::Disk => "disk",
+            MetricGroup::Network => "network",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Sample the system resource usage from `/proc` and `statvfs`
+///
+/// The CPU usage and the network throughput are computed from the difference between two samples,
+/// hence are only available from the second sample onward.
+pub struct SystemSampler {
+    host_root: Utf8PathBuf,
+    groups: Vec<MetricGroup>,
+    disks: Vec<Utf8PathBuf>,
+    last_sample: Option<Instant>,
+    last_cpu: Option<CpuTimes>,
+    last_network: HashMap<String, NetworkCounters>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CpuTimes {
+    total: u64,
+    idle: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct NetworkCounters {
+    rx_bytes: u64,
+    tx_bytes: u64,
+}
+
+impl SystemSampler {
+    pub fn new(host_root: Utf8PathBuf, groups: Vec<MetricGroup>, disks: Vec<Utf8PathBuf>) -> Self {
+        SystemSampler {
+            host_root,
+            groups,
+            disks,
+            last_sample: None,
+            last_cpu: None,
+            last_network: HashMap::new(),
+        }
+    }
+
+    /// Sample the resource usage, returning a thin-edge measurement
+    pub fn sample(&mut self) -> Map<String, Value> {
+        let now = Instant::now();
+        let elapsed = self.last_sample.map(|last| now.duration_since(last));
+        self.last_sample = Some(now);
+        self.sample_with_elapsed(elapsed)
+    }
+
+    fn sample_with_elapsed(&mut self, elapsed: Option<Duration>) -> Map<String, Value> {
+        let mut measurement = Map::new();
+        for group in self.groups.clone() {
+            match group {
+                MetricGroup::Cpu => {
+                    if let Some(cpu) = self.cpu() {
+                        measurement.insert("cpu".to_string(), cpu);
+                    }
+                }
+                MetricGroup::Load => {
+                    if let Some(load) = self.load() {
+                        measurement.insert("load".to_string(), load);
+                    }
+                }
+                MetricGroup::Memory => {
+                    if let Some(memory) = self.memory() {
+                        measurement.insert("memory".to_string(), memory);
+                    }
+                }
+                MetricGroup::Disk => {
+                    for disk in self.disks.iter() {
+                        if let Some(usage) = self.disk(disk) {
+                            measurement.insert(disk_group_name(disk), usage);
+                        }
+                    }
+                }
+                MetricGroup::Network => {
+                    for (interface, throughput) in self.network(elapsed) {
+                        measurement.insert(format!("network_{interface}"), throughput);
+                    }
+                }
+            }
+        }
+        measurement
+    }
+
+    fn read(&self, path: &str) -> Option<String> {
+        std::fs::read_to_string(self.host_root.join(path)).ok()
+    }
+
+    fn cpu(&mut self) -> Option<Value> {
+        let stat = self.read("proc/stat")?;
+        let times: Vec<u64> = stat
+            .lines()
+            .find(|line| line.starts_with("cpu "))?
+            .split_whitespace()
+            .skip(1)
+            .take(8)
+            .filter_map(|value| value.parse().ok())
+            .collect();
+        if times.len() < 5 {
+            return None;
+        }
+        let current = CpuTimes {
+            total: times.iter().sum(),
+            idle: times[3] + times[4],
+        };
+
+        let previous = self.last_cpu.replace(current)?;
+        let total = current.total.saturating_sub(previous.total);
+        let idle = current.idle.saturating_sub(previous.idle);
+        if total == 0 {
+            return None;
+        }
+        let usage = 100.0 * (total - idle.min(total)) as f64 / total as f64;
+        Some(json!({ "usage": round(usage) }))
+    }
+
+    fn load(&self) -> Option<Value> {
+        let loadavg = self.read("proc/loadavg")?;
+        let mut values = loadavg
+            .split_whitespace()
+            .filter_map(|value| value.parse::<f64>().ok());
+        Some(json!({
+            "1min": values.next()?,
+            "5min": values.next()?,
+            "15min": values.next()?,
+        }))
+    }
+
+    fn memory(&self) -> Option<Value> {
+        let memory = MemoryUsage::parse(&self.read("proc/meminfo")?)?;
+        let total = memory.total;
+        let available = memory.available?;
+        let used = total.saturating_sub(available);
+        Some(json!({
+            "total": total,
+            "available": available,
+            "used": used,
+            "usage": percent(used, total),
+        }))
+    }
+
+    fn disk(&self, mount_point: &Utf8Path) -> Option<Value> {
+        let path = self
+            .host_root
+            .join(mount_point.as_str().trim_start_matches('/'));
+        let usage = DiskUsage::of(path).ok()?;
+        let used = usage.used();
+        Some(json!({
+            "total": usage.total,
+            "available": usage.available,
+            "used": used,
+            "usage": percent(used, used + usage.available),
+        }))
+    }
+
+    fn network(&mut self, elapsed: Option<Duration>) -> Vec<(String, Value)> {
+        let Some(netdev) = self.read("proc/net/dev") else {
+            return vec![];
+        };
+
+        let mut throughputs = vec![];
+        for (interface, counters) in netdev.lines().filter_map(parse_netdev_line) {
+            if interface == "lo" {
+                continue;
+            }
+            let previous = self.last_network.insert(interface.clone(), counters);
+            if let (Some(previous), Some(elapsed)) = (previous, elapsed) {
+                let seconds = elapsed.as_secs_f64();
+                if seconds > 0.0 {
+                    let rate = |current: u64, previous: u64| {
+                        round(current.saturating_sub(previous) as f64 / seconds)
+                    };
+                    throughputs.push((
+                        interface,
+                        json!({
+                            "rx_bytes_per_sec": rate(counters.rx_bytes, previous.rx_bytes),
+                            "tx_bytes_per_sec": rate(counters.tx_bytes, previous.tx_bytes),
+                        }),
+                    ));
+                }
+            }
+        }
+        throughputs
+    }
+}
+
+/// Parse a line of `/proc/net/dev`, e.g.
+/// `  eth0: 1234 10 0 0 0 0 0 0 5678 20 0 0 0 0 0 0`
+fn parse_netdev_line(line: &str) -> Option<(String, NetworkCounters)> {
+    let (interface, counters) = line.split_once(':')?;
+    let counters: Vec<u64> = counters
+        .split_whitespace()
+        .filter_map(|value| value.parse().ok())
+        .collect();
+    Some((
+        interface.trim().to_string(),
+        NetworkCounters {
+            rx_bytes: *counters.first()?,
+            tx_bytes: *counters.get(8)?,
+        },
+    ))
+}
+
+/// The measurement group name for a disk, e.g. `disk_root` for `/` and `disk_data` for `/data`
+fn disk_group_name(mount_point: &Utf8Path) -> String {
+    let name = mount_point.as_str().trim_matches('/').replace('/', "_");
+    if name.is_empty() {
+        "disk_root".to_string()
+    } else {
+        format!("disk_{name}")
+    }
+}
+
+fn percent(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        round(100.0 * part as f64 / total as f64)
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[test]
+    fn memory_and_load_are_available_from_the_first_sample() {
+        let root = fake_proc();
+        let mut sampler = SystemSampler::new(
+            root.utf8_path_buf(),
+            vec![MetricGroup::Memory, MetricGroup::Load, MetricGroup::Cpu],
+            vec![],
+        );
+
+        let measurement = sampler.sample_with_elapsed(None);
+        assert_eq!(
+            Value::Object(measurement),
+            json!({
+                "memory": {
+                    "total": 4096 * 1024,
+                    "available": 1024 * 1024,
+                    "used": 3072 * 1024,
+                    "usage": 75.0,
+                },
+                "load": {
+                    "1min": 0.5,
+                    "5min": 0.25,
+                    "15min": 0.1,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn cpu_usage_and_network_throughput_are_computed_between_two_samples() {
+        let root = fake_proc();
+        let mut sampler = SystemSampler::new(
+            root.utf8_path_buf(),
+            vec![MetricGroup::Cpu, MetricGroup::Network],
+            vec![],
+        );
+        assert!(sampler.sample_with_elapsed(None).is_empty());
+
+        let proc = root.utf8_path().join("proc");
+        std::fs::write(proc.join("stat"), "cpu  175 0 25 160 40 0 0 0 0 0\n").unwrap();
+        std::fs::write(
+            proc.join("net/dev"),
+            "Inter-|   Receive |  Transmit\n face |bytes packets|bytes packets\n    lo: 9999 1 0 0 0 0 0 0 9999 1 0 0 0 0 0 0\n  eth0: 3000 30 0 0 0 0 0 0 1000 10 0 0 0 0 0 0\n",
+        )
+        .unwrap();
+
+        let measurement = sampler.sample_with_elapsed(Some(Duration::from_secs(2)));
+        assert_eq!(
+            Value::Object(measurement),
+            json!({
+                "cpu": { "usage": 50.0 },
+                "network_eth0": {
+                    "rx_bytes_per_sec": 1000.0,
+                    "tx_bytes_per_sec": 250.0,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn disk_group_names() {
+        assert_eq!(disk_group_name("/".into()), "disk_root");
+        assert_eq!(disk_group_name("/data".into()), "disk_data");
+        assert_eq!(disk_group_name("/var/log/".into()), "disk_var_log");
+    }
+
+    #[test]
+    fn parse_metric_groups() {
+        assert_eq!("cpu".parse::<MetricGroup>().unwrap(), MetricGroup::Cpu);
+        assert_eq!(
+            " network".parse::<MetricGroup>().unwrap(),
+            MetricGroup::Network
+        );
+        assert!("gpu".parse::<MetricGroup>().is_err());
+    }
+
+    fn fake_proc() -> TempTedgeDir {
+        let root = TempTedgeDir::new();
+        let proc = root.dir("proc");
+        proc.file("meminfo").with_raw_content(
+            "MemTotal:        4096 kB\nMemFree:          512 kB\nMemAvailable:    1024 kB\n",
+        );
+        proc.file("loadavg")
+            .with_raw_content("0.50 0.25 0.10 1/123 4567\n");
+        proc.file("stat")
+            .with_raw_content("cpu  100 0 0 100 0 0 0 0 0 0\n");
+        proc.dir("net").file("dev").with_raw_content(
+            "Inter-|   Receive |  Transmit\n face |bytes packets|bytes packets\n    lo: 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n  eth0: 1000 10 0 0 0 0 0 0 500 5 0 0 0 0 0 0\n",
+        );
+        root
+    }
+}
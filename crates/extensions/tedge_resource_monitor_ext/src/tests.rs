@@ -0,0 +1,155 @@
+use crate::MetricGroup;
+use crate::ProcessMonitorBuilder;
+use crate::ProcessMonitorConfig;
+use crate::ResourceMonitorBuilder;
+use crate::ResourceMonitorConfig;
+use serde_json::json;
+use serde_json::Value;
+use std::time::Duration;
+use tedge_actors::test_helpers::MessageReceiverExt;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::MessageReceiver;
+use tedge_actors::NoMessage;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::Topic;
+use tedge_test_utils::fs::TempTedgeDir;
+
+const TEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[tokio::test]
+async fn publish_resource_usage_on_interval() {
+    let root = TempTedgeDir::new();
+    root.dir("proc")
+        .file("loadavg")
+        .with_raw_content("1.50 1.00 0.50 1/123 4567\n");
+
+    let config = ResourceMonitorConfig::new(
+        Topic::new_unchecked("te/device/main///m/resources"),
+        Duration::from_millis(100),
+    )
+    .with_groups(vec![MetricGroup::Load, MetricGroup::Memory])
+    .with_host_root(root.utf8_path());
+    let mut mqtt = spawn_resource_monitor(config);
+
+    for _ in 0..2 {
+        let message = mqtt.recv().await.expect("a resources measurement");
+        assert_eq!(message.topic.name, "te/device/main///m/resources");
+        let payload: Value = serde_json::from_str(message.payload_str().unwrap()).unwrap();
+        assert_eq!(
+            payload,
+            json!({"load": {"1min": 1.5, "5min": 1.0, "15min": 0.5}})
+        );
+    }
+}
+
+#[tokio::test]
+async fn nothing_is_published_when_no_metrics_are_available() {
+    let root = TempTedgeDir::new();
+    let config = ResourceMonitorConfig::new(
+        Topic::new_unchecked("te/device/main///m/resources"),
+        Duration::from_millis(10),
+    )
+    .with_groups(vec![MetricGroup::Memory])
+    .with_host_root(root.utf8_path());
+    let mut mqtt = spawn_resource_monitor(config);
+
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), mqtt.recv())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn monitor_the_process_of_a_registered_service() {
+    let root = TempTedgeDir::new();
+    let pid_file = root.file("my-service.pid").with_raw_content("4321");
+    let process = root.dir("proc").dir("4321");
+    process.file("stat").with_raw_content(
+        "4321 (my-service) S 1 4321 4321 0 -1 4194560 100 0 0 0 50 10 0 0 20 0 1 0 777 1000 200\n",
+    );
+    process
+        .file("status")
+        .with_raw_content("Name:\tmy-service\nVmRSS:\t    1024 kB\n");
+    process.dir("fd");
+
+    let config = ProcessMonitorConfig::new(
+        MqttSchema::default(),
+        EntityTopicId::default_main_device(),
+        Duration::from_millis(100),
+    )
+    .with_host_root(root.utf8_path());
+    let mut mqtt_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage> =
+        SimpleMessageBoxBuilder::new("MQTT", 16);
+    let actor = ProcessMonitorBuilder::new(config, &mut mqtt_builder).build();
+    let mut mqtt = mqtt_builder.build().with_timeout(TEST_TIMEOUT);
+    tokio::spawn(async move { actor.run().await });
+
+    let registration = json!({
+        "@type": "service",
+        "@process": { "pid_file": pid_file.utf8_path().as_str() },
+    });
+    mqtt.send(
+        MqttMessage::from((
+            "te/device/main/service/my-service",
+            registration.to_string().as_str(),
+        ))
+        .with_retain(),
+    )
+    .await
+    .unwrap();
+
+    let message = mqtt.recv().await.expect("a process measurement");
+    assert_eq!(
+        message.topic.name,
+        "te/device/main/service/my-service/m/process"
+    );
+    let payload: Value = serde_json::from_str(message.payload_str().unwrap()).unwrap();
+    assert_eq!(
+        payload,
+        json!({"process": {"rss": 1024 * 1024, "fds": 0, "restarts": 0}})
+    );
+
+    // When the process is gone, a down status is published on behalf of the service
+    std::fs::remove_file(pid_file.utf8_path()).unwrap();
+    let message = next_health_status(&mut mqtt).await;
+    let payload: Value = serde_json::from_str(message.payload_str().unwrap()).unwrap();
+    assert_eq!(payload["status"], "down");
+    assert!(payload["time"].is_number());
+    assert!(message.retain);
+
+    // This status is cleared as soon as the process is back
+    root.file("my-service.pid").with_raw_content("4321");
+    let message = next_health_status(&mut mqtt).await;
+    assert!(message.payload_bytes().is_empty());
+    assert!(message.retain);
+}
+
+async fn next_health_status(mqtt: &mut impl MessageReceiver<MqttMessage>) -> MqttMessage {
+    loop {
+        let message = mqtt.recv().await.expect("a health status message");
+        if message.topic.name.ends_with("/m/process") {
+            continue;
+        }
+        assert_eq!(
+            message.topic.name,
+            "te/device/main/service/my-service/status/health"
+        );
+        return message;
+    }
+}
+
+fn spawn_resource_monitor(config: ResourceMonitorConfig) -> impl MessageReceiver<MqttMessage> {
+    let mqtt_builder: SimpleMessageBoxBuilder<MqttMessage, NoMessage> =
+        SimpleMessageBoxBuilder::new("MQTT", 16);
+    let actor = ResourceMonitorBuilder::new(config, &mqtt_builder).build();
+    let mqtt: SimpleMessageBox<MqttMessage, NoMessage> = mqtt_builder.build();
+    tokio::spawn(async move { actor.run().await });
+    mqtt.with_timeout(TEST_TIMEOUT)
+}
@@ -226,6 +226,10 @@ impl Actor for TimerActor {
                                 // Stop immediately
                                 return Ok(());
                             }
+                            Err(RuntimeRequest::ReportMetrics(_)) => {
+                                // Answered by the message box
+                                self.start_timer(current_timer);
+                            }
                         }
                     },
                 }
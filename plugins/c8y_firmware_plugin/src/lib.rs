@@ -104,6 +104,7 @@ async fn run_with(
         &mut mqtt_actor,
         &mqtt_schema,
         &tedge_config.service,
+        &runtime.get_handle(),
     );
 
     // Instantiate firmware manager actor
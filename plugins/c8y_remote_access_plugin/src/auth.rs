@@ -20,9 +20,4 @@ impl Auth {
             .map(Auth)
             .into_diagnostic()
     }
-
-    #[cfg(test)]
-    pub fn test_value(test_value: HeaderValue) -> Self {
-        Self(test_value)
-    }
 }
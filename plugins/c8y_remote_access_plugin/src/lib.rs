@@ -6,6 +6,7 @@ use input::parse_arguments;
 use miette::miette;
 use miette::Context;
 use miette::IntoDiagnostic;
+use remote_access::WebsocketSocketProxy;
 use std::io;
 use std::process::Stdio;
 use tedge_config::log_init;
@@ -23,12 +24,10 @@ use crate::auth::Auth;
 pub use crate::input::C8yRemoteAccessPluginOpt;
 use crate::input::Command;
 use crate::input::RemoteAccessConnect;
-use crate::proxy::WebsocketSocketProxy;
 
 mod auth;
 mod csv;
 mod input;
-mod proxy;
 
 const UNIX_SOCKFILE: &str = "/run/c8y-remote-access-plugin.sock";
 
@@ -330,13 +329,15 @@ async fn proxy(
     let proxy = WebsocketSocketProxy::connect(
         &url,
         command.target_address(),
-        auth,
+        Some(auth.authorization_header()),
         Some(client_config),
         &config.proxy,
     )
     .await?;
+    println!("{SUCCESS_MESSAGE}");
 
     proxy.run().await;
+    println!("STOPPING");
     Ok(())
 }
 
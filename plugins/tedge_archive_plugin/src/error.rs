@@ -0,0 +1,56 @@
+use camino::Utf8PathBuf;
+
+#[derive(thiserror::Error, Debug)]
+pub enum InternalError {
+    #[error("A file is required to install {module}: archives are not fetched from a repository")]
+    MissingFile { module: String },
+
+    #[error("A version is required to install {module}")]
+    MissingVersion { module: String },
+
+    #[error("Invalid module name: {0:?}")]
+    InvalidModuleName(String),
+
+    #[error("Invalid module version: {0:?}")]
+    InvalidModuleVersion(String),
+
+    #[error("Unsupported archive format for `{file}`, expected a .tar.gz or a .zip file")]
+    UnsupportedFormat { file: Utf8PathBuf },
+
+    #[error("Fail to extract `{file}`: {error}")]
+    ExtractionError { file: Utf8PathBuf, error: String },
+
+    #[error("Post-install hook of {module} failed: {error}")]
+    HookError { module: String, error: String },
+
+    #[error("Cannot remove {module}: version {installed_version} is installed, but {provided_version} was provided")]
+    VersionMismatch {
+        module: String,
+        installed_version: String,
+        provided_version: String,
+    },
+
+    #[error("Fail to access `{path}`: {from}")]
+    PathError {
+        path: Utf8PathBuf,
+        from: std::io::Error,
+    },
+
+    #[error(transparent)]
+    FromIo(#[from] std::io::Error),
+
+    #[error(transparent)]
+    FromCsv(#[from] csv::Error),
+
+    #[error("Invalid manifest: {0}")]
+    FromJson(#[from] serde_json::Error),
+}
+
+impl InternalError {
+    pub fn path_error(path: impl Into<Utf8PathBuf>, from: std::io::Error) -> InternalError {
+        InternalError::PathError {
+            path: path.into(),
+            from,
+        }
+    }
+}
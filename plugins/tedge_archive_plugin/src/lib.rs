@@ -0,0 +1,154 @@
+mod error;
+mod store;
+
+use crate::error::InternalError;
+use crate::store::ArchiveStore;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+use std::io;
+use tedge_config::cli::CommonArgs;
+use tedge_config::log_init;
+use tracing::error;
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    name = clap::crate_name!(),
+    version = clap::crate_version!(),
+    about = clap::crate_description!(),
+    arg_required_else_help(true)
+)]
+pub struct ArchiveCli {
+    #[command(flatten)]
+    pub common: CommonArgs,
+
+    #[clap(subcommand)]
+    operation: PluginOp,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum PluginOp {
+    /// List all the installed modules
+    List,
+
+    /// Install a module from a .tar.gz or .zip archive
+    Install {
+        module: String,
+        #[clap(short = 'v', long = "module-version")]
+        version: Option<String>,
+        #[clap(long = "file")]
+        file_path: Option<Utf8PathBuf>,
+    },
+
+    /// Uninstall a module
+    Remove {
+        module: String,
+        #[clap(short = 'v', long = "module-version")]
+        version: Option<String>,
+    },
+
+    /// Install or remove multiple modules at once
+    UpdateList,
+
+    /// Prepare a sequences of install/remove commands
+    Prepare,
+
+    /// Finalize a sequences of install/remove commands
+    Finalize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum UpdateAction {
+    Install,
+    Remove,
+}
+
+#[derive(Debug, Deserialize)]
+struct SoftwareModuleUpdate {
+    pub action: UpdateAction,
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub path: Option<Utf8PathBuf>,
+}
+
+fn run_op(cli: ArchiveCli, root: &Utf8Path) -> Result<(), InternalError> {
+    if let Err(err) = log_init(
+        "tedge-archive-plugin",
+        &cli.common.log_args,
+        &cli.common.config_dir,
+    ) {
+        error!("Can't enable logging due to error: {err}");
+    }
+    let store = ArchiveStore::new(root);
+    match cli.operation {
+        PluginOp::List => {
+            for (name, module) in store.manifest()?.modules {
+                println!("{name}\t{}", module.version);
+            }
+        }
+
+        PluginOp::Install {
+            module,
+            version,
+            file_path,
+        } => install(&store, module, version, file_path)?,
+
+        PluginOp::Remove { module, version } => store.remove(&module, version.as_deref())?,
+
+        PluginOp::UpdateList => {
+            let mut rdr = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .delimiter(b'\t')
+                .flexible(true)
+                .from_reader(io::stdin());
+            let mut updates: Vec<SoftwareModuleUpdate> = Vec::new();
+            for result in rdr.deserialize() {
+                updates.push(result?);
+            }
+
+            // Fail fast, as the modules might depend on each other
+            for update in updates {
+                let version = update.version.filter(|version| !version.is_empty());
+                match update.action {
+                    UpdateAction::Install => install(&store, update.name, version, update.path)?,
+                    UpdateAction::Remove => store.remove(&update.name, version.as_deref())?,
+                }
+            }
+        }
+
+        PluginOp::Prepare => {}
+
+        PluginOp::Finalize => store.clean_up()?,
+    }
+
+    Ok(())
+}
+
+fn install(
+    store: &ArchiveStore,
+    module: String,
+    version: Option<String>,
+    file_path: Option<Utf8PathBuf>,
+) -> Result<(), InternalError> {
+    let Some(file_path) = file_path.filter(|path| !path.as_str().is_empty()) else {
+        return Err(InternalError::MissingFile { module });
+    };
+    let Some(version) = version.filter(|version| version != "latest") else {
+        return Err(InternalError::MissingVersion { module });
+    };
+    store.install(&module, &version, &file_path)
+}
+
+pub fn run_and_exit(cli: ArchiveCli, root: &Utf8Path) -> ! {
+    match run_op(cli, root) {
+        Ok(()) => std::process::exit(0),
+
+        Err(err) => {
+            eprintln!("ERROR: {}", err);
+            std::process::exit(2);
+        }
+    }
+}
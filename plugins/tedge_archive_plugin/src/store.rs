@@ -0,0 +1,499 @@
+//! The software modules installed from archives
+//!
+//! Each module is installed in its own directory under the root directory:
+//!
+//! ```text
+//! <root>/
+//! ├── manifest.json              the name and version of the installed modules
+//! └── <module>/
+//!     ├── <version>/             the content of the archive
+//!     └── current -> <version>   a symlink to the installed version
+//! ```
+//!
+//! A new version is extracted in its own directory and its post-install hook run,
+//! before the `current` symlink is atomically replaced. So, on failure, the previous version is left in place.
+
+use crate::error::InternalError;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::io::Seek;
+use std::process::Command;
+use std::process::Stdio;
+use tracing::info;
+use tracing::warn;
+
+/// The hook run, if provided by the archive, once the archive is extracted and before the new version is made current
+///
+/// The hook is run from the directory of the new version, with the `TEDGE_MODULE_NAME`,
+/// `TEDGE_MODULE_VERSION` and `TEDGE_MODULE_DIR` environment variables set.
+pub const POST_INSTALL_HOOK: &str = ".tedge/post-install";
+
+const MANIFEST: &str = "manifest.json";
+const CURRENT: &str = "current";
+
+/// Prefix of the files and directories used while a module is being installed
+const TMP_PREFIX: &str = ".tmp-";
+
+/// The software modules installed from archives
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub modules: BTreeMap<String, InstalledModule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledModule {
+    pub version: String,
+}
+
+pub struct ArchiveStore {
+    root: Utf8PathBuf,
+}
+
+impl ArchiveStore {
+    pub fn new(root: impl Into<Utf8PathBuf>) -> Self {
+        ArchiveStore { root: root.into() }
+    }
+
+    /// Read the manifest of the installed modules
+    pub fn manifest(&self) -> Result<Manifest, InternalError> {
+        let path = self.root.join(MANIFEST);
+        match std::fs::read(&path) {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Manifest::default()),
+            Err(err) => Err(InternalError::path_error(path, err)),
+        }
+    }
+
+    /// The version of a module currently installed, as given by its `current` symlink
+    pub fn installed_version(&self, module: &str) -> Option<String> {
+        let target = std::fs::read_link(self.root.join(module).join(CURRENT)).ok()?;
+        target.to_str().map(str::to_string)
+    }
+
+    /// Install a version of a module from an archive
+    pub fn install(
+        &self,
+        module: &str,
+        version: &str,
+        file: &Utf8Path,
+    ) -> Result<(), InternalError> {
+        validate_module_name(module)?;
+        validate_module_version(version)?;
+
+        if self.installed_version(module).as_deref() == Some(version) {
+            info!("{module} {version} is already installed");
+            return self.update_manifest(module, Some(version));
+        }
+
+        let module_dir = self.root.join(module);
+        let version_dir = module_dir.join(version);
+        let staging_dir = module_dir.join(format!("{TMP_PREFIX}{version}"));
+        create_dir_all(&module_dir)?;
+        remove_if_exists(&staging_dir)?;
+        remove_if_exists(&version_dir)?;
+
+        if let Err(err) = extract(file, &staging_dir) {
+            let _ = remove_if_exists(&staging_dir);
+            return Err(err);
+        }
+        std::fs::rename(&staging_dir, &version_dir)
+            .map_err(|err| InternalError::path_error(&version_dir, err))?;
+
+        if let Err(err) = run_post_install_hook(module, version, &version_dir) {
+            let _ = remove_if_exists(&version_dir);
+            return Err(err);
+        }
+
+        let previous_version = self.installed_version(module);
+        self.make_current(&module_dir, version)?;
+        if let Some(previous_version) = previous_version {
+            if let Err(err) = remove_if_exists(&module_dir.join(&previous_version)) {
+                warn!("Fail to remove the previous version of {module}: {err}");
+            }
+        }
+
+        info!("{module} {version} installed in {version_dir}");
+        self.update_manifest(module, Some(version))
+    }
+
+    /// Remove a module, checking that the installed version is the expected one if provided
+    pub fn remove(&self, module: &str, version: Option<&str>) -> Result<(), InternalError> {
+        validate_module_name(module)?;
+
+        let Some(installed_version) = self.installed_version(module) else {
+            info!("{module} is not installed");
+            return self.update_manifest(module, None);
+        };
+        if let Some(version) = version {
+            if version != installed_version {
+                return Err(InternalError::VersionMismatch {
+                    module: module.to_string(),
+                    installed_version,
+                    provided_version: version.to_string(),
+                });
+            }
+        }
+
+        let module_dir = self.root.join(module);
+        remove_if_exists(&module_dir.join(CURRENT))?;
+        remove_if_exists(&module_dir)?;
+
+        info!("{module} {installed_version} removed");
+        self.update_manifest(module, None)
+    }
+
+    /// Remove the leftovers of interrupted installations
+    pub fn clean_up(&self) -> Result<(), InternalError> {
+        let Ok(modules) = self.root.read_dir_utf8() else {
+            return Ok(());
+        };
+        for module_dir in modules.flatten() {
+            if !module_dir.path().is_dir() {
+                continue;
+            }
+            let entries = module_dir
+                .path()
+                .read_dir_utf8()
+                .map_err(|err| InternalError::path_error(module_dir.path(), err))?;
+            for entry in entries.flatten() {
+                if entry.file_name().starts_with(TMP_PREFIX) {
+                    remove_if_exists(entry.path())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Atomically replace the `current` symlink of a module
+    fn make_current(&self, module_dir: &Utf8Path, version: &str) -> Result<(), InternalError> {
+        let tmp_link = module_dir.join(format!("{TMP_PREFIX}{CURRENT}"));
+        let link = module_dir.join(CURRENT);
+        remove_if_exists(&tmp_link)?;
+        std::os::unix::fs::symlink(version, &tmp_link)
+            .map_err(|err| InternalError::path_error(&tmp_link, err))?;
+        std::fs::rename(&tmp_link, &link).map_err(|err| InternalError::path_error(&link, err))
+    }
+
+    fn update_manifest(&self, module: &str, version: Option<&str>) -> Result<(), InternalError> {
+        let mut manifest = self.manifest()?;
+        match version {
+            Some(version) => manifest.modules.insert(
+                module.to_string(),
+                InstalledModule {
+                    version: version.to_string(),
+                },
+            ),
+            None => manifest.modules.remove(module),
+        };
+
+        create_dir_all(&self.root)?;
+        let path = self.root.join(MANIFEST);
+        let tmp_path = self.root.join(format!("{TMP_PREFIX}{MANIFEST}"));
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(&manifest)?)
+            .map_err(|err| InternalError::path_error(&tmp_path, err))?;
+        std::fs::rename(&tmp_path, &path).map_err(|err| InternalError::path_error(&path, err))
+    }
+}
+
+/// Extract a .tar.gz or .zip archive, the format being detected from the content of the file
+fn extract(file: &Utf8Path, target: &Utf8Path) -> Result<(), InternalError> {
+    let mut archive = File::open(file).map_err(|err| InternalError::path_error(file, err))?;
+    let mut magic = [0u8; 4];
+    let magic_len = archive.read(&mut magic)?;
+    archive.rewind()?;
+
+    let extraction_error = |error: String| InternalError::ExtractionError {
+        file: file.to_owned(),
+        error,
+    };
+    match &magic[..magic_len] {
+        [0x1f, 0x8b, ..] => {
+            // Entries with absolute paths or `..` components are rejected by `tar`
+            let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(archive));
+            archive
+                .unpack(target)
+                .map_err(|err| extraction_error(err.to_string()))
+        }
+        [b'P', b'K', 0x03, 0x04] => {
+            // Entries with absolute paths or `..` components are rejected by `zip`
+            let mut archive =
+                zip::ZipArchive::new(archive).map_err(|err| extraction_error(err.to_string()))?;
+            archive
+                .extract(target)
+                .map_err(|err| extraction_error(err.to_string()))
+        }
+        _ => Err(InternalError::UnsupportedFormat {
+            file: file.to_owned(),
+        }),
+    }
+}
+
+fn run_post_install_hook(
+    module: &str,
+    version: &str,
+    version_dir: &Utf8Path,
+) -> Result<(), InternalError> {
+    let hook = version_dir.join(POST_INSTALL_HOOK);
+    if !hook.is_file() {
+        return Ok(());
+    }
+
+    info!("Running the post-install hook of {module} {version}");
+    let hook_error = |error: String| InternalError::HookError {
+        module: module.to_string(),
+        error,
+    };
+    let status = Command::new(&hook)
+        .current_dir(version_dir)
+        .env("TEDGE_MODULE_NAME", module)
+        .env("TEDGE_MODULE_VERSION", version)
+        .env("TEDGE_MODULE_DIR", version_dir)
+        .stdin(Stdio::null())
+        .status()
+        .map_err(|err| hook_error(format!("fail to run {hook}: {err}")))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(hook_error(format!("{hook} {status}")))
+    }
+}
+
+/// Module names and versions are used as directory names, hence cannot be paths
+fn is_valid_path_segment(segment: &str) -> bool {
+    !segment.is_empty()
+        && !segment.starts_with('.')
+        && !segment.contains(['/', '\\', '\0'])
+        && segment.chars().all(|c| !c.is_control())
+}
+
+fn validate_module_name(module: &str) -> Result<(), InternalError> {
+    if is_valid_path_segment(module) {
+        Ok(())
+    } else {
+        Err(InternalError::InvalidModuleName(module.to_string()))
+    }
+}
+
+fn validate_module_version(version: &str) -> Result<(), InternalError> {
+    if is_valid_path_segment(version) && version != CURRENT {
+        Ok(())
+    } else {
+        Err(InternalError::InvalidModuleVersion(version.to_string()))
+    }
+}
+
+fn create_dir_all(path: &Utf8Path) -> Result<(), InternalError> {
+    std::fs::create_dir_all(path).map_err(|err| InternalError::path_error(path, err))
+}
+
+fn remove_if_exists(path: &Utf8Path) -> Result<(), InternalError> {
+    let result = match std::fs::symlink_metadata(path) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => Err(err),
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+    };
+    result.map_err(|err| InternalError::path_error(path, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    struct TestStore {
+        dir: TempDir,
+        store: ArchiveStore,
+    }
+
+    impl TestStore {
+        fn new() -> Self {
+            let dir = TempDir::new().unwrap();
+            let root = Utf8Path::from_path(dir.path()).unwrap().join("archives");
+            TestStore {
+                dir,
+                store: ArchiveStore::new(root),
+            }
+        }
+
+        fn root(&self) -> &Utf8Path {
+            &self.store.root
+        }
+
+        fn tar_gz(&self, name: &str, files: &[(&str, &str, u32)]) -> Utf8PathBuf {
+            let path = Utf8Path::from_path(self.dir.path()).unwrap().join(name);
+            let encoder = flate2::write::GzEncoder::new(
+                File::create(&path).unwrap(),
+                flate2::Compression::default(),
+            );
+            let mut builder = tar::Builder::new(encoder);
+            for (file, content, mode) in files {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(content.len() as u64);
+                header.set_mode(*mode);
+                header.set_cksum();
+                builder
+                    .append_data(&mut header, file, content.as_bytes())
+                    .unwrap();
+            }
+            builder.into_inner().unwrap().finish().unwrap();
+            path
+        }
+
+        fn zip(&self, name: &str, files: &[(&str, &str)]) -> Utf8PathBuf {
+            let path = Utf8Path::from_path(self.dir.path()).unwrap().join(name);
+            let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+            for (file, content) in files {
+                writer
+                    .start_file(*file, zip::write::SimpleFileOptions::default())
+                    .unwrap();
+                writer.write_all(content.as_bytes()).unwrap();
+            }
+            writer.finish().unwrap();
+            path
+        }
+
+        fn read(&self, path: &str) -> String {
+            std::fs::read_to_string(self.root().join(path)).unwrap()
+        }
+
+        fn installed(&self) -> Vec<(String, String)> {
+            self.store
+                .manifest()
+                .unwrap()
+                .modules
+                .into_iter()
+                .map(|(name, module)| (name, module.version))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn installing_a_tar_gz_archive() {
+        let test = TestStore::new();
+        let file = test.tar_gz("app.tar.gz", &[("bin/app", "v1", 0o755)]);
+
+        test.store.install("app", "1.0.0", &file).unwrap();
+
+        assert_eq!(test.read("app/current/bin/app"), "v1");
+        assert_eq!(test.store.installed_version("app").unwrap(), "1.0.0");
+        assert_eq!(test.installed(), vec![("app".into(), "1.0.0".into())]);
+    }
+
+    #[test]
+    fn installing_a_zip_archive() {
+        let test = TestStore::new();
+        let file = test.zip("app.zip", &[("config/app.toml", "port = 8080")]);
+
+        test.store.install("app", "2.1", &file).unwrap();
+
+        assert_eq!(test.read("app/current/config/app.toml"), "port = 8080");
+        assert_eq!(test.installed(), vec![("app".into(), "2.1".into())]);
+    }
+
+    #[test]
+    fn upgrading_a_module_replaces_the_previous_version() {
+        let test = TestStore::new();
+        let v1 = test.tar_gz("v1.tar.gz", &[("app", "v1", 0o644)]);
+        let v2 = test.tar_gz("v2.tar.gz", &[("app", "v2", 0o644)]);
+
+        test.store.install("app", "1.0", &v1).unwrap();
+        test.store.install("app", "2.0", &v2).unwrap();
+
+        assert_eq!(test.read("app/current/app"), "v2");
+        assert!(!test.root().join("app/1.0").exists());
+        assert_eq!(test.installed(), vec![("app".into(), "2.0".into())]);
+    }
+
+    #[test]
+    fn a_failing_post_install_hook_keeps_the_previous_version() {
+        let test = TestStore::new();
+        let v1 = test.tar_gz("v1.tar.gz", &[("app", "v1", 0o644)]);
+        let v2 = test.tar_gz(
+            "v2.tar.gz",
+            &[
+                ("app", "v2", 0o644),
+                (POST_INSTALL_HOOK, "#!/bin/sh\nexit 1\n", 0o755),
+            ],
+        );
+
+        test.store.install("app", "1.0", &v1).unwrap();
+        let err = test.store.install("app", "2.0", &v2).unwrap_err();
+
+        assert!(matches!(err, InternalError::HookError { .. }));
+        assert_eq!(test.read("app/current/app"), "v1");
+        assert!(!test.root().join("app/2.0").exists());
+        assert_eq!(test.installed(), vec![("app".into(), "1.0".into())]);
+    }
+
+    #[test]
+    fn the_post_install_hook_is_run_from_the_new_version() {
+        let test = TestStore::new();
+        let file = test.tar_gz(
+            "app.tar.gz",
+            &[(
+                POST_INSTALL_HOOK,
+                "#!/bin/sh\necho \"$TEDGE_MODULE_NAME $TEDGE_MODULE_VERSION\" > installed-by-hook\n",
+                0o755,
+            )],
+        );
+
+        test.store.install("app", "1.0", &file).unwrap();
+
+        assert_eq!(test.read("app/current/installed-by-hook"), "app 1.0\n");
+    }
+
+    #[test]
+    fn removing_a_module() {
+        let test = TestStore::new();
+        let file = test.tar_gz("app.tar.gz", &[("app", "v1", 0o644)]);
+        test.store.install("app", "1.0", &file).unwrap();
+
+        let err = test.store.remove("app", Some("2.0")).unwrap_err();
+        assert!(matches!(err, InternalError::VersionMismatch { .. }));
+
+        test.store.remove("app", Some("1.0")).unwrap();
+        assert!(!test.root().join("app").exists());
+        assert_eq!(test.installed(), vec![]);
+
+        // Removing a module that is not installed is not an error
+        test.store.remove("app", None).unwrap();
+    }
+
+    #[test]
+    fn invalid_archives_are_rejected() {
+        let test = TestStore::new();
+        let file = Utf8Path::from_path(test.dir.path())
+            .unwrap()
+            .join("app.deb");
+        std::fs::write(&file, "not an archive").unwrap();
+
+        let err = test.store.install("app", "1.0", &file).unwrap_err();
+
+        assert!(matches!(err, InternalError::UnsupportedFormat { .. }));
+        assert!(test.store.installed_version("app").is_none());
+        assert!(!test.root().join("app/1.0").exists());
+    }
+
+    #[test]
+    fn module_names_and_versions_cannot_be_paths() {
+        let test = TestStore::new();
+        let file = test.tar_gz("app.tar.gz", &[("app", "v1", 0o644)]);
+
+        for name in ["", "..", "../etc", "a/b", ".hidden"] {
+            let err = test.store.install(name, "1.0", &file).unwrap_err();
+            assert!(matches!(err, InternalError::InvalidModuleName(_)));
+        }
+        for version in ["", "..", "1.0/../..", "current"] {
+            let err = test.store.install("app", version, &file).unwrap_err();
+            assert!(matches!(err, InternalError::InvalidModuleVersion(_)));
+        }
+    }
+}
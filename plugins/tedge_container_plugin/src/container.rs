@@ -0,0 +1,257 @@
+//! Containers as software modules
+//!
+//! A module is a container, named after the module, running the image given by the module version,
+//! e.g. `nginx:1.27` or `registry.example.com/my-app:2.0`. The module name is used as image when no version is given.
+
+use crate::engine::Engine;
+use crate::engine::MODULE_LABEL;
+use crate::error::InternalError;
+use camino::Utf8Path;
+use tracing::info;
+
+/// The value of the [MODULE_LABEL] of the containers managed as `container` modules
+const MODULE_TYPE: &str = "container";
+
+pub struct ContainerModules {
+    engine: Engine,
+}
+
+impl ContainerModules {
+    pub fn new(engine: Engine) -> Self {
+        ContainerModules { engine }
+    }
+
+    /// List the containers installed by the plugin, with the image they run
+    pub fn list(&self) -> Result<Vec<(String, String)>, InternalError> {
+        let label_filter = format!("label={MODULE_LABEL}={MODULE_TYPE}");
+        let output = self.engine.output([
+            "ps",
+            "--all",
+            "--filter",
+            label_filter.as_str(),
+            "--format",
+            "{{.Names}}\t{{.Image}}",
+        ])?;
+        Ok(output
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(name, image)| (name.to_string(), image.to_string()))
+            .collect())
+    }
+
+    /// Start a container, replacing the container with the same name if any
+    ///
+    /// The image is loaded from the file if one is given, otherwise pulled.
+    /// On failure to get the image, the current container is left untouched.
+    pub fn install(
+        &self,
+        module: &str,
+        version: Option<&str>,
+        file: Option<&Utf8Path>,
+    ) -> Result<(), InternalError> {
+        validate_container_name(module)?;
+        let image = version.unwrap_or(module);
+        validate_image(image)?;
+
+        match file {
+            Some(file) => self.engine.run(["load", "--input", file.as_str()])?,
+            None => self.engine.run(["pull", "--", image])?,
+        }
+
+        if self.engine.container_exists(module)? {
+            info!("Replacing the container {module}");
+            self.engine.run(["rm", "--force", "--", module])?;
+        }
+        let label = format!("{MODULE_LABEL}={MODULE_TYPE}");
+        self.engine.run([
+            "run",
+            "--detach",
+            "--name",
+            module,
+            "--restart",
+            "unless-stopped",
+            "--label",
+            label.as_str(),
+            "--",
+            image,
+        ])
+    }
+
+    /// Stop and remove a container, checking that it runs the expected image if a version is given
+    pub fn remove(&self, module: &str, version: Option<&str>) -> Result<(), InternalError> {
+        validate_container_name(module)?;
+
+        let installed = self.list()?;
+        let Some((_, installed_version)) = installed.iter().find(|(name, _)| name == module) else {
+            info!("{module} is not installed");
+            return Ok(());
+        };
+        if let Some(version) = version {
+            if version != installed_version {
+                return Err(InternalError::VersionMismatch {
+                    module: module.to_string(),
+                    installed_version: installed_version.clone(),
+                    provided_version: version.to_string(),
+                });
+            }
+        }
+
+        self.engine.run(["rm", "--force", "--", module])
+    }
+
+    /// Remove the images no more used by any container
+    pub fn prune_images(&self) -> Result<(), InternalError> {
+        self.engine.run(["image", "prune", "--force"])
+    }
+}
+
+/// Container names are made of `[a-zA-Z0-9][a-zA-Z0-9_.-]*`
+fn validate_container_name(name: &str) -> Result<(), InternalError> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(InternalError::InvalidModuleName(name.to_string()))
+    }
+}
+
+/// An image reference must not be taken for an option of the container engine
+fn validate_image(image: &str) -> Result<(), InternalError> {
+    if image.is_empty() || image.starts_with('-') || image.contains(char::is_whitespace) {
+        Err(InternalError::InvalidImage(image.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::stub::StubEngine;
+
+    #[test]
+    fn containers_are_listed_with_their_image() {
+        let stub = StubEngine::new();
+        stub.set(
+            "ps-output",
+            "nginx\tnginx:1.27\nmy-app\tregistry.example.com/my-app:2.0\n",
+        );
+        let modules = ContainerModules::new(stub.engine());
+
+        let installed = modules.list().unwrap();
+
+        assert_eq!(
+            installed,
+            vec![
+                ("nginx".to_string(), "nginx:1.27".to_string()),
+                (
+                    "my-app".to_string(),
+                    "registry.example.com/my-app:2.0".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            stub.calls(),
+            vec!["ps --all --filter label=io.thin-edge.module=container --format {{.Names}}\t{{.Image}}"]
+        );
+    }
+
+    #[test]
+    fn installing_a_container_pulls_the_image_and_replaces_the_container() {
+        let stub = StubEngine::new();
+        stub.set("exists-nginx", "");
+        let modules = ContainerModules::new(stub.engine());
+
+        modules.install("nginx", Some("nginx:1.27"), None).unwrap();
+
+        assert_eq!(
+            stub.calls(),
+            vec![
+                "pull -- nginx:1.27",
+                "container inspect -- nginx",
+                "rm --force -- nginx",
+                "run --detach --name nginx --restart unless-stopped --label io.thin-edge.module=container -- nginx:1.27",
+            ]
+        );
+    }
+
+    #[test]
+    fn installing_a_container_from_an_image_file() {
+        let stub = StubEngine::new();
+        let modules = ContainerModules::new(stub.engine());
+
+        modules
+            .install(
+                "my-app",
+                Some("my-app:2.0"),
+                Some(Utf8Path::new("/tmp/my-app.tar")),
+            )
+            .unwrap();
+
+        assert_eq!(
+            stub.calls(),
+            vec![
+                "load --input /tmp/my-app.tar",
+                "container inspect -- my-app",
+                "run --detach --name my-app --restart unless-stopped --label io.thin-edge.module=container -- my-app:2.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn the_current_container_is_kept_when_the_image_cannot_be_pulled() {
+        let stub = StubEngine::new();
+        stub.set("exists-nginx", "");
+        stub.set("fail-pull", "");
+        let modules = ContainerModules::new(stub.engine());
+
+        let err = modules.install("nginx", Some("nginx:1.28"), None);
+
+        assert!(matches!(err, Err(InternalError::CommandFailed { .. })));
+        assert_eq!(stub.calls(), vec!["pull -- nginx:1.28"]);
+    }
+
+    #[test]
+    fn removing_a_container() {
+        let stub = StubEngine::new();
+        stub.set("ps-output", "nginx\tnginx:1.27\n");
+        let modules = ContainerModules::new(stub.engine());
+
+        let err = modules.remove("nginx", Some("nginx:1.28"));
+        assert!(matches!(err, Err(InternalError::VersionMismatch { .. })));
+
+        modules.remove("nginx", Some("nginx:1.27")).unwrap();
+        modules.remove("not-installed", None).unwrap();
+
+        let removals: Vec<_> = stub
+            .calls()
+            .into_iter()
+            .filter(|call| call.starts_with("rm"))
+            .collect();
+        assert_eq!(removals, vec!["rm --force -- nginx"]);
+    }
+
+    #[test]
+    fn container_names_are_checked() {
+        let modules = ContainerModules::new(StubEngine::new().engine());
+
+        for name in ["", "-rm", "a b", "../app", "app;reboot"] {
+            let err = modules.install(name, None, None);
+            assert!(matches!(err, Err(InternalError::InvalidModuleName(_))));
+        }
+    }
+
+    #[test]
+    fn images_looking_like_options_are_rejected() {
+        let stub = StubEngine::new();
+        let modules = ContainerModules::new(stub.engine());
+
+        for image in ["--privileged", "-v/:/host", "nginx --privileged"] {
+            let err = modules.install("nginx", Some(image), None);
+            assert!(matches!(err, Err(InternalError::InvalidImage(_))));
+        }
+        assert!(stub.calls().is_empty());
+    }
+}
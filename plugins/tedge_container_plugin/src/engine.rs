@@ -0,0 +1,166 @@
+use crate::error::InternalError;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+
+/// The label set on the containers managed by the plugin
+pub const MODULE_LABEL: &str = "io.thin-edge.module";
+
+/// The command line interface of a container engine, i.e. `docker` or `podman`
+///
+/// Both engines accepting the same commands and options, the plugin is not aware of which one is used.
+#[derive(Debug, Clone)]
+pub struct Engine {
+    program: PathBuf,
+}
+
+impl Engine {
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Engine {
+            program: program.into(),
+        }
+    }
+
+    /// Use the configured engine if any, otherwise `docker` or `podman`, whichever is installed
+    pub fn detect(configured: Option<&str>) -> Result<Self, InternalError> {
+        if let Some(program) = configured {
+            return Ok(Engine::new(program));
+        }
+        ["docker", "podman"]
+            .into_iter()
+            .find_map(|program| which::which(program).ok())
+            .map(Engine::new)
+            .ok_or(InternalError::NoEngine)
+    }
+
+    /// Run an engine command, its output being forwarded to the output of the plugin
+    pub fn run<I, S>(&self, args: I) -> Result<(), InternalError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut cmd = self.command(args);
+        let status = cmd
+            .stdin(Stdio::null())
+            .status()
+            .map_err(|err| InternalError::exec_error(format!("{cmd:?}"), err))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(InternalError::CommandFailed {
+                cmd: format!("{cmd:?}"),
+                status,
+            })
+        }
+    }
+
+    /// Run an engine command, returning its output
+    pub fn output<I, S>(&self, args: I) -> Result<String, InternalError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut cmd = self.command(args);
+        let output = cmd
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(|err| InternalError::exec_error(format!("{cmd:?}"), err))?;
+        if output.status.success() {
+            Ok(String::from_utf8(output.stdout)?)
+        } else {
+            Err(InternalError::CommandFailed {
+                cmd: format!("{cmd:?}"),
+                status: output.status,
+            })
+        }
+    }
+
+    /// Tell if a container with the given name exists, whether running or not
+    pub fn container_exists(&self, name: &str) -> Result<bool, InternalError> {
+        let mut cmd = self.command(["container", "inspect", "--", name]);
+        let status = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(|err| InternalError::exec_error(format!("{cmd:?}"), err))?;
+        Ok(status.success())
+    }
+
+    fn command<I, S>(&self, args: I) -> Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(args);
+        cmd
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod stub {
+    use super::Engine;
+    use camino::Utf8Path;
+    use camino::Utf8PathBuf;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    /// A fake engine recording the commands it is given
+    ///
+    /// - `ps` outputs the content of the `ps-output` file
+    /// - `container inspect -- <name>` succeeds if there is an `exists-<name>` file
+    /// - any command fails if its first argument has a `fail-<argument>` file
+    pub struct StubEngine {
+        pub dir: TempDir,
+    }
+
+    impl StubEngine {
+        pub fn new() -> Self {
+            let dir = TempDir::new().unwrap();
+            let stub = Self::path(&dir).join("engine");
+            std::fs::write(
+                &stub,
+                r#"#!/bin/sh
+dir=$(dirname "$0")
+echo "$@" >> "$dir/calls"
+[ -f "$dir/fail-$1" ] && exit 1
+case "$1" in
+    ps) cat "$dir/ps-output" 2>/dev/null || true ;;
+    container) [ -f "$dir/exists-$4" ] ;;
+esac
+"#,
+            )
+            .unwrap();
+            std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755)).unwrap();
+            StubEngine { dir }
+        }
+
+        fn path(dir: &TempDir) -> &Utf8Path {
+            Utf8Path::from_path(dir.path()).unwrap()
+        }
+
+        pub fn dir(&self) -> Utf8PathBuf {
+            Self::path(&self.dir).to_owned()
+        }
+
+        pub fn engine(&self) -> Engine {
+            Engine::new(self.dir().join("engine"))
+        }
+
+        pub fn set(&self, file: &str, content: &str) {
+            std::fs::write(self.dir().join(file), content).unwrap()
+        }
+
+        /// The commands received by the engine, one per line
+        pub fn calls(&self) -> Vec<String> {
+            std::fs::read_to_string(self.dir().join("calls"))
+                .unwrap_or_default()
+                .lines()
+                .map(str::to_string)
+                .collect()
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use camino::Utf8PathBuf;
+use std::process::ExitStatus;
+
+#[derive(thiserror::Error, Debug)]
+pub enum InternalError {
+    #[error("No container engine found: neither `docker` nor `podman` is installed")]
+    NoEngine,
+
+    #[error("Fail to run `{cmd}`: {from}")]
+    ExecError { cmd: String, from: std::io::Error },
+
+    #[error("`{cmd}` failed with {status}")]
+    CommandFailed { cmd: String, status: ExitStatus },
+
+    #[error("A compose file is required to install {module}")]
+    MissingFile { module: String },
+
+    #[error("Invalid module name: {0:?}")]
+    InvalidModuleName(String),
+
+    #[error("Invalid image: {0:?}")]
+    InvalidImage(String),
+
+    #[error("Cannot remove {module}: version {installed_version} is installed, but {provided_version} was provided")]
+    VersionMismatch {
+        module: String,
+        installed_version: String,
+        provided_version: String,
+    },
+
+    #[error("Fail to access `{path}`: {from}")]
+    PathError {
+        path: Utf8PathBuf,
+        from: std::io::Error,
+    },
+
+    #[error(transparent)]
+    FromIo(#[from] std::io::Error),
+
+    #[error(transparent)]
+    FromUtf8(#[from] std::string::FromUtf8Error),
+
+    #[error(transparent)]
+    FromCsv(#[from] csv::Error),
+}
+
+impl InternalError {
+    pub fn exec_error(cmd: impl Into<String>, from: std::io::Error) -> InternalError {
+        InternalError::ExecError {
+            cmd: cmd.into(),
+            from,
+        }
+    }
+
+    pub fn path_error(path: impl Into<Utf8PathBuf>, from: std::io::Error) -> InternalError {
+        InternalError::PathError {
+            path: path.into(),
+            from,
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Compose projects as software modules
+//!
+//! A module is a compose project named after the module, the compose file being provided as the module file.
+//! The compose files of the installed projects are stored, along their version, in a directory per project:
+//!
+//! ```text
+//! <compose_dir>/
+//! └── <module>/
+//!     ├── docker-compose.yaml
+//!     └── version
+//! ```
+
+use crate::engine::Engine;
+use crate::error::InternalError;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use tracing::info;
+
+const COMPOSE_FILE: &str = "docker-compose.yaml";
+const VERSION_FILE: &str = "version";
+
+pub struct GroupModules {
+    engine: Engine,
+    compose_dir: Utf8PathBuf,
+}
+
+impl GroupModules {
+    pub fn new(engine: Engine, compose_dir: impl Into<Utf8PathBuf>) -> Self {
+        GroupModules {
+            engine,
+            compose_dir: compose_dir.into(),
+        }
+    }
+
+    /// List the installed projects, with their version
+    pub fn list(&self) -> Result<Vec<(String, String)>, InternalError> {
+        let Ok(entries) = self.compose_dir.read_dir_utf8() else {
+            return Ok(vec![]);
+        };
+        let mut projects = Vec::new();
+        for entry in entries.flatten() {
+            let project_dir = entry.path();
+            if !project_dir.join(COMPOSE_FILE).is_file() {
+                continue;
+            }
+            let version = std::fs::read_to_string(project_dir.join(VERSION_FILE))
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            projects.push((entry.file_name().to_string(), version));
+        }
+        projects.sort();
+        Ok(projects)
+    }
+
+    /// Start or update a project from a compose file
+    ///
+    /// The compose file of the project is only replaced once the project has been successfully started.
+    pub fn install(
+        &self,
+        module: &str,
+        version: Option<&str>,
+        file: Option<&Utf8Path>,
+    ) -> Result<(), InternalError> {
+        validate_project_name(module)?;
+        let Some(file) = file else {
+            return Err(InternalError::MissingFile {
+                module: module.to_string(),
+            });
+        };
+
+        let project_dir = self.compose_dir.join(module);
+        std::fs::create_dir_all(&project_dir)
+            .map_err(|err| InternalError::path_error(&project_dir, err))?;
+        let new_compose_file = project_dir.join(format!("{COMPOSE_FILE}.new"));
+        std::fs::copy(file, &new_compose_file)
+            .map_err(|err| InternalError::path_error(&new_compose_file, err))?;
+
+        if let Err(err) = self.compose(
+            module,
+            &new_compose_file,
+            &["up", "--detach", "--remove-orphans"],
+        ) {
+            let _ = std::fs::remove_file(&new_compose_file);
+            return Err(err);
+        }
+
+        let compose_file = project_dir.join(COMPOSE_FILE);
+        std::fs::rename(&new_compose_file, &compose_file)
+            .map_err(|err| InternalError::path_error(&compose_file, err))?;
+        let version_file = project_dir.join(VERSION_FILE);
+        std::fs::write(&version_file, version.unwrap_or_default())
+            .map_err(|err| InternalError::path_error(&version_file, err))
+    }
+
+    /// Stop and remove the containers of a project, checking the installed version if a version is given
+    ///
+    /// The volumes of the project are kept.
+    pub fn remove(&self, module: &str, version: Option<&str>) -> Result<(), InternalError> {
+        validate_project_name(module)?;
+
+        let installed = self.list()?;
+        let Some((_, installed_version)) = installed.iter().find(|(name, _)| name == module) else {
+            info!("{module} is not installed");
+            return Ok(());
+        };
+        if let Some(version) = version {
+            if version != installed_version {
+                return Err(InternalError::VersionMismatch {
+                    module: module.to_string(),
+                    installed_version: installed_version.clone(),
+                    provided_version: version.to_string(),
+                });
+            }
+        }
+
+        let project_dir = self.compose_dir.join(module);
+        self.compose(module, &project_dir.join(COMPOSE_FILE), &["down"])?;
+        std::fs::remove_dir_all(&project_dir)
+            .map_err(|err| InternalError::path_error(&project_dir, err))
+    }
+
+    fn compose(
+        &self,
+        project: &str,
+        compose_file: &Utf8Path,
+        args: &[&str],
+    ) -> Result<(), InternalError> {
+        let mut compose_args = vec![
+            "compose",
+            "--project-name",
+            project,
+            "--file",
+            compose_file.as_str(),
+        ];
+        compose_args.extend_from_slice(args);
+        self.engine.run(compose_args)
+    }
+}
+
+/// Compose project names are made of `[a-z0-9][a-z0-9_-]*`
+fn validate_project_name(name: &str) -> Result<(), InternalError> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(InternalError::InvalidModuleName(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::stub::StubEngine;
+
+    struct TestGroups {
+        stub: StubEngine,
+        modules: GroupModules,
+    }
+
+    impl TestGroups {
+        fn new() -> Self {
+            let stub = StubEngine::new();
+            let modules = GroupModules::new(stub.engine(), stub.dir().join("groups"));
+            TestGroups { stub, modules }
+        }
+
+        fn compose_file(&self, content: &str) -> Utf8PathBuf {
+            let path = self.stub.dir().join("upload.yaml");
+            std::fs::write(&path, content).unwrap();
+            path
+        }
+
+        fn project_dir(&self, project: &str) -> Utf8PathBuf {
+            self.stub.dir().join("groups").join(project)
+        }
+    }
+
+    #[test]
+    fn installing_a_project() {
+        let test = TestGroups::new();
+        let file = test.compose_file("services: {}");
+
+        test.modules
+            .install("monitoring", Some("1.0"), Some(file.as_path()))
+            .unwrap();
+
+        let project_dir = test.project_dir("monitoring");
+        assert_eq!(
+            test.stub.calls(),
+            vec![format!(
+                "compose --project-name monitoring --file {project_dir}/docker-compose.yaml.new up --detach --remove-orphans"
+            )]
+        );
+        assert_eq!(
+            std::fs::read_to_string(project_dir.join(COMPOSE_FILE)).unwrap(),
+            "services: {}"
+        );
+        assert_eq!(
+            test.modules.list().unwrap(),
+            vec![("monitoring".to_string(), "1.0".to_string())]
+        );
+    }
+
+    #[test]
+    fn a_project_that_fails_to_start_keeps_its_previous_compose_file() {
+        let test = TestGroups::new();
+        let v1 = test.compose_file("version: 1");
+        test.modules
+            .install("monitoring", Some("1.0"), Some(v1.as_path()))
+            .unwrap();
+
+        test.stub.set("fail-compose", "");
+        let v2 = test.compose_file("version: 2");
+        let err = test
+            .modules
+            .install("monitoring", Some("2.0"), Some(v2.as_path()));
+
+        assert!(matches!(err, Err(InternalError::CommandFailed { .. })));
+        assert_eq!(
+            std::fs::read_to_string(test.project_dir("monitoring").join(COMPOSE_FILE)).unwrap(),
+            "version: 1"
+        );
+        assert_eq!(
+            test.modules.list().unwrap(),
+            vec![("monitoring".to_string(), "1.0".to_string())]
+        );
+    }
+
+    #[test]
+    fn removing_a_project() {
+        let test = TestGroups::new();
+        let file = test.compose_file("services: {}");
+        test.modules
+            .install("monitoring", Some("1.0"), Some(file.as_path()))
+            .unwrap();
+
+        test.modules.remove("monitoring", Some("1.0")).unwrap();
+
+        let project_dir = test.project_dir("monitoring");
+        assert_eq!(
+            test.stub.calls().last().unwrap(),
+            &format!(
+                "compose --project-name monitoring --file {project_dir}/docker-compose.yaml down"
+            )
+        );
+        assert!(!project_dir.exists());
+        assert_eq!(test.modules.list().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn a_compose_file_is_required() {
+        let test = TestGroups::new();
+
+        let err = test.modules.install("monitoring", Some("1.0"), None);
+
+        assert!(matches!(err, Err(InternalError::MissingFile { .. })));
+    }
+
+    #[test]
+    fn project_names_are_checked() {
+        let test = TestGroups::new();
+        let file = test.compose_file("services: {}");
+
+        for name in ["", "Monitoring", "..", "a/b", "-down"] {
+            let err = test.modules.install(name, None, Some(file.as_path()));
+            assert!(matches!(err, Err(InternalError::InvalidModuleName(_))));
+        }
+    }
+}
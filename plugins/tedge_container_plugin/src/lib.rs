@@ -0,0 +1,224 @@
+mod container;
+mod engine;
+mod error;
+mod group;
+
+use crate::container::ContainerModules;
+use crate::engine::Engine;
+use crate::error::InternalError;
+use crate::group::GroupModules;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+use std::io;
+use tedge_config::cli::CommonArgs;
+use tedge_config::log_init;
+use tedge_config::TEdgeConfig;
+use tracing::error;
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    name = clap::crate_name!(),
+    version = clap::crate_version!(),
+    about = clap::crate_description!(),
+    arg_required_else_help(true)
+)]
+pub struct ContainerCli {
+    #[command(flatten)]
+    pub common: CommonArgs,
+
+    /// The type of software modules to manage
+    ///
+    /// Set according to the name of the plugin when invoked as `container` or `container-group`.
+    #[clap(long, value_enum, default_value_t = ModuleType::Container)]
+    pub module_type: ModuleType,
+
+    #[clap(subcommand)]
+    operation: PluginOp,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ModuleType {
+    /// A container running an image
+    Container,
+
+    /// A compose project
+    ContainerGroup,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum PluginOp {
+    /// List all the installed modules
+    List,
+
+    /// Install a module
+    Install {
+        module: String,
+        #[clap(short = 'v', long = "module-version")]
+        version: Option<String>,
+        #[clap(long = "file")]
+        file_path: Option<Utf8PathBuf>,
+    },
+
+    /// Uninstall a module
+    Remove {
+        module: String,
+        #[clap(short = 'v', long = "module-version")]
+        version: Option<String>,
+    },
+
+    /// Install or remove multiple modules at once
+    UpdateList,
+
+    /// Prepare a sequences of install/remove commands
+    Prepare,
+
+    /// Finalize a sequences of install/remove commands
+    Finalize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum UpdateAction {
+    Install,
+    Remove,
+}
+
+#[derive(Debug, Deserialize)]
+struct SoftwareModuleUpdate {
+    pub action: UpdateAction,
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub path: Option<Utf8PathBuf>,
+}
+
+enum Modules {
+    Container(ContainerModules),
+    ContainerGroup(GroupModules),
+}
+
+impl Modules {
+    fn list(&self) -> Result<Vec<(String, String)>, InternalError> {
+        match self {
+            Modules::Container(modules) => modules.list(),
+            Modules::ContainerGroup(modules) => modules.list(),
+        }
+    }
+
+    fn install(
+        &self,
+        module: &str,
+        version: Option<&str>,
+        file: Option<&Utf8Path>,
+    ) -> Result<(), InternalError> {
+        // `latest` is the version sent by the cloud when no version is given
+        let version = version.filter(|version| !version.is_empty() && *version != "latest");
+        let file = file.filter(|file| !file.as_str().is_empty());
+        match self {
+            Modules::Container(modules) => modules.install(module, version, file),
+            Modules::ContainerGroup(modules) => modules.install(module, version, file),
+        }
+    }
+
+    fn remove(&self, module: &str, version: Option<&str>) -> Result<(), InternalError> {
+        let version = version.filter(|version| !version.is_empty());
+        match self {
+            Modules::Container(modules) => modules.remove(module, version),
+            Modules::ContainerGroup(modules) => modules.remove(module, version),
+        }
+    }
+
+    fn finalize(&self) -> Result<(), InternalError> {
+        match self {
+            Modules::Container(modules) => modules.prune_images(),
+            Modules::ContainerGroup(_) => Ok(()),
+        }
+    }
+}
+
+impl ContainerCli {
+    pub fn with_module_type(self, module_type: ModuleType) -> Self {
+        ContainerCli {
+            module_type,
+            ..self
+        }
+    }
+}
+
+fn run_op(cli: ContainerCli, tedge_config: &TEdgeConfig) -> Result<(), InternalError> {
+    if let Err(err) = log_init(
+        "tedge-container-plugin",
+        &cli.common.log_args,
+        &cli.common.config_dir,
+    ) {
+        error!("Can't enable logging due to error: {err}");
+    }
+
+    let engine = Engine::detect(tedge_config.container.engine.or_none().map(String::as_str))?;
+    let modules = match cli.module_type {
+        ModuleType::Container => Modules::Container(ContainerModules::new(engine)),
+        ModuleType::ContainerGroup => Modules::ContainerGroup(GroupModules::new(
+            engine,
+            tedge_config.container.compose_dir.clone(),
+        )),
+    };
+
+    match cli.operation {
+        PluginOp::List => {
+            for (name, version) in modules.list()? {
+                println!("{name}\t{version}");
+            }
+        }
+
+        PluginOp::Install {
+            module,
+            version,
+            file_path,
+        } => modules.install(&module, version.as_deref(), file_path.as_deref())?,
+
+        PluginOp::Remove { module, version } => modules.remove(&module, version.as_deref())?,
+
+        PluginOp::UpdateList => {
+            let mut rdr = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .delimiter(b'\t')
+                .from_reader(io::stdin());
+            let mut updates: Vec<SoftwareModuleUpdate> = Vec::new();
+            for result in rdr.deserialize() {
+                updates.push(result?);
+            }
+
+            for update in updates {
+                match update.action {
+                    UpdateAction::Install => modules.install(
+                        &update.name,
+                        update.version.as_deref(),
+                        update.path.as_deref(),
+                    )?,
+                    UpdateAction::Remove => {
+                        modules.remove(&update.name, update.version.as_deref())?
+                    }
+                }
+            }
+        }
+
+        PluginOp::Prepare => {}
+
+        PluginOp::Finalize => modules.finalize()?,
+    }
+
+    Ok(())
+}
+
+pub fn run_and_exit(cli: ContainerCli, tedge_config: TEdgeConfig) -> ! {
+    match run_op(cli, &tedge_config) {
+        Ok(()) => std::process::exit(0),
+
+        Err(err) => {
+            eprintln!("ERROR: {}", err);
+            std::process::exit(2);
+        }
+    }
+}